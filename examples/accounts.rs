@@ -0,0 +1,103 @@
+#![doc = include_str!("./examples.md")]
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use trait_rpc::client::websocket::WebsocketClient;
+use trait_rpc::format::json::Json;
+use trait_rpc::server::axum::Axum;
+use trait_rpc::Rpc;
+
+include!("traits/accounts.rs");
+
+#[derive(Default)]
+struct Bank {
+    balances: Arc<Mutex<HashMap<u64, i64>>>,
+    lookups: Arc<AtomicUsize>,
+    open: Arc<AtomicUsize>,
+}
+
+impl BankServiceServer for Bank {
+    async fn account(&self, id: u64) -> impl trait_rpc::Handler<Rpc = AccountService> {
+        // this stands in for an expensive lookup, eg: from a database
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        self.open.fetch_add(1, Ordering::SeqCst);
+        println!("looked up account {id}");
+        AccountService::server(Account {
+            id,
+            balances: self.balances.clone(),
+            open: self.open.clone(),
+        })
+    }
+}
+
+struct Account {
+    id: u64,
+    balances: Arc<Mutex<HashMap<u64, i64>>>,
+    open: Arc<AtomicUsize>,
+}
+
+impl AccountServiceServer for Account {
+    async fn balance(&self) -> i64 {
+        self.balances.lock().unwrap().get(&self.id).copied().unwrap_or_default()
+    }
+
+    async fn deposit(&self, amount: i64) -> i64 {
+        let mut balances = self.balances.lock().unwrap();
+        let balance = balances.entry(self.id).or_default();
+        *balance += amount;
+        let balance = *balance;
+        drop(balances);
+        balance
+    }
+}
+
+impl Drop for Account {
+    fn drop(&mut self) {
+        self.open.fetch_sub(1, Ordering::SeqCst);
+        println!("released account {}", self.id);
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let bank = Bank::default();
+    let lookups = bank.lookups.clone();
+    let open = bank.open.clone();
+    let app = axum::Router::new().route_service(
+        "/api/bank",
+        Axum::builder()
+            .handler(BankService::server(bank))
+            .allow_json()
+            .build(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:3003").await.unwrap();
+    tokio::spawn(async move {
+        axum::serve::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+    });
+
+    let client = WebsocketClient::new("ws://localhost:3003/api/bank".parse().unwrap(), Json)
+        .await
+        .expect("failed to connect");
+    let bank = BankService::async_client(client);
+
+    // without a handle, the account is looked up again for each call
+    bank.account(1).deposit(10).await.expect("deposit failed");
+    let balance = bank.account(1).balance().await.expect("balance failed");
+    println!("balance is {balance} after {} lookups", lookups.load(Ordering::SeqCst));
+
+    {
+        let account = bank.open_account(1).await.expect("open failed");
+        account.deposit(5).await.expect("deposit failed");
+        account.deposit(5).await.expect("deposit failed");
+        let balance = account.balance().await.expect("balance failed");
+        println!("balance is {balance} after {} lookups", lookups.load(Ordering::SeqCst));
+        // the handle is released here, which drops the account on the server
+    }
+    while open.load(Ordering::SeqCst) != 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    println!("every account has been released");
+}
@@ -0,0 +1,91 @@
+#![doc = include_str!("./examples.md")]
+
+use futures::StreamExt;
+use std::net::SocketAddr;
+use std::time::Duration;
+use trait_rpc::client::websocket::WebsocketClient;
+use trait_rpc::format::json::Json;
+use trait_rpc::server::axum::Axum;
+use trait_rpc::server::topics::Topics;
+use trait_rpc::Rpc;
+
+include!("traits/chat.rs");
+
+struct Room {
+    topics: Topics<ChatService>,
+}
+
+impl ChatServiceServer for Room {
+    async fn send(&self, message: Message) -> usize {
+        ChatService::publish_messages(&self.topics, message)
+    }
+
+    async fn typing(&self, author: String) {
+        ChatService::publish_typing_authors(&self.topics, author);
+    }
+}
+
+type ChatClient = <ChatService as Rpc>::AsyncClient<WebsocketClient<<ChatService as Rpc>::Request, <ChatService as Rpc>::Response>>;
+
+/// Connect a new client to the chat room
+async fn connect() -> ChatClient {
+    ChatService::async_client(
+        WebsocketClient::new("ws://localhost:3002/api/chat".parse().unwrap(), Json)
+            .await
+            .expect("failed to connect"),
+    )
+}
+
+/// Wait until the server has registered the given number of subscribers to a topic of the room
+async fn wait_for_subscribers(topics: &Topics<ChatService>, topic: &'static str, count: usize) {
+    while topics.subscribers(topic) != count {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let topics = Topics::new();
+    let room = Room { topics: topics.clone() };
+    let app = axum::Router::new().route_service(
+        "/api/chat",
+        Axum::builder()
+            .handler(ChatService::server(room))
+            .allow_json()
+            .topics(topics.clone())
+            .build(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:3002").await.unwrap();
+    tokio::spawn(async move {
+        axum::serve::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+    });
+
+    let alice = connect().await;
+    let mut alice_messages = alice.messages().await.expect("messages failed");
+    {
+        let bob = connect().await;
+        let mut bob_messages = bob.messages().await.expect("messages failed");
+        let mut bob_typing = bob.typing_authors().await.expect("typing authors failed");
+        wait_for_subscribers(&topics, "messages", 2).await;
+        wait_for_subscribers(&topics, "typing_authors", 1).await;
+
+        // this returns as soon as the notification has been sent, the server does not reply
+        alice.typing("alice".to_string()).await.expect("typing failed");
+        let author = bob_typing.next().await.expect("subscription ended").expect("typing authors failed");
+        println!("bob sees that {author} is typing");
+
+        let message = Message {
+            author: "alice".to_string(),
+            text: "Hello everyone".to_string(),
+        };
+        let sent = alice.send(message).await.expect("send failed");
+        println!("sent to {sent} clients");
+        let message = alice_messages.next().await.expect("subscription ended").expect("messages failed");
+        println!("alice received {message:?}");
+        let message = bob_messages.next().await.expect("subscription ended").expect("messages failed");
+        println!("bob received {message:?}");
+        // bob leaves the room here, which removes the subscription
+    }
+    wait_for_subscribers(&topics, "messages", 1).await;
+    println!("bob left, {} subscriber remaining", topics.subscribers("messages"));
+}
@@ -0,0 +1,71 @@
+#![doc = include_str!("./examples.md")]
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use tokio::sync::Mutex;
+use trait_rpc::client::websocket::{Callbacks, WebsocketClient};
+use trait_rpc::format::json::Json;
+use trait_rpc::server::axum::Axum;
+use trait_rpc::server::multiplex::Peer;
+use trait_rpc::Rpc;
+
+include!("traits/files.rs");
+
+struct Files {
+    files: Mutex<HashSet<String>>,
+}
+
+impl FileServiceServer for Files {
+    async fn delete_file(&self, ctx: &Context, name: String) -> bool {
+        if !self.files.lock().await.contains(&name) {
+            return false;
+        }
+        // the connection to the caller carries calls back to its confirm service
+        let Some(peer) = ctx.get::<Peer<ConfirmService>>() else {
+            return false;
+        };
+        let confirmed = peer.client().confirm(format!("Delete {name}?")).await.unwrap_or(false);
+        confirmed && self.files.lock().await.remove(&name)
+    }
+}
+
+/// Confirms every prompt except those for files ending in `.rs`
+struct Confirm;
+
+impl ConfirmServiceServer for Confirm {
+    async fn confirm(&self, prompt: String) -> bool {
+        let confirmed = !prompt.ends_with(".rs?");
+        println!("{prompt} {}", if confirmed { "yes" } else { "no" });
+        confirmed
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let files = Files {
+        files: Mutex::new(["notes.txt", "main.rs"].map(String::from).into()),
+    };
+    let app = axum::Router::new().route_service(
+        "/api/files",
+        Axum::builder()
+            .handler(FileService::server(files))
+            .allow_json()
+            .peer::<ConfirmService>(&Json)
+            .build(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:3001").await.unwrap();
+    tokio::spawn(async move {
+        axum::serve::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+    });
+
+    // the client serves its confirm service over the same websocket connection
+    let client = FileService::async_client(
+        WebsocketClient::with_callbacks("ws://localhost:3001/api/files".parse().unwrap(), Json, Callbacks::new(ConfirmService::server(Confirm)))
+            .await
+            .expect("failed to connect"),
+    );
+    for name in ["notes.txt", "main.rs", "missing.txt"] {
+        let deleted = client.delete_file(name.to_string()).await.expect("delete_file failed");
+        println!("{name} deleted: {deleted}");
+    }
+}
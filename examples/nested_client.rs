@@ -1,24 +1,27 @@
 #![doc = include_str!("./examples.md")]
 
+use trait_rpc::batch::Batch;
+use trait_rpc::client::{CallError, RpcError};
+use trait_rpc::futures::join;
 use trait_rpc::client::reqwest::Reqwest;
 use trait_rpc::format::json::Json;
+use trait_rpc::pipeline::PipelineError;
 use trait_rpc::Rpc;
 
 include!("traits/nested.rs");
 
 #[tokio::main]
 async fn main() {
-    let client = ApiService::async_client(
-        trait_rpc::client::builder()
-            .non_blocking()
-            .transport(
-                Reqwest::builder()
-                    .url("http://localhost:8080/api")
-                    .build()
-            )
-            .format(Json)
-            .build()
-    );
+    let transport = trait_rpc::client::builder()
+        .non_blocking()
+        .transport(
+            Reqwest::builder()
+                .url("http://localhost:8080/api")
+                .build()
+        )
+        .format(Json)
+        .build();
+    let client = ApiService::async_client(transport.clone());
     let dylan = client
         .users()
         .new(NewUser {
@@ -45,33 +48,53 @@ async fn main() {
     println!("Successfully logged in as user");
     let current_user = client
         .users()
-        .current(token)
+        .current(token.clone())
         .get()
         .await
-        .expect("failed to get user")
-        .expect("user not found");
+        .expect("failed to get user");
     println!("Current user: {}", current_user.name);
     assert_eq!(current_user, dylan);
 
-    let dylan_service = client.users().by_id(dylan.id);
-    let fetched = dylan_service
-        .get()
+    // the token is passed from login to current without waiting for it, in a single request
+    let current_user = client
+        .pipeline(|api| {
+            let token = api.login("dylan".to_string(), "secret".to_string()).some();
+            api.users().current(token).get()
+        })
         .await
-        .expect("Error getting user")
-        .expect("user not found");
+        .expect("pipeline request failed")
+        .expect("failed to get user");
+    assert_eq!(current_user, dylan);
+    println!("Current user with a pipeline: {}", current_user.name);
+    let wrong_password = client
+        .pipeline(|api| {
+            let token = api.login("dylan".to_string(), "wrong".to_string()).some();
+            api.users().current(token).get()
+        })
+        .await;
+    assert!(matches!(wrong_password, Err(PipelineError::Unavailable(_))));
+    println!("Pipeline skipped the call after a failed login");
+
+    // independent calls are sent together in a single request, each receives its own result
+    let batch = Batch::new(transport);
+    let users = ApiService::async_client(batch.clone()).users();
+    let current = users.current(token);
+    let (users, current_user) = batch.run(async { join!(users.list(), current.get()) }).await;
+    assert_eq!(users.expect("Error getting users"), vec![dylan.clone()]);
+    assert_eq!(current_user.expect("failed to get user"), dylan);
+    println!("Fetched the users and the current user in a batch");
+
+    let dylan_service = client.users().by_id(dylan.id);
+    let fetched = dylan_service.get().await.expect("Error getting user");
     assert_eq!(fetched, dylan);
     println!("Successfully fetched user");
-    let deleted = dylan_service
-        .delete()
-        .await
-        .expect("Error deleting user")
-        .expect("user not found");
+    let deleted = dylan_service.delete().await.expect("Error deleting user");
     assert_eq!(deleted, dylan);
     println!("Successfully deleted user");
-    dylan_service
-        .get()
-        .await
-        .expect("Error getting user")
-        .expect_err("User not deleted");
+    match dylan_service.get().await {
+        Err(CallError::Rpc(RpcError::NotFound(_))) => {}
+        Ok(_) => panic!("User not deleted"),
+        Err(error) => panic!("Error getting user: {error:?}"),
+    }
     println!("Confirmed user deleted");
 }
\ No newline at end of file
@@ -1,8 +1,8 @@
 use axum::Router;
 use axum::http::Method;
-use futures::future::{Either, ready};
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
+use std::net::SocketAddr;
 use std::ops::Deref;
 use tokio::sync::RwLock;
 use trait_rpc::Handler;
@@ -20,13 +20,16 @@ async fn main() {
             .format(&Json)
             .format(&Cbor)
             .method(Method::POST)
+            .batch_concurrency(4)
             .build(),
     );
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:8080")
         .await
         .unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
 }
 
 impl LoginToken {
@@ -87,8 +90,16 @@ impl ApiServiceServer for Api {
         UsersService::server(Users(self))
     }
 
-    async fn login(&self, username: String, password: String) -> Option<LoginToken> {
-        println!("received login request: username: {username}, password: {password}");
+    async fn login(
+        &self,
+        ctx: &Context,
+        username: String,
+        password: String,
+    ) -> Option<LoginToken> {
+        let peer = ctx
+            .peer_addr()
+            .map_or_else(|| "unknown".to_string(), |addr| addr.to_string());
+        println!("received login request from {peer}: username: {username}, password: {password}");
         let user = self
             .users
             .read()
@@ -130,25 +141,22 @@ impl UsersServiceServer for Users<'_> {
         self.users.read().await.values().cloned().collect()
     }
 
-    async fn by_id(&self, user_id: u64) -> impl Handler<Rpc = UserService> {
-        UserService::server(UserServer {
+    async fn by_id(&self, user_id: u64) -> Option<impl Handler<Rpc = UserService>> {
+        if !self.users.read().await.contains_key(&user_id) {
+            return None;
+        }
+        Some(UserService::server(UserServer {
             api: self.0,
             user_id,
-        })
+        }))
     }
 
-    async fn current(&self, token: LoginToken) -> impl Handler<Rpc = UserService> {
-        UserService::server(
-            self.tokens
-                .read()
-                .await
-                .get(&token)
-                .map(|&user_id| UserServer {
-                    api: self.0,
-                    user_id,
-                })
-                .ok_or(UserNotFound),
-        )
+    async fn current(&self, token: LoginToken) -> Result<impl Handler<Rpc = UserService>, LoginExpired> {
+        let user_id = *self.tokens.read().await.get(&token).ok_or(LoginExpired)?;
+        Ok(UserService::server(UserServer {
+            api: self.0,
+            user_id,
+        }))
     }
 }
 
@@ -191,31 +199,3 @@ impl UserServiceServer for UserServer<'_> {
         }
     }
 }
-
-impl<S, E> UserServiceServer for Result<S, E>
-where
-    Self: Send,
-    S: UserServiceServer,
-    E: Into<UserNotFound> + Clone + Sync,
-{
-    fn get(&self) -> impl Future<Output = Result<User, UserNotFound>> + Send {
-        match self {
-            Ok(ok) => Either::Left(ok.get()),
-            Err(err) => Either::Right(ready(Err(err.clone().into()))),
-        }
-    }
-
-    fn update(&self, user: UserUpdate) -> impl Future<Output = Result<User, UserNotFound>> + Send {
-        match self {
-            Ok(ok) => Either::Left(ok.update(user)),
-            Err(err) => Either::Right(ready(Err(err.clone().into()))),
-        }
-    }
-
-    fn delete(&self) -> impl Future<Output = Result<User, UserNotFound>> + Send {
-        match self {
-            Ok(ok) => Either::Left(ok.delete()),
-            Err(err) => Either::Right(ready(Err(err.clone().into()))),
-        }
-    }
-}
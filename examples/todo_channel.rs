@@ -0,0 +1,77 @@
+#![doc = include_str!("./examples.md")]
+
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::lock::Mutex;
+use futures::{future, stream, Stream, StreamExt};
+use trait_rpc::client::multiplex::MultiplexClient;
+use trait_rpc::format::json::Json;
+use trait_rpc::server::multiplex;
+use trait_rpc::server::Context;
+use trait_rpc::Rpc;
+
+include!("traits/todo.rs");
+
+#[derive(Default)]
+struct Todos {
+    todos: Mutex<Vec<Todo>>,
+}
+
+impl TodoServiceServer for Todos {
+    async fn get_todos(&self) -> Vec<Todo> {
+        self.todos.lock().await.clone()
+    }
+
+    async fn get_todo(&self, name: String) -> Option<Todo> {
+        self.todos.lock().await.iter().find(|todo| todo.name == name).cloned()
+    }
+
+    async fn new_todo(&self, todo: Todo) -> () {
+        self.todos.lock().await.push(todo);
+    }
+
+    async fn watch_todos(&self) -> impl Stream<Item = Todo> + Send {
+        stream::iter(self.get_todos().await)
+    }
+
+    async fn import_todos(&self, todos: impl Stream<Item = Todo> + Send + Unpin) -> usize {
+        todos
+            .fold(0, |count, todo| async move {
+                self.new_todo(todo).await;
+                count + 1
+            })
+            .await
+    }
+}
+
+fn main() {
+    // a pair of channels stands in for the connection, each message is a single frame
+    let (client_sink, server_stream) = mpsc::channel::<Vec<u8>>(16);
+    let (server_sink, client_stream) = mpsc::channel::<Vec<u8>>(16);
+
+    let server = TodoService::server(Todos::default());
+    let ctx = Context::new();
+    let serve = multiplex::serve(server_sink, server_stream, &Json, &server, &ctx, 16, 1);
+
+    let (client, worker) = MultiplexClient::new(client_sink, client_stream, Json);
+    let client = TodoService::async_client(client);
+    let calls = async move {
+        let todos = (0..3).map(|i| Todo {
+            name: format!("Imported task {i}"),
+            description: "An imported task".to_string(),
+        });
+        let imported = client.import_todos(stream::iter(todos)).await.expect("import_todos failed");
+        println!("imported {imported} to-do items");
+        let mut todos = client.watch_todos().await.expect("watch_todos failed");
+        while let Some(todo) = todos.next().await {
+            println!("{:?}", todo.expect("watch_todos failed"));
+        }
+        // dropping the client closes the connection once every call has finished, which stops the
+        // server
+    };
+
+    // no async runtime is needed, the server, the client's connection and the calls are all driven
+    // on the current thread
+    let (result, (), ()) = block_on(future::join3(serve, worker, calls));
+    result.expect("received an invalid frame");
+}
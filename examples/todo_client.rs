@@ -1,24 +1,52 @@
 #![doc = include_str!("./examples.md")]
 
+use std::error::Error;
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+use trait_rpc::client::layer::{InterceptLayer, Interceptor, Layer};
 use trait_rpc::client::reqwest::Reqwest;
+use trait_rpc::client::retry::RetryLayer;
 use trait_rpc::{Rpc, client};
 use trait_rpc::format::json::Json;
 
 include!("traits/todo.rs");
 
+/// Logs every request and response, along with how long the call took
+#[derive(Clone)]
+struct Logging;
+
+impl<Req: Debug, Resp: Debug> Interceptor<Req, Resp> for Logging {
+    async fn call<N, F, E>(&self, request: Req, next: N) -> Result<Resp, E>
+    where
+        N: Fn(Req) -> F,
+        F: Future<Output = Result<Resp, E>>,
+        E: Error + 'static,
+    {
+        println!("sending {request:?}");
+        let start = Instant::now();
+        let response = next(request).await;
+        match &response {
+            Ok(response) => println!("received {response:?} after {:?}", start.elapsed()),
+            Err(error) => println!("request failed after {:?}: {error}", start.elapsed()),
+        }
+        response
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    let client = TodoService::async_client(
+    let client = TodoService::async_client(InterceptLayer::new(Logging).layer(
         client::builder()
             .non_blocking()
-            .transport(
+            .transport(RetryLayer::default().layer(
                 Reqwest::builder()
                     .url("http://localhost:8080/api/todo")
                     .build()
-            )
+            ))
             .format(Json)
+            .timeout(Duration::from_secs(5))
             .build()
-    );
+    ));
     for todo in client.get_todos().await.expect("get_todos failed") {
         println!("{todo:?}");
     }
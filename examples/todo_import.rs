@@ -0,0 +1,22 @@
+#![doc = include_str!("./examples.md")]
+
+use trait_rpc::client::websocket::WebsocketClient;
+use trait_rpc::format::json::Json;
+use trait_rpc::Rpc;
+
+include!("traits/todo.rs");
+
+#[tokio::main]
+async fn main() {
+    let client = TodoService::async_client(
+        WebsocketClient::new("ws://localhost:3000/api/todo".parse().unwrap(), Json)
+            .await
+            .expect("failed to connect"),
+    );
+    let todos = futures::stream::iter((1..=1000).map(|i| Todo {
+        name: format!("todo {i}"),
+        description: format!("imported to-do item number {i}"),
+    }));
+    let count = client.import_todos(todos).await.expect("import_todos failed");
+    println!("imported {count} to-do items");
+}
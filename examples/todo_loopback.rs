@@ -0,0 +1,66 @@
+#![doc = include_str!("./examples.md")]
+
+use futures::{stream, Stream, StreamExt};
+use std::ops::Deref;
+use tokio::sync::RwLock;
+use trait_rpc::client::loopback::Loopback;
+use trait_rpc::format::json::Json;
+use trait_rpc::Rpc;
+
+include!("traits/todo.rs");
+
+#[derive(Default)]
+struct Todos {
+    todos: RwLock<Vec<Todo>>,
+}
+
+impl TodoServiceServer for Todos {
+    async fn get_todos(&self) -> Vec<Todo> {
+        self.todos.read().await.deref().clone()
+    }
+
+    async fn get_todo(&self, name: String) -> Option<Todo> {
+        self.todos.read().await.iter().find(|todo| todo.name == name).cloned()
+    }
+
+    async fn new_todo(&self, todo: Todo) -> () {
+        self.todos.write().await.push(todo);
+    }
+
+    async fn watch_todos(&self) -> impl Stream<Item = Todo> + Send {
+        stream::iter(self.get_todos().await)
+    }
+
+    async fn import_todos(&self, todos: impl Stream<Item = Todo> + Send + Unpin) -> usize {
+        todos
+            .fold(0, |count, todo| async move {
+                self.new_todo(todo).await;
+                count + 1
+            })
+            .await
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    // requests and responses are passed to the server as they are
+    let client = TodoService::async_client(Loopback::new(TodoService::server(Todos::default())));
+    client.new_todo(Todo {
+        name: "Some task".to_string(),
+        description: "A description of the task".to_string(),
+    }).await.expect("new_todo failed");
+    println!("{:?}", client.get_todos().await.expect("get_todos failed"));
+
+    // every request and response is written and read as JSON, as it would be by a real transport
+    let client = TodoService::async_client(Loopback::new(TodoService::server(Todos::default())).with_format(Json));
+    let todos = (0..3).map(|i| Todo {
+        name: format!("Imported task {i}"),
+        description: "An imported task".to_string(),
+    });
+    let imported = client.import_todos(stream::iter(todos)).await.expect("import_todos failed");
+    println!("imported {imported} to-do items");
+    let mut todos = client.watch_todos().await.expect("watch_todos failed");
+    while let Some(todo) = todos.next().await {
+        println!("{:?}", todo.expect("watch_todos failed"));
+    }
+}
@@ -1,14 +1,29 @@
 #![doc = include_str!("./examples.md")]
 
+use futures::{Sink, Stream, StreamExt};
+use std::net::SocketAddr;
 use std::ops::Deref;
+use std::time::Instant;
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::sync::RwLock;
 use trait_rpc::server::axum::Axum;
+use trait_rpc::server::{Context, Handler, HandlerError, HandlerLayer};
+use trait_rpc::{RequestInfo, Rpc};
 
 include!("traits/todo.rs");
 
-#[derive(Default)]
 struct Todos {
-    todos: RwLock<Vec<Todo>>
+    todos: RwLock<Vec<Todo>>,
+    created: broadcast::Sender<Todo>,
+}
+
+impl Default for Todos {
+    fn default() -> Self {
+        Self {
+            todos: RwLock::default(),
+            created: broadcast::channel(16).0,
+        }
+    }
 }
 
 impl TodoServiceServer for Todos {
@@ -21,8 +36,60 @@ impl TodoServiceServer for Todos {
     }
 
     async fn new_todo(&self, todo: Todo) -> () {
+        // there may be no watchers, this is not an error
+        let _ = self.created.send(todo.clone());
         self.todos.write().await.push(todo);
     }
+
+    async fn watch_todos(&self) -> impl Stream<Item = Todo> + Send {
+        futures::stream::unfold(self.created.subscribe(), |mut created| async move {
+            loop {
+                match created.recv().await {
+                    Ok(todo) => return Some((todo, created)),
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    async fn import_todos(&self, todos: impl Stream<Item = Todo> + Send + Unpin) -> usize {
+        todos
+            .fold(0, |count, todo| async move {
+                self.new_todo(todo).await;
+                count + 1
+            })
+            .await
+    }
+}
+
+/// Logs the name and duration of every call
+struct Logging;
+
+impl<R: Rpc> HandlerLayer<R> for Logging {
+    async fn handle<H>(&self, ctx: &Context, request: R::Request, inner: &H) -> Result<R::Response, HandlerError>
+    where
+        H: Handler<Rpc = R> + Sync,
+    {
+        let name = request.fn_name();
+        let start = Instant::now();
+        let response = inner.handle(ctx, request).await;
+        println!("{name} took {:?}", start.elapsed());
+        response
+    }
+
+    async fn handle_stream<H, I, S>(&self, ctx: &Context, request: R::Request, incoming: I, sink: S, inner: &H) -> Result<(), HandlerError>
+    where
+        H: Handler<Rpc = R> + Sync,
+        I: Stream<Item = R::Request> + Send + Unpin,
+        S: Sink<R::Response> + Send + Unpin,
+    {
+        let name = request.fn_name();
+        let start = Instant::now();
+        let result = inner.handle_stream(ctx, request, incoming, sink).await;
+        println!("{name} finished after {:?}", start.elapsed());
+        result
+    }
 }
 
 #[tokio::main]
@@ -30,12 +97,12 @@ async fn main() {
     let app = axum::Router::new()
         .route_service("/api/todo",
                Axum::builder()
-                   .handler(TodoService::server(Todos::default()))
+                   .handler(TodoService::server(Todos::default()).layer(Logging))
                    .allow_json()
                    .allow_post()
                    .allow_put()
                    .build()
         );
     let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await.unwrap();
-    axum::serve::serve(listener, app).await.unwrap();
-}
\ No newline at end of file
+    axum::serve::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+}
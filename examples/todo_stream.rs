@@ -0,0 +1,116 @@
+#![doc = include_str!("./examples.md")]
+
+use futures::{stream, Stream, StreamExt};
+use std::env;
+use std::ops::Deref;
+use std::process::Command;
+use tokio::net::{TcpListener, UnixListener};
+use tokio::sync::RwLock;
+use trait_rpc::client::stream::StreamClient;
+use trait_rpc::client::unix_blocking::UnixBlocking;
+use trait_rpc::format::json::Json;
+use trait_rpc::server::stream::StreamServer;
+use trait_rpc::{client, Rpc};
+
+include!("traits/todo.rs");
+
+#[derive(Default)]
+struct Todos {
+    todos: RwLock<Vec<Todo>>,
+}
+
+impl TodoServiceServer for Todos {
+    async fn get_todos(&self) -> Vec<Todo> {
+        self.todos.read().await.deref().clone()
+    }
+
+    async fn get_todo(&self, name: String) -> Option<Todo> {
+        self.todos.read().await.iter().find(|todo| todo.name == name).cloned()
+    }
+
+    async fn new_todo(&self, todo: Todo) -> () {
+        self.todos.write().await.push(todo);
+    }
+
+    async fn watch_todos(&self) -> impl Stream<Item = Todo> + Send {
+        stream::iter(self.get_todos().await)
+    }
+
+    async fn import_todos(&self, todos: impl Stream<Item = Todo> + Send + Unpin) -> usize {
+        todos
+            .fold(0, |count, todo| async move {
+                self.new_todo(todo).await;
+                count + 1
+            })
+            .await
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let server = || {
+        StreamServer::builder()
+            .handler(TodoService::server(Todos::default()))
+            .allow_json()
+            .build()
+    };
+    if env::args().nth(1).as_deref() == Some("plugin") {
+        // running as a plugin of the process below, stdout is used by the server so log to stderr
+        eprintln!("plugin started");
+        server().serve_stdio().await;
+        eprintln!("plugin stopped");
+        return;
+    }
+
+    // serve on any free port of the loopback interface, then connect to it from the same process
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(server().serve_tcp(listener));
+    let client = TodoService::async_client(StreamClient::connect_tcp(addr, Json).await.expect("failed to connect"));
+    client.new_todo(Todo {
+        name: "Some task".to_string(),
+        description: "A description of the task".to_string(),
+    }).await.expect("new_todo failed");
+    let todos = (0..3).map(|i| Todo {
+        name: format!("Imported task {i}"),
+        description: "An imported task".to_string(),
+    });
+    let imported = client.import_todos(stream::iter(todos)).await.expect("import_todos failed");
+    println!("imported {imported} to-do items");
+    let mut todos = client.watch_todos().await.expect("watch_todos failed");
+    while let Some(todo) = todos.next().await {
+        println!("{:?}", todo.expect("watch_todos failed"));
+    }
+
+    // the same service can be served over a Unix socket, and called with a blocking client
+    let path = env::temp_dir().join(format!("todo-{}.sock", std::process::id()));
+    let listener = UnixListener::bind(&path).unwrap();
+    tokio::spawn(server().serve_unix(listener));
+    let blocking = tokio::task::spawn_blocking(move || {
+        let client = TodoService::blocking_client(
+            client::builder()
+                .blocking()
+                .transport(UnixBlocking::new(&path))
+                .format(Json)
+                .build()
+        );
+        client.new_todo(Todo {
+            name: "Local task".to_string(),
+            description: "Created over a Unix socket".to_string(),
+        }).expect("new_todo failed");
+        let todo = client.get_todo("Local task".to_string()).expect("get_todo failed");
+        std::fs::remove_file(path).unwrap();
+        todo
+    });
+    println!("{:?}", blocking.await.unwrap());
+
+    // or over the stdin and stdout of a child process, here this example is run again as a plugin
+    let mut plugin = Command::new(env::current_exe().unwrap());
+    plugin.arg("plugin");
+    let client = TodoService::async_client(StreamClient::spawn(plugin, Json).await.expect("failed to spawn plugin"));
+    client.new_todo(Todo {
+        name: "Plugin task".to_string(),
+        description: "Created in a child process".to_string(),
+    }).await.expect("new_todo failed");
+    println!("{:?}", client.get_todos().await.expect("get_todos failed"));
+}
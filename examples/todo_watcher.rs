@@ -0,0 +1,21 @@
+#![doc = include_str!("./examples.md")]
+
+use futures::StreamExt;
+use trait_rpc::client::websocket::WebsocketClient;
+use trait_rpc::format::json::Json;
+use trait_rpc::Rpc;
+
+include!("traits/todo.rs");
+
+#[tokio::main]
+async fn main() {
+    let client = TodoService::async_client(
+        WebsocketClient::new("ws://localhost:3000/api/todo".parse().unwrap(), Json)
+            .await
+            .expect("failed to connect"),
+    );
+    let mut todos = client.watch_todos().await.expect("watch_todos failed");
+    while let Some(todo) = todos.next().await {
+        println!("new to-do item: {:?}", todo.expect("failed to receive to-do item"));
+    }
+}
@@ -0,0 +1,19 @@
+use macros::rpc;
+
+#[rpc]
+/// A bank which keeps the accounts of its customers
+trait BankService {
+    /// The account with the given id, this can be opened as a handle so that the account is only
+    /// looked up once
+    #[rpc(handle)]
+    fn account(&self, id: u64) -> impl AccountService;
+}
+
+#[rpc]
+/// A single bank account
+trait AccountService {
+    /// The current balance of the account
+    fn balance(&self) -> i64;
+    /// Add the given amount to the account, returns the new balance
+    fn deposit(&self, amount: i64) -> i64;
+}
@@ -0,0 +1,25 @@
+use macros::rpc;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(crate = "::trait_rpc::serde")]
+struct Message {
+    author: String,
+    text: String,
+}
+
+#[rpc]
+/// A chat room shared by every connected client
+trait ChatService {
+    /// Send a message to the room, returns the number of clients it was sent to
+    fn send(&self, message: Message) -> usize;
+    /// Each message sent to the room
+    #[rpc(topic)]
+    fn messages(&self) -> Message;
+    /// Tell the room that the given author is typing, this does not wait for a reply
+    #[rpc(notify)]
+    fn typing(&self, author: String);
+    /// The author of each typing notification
+    #[rpc(topic)]
+    fn typing_authors(&self) -> String;
+}
@@ -0,0 +1,17 @@
+use macros::rpc;
+use trait_rpc::server::Context;
+
+#[rpc]
+/// A service for deleting files, which asks the caller to confirm each deletion
+trait FileService {
+    /// Delete the file with the given name once the caller has confirmed it, returns true if the
+    /// file was deleted
+    fn delete_file(&self, #[context] ctx: &Context, name: String) -> bool;
+}
+
+#[rpc]
+/// A service provided by the caller of the file service, to ask the user for confirmation
+trait ConfirmService {
+    /// Ask the user to confirm the given prompt, returns true if they agreed
+    fn confirm(&self, prompt: String) -> bool;
+}
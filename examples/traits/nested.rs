@@ -1,4 +1,5 @@
 use trait_rpc::rpc;
+use trait_rpc::server::Context;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, serde::Deserialize, serde::Serialize)]
 pub struct LoginToken(String);
@@ -38,6 +39,12 @@ pub struct UserNotFound;
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct LoginExpired;
 
+impl std::fmt::Display for LoginExpired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the login has expired")
+    }
+}
+
 
 
 
@@ -45,25 +52,25 @@ pub struct LoginExpired;
 
 
 
-#[rpc]
+#[rpc(pipeline)]
 pub trait ApiService {
     fn users(&self) -> impl UsersService;
-    fn login(&self, username: String, password: String) -> Option<LoginToken>;
+    fn login(&self, #[context] ctx: &Context, username: String, password: String) -> Option<LoginToken>;
 }
 
-#[rpc]
+#[rpc(pipeline)]
 pub trait UsersService {
     fn new(&self, user: NewUser) -> User;
     fn list(&self) -> Vec<User>;
-    fn by_id(&self, id: u64) -> impl UserService;
-    fn current(&self, token: LoginToken) -> impl UserService;
+    fn by_id(&self, id: u64) -> Option<impl UserService>;
+    fn current(&self, token: LoginToken) -> Result<impl UserService, LoginExpired>;
 }
 
-#[rpc]
+#[rpc(error = UserNotFound, pipeline)]
 pub trait UserService {
-    fn get(&self) -> Result<User, UserNotFound>;
-    fn update(&self, user: UserUpdate) -> Result<User, UserNotFound>;
-    fn delete(&self) -> Result<User, UserNotFound>;
+    fn get(&self) -> User;
+    fn update(&self, user: UserUpdate) -> User;
+    fn delete(&self) -> User;
 }
 
 // include expanded form here for debugging:
@@ -13,11 +13,17 @@ struct Todo {
 /// A service for managing to-do items
 trait TodoService {
     /// Get a list of to-do items
+    #[rpc(idempotent)]
     fn get_todos(&self) -> Vec<Todo>;
     /// Get a to-do item by name, returns None if no to-do item with the given name exists
+    #[rpc(idempotent)]
     fn get_todo(&self, name: String) -> Option<Todo>;
     /// Create a new to-do item
     fn new_todo(&self, todo: Todo);
+    /// Receive each new to-do item as it is created
+    fn watch_todos(&self) -> impl futures::Stream<Item = Todo>;
+    /// Create each of the given to-do items, returns the number of items created
+    fn import_todos(&self, todos: impl futures::Stream<Item = Todo>) -> usize;
 }
 
 // include!("../../macros_impl/src/tests/outputs/simple.rs");
\ No newline at end of file
@@ -13,8 +13,8 @@ mod output;
 /// 
 /// # Errors
 /// Can return a [`syn::Error`] if it fails to parse the input or rejects some part of the input
-pub fn rpc(_args: TokenStream, input: ItemTrait) -> syn::Result<impl ToTokens> {
-    let parser = Parser;
+pub fn rpc(args: TokenStream, input: ItemTrait) -> syn::Result<impl ToTokens> {
+    let parser = Parser::new(args)?;
     parser.rpc(input)
 }
 
@@ -24,17 +24,64 @@ struct Rpc {
     generics: Generics,
     name: Ident,
     methods: Vec<Method>,
+    /// Whether calls to the service can be pipelined, see `trait_rpc::pipeline`
+    pipeline: bool,
 }
 
+#[allow(clippy::struct_excessive_bools, reason = "Each of these is a separate option of the method, they are not states")]
 struct Method {
     docs: Vec<Expr>,
     name: Ident,
     args: Vec<PatType>,
+    stream_arg: Option<StreamArg>,
+    context: Option<ContextArg>,
     ret: ReturnType,
+    /// The application error type returned by this method, if any
+    error: Option<Type>,
+    /// Whether the method can safely be called more than once, eg: when retrying a failed call
+    idempotent: bool,
+    /// Whether the method is a topic, its return type is then a stream of each event published to
+    /// the topic, see `trait_rpc::server::topics`
+    topic: bool,
+    /// Whether the nested service can be opened as a handle, which keeps the nested handler on the
+    /// server, see `trait_rpc::server::objects`
+    handle: bool,
+    /// Whether the method is a notification, the client does not wait for a response and the
+    /// server does not send one
+    notify: bool,
+}
+
+/// An `impl Stream<Item = T>` argument, this is not part of the request itself, instead the items
+/// are sent after the request
+struct StreamArg {
+    /// The position of this argument among the method's arguments
+    index: usize,
+    arg: PatType,
+    item: Type,
+}
+
+/// A `&Context` argument, this is not part of the request, instead the server passes the context
+/// of the request
+struct ContextArg {
+    /// The position of this argument among the method's arguments
+    index: usize,
+    arg: PatType,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 enum ReturnType {
     Simple(Type),
-    Nested { service: Path }
+    Nested { service: Path, lookup: Lookup },
+    Stream { item: Type },
+}
+
+/// How the server looks up a nested service
+#[derive(Debug, PartialEq, Eq)]
+enum Lookup {
+    /// `impl Service`, the service always exists
+    Infallible,
+    /// `Option<impl Service>`
+    Option,
+    /// `Result<impl Service, E>`, the error is shown to the client
+    Result(Box<Type>),
 }
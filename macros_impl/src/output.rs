@@ -1,8 +1,8 @@
-use crate::{ReturnType, Rpc};
+use crate::{Lookup, Method, ReturnType, Rpc, StreamArg};
 use convert_case::ccase;
 use proc_macro2::{Ident, TokenStream};
 use quote::{ToTokens, format_ident, quote};
-use syn::{Field, FieldMutability, Visibility, parse_quote};
+use syn::{Field, FieldMutability, PatType, Type, Visibility, parse_quote};
 
 macro_rules! ident_ccase {
     ($case:ident, $ident:expr) => {
@@ -44,6 +44,10 @@ impl ToTokens for Rpc {
         let async_client = format_ident!("{}AsyncClient", service);
         let blocking_client = format_ident!("{}BlockingClient", service);
         let handler = format_ident!("{}Handler", service);
+        let pipeline = self.pipeline.then(|| {
+            let pipeline = format_ident!("{}Pipeline", service);
+            quote!(, #pipeline)
+        });
 
         let imports = {
             let vis = &self.vis;
@@ -53,11 +57,12 @@ impl ToTokens for Rpc {
                     #async_client,
                     #blocking_client,
                     #server
+                    #pipeline
                 };
             )
         };
 
-        let request_variants = self.methods.iter().map(|method| {
+        let request_variants = self.methods.iter().flat_map(|method| {
             let snake_name = method.name.to_string();
             let name = ident_ccase!(pascal, method.name);
             let mut fields: Vec<_> = method
@@ -73,86 +78,314 @@ impl ToTokens for Rpc {
                 })
                 .collect();
             if let ReturnType::Nested {
-                service: ret,
+                service: ret, ..
             } = &method.ret
             {
                 fields.push(parse_quote! {
                     <#ret as Rpc>::Request
                 });
             }
-            quote!(
+            let variant = quote!(
                 #[serde(rename = #snake_name)]
                 #name(#(#fields),*)
-            )
-        });
+            );
+            let item_variant = method.stream_arg.as_ref().map(|stream_arg| {
+                let item_name = format!("{snake_name}.item");
+                let item_variant = method.item_variant();
+                let item = &stream_arg.item;
+                quote!(
+                    #[serde(rename = #item_name)]
+                    #item_variant(#item)
+                )
+            });
+            // a handle is opened with the arguments of the method, then each request to the nested
+            // service is sent to the object with the id returned by the server
+            let handle_variants = method.handle_service().map(|(service, _)| {
+                let open_name = format!("{snake_name}.open");
+                let object_name = format!("{snake_name}.object");
+                let open_variant = method.open_variant();
+                let object_variant = method.object_variant();
+                let args = &fields[..fields.len() - 1];
+                quote!(
+                    #[serde(rename = #open_name)]
+                    #open_variant(#(#args),*),
+                    #[serde(rename = #object_name)]
+                    #object_variant(u64, <#service as Rpc>::Request)
+                )
+            });
+            std::iter::once(variant).chain(item_variant).chain(handle_variants)
+        }).chain(self.pipeline.then(|| quote!(
+            #[serde(rename = "pipeline")]
+            Pipeline(Vec<Call>)
+        )));
 
-        let response_variants = self.methods.iter().map(|method| {
+        let response_variants = self.methods.iter().flat_map(|method| {
             let snake_name = method.name.to_string();
             let name = ident_ccase!(pascal, method.name);
             let ret = match &method.ret {
-                ReturnType::Simple(ty) => ty.clone(),
+                ReturnType::Simple(ty) | ReturnType::Stream { item: ty } => method.with_error(ty),
                 ReturnType::Nested {
-                    service: path,
+                    service: path, lookup,
                 } => {
-                    parse_quote!(<#path as Rpc>::Response)
+                    lookup.wrap_type(&parse_quote!(<#path as Rpc>::Response))
                 }
             };
-            quote!(
+            let variant = quote!(
                 #[serde(rename = #snake_name)]
                 #name(#ret)
-            )
-        });
-        let response_to_name = self.methods.iter().map(|method| {
+            );
+            // the responses of an object reuse the variant of the nested method
+            let open_variant = method.handle.then(|| {
+                let open_name = format!("{snake_name}.open");
+                let open_variant = method.open_variant();
+                quote!(
+                    #[serde(rename = #open_name)]
+                    #open_variant(u64)
+                )
+            });
+            std::iter::once(variant).chain(open_variant)
+        }).chain(self.pipeline.then(|| quote!(
+            #[serde(rename = "pipeline")]
+            Pipeline(Vec<Option<Response>>)
+        )));
+        let request_to_name = self.methods.iter().flat_map(|method| {
             let name = method.name.to_string();
             let variant = ident_ccase!(pascal, method.name);
-            quote!(Self::#variant(..) => #name)
-        });
+            let item = method.stream_arg.as_ref().map(|_| {
+                let item_name = format!("{name}.item");
+                let item_variant = method.item_variant();
+                quote!(Self::#item_variant(..) => #item_name)
+            });
+            let handle = method.handle.then(|| {
+                let open_name = format!("{name}.open");
+                let object_name = format!("{name}.object");
+                let open_variant = method.open_variant();
+                let object_variant = method.object_variant();
+                quote!(Self::#open_variant(..) => #open_name, Self::#object_variant(..) => #object_name)
+            });
+            std::iter::once(quote!(Self::#variant(..) => #name)).chain(item).chain(handle)
+        }).chain(self.pipeline.then(|| quote!(Self::Pipeline(..) => "pipeline")));
+        let response_to_name = self.methods.iter().flat_map(|method| {
+            let name = method.name.to_string();
+            let variant = ident_ccase!(pascal, method.name);
+            let open = method.handle.then(|| {
+                let open_name = format!("{name}.open");
+                let open_variant = method.open_variant();
+                quote!(Self::#open_variant(..) => #open_name)
+            });
+            std::iter::once(quote!(Self::#variant(..) => #name)).chain(open)
+        }).chain(self.pipeline.then(|| quote!(Self::Pipeline(..) => "pipeline")));
 
-        let server_fns = self.methods.iter().map(|method| {
+        // topics are published to rather than served, so they are not part of the server trait
+        let server_fns = self.methods.iter().filter(|method| !method.topic).map(|method| {
             let name = &method.name;
-            let params = &method.args;
+            let stream_ty: Option<Type> = method.stream_arg.as_ref().map(|stream_arg| {
+                let item = &stream_arg.item;
+                parse_quote!(impl ::trait_rpc::futures::Stream<Item = #item> + Send + Unpin)
+            });
+            let params = method.params(stream_ty.as_ref(), true);
             let docs = &method.docs;
             let docs = quote! {
                 #(#[doc = #docs])*
             };
             match &method.ret {
                 ReturnType::Simple(ret) => {
+                    let ret = method.with_error(ret);
                     quote! {
                         #docs
                         fn #name(&self #(,#params)*) -> impl Future<Output=#ret> + Send;
                     }
                 }
-                ReturnType::Nested { service: path } => {
+                ReturnType::Nested { service: path, lookup } => {
+                    // the handler of a handle is shared by every call made to it at once
+                    let handler: Type = if method.handle {
+                        parse_quote!(impl Handler<Rpc = #path> + Sync)
+                    } else {
+                        parse_quote!(impl Handler<Rpc = #path>)
+                    };
+                    let output = lookup.wrap_type(&handler);
+                    quote! {
+                        #docs
+                        fn #name(&self #(,#params)*) -> impl Future<Output = #output> + Send;
+                    }
+                }
+                ReturnType::Stream { item } => {
+                    let item = method.with_error(item);
                     quote! {
                         #docs
-                        fn #name(&self #(,#params)*) -> impl Future<Output = impl Handler<Rpc = #path>> + Send;
+                        fn #name(&self #(,#params)*) -> impl Future<Output = impl ::trait_rpc::futures::Stream<Item = #item> + Send> + Send;
                     }
                 }
             }
         });
-        let handle_arms = self.methods.iter().map(|method| {
+        let mut handle_arms: Vec<_> = self.methods.iter().flat_map(|method| {
             let name = &method.name;
             let variant = ident_ccase!(pascal, method.name);
             let params = method.args.iter().map(|pat| &pat.pat).collect::<Vec<_>>();
-            match &method.ret {
-                ReturnType::Nested { service: _ } => {
+            let args = method.arg_values();
+            let arm = match &method.ret {
+                ReturnType::Nested { lookup: Lookup::Infallible, .. } => {
                     quote! {
                         Request::#variant(#(#params, )*request) => {
-                            let response = self.0.#name(#(#params),*).await.handle(request).await;
-                            Response::#variant(response)
+                            self.0.#name(#(#args),*).await.handle(ctx, request).await.map(Response::#variant)
                         },
                     }
                 }
+                ReturnType::Nested { lookup, .. } => {
+                    let wrap = lookup.wrap(&variant);
+                    let handle = lookup.match_handler(
+                        &quote!(self.0.#name(#(#args),*).await),
+                        &quote!(handler.handle(ctx, request).await.map(#wrap)),
+                        |missing| quote!(Ok(Response::#variant(#missing))),
+                    );
+                    quote! {
+                        Request::#variant(#(#params, )*request) => #handle,
+                    }
+                }
+                ReturnType::Simple(_) if method.stream_arg.is_some() => {
+                    let message = format!("`{name}` takes a stream argument, requests for it must be passed to `Handler::handle_stream`");
+                    quote! {
+                        Request::#variant(..) => Err(HandlerError::Unsupported(#message.to_string())),
+                    }
+                }
                 ReturnType::Simple(_) => {
                     quote! {
-                    Request::#variant(#(#params),*) => Response::#variant(self.0.#name(#(#params),*).await),
+                    Request::#variant(#(#params),*) => Ok(Response::#variant(self.0.#name(#(#args),*).await)),
                 }
                 }
+                ReturnType::Stream { .. } => {
+                    let message = format!("`{name}` is a streaming method, requests for it must be passed to `Handler::handle_stream`");
+                    quote! {
+                        Request::#variant(..) => Err(HandlerError::Unsupported(#message.to_string())),
+                    }
+                }
+            };
+            let handle = method.handle_service().map(|(service, lookup)| {
+                let wrap = lookup.wrap(&variant);
+                let message = format!("`{name}` is opened as a handle, requests to open it must be passed to `Handler::handle_stream`");
+                let open_variant = method.open_variant();
+                let object_variant = method.object_variant();
+                quote! {
+                    Request::#open_variant(..) => Err(HandlerError::Unsupported(#message.to_string())),
+                    Request::#object_variant(object, request) => {
+                        ::trait_rpc::server::objects::call::<#service>(ctx, object, request).await.map(#wrap)
+                    },
+                }
+            });
+            std::iter::once(arm).chain(handle)
+        }).collect();
+        if let Some(items) = self.item_pattern() {
+            handle_arms.push(quote! {
+                #items => Err(HandlerError::Unsupported("stream items are not requests, they must be passed to `Handler::handle_stream` as incoming items".to_string())),
+            });
+        }
+        if self.pipeline {
+            handle_arms.push(quote! {
+                Request::Pipeline(calls) => Ok(Response::Pipeline(::trait_rpc::pipeline::run(self, ctx, calls).await?)),
+            });
+        }
+        let handle_stream_arms = self.handle_stream_arms();
+        let incoming = if self.methods.iter().any(|method| method.stream_arg.is_some() || matches!(method.ret, ReturnType::Nested { .. })) {
+            quote!(incoming)
+        } else {
+            quote!(_incoming)
+        };
+        // the context is passed to nested services, to any method which takes it and to each call of
+        // a pipeline
+        let handle_ctx = if self.pipeline || self.methods.iter().any(|method| match method.ret {
+            ReturnType::Simple(_) => method.stream_arg.is_none() && method.context.is_some(),
+            ReturnType::Stream { .. } => false,
+            ReturnType::Nested { .. } => true,
+        }) {
+            quote!(ctx)
+        } else {
+            quote!(_ctx)
+        };
+        let handle_stream_ctx = if self.pipeline || self.methods.iter().any(|method| match method.ret {
+            // requests for simple methods are passed to `handle`
+            ReturnType::Simple(_) => method.stream_arg.is_none() || method.context.is_some(),
+            ReturnType::Stream { .. } => method.context.is_some() || method.topic,
+            ReturnType::Nested { .. } => true,
+        }) {
+            quote!(ctx)
+        } else {
+            quote!(_ctx)
+        };
+        // a handle stays open as a streaming request
+        let is_stream = self.request_info("is_stream", |method| {
+            if method.handle {
+                let variant = method.open_variant();
+                Some(quote!(Self::#variant(..)))
+            } else {
+                matches!(method.ret, ReturnType::Stream { .. }).then(|| {
+                    let variant = ident_ccase!(pascal, method.name);
+                    quote!(Self::#variant(..))
+                })
+            }
+        });
+        let is_client_stream = self.request_info("is_client_stream", |method| {
+            method.stream_arg.as_ref().map(|_| {
+                let variant = ident_ccase!(pascal, method.name);
+                quote!(Self::#variant(..))
+            })
+        });
+        let is_stream_item = self.request_info("is_stream_item", |method| {
+            method.stream_arg.as_ref().map(|_| {
+                let variant = method.item_variant();
+                quote!(Self::#variant(..))
+            })
+        });
+        let is_idempotent = self.request_info("is_idempotent", |method| {
+            method.idempotent.then(|| {
+                let variant = ident_ccase!(pascal, method.name);
+                quote!(Self::#variant(..))
+            })
+        });
+        let is_notification = self.request_info("is_notification", |method| {
+            method.notify.then(|| {
+                let variant = ident_ccase!(pascal, method.name);
+                quote!(Self::#variant(..))
+            })
+        });
+
+        // the return type of a topic is a stream of each event
+        let publish_fns = self.methods.iter().filter_map(|method| match &method.ret {
+            ReturnType::Stream { item } if method.topic => Some((method, item)),
+            _ => None,
+        }).map(|(method, item)| {
+            let name = method.name.to_string();
+            let publish = format_ident!("publish_{}", method.name);
+            let variant = ident_ccase!(pascal, method.name);
+            let docs = &method.docs;
+            quote! {
+                #(#[doc = #docs])*
+                ///
+                /// Publish an event to every client subscribed to this topic, returns the number of
+                /// subscribers it was sent to
+                pub fn #publish(topics: &::trait_rpc::server::topics::Topics<Self>, event: #item) -> usize {
+                    topics.publish(#name, || Response::#variant(event.clone()))
+                }
             }
         });
 
-        let async_client_fns = self.client_fns(true);
-        let blocking_client_fns = self.client_fns(false);
+        let async_client_fns = self.client_fns(true).chain(self.pipeline_fn(true));
+        let blocking_client_fns = self.client_fns(false).chain(self.pipeline_fn(false));
+        let pipeline_items = self.pipeline_items();
+        // a layered handler makes each call of a pipeline itself, so that its layer sees them
+        let handle_pipeline = self.pipeline.then(|| quote! {
+            fn handle_pipeline<'a, _Handler: Handler<Rpc = Self> + Sync>(
+                handler: &'a _Handler,
+                ctx: &'a ::trait_rpc::server::Context,
+                request: Self::Request,
+            ) -> Result<::trait_rpc::futures::future::BoxFuture<'a, Result<Self::Response, HandlerError>>, Self::Request> {
+                match request {
+                    Request::Pipeline(calls) => Ok(Box::pin(async move {
+                        Ok(Response::Pipeline(::trait_rpc::pipeline::run(handler, ctx, calls).await?))
+                    })),
+                    request => Err(request),
+                }
+            }
+        });
 
         quote! {
             #[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
@@ -163,9 +396,11 @@ impl ToTokens for Rpc {
                 use super::*;
                 use ::trait_rpc::{
                     Rpc,
-                    client::{AsyncClient, BlockingClient, MappedClient, WrongResponseType},
+                    RequestInfo,
+                    client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+                    futures::{SinkExt as _, StreamExt as _},
                     serde::{Deserialize, Serialize},
-                    server::Handler,
+                    server::{Handler, HandlerError, MappedSink, filter_incoming},
                 };
                 use std::marker::PhantomData;
 
@@ -187,6 +422,7 @@ impl ToTokens for Rpc {
                     fn blocking_client<_Client: BlockingClient<Request #generics, Response #generics>>(transport: _Client) -> #blocking_client<_Client #(,#gen_params)*> {
                         #blocking_client(transport, #phantom_data_new)
                     }
+                    #handle_pipeline
                 }
 
                 impl<#(#gen_params: Send + 'static),*> #service #generics {
@@ -194,10 +430,12 @@ impl ToTokens for Rpc {
                     pub fn server(server: impl #server #generics) -> impl Handler<Rpc = Self> {
                         #handler(server, #phantom_data_new)
                     }
+
+                    #(#publish_fns)*
                 }
 
 
-                #[derive(Debug, Serialize, Deserialize)]
+                #[derive(Debug, Clone, Serialize, Deserialize)]
                 #[serde(crate = "::trait_rpc::serde")]
                 #[serde(tag = "method", content = "args")]
                 pub enum Request #generics {
@@ -219,6 +457,34 @@ impl ToTokens for Rpc {
                     }
                 }
 
+                impl #generics RequestInfo for Request #generics {
+                    fn fn_name(&self) -> &'static str {
+                        match self {
+                            #(#request_to_name),*
+                        }
+                    }
+                    #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+                    fn is_stream(&self) -> bool {
+                        #is_stream
+                    }
+                    #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+                    fn is_client_stream(&self) -> bool {
+                        #is_client_stream
+                    }
+                    #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+                    fn is_stream_item(&self) -> bool {
+                        #is_stream_item
+                    }
+                    #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+                    fn is_idempotent(&self) -> bool {
+                        #is_idempotent
+                    }
+                    #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+                    fn is_notification(&self) -> bool {
+                        #is_notification
+                    }
+                }
+
                 #(
                     #(#[doc = #docs])*
                     ///
@@ -233,11 +499,21 @@ impl ToTokens for Rpc {
                 pub struct #handler<_Server #(,#gen_params)*>(_Server, #phantom_data);
                 impl<_Server: #server #generics #(, #gen_params: Send + 'static)*> Handler for #handler<_Server #(,#gen_params)*> {
                     type Rpc = #service #generics;
-                    async fn handle(&self, request: Request #generics) -> Response #generics {
+                    async fn handle(&self, #handle_ctx: &::trait_rpc::server::Context, request: Request #generics) -> Result<Response #generics, HandlerError> {
                         match request {
                             #(#handle_arms)*
                         }
                     }
+                    async fn handle_stream<_Incoming, _Sink>(&self, #handle_stream_ctx: &::trait_rpc::server::Context, request: Request #generics, #incoming: _Incoming, mut sink: _Sink) -> Result<(), HandlerError>
+                    where
+                        _Incoming: ::trait_rpc::futures::Stream<Item = Request #generics> + Send + Unpin,
+                        _Sink: ::trait_rpc::futures::Sink<Response #generics> + Send + Unpin,
+                    {
+                        match request {
+                            #(#handle_stream_arms)*
+                        }
+                        Ok(())
+                    }
                 }
 
                 #(
@@ -270,6 +546,8 @@ impl ToTokens for Rpc {
                 impl<_Client: BlockingClient<Request #generics, Response #generics> #(, #gen_params)*> #blocking_client<_Client #(,#gen_params)*> {
                     #(#blocking_client_fns)*
                 }
+
+                #pipeline_items
             }
         }
     }
@@ -293,8 +571,7 @@ impl Rpc {
         };
         self.methods.iter().map(move |method| {
             let name = &method.name;
-            let name_str = name.to_string();
-            let params = &method.args;
+            let params = method.client_params(is_async);
             let args = method.args.iter().map(|pat| &pat.pat);
             let variant = ident_ccase!(pascal, name);
             let docs = &method.docs;
@@ -307,44 +584,769 @@ impl Rpc {
                 format_ident!("BlockingClient")
             };
             let new_client = ident_ccase!(snake, client);
+            let ClientError { error, rpc_error, value, wrong_response } = method.client_error();
             match &method.ret {
+                // the server does not reply to a notification, so there is no response to check
+                ReturnType::Simple(_) if method.notify => quote! {
+                    #docs
+                    pub #(#async_)* fn #name(&self #(, #params)*) -> Result<(), #error> {
+                        self.0.notify(Request::#variant(#(#args),*)) #(#await_)*
+                    }
+                },
                 ReturnType::Simple(ret) => {
+                    let send = if let Some(stream_arg) = &method.stream_arg {
+                        let stream = &stream_arg.arg.pat;
+                        let item_variant = method.item_variant();
+                        let items = if is_async {
+                            quote!(#stream.map(Request::#item_variant))
+                        } else {
+                            quote!(#stream.into_iter().map(Request::#item_variant))
+                        };
+                        quote!(send_with_stream(Request::#variant(#(#args),*), #items))
+                    } else {
+                        quote!(send(Request::#variant(#(#args),*)))
+                    };
                     quote! {
                         #docs
-                        pub #(#async_)* fn #name(&self #(, #params)*) -> Result<#ret, _Client::Error> {
-                            match self.0.send(Request::#variant(#(#args),*))#(#await_)*? {
-                                Response::#variant(value) => Ok(value),
-                                other => Err(WrongResponseType::new(#name_str, other.fn_name()).into()),
+                        pub #(#async_)* fn #name(&self #(, #params)*) -> Result<#ret, #error> {
+                            match self.0.#send #(#await_)* #rpc_error? {
+                                Response::#variant(value) => #value,
+                                other => #wrong_response,
                             }
                         }
                     }
                 }
-                ReturnType::Nested { service: nested } => { // TODO account for sub-service error
+                ReturnType::Stream { item } => {
+                    let stream = if is_async {
+                        quote!(::trait_rpc::futures::Stream)
+                    } else {
+                        quote!(Iterator)
+                    };
+                    quote! {
+                        #docs
+                        pub #(#async_)* fn #name(&self #(, #params)*) -> Result<impl #stream<Item = Result<#item, #error>>, #error> {
+                            let responses = self.0.send_stream(Request::#variant(#(#args),*)) #(#await_)* #rpc_error?;
+                            Ok(responses.map(|response| match response #rpc_error? {
+                                Response::#variant(value) => #value,
+                                other => #wrong_response,
+                            }))
+                        }
+                    }
+                }
+                ReturnType::Nested { service: nested, lookup } => {
                     let to_inner = format_ident!("{name}_to_inner");
                     let to_outer = format_ident!("{name}_to_outer");
                     let variant = ident_ccase!(pascal, name);
                     let args = method.args.iter().map(|pat| &pat.pat).collect::<Vec<_>>();
                     let types = method.args.iter().map(|pat| &pat.ty).collect::<Vec<_>>();
+                    // a handle needs a connection which stays open, so only the async client can open one
+                    let open = if is_async { method.open_fn(nested) } else { None };
+                    let to_inner_fn = method.to_inner_fn(nested, lookup);
                     quote! {
                         #docs
                         pub fn #name(&self #(, #params)*) -> <#nested as Rpc>::#client<MappedClient<_Client, <#nested as Rpc>::Request, Request, <#nested as Rpc>::Response, Response, (#(#types,)*)>> {
                             #nested::#new_client(MappedClient::new(self.0.clone(), (#(#args,)*), Self::#to_inner, Self::#to_outer))
                         }
 
-                        fn #to_inner(outer: Result<Response, WrongResponseType>) -> Result<<#nested as Rpc>::Response, WrongResponseType> {
-                            match outer {
-                                Ok(Response::#variant(inner)) => Ok(inner),
-                                Ok(other) => Err(WrongResponseType::new(#name_str, other.fn_name()).into()),
-                                Err(err) => Err(err.in_subservice(#name_str)),
-                            }
-                        }
+                        #to_inner_fn
 
                         fn #to_outer((#(#args,)*): (#(#types,)*), inner: <#nested as Rpc>::Request) -> Request {
                             Request::#variant(#(#args,)*inner)
                         }
+
+                        #open
                     }
                 }
             }
         })
     }
+
+    /// The match arms of the generated `Handler::handle_stream`
+    fn handle_stream_arms(&self) -> Vec<TokenStream> {
+        let mut arms = Vec::new();
+        let mut simple = Vec::new();
+        for method in &self.methods {
+            let name = &method.name;
+            let variant = ident_ccase!(pascal, method.name);
+            let params = method.args.iter().map(|pat| &pat.pat).collect::<Vec<_>>();
+            let args = method.arg_values();
+            match &method.ret {
+                ReturnType::Simple(_) => if let Some(stream_arg) = &method.stream_arg {
+                    let stream = &stream_arg.arg.pat;
+                    let item_variant = method.item_variant();
+                    arms.push(quote! {
+                        Request::#variant(#(#params),*) => {
+                            let #stream = filter_incoming(incoming, |request| match request {
+                                Request::#item_variant(item) => Some(item),
+                                _ => None,
+                            });
+                            let _ = sink.send(Response::#variant(self.0.#name(#(#args),*).await)).await;
+                        }
+                    });
+                } else {
+                    simple.push(quote!(Request::#variant(..)));
+                },
+                ReturnType::Stream { .. } if method.topic => {
+                    let name = name.to_string();
+                    let message = format!("`{name}` is a topic, but there is no `Topics` for the service in the context");
+                    arms.push(quote! {
+                        Request::#variant() => {
+                            let Some(topics) = ctx.get::<::trait_rpc::server::topics::Topics<<Self as Handler>::Rpc>>() else {
+                                return Err(HandlerError::Other(#message.to_string()));
+                            };
+                            let mut events = topics.subscribe(#name).map(Ok);
+                            let _ = sink.send_all(&mut events).await;
+                        }
+                    });
+                }
+                ReturnType::Stream { .. } => arms.push(quote! {
+                    Request::#variant(#(#params),*) => {
+                        let responses = self.0.#name(#(#args),*).await.map(|value| Ok(Response::#variant(value)));
+                        let _ = sink.send_all(&mut ::std::pin::pin!(responses)).await;
+                    }
+                }),
+                ReturnType::Nested { service, lookup } => {
+                    // with a single variant the pattern is irrefutable, so a match would be unreachable
+                    let irrefutable = self.methods.len() == 1 && !method.handle;
+                    arms.extend(method.nested_stream_arms(service, lookup, irrefutable));
+                }
+            }
+        }
+        if let Some(items) = self.item_pattern() {
+            // items without a matching request are ignored
+            arms.push(quote!(#items => {}));
+        }
+        if self.pipeline {
+            simple.push(quote!(Request::Pipeline(..)));
+        }
+        if !simple.is_empty() {
+            let pattern = if simple.len() == 1 {
+                quote!(#(#simple)*)
+            } else {
+                quote!((#(#simple)|*))
+            };
+            arms.push(quote! {
+                request @ #pattern => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            });
+        }
+        arms
+    }
+
+    /// The pattern matching any stream item of this service, if any method takes a stream argument
+    fn item_pattern(&self) -> Option<TokenStream> {
+        let items: Vec<_> = self
+            .methods
+            .iter()
+            .filter(|method| method.stream_arg.is_some())
+            .map(|method| {
+                let variant = method.item_variant();
+                quote!(Request::#variant(..))
+            })
+            .collect();
+        if items.is_empty() {
+            None
+        } else {
+            Some(quote!(#(#items)|*))
+        }
+    }
+
+    /// The body of a generated `RequestInfo` method named `info`, `pattern` returns the pattern of
+    /// a request which should return true (if any) for a given method, nested requests are delegated
+    /// to the nested service
+    fn request_info(&self, info: &str, pattern: impl Fn(&Method) -> Option<TokenStream>) -> TokenStream {
+        let info = Ident::new(info, proc_macro2::Span::call_site());
+        let mut nested = Vec::new();
+        let mut patterns = Vec::new();
+        for method in &self.methods {
+            if let ReturnType::Nested { .. } = &method.ret {
+                let variant = ident_ccase!(pascal, method.name);
+                nested.push(quote!(Self::#variant(.., request) => request.#info()));
+                if method.handle {
+                    let object_variant = method.object_variant();
+                    nested.push(quote!(Self::#object_variant(.., request) => request.#info()));
+                }
+            }
+            if let Some(pattern) = pattern(method) {
+                patterns.push(pattern);
+            }
+        }
+        if nested.is_empty() {
+            return if patterns.is_empty() {
+                quote!(false)
+            } else {
+                quote!(matches!(self, #(#patterns)|*))
+            };
+        }
+        let variants = self.methods.len()
+            + self.methods.iter().filter(|method| method.stream_arg.is_some()).count()
+            + 2 * self.methods.iter().filter(|method| method.handle).count()
+            + usize::from(self.pipeline);
+        let fallback = if nested.len() == variants {
+            None
+        } else if patterns.is_empty() {
+            Some(quote!(_ => false))
+        } else {
+            Some(quote!(other => matches!(other, #(#patterns)|*)))
+        };
+        quote! {
+            match self {
+                #(#nested,)*
+                #fallback
+            }
+        }
+    }
+}
+
+impl Rpc {
+    /// The `pipeline` method of a client, which sends each call made by the given closure as a
+    /// single request, if the service can be pipelined
+    fn pipeline_fn(&self, is_async: bool) -> Option<TokenStream> {
+        if !self.pipeline {
+            return None;
+        }
+        let service = &self.name;
+        let pipeline = format_ident!("{}Pipeline", service);
+        let (async_, await_) = if is_async {
+            (Some(quote!(async)), Some(quote!(.await)))
+        } else {
+            (None, None)
+        };
+        Some(quote! {
+            /// Send several calls to this service as a single request, `calls` makes each call with
+            /// the given pipeline and returns the result to wait for. The result of a call can be
+            /// passed as an argument to a later call, see [pipeline](::trait_rpc::pipeline)
+            pub #async_ fn pipeline<_Output: Clone + 'static>(
+                &self,
+                calls: impl FnOnce(#pipeline<&::trait_rpc::pipeline::Calls<Call>>) -> ::trait_rpc::pipeline::Pending<_Output>,
+            ) -> Result<_Output, ::trait_rpc::pipeline::PipelineError<_Client::Error>> {
+                let pipeline = ::trait_rpc::pipeline::Calls::default();
+                let result = calls(#pipeline(&pipeline));
+                match self.0.send(Request::Pipeline(pipeline.into_inner())) #await_ .map_err(::trait_rpc::pipeline::PipelineError::Rpc)? {
+                    Response::Pipeline(responses) => ::trait_rpc::pipeline::result::<#service, _, _>(&responses, result),
+                    other => Err(::trait_rpc::pipeline::PipelineError::Rpc(WrongResponseType::new("pipeline", other.fn_name()).into())),
+                }
+            }
+        })
+    }
+
+    /// The calls of a pipeline, the pipeline making them and the implementation of `Pipelined`, if
+    /// the service can be pipelined
+    fn pipeline_items(&self) -> Option<TokenStream> {
+        if !self.pipeline {
+            return None;
+        }
+        let service = &self.name;
+        let pipeline = format_ident!("{}Pipeline", service);
+        let docs = if self.docs.is_empty() {
+            None
+        } else {
+            Some(&self.docs)
+        }.into_iter();
+        let methods: Vec<_> = self.methods.iter().filter(|method| method.pipelined()).collect();
+        let call_variants = methods.iter().map(|method| {
+            let name = method.name.to_string();
+            let variant = ident_ccase!(pascal, method.name);
+            let types = method.args.iter().map(|pat| &pat.ty);
+            let nested = method.nested_call();
+            quote! {
+                #[serde(rename = #name)]
+                #variant(#(::trait_rpc::pipeline::Arg<#types>,)* #nested)
+            }
+        });
+        let resolve_arms = methods.iter().map(|method| {
+            let variant = ident_ccase!(pascal, method.name);
+            let params = method.args.iter().map(|pat| &pat.pat).collect::<Vec<_>>();
+            if let ReturnType::Nested { service: nested, .. } = &method.ret {
+                quote! {
+                    Call::#variant(#(#params, )*call) => Request::#variant(
+                        #(#params.resolve(results)?, )*
+                        <#nested as ::trait_rpc::pipeline::Pipelined>::resolve(call, results)?,
+                    ),
+                }
+            } else {
+                quote! {
+                    Call::#variant(#(#params),*) => Request::#variant(#(#params.resolve(results)?),*),
+                }
+            }
+        });
+        let results = if methods.iter().any(|method| !method.args.is_empty() || matches!(method.ret, ReturnType::Nested { .. })) {
+            quote!(results)
+        } else {
+            quote!(_results)
+        };
+        let (output_arms, unavailable): (Vec<_>, Vec<_>) = self.methods.iter().map(Method::output_arm).unzip();
+        let unavailable = unavailable.into_iter().flatten().chain(std::iter::once(quote!(Response::Pipeline(..))));
+        let output_arms = output_arms.into_iter().flatten();
+        let project = self.methods.iter().any(|method| matches!(method.ret, ReturnType::Simple(_)) && method.stream_arg.is_none()).then(|| quote! {
+            use ::trait_rpc::pipeline::{ProjectOption as _, ProjectResult as _, ProjectValue as _};
+        });
+        let projection = if methods.is_empty() {
+            quote!(_projection)
+        } else {
+            quote!(projection)
+        };
+        let pipeline_fns = methods.iter().map(|method| method.pipeline_fn());
+        Some(quote! {
+            /// A call to the service in a pipeline, this is a request whose arguments may be the
+            /// results of earlier calls, see [pipeline](::trait_rpc::pipeline)
+            #[derive(Debug, Clone, Serialize, Deserialize)]
+            #[serde(crate = "::trait_rpc::serde")]
+            #[serde(tag = "method", content = "args")]
+            pub enum Call {
+                #(#call_variants,)*
+            }
+
+            impl ::trait_rpc::pipeline::Pipelined for #service {
+                type Call = Call;
+                type Pipeline<_Calls: ::trait_rpc::pipeline::PipelineCalls<Call>> = #pipeline<_Calls>;
+                fn pipeline<_Calls: ::trait_rpc::pipeline::PipelineCalls<Call>>(calls: _Calls) -> #pipeline<_Calls> {
+                    #pipeline(calls)
+                }
+                fn resolve<_Root: ::trait_rpc::pipeline::Pipelined>(call: Call, #results: &::trait_rpc::pipeline::Results<'_, _Root>) -> Option<Request> {
+                    Some(match call {
+                        #(#resolve_arms)*
+                    })
+                }
+                fn output(response: &Response, #projection: ::trait_rpc::pipeline::Projection) -> Option<&dyn ::std::any::Any> {
+                    #project
+                    match response {
+                        #(#output_arms)*
+                        #(#unavailable)|* => None,
+                    }
+                }
+            }
+
+            #(
+                #(#[doc = #docs])*
+                ///
+            )*
+            /// This is the pipeline of calls to the service, each call returns its pending result
+            /// straight away, see [pipeline](::trait_rpc::pipeline)
+            #[derive(Debug, Copy, Clone)]
+            pub struct #pipeline<_Calls>(_Calls);
+            impl<_Calls: ::trait_rpc::pipeline::PipelineCalls<Call>> #pipeline<_Calls> {
+                #(#pipeline_fns)*
+            }
+        })
+    }
+}
+
+/// The parts of a generated client method which depend on whether it has an application error
+struct ClientError {
+    /// The error type returned by the client method
+    error: TokenStream,
+    /// Maps the error returned by the client transport
+    rpc_error: TokenStream,
+    /// Maps the value of a response from the server
+    value: TokenStream,
+    /// The error returned when the response is for the wrong method
+    wrong_response: TokenStream,
+}
+
+impl Method {
+    /// An application error is flattened together with the client error into a `CallError`
+    fn client_error(&self) -> ClientError {
+        let name = self.name.to_string();
+        self.error.as_ref().map_or_else(
+            || ClientError {
+                error: quote!(_Client::Error),
+                rpc_error: TokenStream::new(),
+                value: quote!(Ok(value)),
+                wrong_response: quote!(Err(WrongResponseType::new(#name, other.fn_name()).into())),
+            },
+            |error| ClientError {
+                error: quote!(CallError<#error, _Client::Error>),
+                rpc_error: quote!(.map_err(CallError::Rpc)),
+                value: quote!(value.map_err(CallError::Application)),
+                wrong_response: quote!(Err(CallError::Rpc(WrongResponseType::new(#name, other.fn_name()).into()))),
+            },
+        )
+    }
+
+    /// The type returned by the server, this is `Result<T, E>` if the method has an application error
+    fn with_error(&self, ty: &Type) -> Type {
+        self.error.as_ref().map_or_else(|| ty.clone(), |error| parse_quote!(Result<#ty, #error>))
+    }
+
+    /// The match arms of the generated `Handler::handle_stream` for a nested service, `irrefutable`
+    /// is true if the request has no other variants
+    fn nested_stream_arms(&self, service: &syn::Path, lookup: &Lookup, irrefutable: bool) -> Vec<TokenStream> {
+        let name = &self.name;
+        let variant = ident_ccase!(pascal, self.name);
+        let params = self.args.iter().map(|pat| &pat.pat).collect::<Vec<_>>();
+        let args = self.arg_values();
+        let mut arms = Vec::new();
+        let incoming = if irrefutable {
+            quote!(filter_incoming(incoming, |Request::#variant(.., request)| Some(request)))
+        } else {
+            quote! {
+                filter_incoming(incoming, |request| match request {
+                    Request::#variant(.., request) => Some(request),
+                    _ => None,
+                })
+            }
+        };
+        let lookup_handler = quote!(self.0.#name(#(#args),*).await);
+        // the client is sent a single response if the nested service was not found
+        let not_found = |missing| quote! {
+            let _ = sink.send(Response::#variant(#missing)).await;
+        };
+        let wrap = lookup.wrap(&variant);
+        let handle_stream = if *lookup == Lookup::Infallible {
+            quote! {
+                let sink = MappedSink::new(&mut sink, Response::#variant);
+                self.0.#name(#(#args),*).await.handle_stream(ctx, request, incoming, sink).await?;
+            }
+        } else {
+            let found = quote! {
+                let sink = MappedSink::new(&mut sink, #wrap);
+                handler.handle_stream(ctx, request, incoming, sink).await?;
+            };
+            lookup.match_handler(&lookup_handler, &found, not_found)
+        };
+        arms.push(quote! {
+            Request::#variant(#(#params, )*request) => {
+                let incoming = #incoming;
+                #handle_stream
+            }
+        });
+        if self.handle {
+            let open_variant = self.open_variant();
+            let object_variant = self.object_variant();
+            let found = quote! {
+                let sink = MappedSink::new(&mut sink, Response::#open_variant);
+                ::trait_rpc::server::objects::serve(ctx, handler, sink).await?;
+            };
+            let open = lookup.match_handler(&lookup_handler, &found, not_found);
+            arms.push(quote! {
+                Request::#open_variant(#(#params),*) => {
+                    #open
+                }
+                Request::#object_variant(object, request) => {
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::#object_variant(_, request) => Some(request),
+                        _ => None,
+                    });
+                    let sink = MappedSink::new(&mut sink, #wrap);
+                    ::trait_rpc::server::objects::call_stream::<#service, _, _>(ctx, object, request, incoming, sink).await?;
+                }
+            });
+        }
+        arms
+    }
+
+    /// The function of the client which maps the response of the outer service to the response of
+    /// the nested service, a nested service which was not found is a client error
+    fn to_inner_fn(&self, nested: &syn::Path, lookup: &Lookup) -> TokenStream {
+        let name = self.name.to_string();
+        let to_inner = format_ident!("{name}_to_inner");
+        let variant = ident_ccase!(pascal, self.name);
+        let found = lookup.found(&format_ident!("inner"));
+        let not_found = lookup.missing().map(|missing| {
+            let error = lookup.not_found_error(&name);
+            quote!(Ok(Response::#variant(#missing)) => Err(#error.into()),)
+        });
+        quote! {
+            fn #to_inner(outer: Result<Response, ::trait_rpc::client::NestedError>) -> Result<<#nested as Rpc>::Response, ::trait_rpc::client::NestedError> {
+                match outer {
+                    Ok(Response::#variant(#found)) => Ok(inner),
+                    #not_found
+                    Ok(other) => Err(WrongResponseType::new(#name, other.fn_name()).into()),
+                    Err(err) => Err(err.in_subservice(#name)),
+                }
+            }
+        }
+    }
+
+    /// The variant of the request used to send each item of the stream argument
+    fn item_variant(&self) -> Ident {
+        format_ident!("{}Item", ident_ccase!(pascal, self.name))
+    }
+
+    /// The nested service and how it is looked up, if it can be opened as a handle
+    const fn handle_service(&self) -> Option<(&syn::Path, &Lookup)> {
+        match &self.ret {
+            ReturnType::Nested { service, lookup } if self.handle => Some((service, lookup)),
+            _ => None,
+        }
+    }
+
+    /// The variant of the request used to open a handle, and of the response with its id
+    fn open_variant(&self) -> Ident {
+        format_ident!("{}Open", ident_ccase!(pascal, self.name))
+    }
+
+    /// The variant of the request used to call the object of a handle
+    fn object_variant(&self) -> Ident {
+        format_ident!("{}Object", ident_ccase!(pascal, self.name))
+    }
+
+    /// The function of the async client which opens a handle to the nested service, if it can be
+    /// opened as a handle
+    fn open_fn(&self, nested: &syn::Path) -> Option<TokenStream> {
+        let (_, lookup) = self.handle_service()?;
+        let name = &self.name;
+        let docs = &self.docs;
+        let params = self.client_params(true);
+        let args = self.args.iter().map(|pat| &pat.pat);
+        let to_inner = format_ident!("{name}_to_inner");
+        let to_object = format_ident!("{name}_to_object");
+        let open_fn = format_ident!("open_{name}");
+        let open_variant = self.open_variant();
+        let object_variant = self.object_variant();
+        let open_name = format!("{name}.open");
+        // the server sends the response of the nested method instead if it was not found
+        let not_found = lookup.missing().map(|missing| {
+            let variant = ident_ccase!(pascal, name);
+            let error = lookup.not_found_error(&name.to_string());
+            quote!((Response::#variant(#missing), _) => Err(::trait_rpc::client::NestedError::from(#error).into()),)
+        });
+        Some(quote! {
+            #(#[doc = #docs])*
+            ///
+            /// Open a handle to the nested service, the server calls this method once and keeps
+            /// the nested service for each call made with the returned client, until every clone
+            /// of it has been dropped
+            pub async fn #open_fn(&self #(, #params)*) -> Result<<#nested as Rpc>::AsyncClient<MappedClient<_Client, <#nested as Rpc>::Request, Request, <#nested as Rpc>::Response, Response, ::trait_rpc::client::ObjectHandle>>, _Client::Error>
+            where
+                _Client: ::trait_rpc::client::HandleClient<Request, Response>,
+            {
+                match ::trait_rpc::client::HandleClient::open(&self.0, Request::#open_variant(#(#args),*)).await? {
+                    (Response::#open_variant(object), open) => {
+                        let object = ::trait_rpc::client::ObjectHandle::new(object, open);
+                        Ok(#nested::async_client(MappedClient::new(self.0.clone(), object, Self::#to_inner, Self::#to_object)))
+                    }
+                    #not_found
+                    (other, _) => Err(WrongResponseType::new(#open_name, other.fn_name()).into()),
+                }
+            }
+
+            fn #to_object(object: ::trait_rpc::client::ObjectHandle, inner: <#nested as Rpc>::Request) -> Request {
+                Request::#object_variant(object.id(), inner)
+            }
+        })
+    }
+
+    /// Whether the method can be called in a pipeline, this excludes any method with a stream
+    const fn pipelined(&self) -> bool {
+        match self.ret {
+            ReturnType::Simple(_) => self.stream_arg.is_none(),
+            ReturnType::Nested { .. } => true,
+            ReturnType::Stream { .. } => false,
+        }
+    }
+
+    /// The call to the nested service, which is the last field of a call in a pipeline
+    fn nested_call(&self) -> Option<TokenStream> {
+        match &self.ret {
+            ReturnType::Nested { service, .. } => Some(quote!(<#service as ::trait_rpc::pipeline::Pipelined>::Call)),
+            _ => None,
+        }
+    }
+
+    /// The match arm of `Pipelined::output` for the response to this method, and the pattern of
+    /// any response which has no result
+    fn output_arm(&self) -> (Option<TokenStream>, Option<TokenStream>) {
+        let variant = ident_ccase!(pascal, self.name);
+        let open = self.handle.then(|| {
+            let open_variant = self.open_variant();
+            quote!(Response::#open_variant(..))
+        });
+        match &self.ret {
+            ReturnType::Simple(_) if self.stream_arg.is_some() => (None, Some(quote!(Response::#variant(..)))),
+            // projected by the type of the output, see `Output`
+            ReturnType::Simple(_) => (
+                Some(quote!(Response::#variant(value) => (&&::trait_rpc::pipeline::Output(value)).project(projection),)),
+                None,
+            ),
+            ReturnType::Stream { .. } => (None, Some(quote!(Response::#variant(..)))),
+            ReturnType::Nested { service, lookup } => {
+                let found = lookup.found(&format_ident!("response"));
+                let arm = quote! {
+                    Response::#variant(#found) => <#service as ::trait_rpc::pipeline::Pipelined>::output(response, projection),
+                };
+                // a nested service which was not found has no result
+                let missing = (*lookup != Lookup::Infallible).then(|| quote!(Response::#variant(..)));
+                let unavailable = match (missing, open) {
+                    (Some(missing), Some(open)) => Some(quote!(#missing | #open)),
+                    (missing, open) => missing.or(open),
+                };
+                (Some(arm), unavailable)
+            }
+        }
+    }
+
+    /// The method of the pipeline of the service, which pushes a call to this method
+    fn pipeline_fn(&self) -> TokenStream {
+        let name = &self.name;
+        let docs = &self.docs;
+        let variant = ident_ccase!(pascal, name);
+        let params = self.args.iter().map(|pat| &pat.pat).collect::<Vec<_>>();
+        let types = self.args.iter().map(|pat| &pat.ty).collect::<Vec<_>>();
+        match &self.ret {
+            ReturnType::Nested { service: nested, .. } => {
+                let to_outer = format_ident!("{name}_to_outer");
+                quote! {
+                    #(#[doc = #docs])*
+                    pub fn #name(&self #(, #params: impl Into<::trait_rpc::pipeline::Arg<#types>>)*) -> <#nested as ::trait_rpc::pipeline::Pipelined>::Pipeline<::trait_rpc::pipeline::MappedCalls<_Calls, <#nested as ::trait_rpc::pipeline::Pipelined>::Call, Call, (#(::trait_rpc::pipeline::Arg<#types>,)*)>> {
+                        <#nested as ::trait_rpc::pipeline::Pipelined>::pipeline(::trait_rpc::pipeline::MappedCalls::new(self.0.clone(), (#(#params.into(),)*), Self::#to_outer))
+                    }
+
+                    fn #to_outer((#(#params,)*): (#(::trait_rpc::pipeline::Arg<#types>,)*), inner: <#nested as ::trait_rpc::pipeline::Pipelined>::Call) -> Call {
+                        Call::#variant(#(#params,)*inner)
+                    }
+                }
+            }
+            ReturnType::Simple(ret) => {
+                let ret = self.with_error(ret);
+                quote! {
+                    #(#[doc = #docs])*
+                    pub fn #name(&self #(, #params: impl Into<::trait_rpc::pipeline::Arg<#types>>)*) -> ::trait_rpc::pipeline::Pending<#ret> {
+                        ::trait_rpc::pipeline::Pending::new(::trait_rpc::pipeline::PipelineCalls::push(&self.0, Call::#variant(#(#params.into()),*)))
+                    }
+                }
+            }
+            ReturnType::Stream { .. } => unreachable!("streaming methods cannot be pipelined"),
+        }
+    }
+
+    /// All arguments in their declared order
+    fn all_args(&self) -> Vec<Arg<'_>> {
+        let mut args: Vec<_> = self.args.iter().map(Arg::Request).collect();
+        let mut others = Vec::new();
+        if let Some(stream_arg) = &self.stream_arg {
+            others.push((stream_arg.index, Arg::Stream(stream_arg)));
+        }
+        if let Some(context) = &self.context {
+            others.push((context.index, Arg::Context(&context.arg)));
+        }
+        // inserting in order of position, so that each position is correct at the time of insertion
+        others.sort_by_key(|(index, _)| *index);
+        for (index, arg) in others {
+            args.insert(index, arg);
+        }
+        args
+    }
+
+    /// The values passed to the server method by the handler, in their declared order
+    fn arg_values(&self) -> Vec<TokenStream> {
+        self.all_args()
+            .into_iter()
+            .map(|arg| match arg {
+                Arg::Request(arg) => arg.pat.to_token_stream(),
+                Arg::Stream(stream_arg) => stream_arg.arg.pat.to_token_stream(),
+                Arg::Context(_) => quote!(ctx),
+            })
+            .collect()
+    }
+
+    /// The parameters of the client method, a blocking client takes the items of a stream argument
+    /// as an iterator
+    fn client_params(&self, is_async: bool) -> Vec<PatType> {
+        if is_async {
+            return self.params(None, false);
+        }
+        let stream_ty: Option<Type> = self.stream_arg.as_ref().map(|stream_arg| {
+            let item = &stream_arg.item;
+            parse_quote!(impl IntoIterator<Item = #item>)
+        });
+        self.params(stream_ty.as_ref(), false)
+    }
+
+    /// All parameters in their declared order, the stream argument will have the type `stream_ty`
+    /// if given, or its declared type otherwise. The context argument is only included for the server
+    fn params(&self, stream_ty: Option<&Type>, server: bool) -> Vec<PatType> {
+        self.all_args()
+            .into_iter()
+            .filter_map(|arg| match arg {
+                Arg::Request(arg) => Some(arg.clone()),
+                Arg::Stream(stream_arg) => {
+                    let mut arg = stream_arg.arg.clone();
+                    if let Some(ty) = stream_ty {
+                        arg.ty = Box::new(ty.clone());
+                    }
+                    Some(arg)
+                }
+                Arg::Context(arg) => server.then(|| arg.clone()),
+            })
+            .collect()
+    }
+}
+
+impl Lookup {
+    /// The type sent or returned by the outer service in place of `ty`, which is only present if
+    /// the nested service was found
+    fn wrap_type(&self, ty: &Type) -> Type {
+        match self {
+            Self::Infallible => ty.clone(),
+            Self::Option => parse_quote!(Option<#ty>),
+            Self::Result(error) => parse_quote!(Result<#ty, #error>),
+        }
+    }
+
+    /// Maps a response of the nested service to the given variant of the outer response
+    fn wrap(&self, variant: &Ident) -> TokenStream {
+        match self {
+            Self::Infallible => quote!(Response::#variant),
+            Self::Option => quote!(|response| Response::#variant(Some(response))),
+            Self::Result(_) => quote!(|response| Response::#variant(Ok(response))),
+        }
+    }
+
+    /// The pattern of a value which is present, bound to `value`
+    fn found(&self, value: &Ident) -> TokenStream {
+        match self {
+            Self::Infallible => quote!(#value),
+            Self::Option => quote!(Some(#value)),
+            Self::Result(_) => quote!(Ok(#value)),
+        }
+    }
+
+    /// The value sent in place of the response if the nested service was not found, this is also
+    /// its pattern, binding the error to `error`
+    fn missing(&self) -> Option<TokenStream> {
+        match self {
+            Self::Infallible => None,
+            Self::Option => Some(quote!(None)),
+            Self::Result(_) => Some(quote!(Err(error))),
+        }
+    }
+
+    /// The client error for a nested service which was not found, see [`Self::missing`], the error
+    /// returned by the lookup is passed on to the client
+    fn not_found_error(&self, name: &str) -> TokenStream {
+        match self {
+            Self::Result(_) => quote!(::trait_rpc::client::ApplicationError::new(#name, error)),
+            Self::Infallible | Self::Option => quote!(::trait_rpc::client::NotFound::new(#name)),
+        }
+    }
+
+    /// Looks up the nested handler with `lookup`, then runs `found` with the handler bound to
+    /// `handler`, or `not_found` with the value sent in place of the response
+    fn match_handler(&self, lookup: &TokenStream, found: &TokenStream, not_found: impl FnOnce(TokenStream) -> TokenStream) -> TokenStream {
+        let Some(missing) = self.missing() else {
+            return quote! {
+                let handler = #lookup;
+                #found
+            };
+        };
+        let handler = self.found(&format_ident!("handler"));
+        let not_found = not_found(missing.clone());
+        quote! {
+            match #lookup {
+                #handler => { #found }
+                #missing => { #not_found }
+            }
+        }
+    }
+}
+
+/// An argument of a method
+enum Arg<'a> {
+    /// An argument sent as part of the request
+    Request(&'a PatType),
+    Stream(&'a StreamArg),
+    Context(&'a PatType),
 }
@@ -1,9 +1,92 @@
-use crate::{Method, Rpc};
-use syn::{FnArg, ItemTrait, ReturnType, TraitItem, TraitItemFn, Type, TypeParamBound, parse_quote, Attribute, MetaNameValue, Meta, Expr};
+use crate::{ContextArg, Lookup, Method, Rpc, StreamArg};
+use proc_macro2::TokenStream;
+use syn::meta::ParseNestedMeta;
+use syn::parse::Parser as _;
+use syn::{FnArg, ItemTrait, ReturnType, TraitItem, TraitItemFn, Type, TypeImplTrait, TypeParamBound, parse_quote, Attribute, MetaNameValue, Meta, Expr, PatType, Path, Receiver, PathArguments, GenericArgument};
 
 /// This contains any args in the attribute macro invocation that may affect parsing
-// There are no such args for now, but we will keep this just in case tha changes
-pub struct Parser;
+#[derive(Default)]
+pub struct Parser {
+    /// The options given in the attribute macro invocation, these apply to every method
+    options: Options,
+}
+
+/// Options which can be given either as args of the attribute macro invocation (eg:
+/// `#[rpc(error = ApiError)]`), or on an individual method with an `#[rpc(..)]` attribute
+#[derive(Default)]
+struct Options {
+    /// The application error type returned by methods
+    error: Option<Type>,
+    /// The `idempotent` option, if given: methods can safely be called more than once
+    idempotent: Option<Path>,
+    /// The `topic` option, if given: the method is a topic which clients subscribe to, this is only
+    /// allowed on a method
+    topic: Option<Path>,
+    /// The `handle` option, if given: the nested service can be opened as a handle, this is only
+    /// allowed on a nested method
+    handle: Option<Path>,
+    /// The `pipeline` option, if given: calls to the service can be pipelined, this is only allowed
+    /// on the trait
+    pipeline: Option<Path>,
+    /// The `notify` option, if given: the client does not wait for a response to the method, this
+    /// is only allowed on a method
+    notify: Option<Path>,
+}
+
+/// The options of a single method, after inheriting the options given to the trait
+struct MethodOptions {
+    error: Option<Type>,
+    idempotent: bool,
+    /// The `topic` option, if the method is a topic
+    topic: Option<Path>,
+    handle: bool,
+    /// The `notify` option, if the method is a notification
+    notify: Option<Path>,
+}
+
+impl Options {
+    fn parse_meta(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("error") {
+            self.error = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("idempotent") {
+            self.idempotent = Some(meta.path.clone());
+            Ok(())
+        } else if meta.path.is_ident("topic") {
+            self.topic = Some(meta.path.clone());
+            Ok(())
+        } else if meta.path.is_ident("handle") {
+            self.handle = Some(meta.path.clone());
+            Ok(())
+        } else if meta.path.is_ident("pipeline") {
+            self.pipeline = Some(meta.path.clone());
+            Ok(())
+        } else if meta.path.is_ident("notify") {
+            self.notify = Some(meta.path.clone());
+            Ok(())
+        } else {
+            Err(meta.error("unsupported rpc option"))
+        }
+    }
+}
+
+impl Parser {
+    /// Create a parser from the args of the attribute macro invocation
+    pub fn new(args: TokenStream) -> syn::Result<Self> {
+        let mut options = Options::default();
+        syn::meta::parser(|meta| options.parse_meta(&meta)).parse2(args)?;
+        if let Some(topic) = options.topic {
+            return Err(syn::Error::new_spanned(topic, "only a method can be a topic"));
+        }
+        if let Some(handle) = options.handle {
+            return Err(syn::Error::new_spanned(handle, "only a nested method can be a handle"));
+        }
+        if let Some(notify) = options.notify {
+            return Err(syn::Error::new_spanned(notify, "only a method can be a notification"));
+        }
+        Ok(Self { options })
+    }
+}
 
 #[allow(clippy::unused_self)]
 impl Parser {
@@ -20,6 +103,18 @@ impl Parser {
                 "supertraits are not supported",
             ));
         }
+        if let Some(pipeline) = &self.options.pipeline {
+            if !input.generics.params.is_empty() {
+                return Err(syn::Error::new_spanned(pipeline, "generic services cannot be pipelined"));
+            }
+            // the client of a pipelined service has a `pipeline` method
+            if let Some(method) = methods.iter().find(|method| method.name == "pipeline") {
+                return Err(syn::Error::new_spanned(
+                    &method.name,
+                    "a pipelined service cannot have a method named `pipeline`",
+                ));
+            }
+        }
         let docs = input.attrs.iter().filter_map(docs).collect();
         Ok(Rpc {
             docs,
@@ -27,6 +122,7 @@ impl Parser {
             generics: input.generics,
             name: input.ident,
             methods,
+            pipeline: self.options.pipeline.is_some(),
         })
     }
 
@@ -48,6 +144,8 @@ impl Parser {
         }
         let name = item.sig.ident.clone();
         let mut args = Vec::with_capacity(item.sig.inputs.len() - 1);
+        let mut stream_arg = None;
+        let mut context = None;
         let mut has_self = false;
         for arg in &item.sig.inputs {
             match arg {
@@ -55,38 +153,247 @@ impl Parser {
                     if has_self {
                         return Err(syn::Error::new_spanned(s, "cannot have multiple receivers"));
                     }
-                    if s.reference.is_none() {
-                        return Err(syn::Error::new_spanned(s, "cannot take owned self value"));
-                    }
-                    if s.mutability.is_some() {
-                        return Err(syn::Error::new_spanned(
-                            s,
-                            "cannot take a mutable self reference",
-                        ));
-                    }
-                    if let Type::Reference(ty) = &*s.ty
-                        && ty.mutability.is_none()
-                        && let Type::Path(ty) = &*ty.elem
-                        && ty.path.segments.len() == 1
-                        && ty.path.segments[0].ident == "Self"
-                    {
+                    self.receiver(s)?;
+                    has_self = true;
+                }
+                FnArg::Typed(arg) => {
+                    let index = args.len() + usize::from(stream_arg.is_some()) + usize::from(context.is_some());
+                    if is_context(arg) {
+                        if context.is_some() {
+                            return Err(syn::Error::new_spanned(arg, "cannot have multiple context arguments"));
+                        }
+                        let mut arg = arg.clone();
+                        // the marker is not a real attribute, so it is not passed on to the server trait
+                        arg.attrs.retain(|attr| !attr.path().is_ident("context"));
+                        context = Some(ContextArg { index, arg });
+                    } else if let Some(item) = self.stream_arg(arg)? {
+                        if stream_arg.is_some() {
+                            return Err(syn::Error::new_spanned(arg, "cannot have multiple stream arguments"));
+                        }
+                        stream_arg = Some(StreamArg {
+                            index,
+                            arg: arg.clone(),
+                            item,
+                        });
                     } else {
-                        return Err(syn::Error::new_spanned(
-                            s,
-                            "cannot use a smart pointer for self type, must use &Self",
-                        ));
+                        args.push(arg.clone());
                     }
-                    has_self = true;
                 }
-                FnArg::Typed(arg) => args.push(arg.clone()),
             }
         }
         if !has_self {
             return Err(syn::Error::new_spanned(item, "missing self"));
         }
-        let ret = self.return_type(item.sig.output)?;
+        let mut ret = self.return_type(item.sig.output)?;
+        if let Some(stream_arg) = &stream_arg {
+            match &ret {
+                super::ReturnType::Simple(_) => {}
+                super::ReturnType::Stream { .. } => {
+                    return Err(syn::Error::new_spanned(
+                        &stream_arg.arg,
+                        "stream arguments are not supported for streaming methods",
+                    ));
+                }
+                super::ReturnType::Nested { .. } => {
+                    return Err(syn::Error::new_spanned(
+                        &stream_arg.arg,
+                        "stream arguments are not supported for nested services",
+                    ));
+                }
+            }
+        }
+        let MethodOptions { error, idempotent, topic, handle, notify } = self.method_options(&item.attrs, &ret)?;
+        if let Some(topic) = &topic {
+            ret = self.topic(topic, &args, stream_arg.as_ref(), context.as_ref(), ret)?;
+        }
+        if let Some(notify) = &notify {
+            self.notification(notify, stream_arg.as_ref(), &ret)?;
+        }
+        let topic = topic.is_some();
+        let notify = notify.is_some();
         let docs = item.attrs.iter().filter_map(docs).collect();
-        Ok(Method { docs, name, args, ret })
+        Ok(Method { docs, name, args, stream_arg, context, ret, error, idempotent, topic, handle, notify })
+    }
+
+    fn receiver(&self, s: &Receiver) -> syn::Result<()> {
+        if s.reference.is_none() {
+            return Err(syn::Error::new_spanned(s, "cannot take owned self value"));
+        }
+        if s.mutability.is_some() {
+            return Err(syn::Error::new_spanned(
+                s,
+                "cannot take a mutable self reference",
+            ));
+        }
+        if let Type::Reference(ty) = &*s.ty
+            && ty.mutability.is_none()
+            && let Type::Path(ty) = &*ty.elem
+            && ty.path.segments.len() == 1
+            && ty.path.segments[0].ident == "Self"
+        {
+            Ok(())
+        } else {
+            Err(syn::Error::new_spanned(
+                s,
+                "cannot use a smart pointer for self type, must use &Self",
+            ))
+        }
+    }
+
+    /// Returns the options of a method, these may be set for the method with an `#[rpc(..)]`
+    /// attribute, the error and idempotency are otherwise inherited from the trait. A notification
+    /// never has an error, as the server does not reply to it
+    fn method_options(&self, attrs: &[Attribute], ret: &super::ReturnType) -> syn::Result<MethodOptions> {
+        let mut options = Options::default();
+        for attr in attrs {
+            if attr.path().is_ident("rpc") {
+                attr.parse_nested_meta(|meta| options.parse_meta(&meta))?;
+            }
+        }
+        if let Some(pipeline) = options.pipeline {
+            return Err(syn::Error::new_spanned(
+                pipeline,
+                "only a whole service can be pipelined, add `pipeline` to the `#[rpc]` of the trait",
+            ));
+        }
+        if let super::ReturnType::Nested { .. } = ret {
+            // nested services are not affected by the trait's options
+            if let Some(error) = options.error {
+                return Err(syn::Error::new_spanned(
+                    error,
+                    "error types are not supported for nested services",
+                ));
+            }
+            if let Some(idempotent) = options.idempotent {
+                return Err(syn::Error::new_spanned(
+                    idempotent,
+                    "nested services cannot be idempotent, mark the methods of the nested service instead",
+                ));
+            }
+            if let Some(notify) = options.notify {
+                return Err(syn::Error::new_spanned(notify, "nested services cannot be notifications"));
+            }
+            return Ok(MethodOptions {
+                error: None,
+                idempotent: false,
+                topic: options.topic,
+                handle: options.handle.is_some(),
+                notify: None,
+            });
+        }
+        if let Some(handle) = options.handle {
+            return Err(syn::Error::new_spanned(
+                handle,
+                "only nested services can be opened as a handle",
+            ));
+        }
+        let idempotent = options.idempotent.is_some() || self.options.idempotent.is_some();
+        if let Some(notify) = options.notify {
+            if let Some(topic) = options.topic {
+                return Err(syn::Error::new_spanned(topic, "topics cannot be notifications"));
+            }
+            // the server does not reply to a notification, so an error would never reach the client
+            if let Some(error) = options.error {
+                return Err(syn::Error::new_spanned(error, "error types are not supported for notifications"));
+            }
+            return Ok(MethodOptions {
+                error: None,
+                idempotent,
+                topic: None,
+                handle: false,
+                notify: Some(notify),
+            });
+        }
+        if let Some(topic) = options.topic {
+            // events are published by the server rather than returned by a method, so they never
+            // carry an application error
+            if let Some(error) = options.error {
+                return Err(syn::Error::new_spanned(error, "error types are not supported for topics"));
+            }
+            return Ok(MethodOptions {
+                error: None,
+                idempotent,
+                topic: Some(topic),
+                handle: false,
+                notify: None,
+            });
+        }
+        Ok(MethodOptions {
+            error: options.error.or_else(|| self.options.error.clone()),
+            idempotent,
+            topic: None,
+            handle: false,
+            notify: None,
+        })
+    }
+
+    /// Returns the return type of a topic, this is a stream of each event. A topic is only
+    /// identified by its name, so it cannot take any arguments
+    fn topic(
+        &self,
+        topic: &Path,
+        args: &[PatType],
+        stream_arg: Option<&StreamArg>,
+        context: Option<&ContextArg>,
+        ret: super::ReturnType,
+    ) -> syn::Result<super::ReturnType> {
+        if let Some(arg) = args
+            .first()
+            .or_else(|| stream_arg.map(|stream_arg| &stream_arg.arg))
+            .or_else(|| context.map(|context| &context.arg))
+        {
+            return Err(syn::Error::new_spanned(arg, "topics cannot take arguments"));
+        }
+        match ret {
+            super::ReturnType::Simple(item) => Ok(super::ReturnType::Stream { item }),
+            super::ReturnType::Stream { .. } => Err(syn::Error::new_spanned(
+                topic,
+                "topics return the type of each event, not a stream",
+            )),
+            super::ReturnType::Nested { .. } => Err(syn::Error::new_spanned(topic, "nested services cannot be topics")),
+        }
+    }
+
+    /// Checks that a notification has nothing to send back, the client does not wait for a response
+    /// so it cannot return a value or take a stream argument
+    fn notification(
+        &self,
+        notify: &Path,
+        stream_arg: Option<&StreamArg>,
+        ret: &super::ReturnType,
+    ) -> syn::Result<()> {
+        if let Some(stream_arg) = stream_arg {
+            return Err(syn::Error::new_spanned(
+                &stream_arg.arg,
+                "notifications cannot take a stream argument",
+            ));
+        }
+        match ret {
+            super::ReturnType::Simple(Type::Tuple(ty)) if ty.elems.is_empty() => Ok(()),
+            super::ReturnType::Simple(ty) => Err(syn::Error::new_spanned(ty, "notifications cannot return a value")),
+            super::ReturnType::Stream { .. } => Err(syn::Error::new_spanned(
+                notify,
+                "streaming methods cannot be notifications",
+            )),
+            super::ReturnType::Nested { .. } => Err(syn::Error::new_spanned(notify, "nested services cannot be notifications")),
+        }
+    }
+
+    /// Returns the item type if the given argument is an `impl Stream<Item = T>`
+    fn stream_arg(&self, arg: &PatType) -> syn::Result<Option<Type>> {
+        let Type::ImplTrait(ty) = &*arg.ty else {
+            return Ok(None);
+        };
+        if ty.bounds.len() > 1 {
+            return Err(syn::Error::new_spanned(&ty.bounds, "cannot specify multiple bounds here"));
+        }
+        match ty.bounds.first() {
+            Some(TypeParamBound::Trait(bound)) => stream_item(&bound.path)?
+                .map(Some)
+                .ok_or_else(|| syn::Error::new_spanned(ty, "only `impl Stream<Item = T>` is supported here")),
+            Some(_) => Err(syn::Error::new_spanned(ty, "unsupported bound")),
+            None => Err(syn::Error::new_spanned(ty, "no bounds found")),
+        }
     }
 
     fn return_type(&self, output: ReturnType) -> syn::Result<super::ReturnType> {
@@ -94,35 +401,131 @@ impl Parser {
             ReturnType::Default => Ok(super::ReturnType::Simple(parse_quote! {()})),
             ReturnType::Type(_, ty) => {
                 if let Type::ImplTrait(ty) = &*ty {
-                    if let Some(first) = ty.bounds.first() {
-                        if ty.bounds.len() > 1 {
-                            return Err(syn::Error::new_spanned(
-                                &ty.bounds,
-                                "cannot specify multiple bounds here",
-                            ));
-                        }
-                        if let TypeParamBound::Trait(bound) = first {
-                            if bound.lifetimes.is_some() {
-                                return Err(syn::Error::new_spanned(
-                                    &bound.lifetimes,
-                                    "lifetimes not supported here",
-                                ));
-                            }
-                            Ok(super::ReturnType::Nested {
-                                service: bound.path.clone(),
-                            })
-                        } else {
-                            Err(syn::Error::new_spanned(ty, "unsupported bound"))
-                        }
-                    } else {
-                        Err(syn::Error::new_spanned(ty, "no bounds found"))
-                    }
+                    self.impl_return_type(ty)
+                } else if let Some(nested) = self.fallible_nested(&ty)? {
+                    Ok(nested)
                 } else {
                     Ok(super::ReturnType::Simple(*ty))
                 }
             }
         }
     }
+
+    /// Returns the return type for an `impl Trait`, this is either a stream or a nested service
+    fn impl_return_type(&self, ty: &TypeImplTrait) -> syn::Result<super::ReturnType> {
+        if let Some(first) = ty.bounds.first() {
+            if ty.bounds.len() > 1 {
+                return Err(syn::Error::new_spanned(
+                    &ty.bounds,
+                    "cannot specify multiple bounds here",
+                ));
+            }
+            if let TypeParamBound::Trait(bound) = first {
+                if bound.lifetimes.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        &bound.lifetimes,
+                        "lifetimes not supported here",
+                    ));
+                }
+                if let Some(item) = stream_item(&bound.path)? {
+                    return Ok(super::ReturnType::Stream { item });
+                }
+                Ok(super::ReturnType::Nested {
+                    service: bound.path.clone(),
+                    lookup: Lookup::Infallible,
+                })
+            } else {
+                Err(syn::Error::new_spanned(ty, "unsupported bound"))
+            }
+        } else {
+            Err(syn::Error::new_spanned(ty, "no bounds found"))
+        }
+    }
+
+    /// Returns a nested service if the given type is an `Option<impl Service>` or a
+    /// `Result<impl Service, E>`, which the server may not find
+    fn fallible_nested(&self, ty: &Type) -> syn::Result<Option<super::ReturnType>> {
+        let Type::Path(path) = ty else {
+            return Ok(None);
+        };
+        let Some(last) = path.path.segments.last() else {
+            return Ok(None);
+        };
+        let PathArguments::AngleBracketed(args) = &last.arguments else {
+            return Ok(None);
+        };
+        let Some(GenericArgument::Type(Type::ImplTrait(service))) = args.args.first() else {
+            return Ok(None);
+        };
+        let lookup = match args.args.iter().nth(1) {
+            None if last.ident == "Option" => Lookup::Option,
+            Some(GenericArgument::Type(error)) if last.ident == "Result" && args.args.len() == 2 => {
+                Lookup::Result(Box::new(error.clone()))
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "only `Option<impl Service>` and `Result<impl Service, E>` are supported here",
+                ));
+            }
+        };
+        match self.impl_return_type(service)? {
+            super::ReturnType::Nested { service, .. } => Ok(Some(super::ReturnType::Nested { service, lookup })),
+            _ => Err(syn::Error::new_spanned(
+                service,
+                "only nested services can be returned in an `Option` or a `Result`",
+            )),
+        }
+    }
+}
+
+/// Returns true if the given argument is the server context, which is provided by the server rather
+/// than sent by the client. This is an argument marked with `#[context]`, or one whose type is
+/// spelled out as `&trait_rpc::server::Context`, any other type named `Context` is the user's own
+fn is_context(arg: &PatType) -> bool {
+    if arg.attrs.iter().any(|attr| attr.path().is_ident("context")) {
+        return true;
+    }
+    if let Type::Reference(ty) = &*arg.ty
+        && ty.mutability.is_none()
+        && let Type::Path(ty) = &*ty.elem
+        && ty.qself.is_none()
+    {
+        let segments: Vec<_> = ty.path.segments.iter().collect();
+        segments.len() == 3
+            && segments.iter().all(|segment| segment.arguments.is_none())
+            && segments[0].ident == "trait_rpc"
+            && segments[1].ident == "server"
+            && segments[2].ident == "Context"
+    } else {
+        false
+    }
+}
+
+/// Returns the item type if the given bound is a `Stream<Item = T>`
+fn stream_item(bound: &Path) -> syn::Result<Option<Type>> {
+    let Some(last) = bound.segments.last() else {
+        return Ok(None);
+    };
+    if last.ident != "Stream" {
+        return Ok(None);
+    }
+    let PathArguments::AngleBracketed(args) = &last.arguments else {
+        return Err(syn::Error::new_spanned(bound, "missing stream item type, expected `Stream<Item = T>`"));
+    };
+    let mut item = None;
+    for arg in &args.args {
+        match arg {
+            GenericArgument::AssocType(assoc) if assoc.ident == "Item" && assoc.generics.is_none() => {
+                item = Some(assoc.ty.clone());
+            }
+            _ => return Err(syn::Error::new_spanned(arg, "unsupported stream argument, expected `Item = T`")),
+        }
+    }
+    item.map_or_else(
+        || Err(syn::Error::new_spanned(bound, "missing stream item type, expected `Stream<Item = T>`")),
+        |item| Ok(Some(item)),
+    )
 }
 
 fn docs(attr: &Attribute) -> Option<Expr> {
@@ -159,12 +562,16 @@ mod test {
     return_type_tests![
         unit: crate::ReturnType::Simple(Type::Tuple(TypeTuple { paren_token: Paren::default(),elems: Punctuated::default(),})) => {},
         simple: crate::ReturnType::Simple(Type::Path(parse_quote!(String))) => {-> String},
-        service: crate::ReturnType::Nested {  service: parse_quote!(SubService) } => { -> impl SubService }
+        service: crate::ReturnType::Nested {  service: parse_quote!(SubService), lookup: crate::Lookup::Infallible } => { -> impl SubService },
+        option_service: crate::ReturnType::Nested { service: parse_quote!(SubService), lookup: crate::Lookup::Option } => { -> Option<impl SubService> },
+        result_service: crate::ReturnType::Nested { service: parse_quote!(SubService), lookup: crate::Lookup::Result(Box::new(parse_quote!(NotFound))) } => { -> Result<impl SubService, NotFound> },
+        stream: crate::ReturnType::Stream { item: parse_quote!(Todo) } => { -> impl Stream<Item = Todo> },
+        stream_path: crate::ReturnType::Stream { item: parse_quote!(Vec<u8>) } => { -> impl futures::Stream<Item = Vec<u8>> }
     ];
 
     #[allow(clippy::needless_pass_by_value)]
     fn test_return_type(input: ReturnType, expected: crate::ReturnType) {
-        let parser = Parser;
+        let parser = Parser::default();
         let output = parser.return_type(input).expect("failed to parse input");
         assert_eq!(output, expected);
     }
@@ -0,0 +1,8 @@
+#[rpc]
+/// A service for importing rows in bulk
+pub trait Importer {
+    /// Get the number of rows imported so far
+    fn count(&self) -> u64;
+    /// Import each of the given rows into the given table
+    fn import(&self, table: String, rows: impl Stream<Item = Row>, dry_run: bool) -> ImportSummary;
+}
@@ -0,0 +1,14 @@
+#[rpc]
+/// A service which records changes along with who made them
+pub trait Audited {
+    /// Get the address of the caller
+    fn whoami(&self, #[context] ctx: &Context) -> String;
+    /// Record a change made by the caller
+    fn record(&self, change: Change, #[context] ctx: &Context);
+    /// Receive each change made by the caller
+    fn watch(&self, ctx: &trait_rpc::server::Context) -> impl Stream<Item = Change>;
+    /// Record each of the given changes, returns the number of changes recorded
+    fn import(&self, #[context] ctx: &Context, changes: impl Stream<Item = Change>) -> u64;
+    /// Get the service for the user making the request
+    fn current(&self, #[context] ctx: &Context) -> impl UserService;
+}
@@ -0,0 +1,10 @@
+#[rpc]
+/// A service with its own type named `Context`
+pub trait Sessions {
+    /// Open a session with the given context, which is sent by the client
+    fn open(&self, context: session::Context) -> u64;
+    /// Compare the given context with the caller's session, this is not the server context
+    fn compare(&self, context: &session::Context) -> bool;
+    /// The id of the caller's session, found in the server context
+    fn current(&self, #[context] ctx: &Context) -> u64;
+}
@@ -0,0 +1,13 @@
+#[rpc(error = ApiError)]
+/// A service for managing bank accounts
+pub trait Bank {
+    /// Get the balance of an account
+    fn balance(&self, account: u64) -> u64;
+    /// Transfer an amount between two accounts
+    #[rpc(error = TransferError)]
+    fn transfer(&self, from: u64, to: u64, amount: u64);
+    /// Receive every transaction on an account
+    fn transactions(&self, account: u64) -> impl Stream<Item = Transaction>;
+    /// Get the service for a single account
+    fn account(&self, account: u64) -> impl AccountService;
+}
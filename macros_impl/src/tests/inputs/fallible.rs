@@ -0,0 +1,9 @@
+#[rpc]
+/// A service for users
+pub trait UsersService {
+    /// Get the service for a single user, if the user exists
+    fn by_id(&self, id: u64) -> Option<impl UserService>;
+    /// Get the service for the logged in user
+    #[rpc(handle)]
+    fn current(&self, token: LoginToken) -> Result<impl UserService, LoginExpired>;
+}
@@ -0,0 +1,16 @@
+#[rpc]
+/// A service for users
+pub trait UsersService {
+    /// Get a list of users
+    fn list(&self) -> Vec<User>;
+    /// Get the service for a single user
+    #[rpc(handle)]
+    fn by_id(&self, id: u64) -> impl UserService;
+}
+
+#[rpc]
+/// A service for a single user
+pub trait UserService {
+    /// Get the user
+    fn get(&self) -> User;
+}
@@ -0,0 +1,14 @@
+#[rpc]
+/// A service for managing to-do items
+pub trait TodoService {
+    /// Get every to-do item
+    #[rpc(idempotent)]
+    fn get_todos(&self) -> Vec<Todo>;
+    /// Get a to-do item by name
+    #[rpc(idempotent)]
+    fn get_todo(&self, name: String) -> Option<Todo>;
+    /// Add a new to-do item
+    fn new_todo(&self, todo: Todo);
+    /// Get the service for a single list of to-do items
+    fn list(&self, name: String) -> impl ListService;
+}
@@ -0,0 +1,9 @@
+#[rpc]
+/// A service for collecting telemetry
+pub trait Telemetry {
+    /// Record an event, the client does not wait for the server to handle it
+    #[rpc(notify)]
+    fn log_event(&self, event: Event);
+    /// Get the number of events recorded so far
+    fn event_count(&self) -> u64;
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+#[rpc(pipeline)]
+/// A service for signing in
+pub trait AuthService {
+    /// Sign in, returning a token if the credentials are correct
+    fn login(&self, username: String, password: String) -> Option<Token>;
+    /// Receive every token which is revoked
+    fn revoked(&self) -> impl Stream<Item = Token>;
+    /// Get the session of a token
+    fn session(&self, token: Token) -> Result<impl SessionService, Expired>;
+}
+
+#[rpc(error = SessionError, pipeline)]
+pub trait SessionService {
+    fn user(&self) -> User;
+    fn logout(&self);
+}
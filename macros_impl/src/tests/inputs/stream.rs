@@ -0,0 +1,10 @@
+#[rpc]
+/// A service for watching to-do items
+pub trait TodoWatcher {
+    /// Get a list of to-do items
+    fn get_todos(&self) -> Vec<Todo>;
+    /// Receive each new to-do item as it is created
+    fn watch_todos(&self) -> impl Stream<Item = Todo>;
+    /// Receive every update to the to-do item with the given name
+    fn watch_todo(&self, name: String) -> impl Stream<Item = Todo>;
+}
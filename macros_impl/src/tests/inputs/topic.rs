@@ -0,0 +1,9 @@
+#[rpc]
+/// A service for to-do items which publishes each change
+pub trait TodoFeed {
+    /// Get a list of to-do items
+    fn get_todos(&self) -> Vec<Todo>;
+    /// Each new to-do item as it is created
+    #[rpc(topic)]
+    fn todo_created(&self) -> Todo;
+}
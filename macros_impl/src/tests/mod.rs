@@ -61,4 +61,4 @@ fn test_case(input: &'static str, expected: &'static str) {
     difference::assert_diff!(&actual, &expected, "\n", 0);
 }
 
-tests!(simple, resource, nested);
+tests!(simple, resource, nested, stream, client_stream, errors, context, context_type, idempotent, topic, handle, fallible, pipeline, notify);
@@ -0,0 +1,217 @@
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use importer::{Importer, ImporterAsyncClient, ImporterBlockingClient, ImporterServer};
+
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod importer {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+
+    use std::marker::PhantomData;
+
+    /// A service for importing rows in bulk
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct Importer;
+
+    impl Rpc for Importer {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = ImporterAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> = ImporterBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(transport: _Client) -> ImporterAsyncClient<_Client> {
+            ImporterAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(transport: _Client) -> ImporterBlockingClient<_Client> {
+            ImporterBlockingClient(transport)
+        }
+    }
+
+    impl Importer {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl ImporterServer) -> impl Handler<Rpc = Self> {
+            ImporterHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "count")]
+        Count(),
+        #[serde(rename = "import")]
+        Import(String, bool),
+        #[serde(rename = "import.item")]
+        ImportItem(Row),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "count")]
+        Count(u64),
+        #[serde(rename = "import")]
+        Import(ImportSummary),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Count(..) => "count",
+                Self::Import(..) => "import",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Count(..) => "count",
+                Self::Import(..) => "import",
+                Self::ImportItem(..) => "import.item",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            matches!(self, Self::Import(..))
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            matches!(self, Self::ImportItem(..))
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            false
+        }
+    }
+
+    /// A service for importing rows in bulk
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait ImporterServer: Send + Sync {
+        /// Get the number of rows imported so far
+        fn count(&self) -> impl Future<Output = u64> + Send;
+        /// Import each of the given rows into the given table
+        fn import(
+            &self,
+            table: String,
+            rows: impl ::trait_rpc::futures::Stream<Item = Row> + Send + Unpin,
+            dry_run: bool,
+        ) -> impl Future<Output = ImportSummary> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct ImporterHandler<_Server>(_Server);
+
+    impl<_Server: ImporterServer> Handler for ImporterHandler<_Server> {
+        type Rpc = Importer;
+        async fn handle(&self, _ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::Count() => Ok(Response::Count(self.0.count().await)),
+                Request::Import(..) => {
+                    Err(HandlerError::Unsupported("`import` takes a stream argument, requests for it must be passed to `Handler::handle_stream`".to_string()))
+                }
+                Request::ImportItem(..) => {
+                    Err(HandlerError::Unsupported("stream items are not requests, they must be passed to `Handler::handle_stream` as incoming items".to_string()))
+                }
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                Request::Import(table, dry_run) => {
+                    let rows = filter_incoming(incoming, |request| match request {
+                        Request::ImportItem(item) => Some(item),
+                        _ => None,
+                    });
+                    let _ = sink.send(Response::Import(self.0.import(table, rows, dry_run).await)).await;
+                }
+                Request::ImportItem(..) => {}
+                request @ Request::Count(..) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service for importing rows in bulk
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct ImporterAsyncClient<_Client>(_Client);
+
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> ImporterAsyncClient<_Client> {
+        /// Get the number of rows imported so far
+        pub async fn count(&self) -> Result<u64, _Client::Error> {
+            match self.0.send(Request::Count()).await? {
+                Response::Count(value) => Ok(value),
+                other => Err(WrongResponseType::new("count", other.fn_name()).into()),
+            }
+        }
+        /// Import each of the given rows into the given table
+        pub async fn import(&self, table: String, rows: impl Stream<Item = Row>, dry_run: bool) -> Result<ImportSummary, _Client::Error> {
+            match self.0.send_with_stream(Request::Import(table, dry_run), rows.map(Request::ImportItem)).await? {
+                Response::Import(value) => Ok(value),
+                other => Err(WrongResponseType::new("import", other.fn_name()).into()),
+            }
+        }
+    }
+
+    /// A service for importing rows in bulk
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct ImporterBlockingClient<_Client>(_Client);
+
+    impl<_Client: BlockingClient<Request, Response>> ImporterBlockingClient<_Client> {
+        /// Get the number of rows imported so far
+        pub fn count(&self) -> Result<u64, _Client::Error> {
+            match self.0.send(Request::Count())? {
+                Response::Count(value) => Ok(value),
+                other => Err(WrongResponseType::new("count", other.fn_name()).into()),
+            }
+        }
+        /// Import each of the given rows into the given table
+        pub fn import(&self, table: String, rows: impl IntoIterator<Item = Row>, dry_run: bool) -> Result<ImportSummary, _Client::Error> {
+            match self.0.send_with_stream(Request::Import(table, dry_run), rows.into_iter().map(Request::ImportItem))? {
+                Response::Import(value) => Ok(value),
+                other => Err(WrongResponseType::new("import", other.fn_name()).into()),
+            }
+        }
+    }
+}
@@ -0,0 +1,335 @@
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use audited::{Audited, AuditedAsyncClient, AuditedBlockingClient, AuditedServer};
+
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod audited {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+
+    use std::marker::PhantomData;
+
+    /// A service which records changes along with who made them
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct Audited;
+
+    impl Rpc for Audited {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = AuditedAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> = AuditedBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(transport: _Client) -> AuditedAsyncClient<_Client> {
+            AuditedAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(transport: _Client) -> AuditedBlockingClient<_Client> {
+            AuditedBlockingClient(transport)
+        }
+    }
+
+    impl Audited {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl AuditedServer) -> impl Handler<Rpc = Self> {
+            AuditedHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "whoami")]
+        Whoami(),
+        #[serde(rename = "record")]
+        Record(Change),
+        #[serde(rename = "watch")]
+        Watch(),
+        #[serde(rename = "import")]
+        Import(),
+        #[serde(rename = "import.item")]
+        ImportItem(Change),
+        #[serde(rename = "current")]
+        Current(<UserService as Rpc>::Request),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "whoami")]
+        Whoami(String),
+        #[serde(rename = "record")]
+        Record(()),
+        #[serde(rename = "watch")]
+        Watch(Change),
+        #[serde(rename = "import")]
+        Import(u64),
+        #[serde(rename = "current")]
+        Current(<UserService as Rpc>::Response),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Whoami(..) => "whoami",
+                Self::Record(..) => "record",
+                Self::Watch(..) => "watch",
+                Self::Import(..) => "import",
+                Self::Current(..) => "current",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Whoami(..) => "whoami",
+                Self::Record(..) => "record",
+                Self::Watch(..) => "watch",
+                Self::Import(..) => "import",
+                Self::ImportItem(..) => "import.item",
+                Self::Current(..) => "current",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            match self {
+                Self::Current(.., request) => request.is_stream(),
+                other => matches!(other, Self::Watch(..)),
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            match self {
+                Self::Current(.., request) => request.is_client_stream(),
+                other => matches!(other, Self::Import(..)),
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            match self {
+                Self::Current(.., request) => request.is_stream_item(),
+                other => matches!(other, Self::ImportItem(..)),
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            match self {
+                Self::Current(.., request) => request.is_idempotent(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            match self {
+                Self::Current(.., request) => request.is_notification(),
+                _ => false,
+            }
+        }
+    }
+
+    /// A service which records changes along with who made them
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait AuditedServer: Send + Sync {
+        /// Get the address of the caller
+        fn whoami(&self, ctx: &Context) -> impl Future<Output = String> + Send;
+        /// Record a change made by the caller
+        fn record(&self, change: Change, ctx: &Context) -> impl Future<Output = ()> + Send;
+        /// Receive each change made by the caller
+        fn watch(&self, ctx: &trait_rpc::server::Context) -> impl Future<Output = impl ::trait_rpc::futures::Stream<Item = Change> + Send> + Send;
+        /// Record each of the given changes, returns the number of changes recorded
+        fn import(&self, ctx: &Context, changes: impl ::trait_rpc::futures::Stream<Item = Change> + Send + Unpin)
+        -> impl Future<Output = u64> + Send;
+        /// Get the service for the user making the request
+        fn current(&self, ctx: &Context) -> impl Future<Output = impl Handler<Rpc = UserService>> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct AuditedHandler<_Server>(_Server);
+
+    impl<_Server: AuditedServer> Handler for AuditedHandler<_Server> {
+        type Rpc = Audited;
+        async fn handle(&self, ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::Whoami() => Ok(Response::Whoami(self.0.whoami(ctx).await)),
+                Request::Record(change) => Ok(Response::Record(self.0.record(change, ctx).await)),
+                Request::Watch(..) => {
+                    Err(HandlerError::Unsupported("`watch` is a streaming method, requests for it must be passed to `Handler::handle_stream`".to_string()))
+                }
+                Request::Import(..) => {
+                    Err(HandlerError::Unsupported("`import` takes a stream argument, requests for it must be passed to `Handler::handle_stream`".to_string()))
+                }
+                Request::Current(request) => self.0.current(ctx).await.handle(ctx, request).await.map(Response::Current),
+                Request::ImportItem(..) => {
+                    Err(HandlerError::Unsupported("stream items are not requests, they must be passed to `Handler::handle_stream` as incoming items".to_string()))
+                }
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                Request::Watch() => {
+                    let responses = self.0.watch(ctx).await.map(|value| Ok(Response::Watch(value)));
+                    let _ = sink.send_all(&mut ::std::pin::pin!(responses)).await;
+                }
+                Request::Import() => {
+                    let changes = filter_incoming(incoming, |request| match request {
+                        Request::ImportItem(item) => Some(item),
+                        _ => None,
+                    });
+                    let _ = sink.send(Response::Import(self.0.import(ctx, changes).await)).await;
+                }
+                Request::Current(request) => {
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::Current(.., request) => Some(request),
+                        _ => None,
+                    });
+                    let sink = MappedSink::new(&mut sink, Response::Current);
+                    self.0.current(ctx).await.handle_stream(ctx, request, incoming, sink).await?;
+                }
+                Request::ImportItem(..) => {}
+                request @ (Request::Whoami(..) | Request::Record(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service which records changes along with who made them
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct AuditedAsyncClient<_Client>(_Client);
+
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> AuditedAsyncClient<_Client> {
+        /// Get the address of the caller
+        pub async fn whoami(&self) -> Result<String, _Client::Error> {
+            match self.0.send(Request::Whoami()).await? {
+                Response::Whoami(value) => Ok(value),
+                other => Err(WrongResponseType::new("whoami", other.fn_name()).into()),
+            }
+        }
+        /// Record a change made by the caller
+        pub async fn record(&self, change: Change) -> Result<(), _Client::Error> {
+            match self.0.send(Request::Record(change)).await? {
+                Response::Record(value) => Ok(value),
+                other => Err(WrongResponseType::new("record", other.fn_name()).into()),
+            }
+        }
+        /// Receive each change made by the caller
+        pub async fn watch(&self) -> Result<impl ::trait_rpc::futures::Stream<Item = Result<Change, _Client::Error>>, _Client::Error> {
+            let responses = self.0.send_stream(Request::Watch()).await?;
+            Ok(responses.map(|response| match response? {
+                Response::Watch(value) => Ok(value),
+                other => Err(WrongResponseType::new("watch", other.fn_name()).into()),
+            }))
+        }
+        /// Record each of the given changes, returns the number of changes recorded
+        pub async fn import(&self, changes: impl Stream<Item = Change>) -> Result<u64, _Client::Error> {
+            match self.0.send_with_stream(Request::Import(), changes.map(Request::ImportItem)).await? {
+                Response::Import(value) => Ok(value),
+                other => Err(WrongResponseType::new("import", other.fn_name()).into()),
+            }
+        }
+        /// Get the service for the user making the request
+        pub fn current(
+            &self,
+        ) -> <UserService as Rpc>::AsyncClient<
+            MappedClient<_Client, <UserService as Rpc>::Request, Request, <UserService as Rpc>::Response, Response, ()>,
+        > {
+            UserService::async_client(MappedClient::new(self.0.clone(), (), Self::current_to_inner, Self::current_to_outer))
+        }
+        fn current_to_inner(outer: Result<Response, ::trait_rpc::client::NestedError>) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::Current(inner)) => Ok(inner),
+                Ok(other) => Err(WrongResponseType::new("current", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("current")),
+            }
+        }
+        fn current_to_outer((): (), inner: <UserService as Rpc>::Request) -> Request {
+            Request::Current(inner)
+        }
+    }
+
+    /// A service which records changes along with who made them
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct AuditedBlockingClient<_Client>(_Client);
+
+    impl<_Client: BlockingClient<Request, Response>> AuditedBlockingClient<_Client> {
+        /// Get the address of the caller
+        pub fn whoami(&self) -> Result<String, _Client::Error> {
+            match self.0.send(Request::Whoami())? {
+                Response::Whoami(value) => Ok(value),
+                other => Err(WrongResponseType::new("whoami", other.fn_name()).into()),
+            }
+        }
+        /// Record a change made by the caller
+        pub fn record(&self, change: Change) -> Result<(), _Client::Error> {
+            match self.0.send(Request::Record(change))? {
+                Response::Record(value) => Ok(value),
+                other => Err(WrongResponseType::new("record", other.fn_name()).into()),
+            }
+        }
+        /// Receive each change made by the caller
+        pub fn watch(&self) -> Result<impl Iterator<Item = Result<Change, _Client::Error>>, _Client::Error> {
+            let responses = self.0.send_stream(Request::Watch())?;
+            Ok(responses.map(|response| match response? {
+                Response::Watch(value) => Ok(value),
+                other => Err(WrongResponseType::new("watch", other.fn_name()).into()),
+            }))
+        }
+        /// Record each of the given changes, returns the number of changes recorded
+        pub fn import(&self, changes: impl IntoIterator<Item = Change>) -> Result<u64, _Client::Error> {
+            match self.0.send_with_stream(Request::Import(), changes.into_iter().map(Request::ImportItem))? {
+                Response::Import(value) => Ok(value),
+                other => Err(WrongResponseType::new("import", other.fn_name()).into()),
+            }
+        }
+        /// Get the service for the user making the request
+        pub fn current(
+            &self,
+        ) -> <UserService as Rpc>::BlockingClient<
+            MappedClient<_Client, <UserService as Rpc>::Request, Request, <UserService as Rpc>::Response, Response, ()>,
+        > {
+            UserService::blocking_client(MappedClient::new(self.0.clone(), (), Self::current_to_inner, Self::current_to_outer))
+        }
+        fn current_to_inner(outer: Result<Response, ::trait_rpc::client::NestedError>) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::Current(inner)) => Ok(inner),
+                Ok(other) => Err(WrongResponseType::new("current", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("current")),
+            }
+        }
+        fn current_to_outer((): (), inner: <UserService as Rpc>::Request) -> Request {
+            Request::Current(inner)
+        }
+    }
+}
@@ -0,0 +1,256 @@
+#[allow(
+    unused_imports,
+    reason = "These might not always be used, but they should be available in this module anyway"
+)]
+pub use sessions::{
+    Sessions, SessionsAsyncClient, SessionsBlockingClient, SessionsServer,
+};
+#[allow(
+    unused_imports,
+    reason = "These might not always be used, but it's easier to include always"
+)]
+mod sessions {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{
+            AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType,
+        },
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+    use std::marker::PhantomData;
+    /// A service with its own type named `Context`
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct Sessions;
+    impl Rpc for Sessions {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = SessionsAsyncClient<
+            _Client,
+        >;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> = SessionsBlockingClient<
+            _Client,
+        >;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(
+            transport: _Client,
+        ) -> SessionsAsyncClient<_Client> {
+            SessionsAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(
+            transport: _Client,
+        ) -> SessionsBlockingClient<_Client> {
+            SessionsBlockingClient(transport)
+        }
+    }
+    impl Sessions {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl SessionsServer) -> impl Handler<Rpc = Self> {
+            SessionsHandler(server)
+        }
+    }
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "open")]
+        Open(session::Context),
+        #[serde(rename = "compare")]
+        Compare(&session::Context),
+        #[serde(rename = "current")]
+        Current(),
+    }
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "open")]
+        Open(u64),
+        #[serde(rename = "compare")]
+        Compare(bool),
+        #[serde(rename = "current")]
+        Current(u64),
+    }
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Open(..) => "open",
+                Self::Compare(..) => "compare",
+                Self::Current(..) => "current",
+            }
+        }
+    }
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Open(..) => "open",
+                Self::Compare(..) => "compare",
+                Self::Current(..) => "current",
+            }
+        }
+        #[allow(
+            clippy::match_same_arms,
+            reason = "nested services of the same type have identical arms"
+        )]
+        fn is_stream(&self) -> bool {
+            false
+        }
+        #[allow(
+            clippy::match_same_arms,
+            reason = "nested services of the same type have identical arms"
+        )]
+        fn is_client_stream(&self) -> bool {
+            false
+        }
+        #[allow(
+            clippy::match_same_arms,
+            reason = "nested services of the same type have identical arms"
+        )]
+        fn is_stream_item(&self) -> bool {
+            false
+        }
+        #[allow(
+            clippy::match_same_arms,
+            reason = "nested services of the same type have identical arms"
+        )]
+        fn is_idempotent(&self) -> bool {
+            false
+        }
+        #[allow(
+            clippy::match_same_arms,
+            reason = "nested services of the same type have identical arms"
+        )]
+        fn is_notification(&self) -> bool {
+            false
+        }
+    }
+    /// A service with its own type named `Context`
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait SessionsServer: Send + Sync {
+        /// Open a session with the given context, which is sent by the client
+        fn open(&self, context: session::Context) -> impl Future<Output = u64> + Send;
+        /// Compare the given context with the caller's session, this is not the server context
+        fn compare(
+            &self,
+            context: &session::Context,
+        ) -> impl Future<Output = bool> + Send;
+        /// The id of the caller's session, found in the server context
+        fn current(&self, ctx: &Context) -> impl Future<Output = u64> + Send;
+    }
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct SessionsHandler<_Server>(_Server);
+    impl<_Server: SessionsServer> Handler for SessionsHandler<_Server> {
+        type Rpc = Sessions;
+        async fn handle(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+        ) -> Result<Response, HandlerError> {
+            match request {
+                Request::Open(context) => Ok(Response::Open(self.0.open(context).await)),
+                Request::Compare(context) => {
+                    Ok(Response::Compare(self.0.compare(context).await))
+                }
+                Request::Current() => Ok(Response::Current(self.0.current(ctx).await)),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            _incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                request @ (Request::Open(..)
+                | Request::Compare(..)
+                | Request::Current(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+    /// A service with its own type named `Context`
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct SessionsAsyncClient<_Client>(_Client);
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> SessionsAsyncClient<_Client> {
+        /// Open a session with the given context, which is sent by the client
+        pub async fn open(
+            &self,
+            context: session::Context,
+        ) -> Result<u64, _Client::Error> {
+            match self.0.send(Request::Open(context)).await? {
+                Response::Open(value) => Ok(value),
+                other => Err(WrongResponseType::new("open", other.fn_name()).into()),
+            }
+        }
+        /// Compare the given context with the caller's session, this is not the server context
+        pub async fn compare(
+            &self,
+            context: &session::Context,
+        ) -> Result<bool, _Client::Error> {
+            match self.0.send(Request::Compare(context)).await? {
+                Response::Compare(value) => Ok(value),
+                other => Err(WrongResponseType::new("compare", other.fn_name()).into()),
+            }
+        }
+        /// The id of the caller's session, found in the server context
+        pub async fn current(&self) -> Result<u64, _Client::Error> {
+            match self.0.send(Request::Current()).await? {
+                Response::Current(value) => Ok(value),
+                other => Err(WrongResponseType::new("current", other.fn_name()).into()),
+            }
+        }
+    }
+    /// A service with its own type named `Context`
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct SessionsBlockingClient<_Client>(_Client);
+    impl<_Client: BlockingClient<Request, Response>> SessionsBlockingClient<_Client> {
+        /// Open a session with the given context, which is sent by the client
+        pub fn open(&self, context: session::Context) -> Result<u64, _Client::Error> {
+            match self.0.send(Request::Open(context))? {
+                Response::Open(value) => Ok(value),
+                other => Err(WrongResponseType::new("open", other.fn_name()).into()),
+            }
+        }
+        /// Compare the given context with the caller's session, this is not the server context
+        pub fn compare(
+            &self,
+            context: &session::Context,
+        ) -> Result<bool, _Client::Error> {
+            match self.0.send(Request::Compare(context))? {
+                Response::Compare(value) => Ok(value),
+                other => Err(WrongResponseType::new("compare", other.fn_name()).into()),
+            }
+        }
+        /// The id of the caller's session, found in the server context
+        pub fn current(&self) -> Result<u64, _Client::Error> {
+            match self.0.send(Request::Current())? {
+                Response::Current(value) => Ok(value),
+                other => Err(WrongResponseType::new("current", other.fn_name()).into()),
+            }
+        }
+    }
+}
@@ -0,0 +1,309 @@
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use bank::{Bank, BankAsyncClient, BankBlockingClient, BankServer};
+
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod bank {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+
+    use std::marker::PhantomData;
+
+    /// A service for managing bank accounts
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct Bank;
+
+    impl Rpc for Bank {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = BankAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> = BankBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(transport: _Client) -> BankAsyncClient<_Client> {
+            BankAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(transport: _Client) -> BankBlockingClient<_Client> {
+            BankBlockingClient(transport)
+        }
+    }
+
+    impl Bank {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl BankServer) -> impl Handler<Rpc = Self> {
+            BankHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "balance")]
+        Balance(u64),
+        #[serde(rename = "transfer")]
+        Transfer(u64, u64, u64),
+        #[serde(rename = "transactions")]
+        Transactions(u64),
+        #[serde(rename = "account")]
+        Account(u64, <AccountService as Rpc>::Request),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "balance")]
+        Balance(Result<u64, ApiError>),
+        #[serde(rename = "transfer")]
+        Transfer(Result<(), TransferError>),
+        #[serde(rename = "transactions")]
+        Transactions(Result<Transaction, ApiError>),
+        #[serde(rename = "account")]
+        Account(<AccountService as Rpc>::Response),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Balance(..) => "balance",
+                Self::Transfer(..) => "transfer",
+                Self::Transactions(..) => "transactions",
+                Self::Account(..) => "account",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Balance(..) => "balance",
+                Self::Transfer(..) => "transfer",
+                Self::Transactions(..) => "transactions",
+                Self::Account(..) => "account",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            match self {
+                Self::Account(.., request) => request.is_stream(),
+                other => matches!(other, Self::Transactions(..)),
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            match self {
+                Self::Account(.., request) => request.is_client_stream(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            match self {
+                Self::Account(.., request) => request.is_stream_item(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            match self {
+                Self::Account(.., request) => request.is_idempotent(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            match self {
+                Self::Account(.., request) => request.is_notification(),
+                _ => false,
+            }
+        }
+    }
+
+    /// A service for managing bank accounts
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait BankServer: Send + Sync {
+        /// Get the balance of an account
+        fn balance(&self, account: u64) -> impl Future<Output = Result<u64, ApiError>> + Send;
+        /// Transfer an amount between two accounts
+        fn transfer(&self, from: u64, to: u64, amount: u64) -> impl Future<Output = Result<(), TransferError>> + Send;
+        /// Receive every transaction on an account
+        fn transactions(
+            &self,
+            account: u64,
+        ) -> impl Future<Output = impl ::trait_rpc::futures::Stream<Item = Result<Transaction, ApiError>> + Send> + Send;
+        /// Get the service for a single account
+        fn account(&self, account: u64) -> impl Future<Output = impl Handler<Rpc = AccountService>> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct BankHandler<_Server>(_Server);
+
+    impl<_Server: BankServer> Handler for BankHandler<_Server> {
+        type Rpc = Bank;
+        async fn handle(&self, ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::Balance(account) => Ok(Response::Balance(self.0.balance(account).await)),
+                Request::Transfer(from, to, amount) => Ok(Response::Transfer(self.0.transfer(from, to, amount).await)),
+                Request::Transactions(..) => {
+                    Err(HandlerError::Unsupported("`transactions` is a streaming method, requests for it must be passed to `Handler::handle_stream`".to_string()))
+                }
+                Request::Account(account, request) => self.0.account(account).await.handle(ctx, request).await.map(Response::Account),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                Request::Transactions(account) => {
+                    let responses = self.0.transactions(account).await.map(|value| Ok(Response::Transactions(value)));
+                    let _ = sink.send_all(&mut ::std::pin::pin!(responses)).await;
+                }
+                Request::Account(account, request) => {
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::Account(.., request) => Some(request),
+                        _ => None,
+                    });
+                    let sink = MappedSink::new(&mut sink, Response::Account);
+                    self.0.account(account).await.handle_stream(ctx, request, incoming, sink).await?;
+                }
+                request @ (Request::Balance(..) | Request::Transfer(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service for managing bank accounts
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct BankAsyncClient<_Client>(_Client);
+
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> BankAsyncClient<_Client> {
+        /// Get the balance of an account
+        pub async fn balance(&self, account: u64) -> Result<u64, CallError<ApiError, _Client::Error>> {
+            match self.0.send(Request::Balance(account)).await.map_err(CallError::Rpc)? {
+                Response::Balance(value) => value.map_err(CallError::Application),
+                other => Err(CallError::Rpc(WrongResponseType::new("balance", other.fn_name()).into())),
+            }
+        }
+        /// Transfer an amount between two accounts
+        pub async fn transfer(&self, from: u64, to: u64, amount: u64) -> Result<(), CallError<TransferError, _Client::Error>> {
+            match self.0.send(Request::Transfer(from, to, amount)).await.map_err(CallError::Rpc)? {
+                Response::Transfer(value) => value.map_err(CallError::Application),
+                other => Err(CallError::Rpc(WrongResponseType::new("transfer", other.fn_name()).into())),
+            }
+        }
+        /// Receive every transaction on an account
+        pub async fn transactions(
+            &self,
+            account: u64,
+        ) -> Result<
+            impl ::trait_rpc::futures::Stream<Item = Result<Transaction, CallError<ApiError, _Client::Error>>>,
+            CallError<ApiError, _Client::Error>,
+        > {
+            let responses = self.0.send_stream(Request::Transactions(account)).await.map_err(CallError::Rpc)?;
+            Ok(responses.map(|response| match response.map_err(CallError::Rpc)? {
+                Response::Transactions(value) => value.map_err(CallError::Application),
+                other => Err(CallError::Rpc(WrongResponseType::new("transactions", other.fn_name()).into())),
+            }))
+        }
+        /// Get the service for a single account
+        pub fn account(
+            &self,
+            account: u64,
+        ) -> <AccountService as Rpc>::AsyncClient<
+            MappedClient<_Client, <AccountService as Rpc>::Request, Request, <AccountService as Rpc>::Response, Response, (u64,)>,
+        > {
+            AccountService::async_client(MappedClient::new(self.0.clone(), (account,), Self::account_to_inner, Self::account_to_outer))
+        }
+        fn account_to_inner(outer: Result<Response, ::trait_rpc::client::NestedError>) -> Result<<AccountService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::Account(inner)) => Ok(inner),
+                Ok(other) => Err(WrongResponseType::new("account", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("account")),
+            }
+        }
+        fn account_to_outer((account,): (u64,), inner: <AccountService as Rpc>::Request) -> Request {
+            Request::Account(account, inner)
+        }
+    }
+
+    /// A service for managing bank accounts
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct BankBlockingClient<_Client>(_Client);
+
+    impl<_Client: BlockingClient<Request, Response>> BankBlockingClient<_Client> {
+        /// Get the balance of an account
+        pub fn balance(&self, account: u64) -> Result<u64, CallError<ApiError, _Client::Error>> {
+            match self.0.send(Request::Balance(account)).map_err(CallError::Rpc)? {
+                Response::Balance(value) => value.map_err(CallError::Application),
+                other => Err(CallError::Rpc(WrongResponseType::new("balance", other.fn_name()).into())),
+            }
+        }
+        /// Transfer an amount between two accounts
+        pub fn transfer(&self, from: u64, to: u64, amount: u64) -> Result<(), CallError<TransferError, _Client::Error>> {
+            match self.0.send(Request::Transfer(from, to, amount)).map_err(CallError::Rpc)? {
+                Response::Transfer(value) => value.map_err(CallError::Application),
+                other => Err(CallError::Rpc(WrongResponseType::new("transfer", other.fn_name()).into())),
+            }
+        }
+        /// Receive every transaction on an account
+        pub fn transactions(
+            &self,
+            account: u64,
+        ) -> Result<impl Iterator<Item = Result<Transaction, CallError<ApiError, _Client::Error>>>, CallError<ApiError, _Client::Error>> {
+            let responses = self.0.send_stream(Request::Transactions(account)).map_err(CallError::Rpc)?;
+            Ok(responses.map(|response| match response.map_err(CallError::Rpc)? {
+                Response::Transactions(value) => value.map_err(CallError::Application),
+                other => Err(CallError::Rpc(WrongResponseType::new("transactions", other.fn_name()).into())),
+            }))
+        }
+        /// Get the service for a single account
+        pub fn account(
+            &self,
+            account: u64,
+        ) -> <AccountService as Rpc>::BlockingClient<
+            MappedClient<_Client, <AccountService as Rpc>::Request, Request, <AccountService as Rpc>::Response, Response, (u64,)>,
+        > {
+            AccountService::blocking_client(MappedClient::new(self.0.clone(), (account,), Self::account_to_inner, Self::account_to_outer))
+        }
+        fn account_to_inner(outer: Result<Response, ::trait_rpc::client::NestedError>) -> Result<<AccountService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::Account(inner)) => Ok(inner),
+                Ok(other) => Err(WrongResponseType::new("account", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("account")),
+            }
+        }
+        fn account_to_outer((account,): (u64,), inner: <AccountService as Rpc>::Request) -> Request {
+            Request::Account(account, inner)
+        }
+    }
+}
@@ -0,0 +1,458 @@
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use users_service::{UsersService, UsersServiceAsyncClient, UsersServiceBlockingClient, UsersServiceServer};
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod users_service {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+    use std::marker::PhantomData;
+
+    /// A service for users
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct UsersService;
+    impl Rpc for UsersService {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = UsersServiceAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> =
+            UsersServiceBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(
+            transport: _Client,
+        ) -> UsersServiceAsyncClient<_Client> {
+            UsersServiceAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(
+            transport: _Client,
+        ) -> UsersServiceBlockingClient<_Client> {
+            UsersServiceBlockingClient(transport)
+        }
+    }
+
+    impl UsersService {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl UsersServiceServer) -> impl Handler<Rpc = Self> {
+            UsersServiceHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "by_id")]
+        ById(u64, <UserService as Rpc>::Request),
+        #[serde(rename = "current")]
+        Current(LoginToken, <UserService as Rpc>::Request),
+        #[serde(rename = "current.open")]
+        CurrentOpen(LoginToken),
+        #[serde(rename = "current.object")]
+        CurrentObject(u64, <UserService as Rpc>::Request),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "by_id")]
+        ById(Option<<UserService as Rpc>::Response>),
+        #[serde(rename = "current")]
+        Current(Result<<UserService as Rpc>::Response, LoginExpired>),
+        #[serde(rename = "current.open")]
+        CurrentOpen(u64),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::ById(..) => "by_id",
+                Self::Current(..) => "current",
+                Self::CurrentOpen(..) => "current.open",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::ById(..) => "by_id",
+                Self::Current(..) => "current",
+                Self::CurrentOpen(..) => "current.open",
+                Self::CurrentObject(..) => "current.object",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_stream(),
+                Self::Current(.., request) => request.is_stream(),
+                Self::CurrentObject(.., request) => request.is_stream(),
+                other => matches!(other, Self::CurrentOpen(..)),
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_client_stream(),
+                Self::Current(.., request) => request.is_client_stream(),
+                Self::CurrentObject(.., request) => request.is_client_stream(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_stream_item(),
+                Self::Current(.., request) => request.is_stream_item(),
+                Self::CurrentObject(.., request) => request.is_stream_item(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_idempotent(),
+                Self::Current(.., request) => request.is_idempotent(),
+                Self::CurrentObject(.., request) => request.is_idempotent(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_notification(),
+                Self::Current(.., request) => request.is_notification(),
+                Self::CurrentObject(.., request) => request.is_notification(),
+                _ => false,
+            }
+        }
+    }
+
+    /// A service for users
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait UsersServiceServer: Send + Sync {
+        /// Get the service for a single user, if the user exists
+        fn by_id(&self, id: u64) -> impl Future<Output = Option<impl Handler<Rpc = UserService>>> + Send;
+        /// Get the service for the logged in user
+        fn current(
+            &self,
+            token: LoginToken,
+        ) -> impl Future<Output = Result<impl Handler<Rpc = UserService> + Sync, LoginExpired>> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct UsersServiceHandler<_Server>(_Server);
+    impl<_Server: UsersServiceServer> Handler for UsersServiceHandler<_Server> {
+        type Rpc = UsersService;
+        async fn handle(&self, ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::ById(id, request) => match self.0.by_id(id).await {
+                    Some(handler) => handler
+                        .handle(ctx, request)
+                        .await
+                        .map(|response| Response::ById(Some(response))),
+                    None => Ok(Response::ById(None)),
+                },
+                Request::Current(token, request) => match self.0.current(token).await {
+                    Ok(handler) => handler
+                        .handle(ctx, request)
+                        .await
+                        .map(|response| Response::Current(Ok(response))),
+                    Err(error) => Ok(Response::Current(Err(error))),
+                },
+                Request::CurrentOpen(..) => {
+                    Err(HandlerError::Unsupported("`current` is opened as a handle, requests to open it must be passed to `Handler::handle_stream`".to_string()))
+                }
+                Request::CurrentObject(object, request) => {
+                    ::trait_rpc::server::objects::call::<UserService>(ctx, object, request)
+                        .await
+                        .map(|response| Response::Current(Ok(response)))
+                }
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                Request::ById(id, request) => {
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::ById(.., request) => Some(request),
+                        _ => None,
+                    });
+                    match self.0.by_id(id).await {
+                        Some(handler) => {
+                            let sink = MappedSink::new(&mut sink, |response| Response::ById(Some(response)));
+                            handler.handle_stream(ctx, request, incoming, sink).await?;
+                        }
+                        None => {
+                            let _ = sink.send(Response::ById(None)).await;
+                        }
+                    }
+                }
+                Request::Current(token, request) => {
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::Current(.., request) => Some(request),
+                        _ => None,
+                    });
+                    match self.0.current(token).await {
+                        Ok(handler) => {
+                            let sink = MappedSink::new(&mut sink, |response| Response::Current(Ok(response)));
+                            handler.handle_stream(ctx, request, incoming, sink).await?;
+                        }
+                        Err(error) => {
+                            let _ = sink.send(Response::Current(Err(error))).await;
+                        }
+                    }
+                }
+                Request::CurrentOpen(token) => match self.0.current(token).await {
+                    Ok(handler) => {
+                        let sink = MappedSink::new(&mut sink, Response::CurrentOpen);
+                        ::trait_rpc::server::objects::serve(ctx, handler, sink).await?;
+                    }
+                    Err(error) => {
+                        let _ = sink.send(Response::Current(Err(error))).await;
+                    }
+                },
+                Request::CurrentObject(object, request) => {
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::CurrentObject(_, request) => Some(request),
+                        _ => None,
+                    });
+                    let sink = MappedSink::new(&mut sink, |response| Response::Current(Ok(response)));
+                    ::trait_rpc::server::objects::call_stream::<UserService, _, _>(
+                        ctx, object, request, incoming, sink,
+                    )
+                    .await?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service for users
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct UsersServiceAsyncClient<_Client>(_Client);
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> UsersServiceAsyncClient<_Client> {
+        /// Get the service for a single user, if the user exists
+        pub fn by_id(
+            &self,
+            id: u64,
+        ) -> <UserService as Rpc>::AsyncClient<
+            MappedClient<
+                _Client,
+                <UserService as Rpc>::Request,
+                Request,
+                <UserService as Rpc>::Response,
+                Response,
+                (u64,),
+            >,
+        > {
+            UserService::async_client(MappedClient::new(
+                self.0.clone(),
+                (id,),
+                Self::by_id_to_inner,
+                Self::by_id_to_outer,
+            ))
+        }
+        fn by_id_to_inner(
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::ById(Some(inner))) => Ok(inner),
+                Ok(Response::ById(None)) => Err(::trait_rpc::client::NotFound::new("by_id").into()),
+                Ok(other) => Err(WrongResponseType::new("by_id", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("by_id")),
+            }
+        }
+        fn by_id_to_outer((id,): (u64,), inner: <UserService as Rpc>::Request) -> Request {
+            Request::ById(id, inner)
+        }
+        /// Get the service for the logged in user
+        pub fn current(
+            &self,
+            token: LoginToken,
+        ) -> <UserService as Rpc>::AsyncClient<
+            MappedClient<
+                _Client,
+                <UserService as Rpc>::Request,
+                Request,
+                <UserService as Rpc>::Response,
+                Response,
+                (LoginToken,),
+            >,
+        > {
+            UserService::async_client(MappedClient::new(
+                self.0.clone(),
+                (token,),
+                Self::current_to_inner,
+                Self::current_to_outer,
+            ))
+        }
+        fn current_to_inner(
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::Current(Ok(inner))) => Ok(inner),
+                Ok(Response::Current(Err(error))) => {
+                    Err(::trait_rpc::client::ApplicationError::new("current", error).into())
+                }
+                Ok(other) => Err(WrongResponseType::new("current", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("current")),
+            }
+        }
+        fn current_to_outer((token,): (LoginToken,), inner: <UserService as Rpc>::Request) -> Request {
+            Request::Current(token, inner)
+        }
+        /// Get the service for the logged in user
+        ///
+        /// Open a handle to the nested service, the server calls this method once and keeps
+        /// the nested service for each call made with the returned client, until every clone
+        /// of it has been dropped
+        pub async fn open_current(
+            &self,
+            token: LoginToken,
+        ) -> Result<
+            <UserService as Rpc>::AsyncClient<
+                MappedClient<
+                    _Client,
+                    <UserService as Rpc>::Request,
+                    Request,
+                    <UserService as Rpc>::Response,
+                    Response,
+                    ::trait_rpc::client::ObjectHandle,
+                >,
+            >,
+            _Client::Error,
+        >
+        where
+            _Client: ::trait_rpc::client::HandleClient<Request, Response>,
+        {
+            match ::trait_rpc::client::HandleClient::open(&self.0, Request::CurrentOpen(token)).await? {
+                (Response::CurrentOpen(object), open) => {
+                    let object = ::trait_rpc::client::ObjectHandle::new(object, open);
+                    Ok(UserService::async_client(MappedClient::new(
+                        self.0.clone(),
+                        object,
+                        Self::current_to_inner,
+                        Self::current_to_object,
+                    )))
+                }
+                (Response::Current(Err(error)), _) => Err(::trait_rpc::client::NestedError::from(
+                    ::trait_rpc::client::ApplicationError::new("current", error),
+                )
+                .into()),
+                (other, _) => Err(WrongResponseType::new("current.open", other.fn_name()).into()),
+            }
+        }
+        fn current_to_object(
+            object: ::trait_rpc::client::ObjectHandle,
+            inner: <UserService as Rpc>::Request,
+        ) -> Request {
+            Request::CurrentObject(object.id(), inner)
+        }
+    }
+
+    /// A service for users
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct UsersServiceBlockingClient<_Client>(_Client);
+    impl<_Client: BlockingClient<Request, Response>> UsersServiceBlockingClient<_Client> {
+        /// Get the service for a single user, if the user exists
+        pub fn by_id(
+            &self,
+            id: u64,
+        ) -> <UserService as Rpc>::BlockingClient<
+            MappedClient<
+                _Client,
+                <UserService as Rpc>::Request,
+                Request,
+                <UserService as Rpc>::Response,
+                Response,
+                (u64,),
+            >,
+        > {
+            UserService::blocking_client(MappedClient::new(
+                self.0.clone(),
+                (id,),
+                Self::by_id_to_inner,
+                Self::by_id_to_outer,
+            ))
+        }
+        fn by_id_to_inner(
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::ById(Some(inner))) => Ok(inner),
+                Ok(Response::ById(None)) => Err(::trait_rpc::client::NotFound::new("by_id").into()),
+                Ok(other) => Err(WrongResponseType::new("by_id", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("by_id")),
+            }
+        }
+        fn by_id_to_outer((id,): (u64,), inner: <UserService as Rpc>::Request) -> Request {
+            Request::ById(id, inner)
+        }
+        /// Get the service for the logged in user
+        pub fn current(
+            &self,
+            token: LoginToken,
+        ) -> <UserService as Rpc>::BlockingClient<
+            MappedClient<
+                _Client,
+                <UserService as Rpc>::Request,
+                Request,
+                <UserService as Rpc>::Response,
+                Response,
+                (LoginToken,),
+            >,
+        > {
+            UserService::blocking_client(MappedClient::new(
+                self.0.clone(),
+                (token,),
+                Self::current_to_inner,
+                Self::current_to_outer,
+            ))
+        }
+        fn current_to_inner(
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::Current(Ok(inner))) => Ok(inner),
+                Ok(Response::Current(Err(error))) => {
+                    Err(::trait_rpc::client::ApplicationError::new("current", error).into())
+                }
+                Ok(other) => Err(WrongResponseType::new("current", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("current")),
+            }
+        }
+        fn current_to_outer((token,): (LoginToken,), inner: <UserService as Rpc>::Request) -> Request {
+            Request::Current(token, inner)
+        }
+    }
+}
@@ -0,0 +1,529 @@
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use users_service::{UsersService, UsersServiceAsyncClient, UsersServiceBlockingClient, UsersServiceServer};
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod users_service {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+    use std::marker::PhantomData;
+
+    /// A service for users
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct UsersService;
+    impl Rpc for UsersService {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = UsersServiceAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> =
+            UsersServiceBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(
+            transport: _Client,
+        ) -> UsersServiceAsyncClient<_Client> {
+            UsersServiceAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(
+            transport: _Client,
+        ) -> UsersServiceBlockingClient<_Client> {
+            UsersServiceBlockingClient(transport)
+        }
+    }
+
+    impl UsersService {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl UsersServiceServer) -> impl Handler<Rpc = Self> {
+            UsersServiceHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "list")]
+        List(),
+        #[serde(rename = "by_id")]
+        ById(u64, <UserService as Rpc>::Request),
+        #[serde(rename = "by_id.open")]
+        ByIdOpen(u64),
+        #[serde(rename = "by_id.object")]
+        ByIdObject(u64, <UserService as Rpc>::Request),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "list")]
+        List(Vec<User>),
+        #[serde(rename = "by_id")]
+        ById(<UserService as Rpc>::Response),
+        #[serde(rename = "by_id.open")]
+        ByIdOpen(u64),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::List(..) => "list",
+                Self::ById(..) => "by_id",
+                Self::ByIdOpen(..) => "by_id.open",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::List(..) => "list",
+                Self::ById(..) => "by_id",
+                Self::ByIdOpen(..) => "by_id.open",
+                Self::ByIdObject(..) => "by_id.object",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_stream(),
+                Self::ByIdObject(.., request) => request.is_stream(),
+                other => matches!(other, Self::ByIdOpen(..)),
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_client_stream(),
+                Self::ByIdObject(.., request) => request.is_client_stream(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_stream_item(),
+                Self::ByIdObject(.., request) => request.is_stream_item(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_idempotent(),
+                Self::ByIdObject(.., request) => request.is_idempotent(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_notification(),
+                Self::ByIdObject(.., request) => request.is_notification(),
+                _ => false,
+            }
+        }
+    }
+
+    /// A service for users
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait UsersServiceServer: Send + Sync {
+        /// Get a list of users
+        fn list(&self) -> impl Future<Output = Vec<User>> + Send;
+        /// Get the service for a single user
+        fn by_id(&self, id: u64) -> impl Future<Output = impl Handler<Rpc = UserService> + Sync> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct UsersServiceHandler<_Server>(_Server);
+    impl<_Server: UsersServiceServer> Handler for UsersServiceHandler<_Server> {
+        type Rpc = UsersService;
+        async fn handle(&self, ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::List() => Ok(Response::List(self.0.list().await)),
+                Request::ById(id, request) => self.0.by_id(id).await.handle(ctx, request).await.map(Response::ById),
+                Request::ByIdOpen(..) => {
+                    Err(HandlerError::Unsupported("`by_id` is opened as a handle, requests to open it must be passed to `Handler::handle_stream`".to_string()))
+                }
+                Request::ByIdObject(object, request) => {
+                    ::trait_rpc::server::objects::call::<UserService>(ctx, object, request)
+                        .await
+                        .map(Response::ById)
+                }
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                Request::ById(id, request) => {
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::ById(.., request) => Some(request),
+                        _ => None,
+                    });
+                    let sink = MappedSink::new(&mut sink, Response::ById);
+                    self.0
+                        .by_id(id)
+                        .await
+                        .handle_stream(ctx, request, incoming, sink)
+                        .await?;
+                }
+                Request::ByIdOpen(id) => {
+                    let handler = self.0.by_id(id).await;
+                    let sink = MappedSink::new(&mut sink, Response::ByIdOpen);
+                    ::trait_rpc::server::objects::serve(ctx, handler, sink).await?;
+                }
+                Request::ByIdObject(object, request) => {
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::ByIdObject(_, request) => Some(request),
+                        _ => None,
+                    });
+                    let sink = MappedSink::new(&mut sink, Response::ById);
+                    ::trait_rpc::server::objects::call_stream::<UserService, _, _>(
+                        ctx, object, request, incoming, sink,
+                    )
+                    .await?;
+                }
+                request @ Request::List(..) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service for users
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct UsersServiceAsyncClient<_Client>(_Client);
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> UsersServiceAsyncClient<_Client> {
+        /// Get a list of users
+        pub async fn list(&self) -> Result<Vec<User>, _Client::Error> {
+            match self.0.send(Request::List()).await? {
+                Response::List(value) => Ok(value),
+                other => Err(WrongResponseType::new("list", other.fn_name()).into()),
+            }
+        }
+        /// Get the service for a single user
+        pub fn by_id(
+            &self,
+            id: u64,
+        ) -> <UserService as Rpc>::AsyncClient<
+            MappedClient<
+                _Client,
+                <UserService as Rpc>::Request,
+                Request,
+                <UserService as Rpc>::Response,
+                Response,
+                (u64,),
+            >,
+        > {
+            UserService::async_client(MappedClient::new(
+                self.0.clone(),
+                (id,),
+                Self::by_id_to_inner,
+                Self::by_id_to_outer,
+            ))
+        }
+        fn by_id_to_inner(
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::ById(inner)) => Ok(inner),
+                Ok(other) => Err(WrongResponseType::new("by_id", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("by_id")),
+            }
+        }
+        fn by_id_to_outer((id,): (u64,), inner: <UserService as Rpc>::Request) -> Request {
+            Request::ById(id, inner)
+        }
+        /// Get the service for a single user
+        ///
+        /// Open a handle to the nested service, the server calls this method once and keeps
+        /// the nested service for each call made with the returned client, until every clone
+        /// of it has been dropped
+        pub async fn open_by_id(
+            &self,
+            id: u64,
+        ) -> Result<
+            <UserService as Rpc>::AsyncClient<
+                MappedClient<
+                    _Client,
+                    <UserService as Rpc>::Request,
+                    Request,
+                    <UserService as Rpc>::Response,
+                    Response,
+                    ::trait_rpc::client::ObjectHandle,
+                >,
+            >,
+            _Client::Error,
+        >
+        where
+            _Client: ::trait_rpc::client::HandleClient<Request, Response>,
+        {
+            match ::trait_rpc::client::HandleClient::open(&self.0, Request::ByIdOpen(id)).await? {
+                (Response::ByIdOpen(object), open) => {
+                    let object = ::trait_rpc::client::ObjectHandle::new(object, open);
+                    Ok(UserService::async_client(MappedClient::new(
+                        self.0.clone(),
+                        object,
+                        Self::by_id_to_inner,
+                        Self::by_id_to_object,
+                    )))
+                }
+                (other, _) => Err(WrongResponseType::new("by_id.open", other.fn_name()).into()),
+            }
+        }
+        fn by_id_to_object(object: ::trait_rpc::client::ObjectHandle, inner: <UserService as Rpc>::Request) -> Request {
+            Request::ByIdObject(object.id(), inner)
+        }
+    }
+
+    /// A service for users
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct UsersServiceBlockingClient<_Client>(_Client);
+    impl<_Client: BlockingClient<Request, Response>> UsersServiceBlockingClient<_Client> {
+        /// Get a list of users
+        pub fn list(&self) -> Result<Vec<User>, _Client::Error> {
+            match self.0.send(Request::List())? {
+                Response::List(value) => Ok(value),
+                other => Err(WrongResponseType::new("list", other.fn_name()).into()),
+            }
+        }
+        /// Get the service for a single user
+        pub fn by_id(
+            &self,
+            id: u64,
+        ) -> <UserService as Rpc>::BlockingClient<
+            MappedClient<
+                _Client,
+                <UserService as Rpc>::Request,
+                Request,
+                <UserService as Rpc>::Response,
+                Response,
+                (u64,),
+            >,
+        > {
+            UserService::blocking_client(MappedClient::new(
+                self.0.clone(),
+                (id,),
+                Self::by_id_to_inner,
+                Self::by_id_to_outer,
+            ))
+        }
+        fn by_id_to_inner(
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::ById(inner)) => Ok(inner),
+                Ok(other) => Err(WrongResponseType::new("by_id", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("by_id")),
+            }
+        }
+        fn by_id_to_outer((id,): (u64,), inner: <UserService as Rpc>::Request) -> Request {
+            Request::ById(id, inner)
+        }
+    }
+}
+
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use user_service::{UserService, UserServiceAsyncClient, UserServiceBlockingClient, UserServiceServer};
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod user_service {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+    use std::marker::PhantomData;
+
+    /// A service for a single user
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct UserService;
+    impl Rpc for UserService {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = UserServiceAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> =
+            UserServiceBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(
+            transport: _Client,
+        ) -> UserServiceAsyncClient<_Client> {
+            UserServiceAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(
+            transport: _Client,
+        ) -> UserServiceBlockingClient<_Client> {
+            UserServiceBlockingClient(transport)
+        }
+    }
+
+    impl UserService {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl UserServiceServer) -> impl Handler<Rpc = Self> {
+            UserServiceHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "get")]
+        Get(),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "get")]
+        Get(User),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Get(..) => "get",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Get(..) => "get",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            false
+        }
+    }
+
+    /// A service for a single user
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait UserServiceServer: Send + Sync {
+        /// Get the user
+        fn get(&self) -> impl Future<Output = User> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct UserServiceHandler<_Server>(_Server);
+    impl<_Server: UserServiceServer> Handler for UserServiceHandler<_Server> {
+        type Rpc = UserService;
+        async fn handle(
+            &self,
+            _ctx: &::trait_rpc::server::Context,
+            request: Request,
+        ) -> Result<Response, HandlerError> {
+            match request {
+                Request::Get() => Ok(Response::Get(self.0.get().await)),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            _incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                request @ Request::Get(..) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service for a single user
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct UserServiceAsyncClient<_Client>(_Client);
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> UserServiceAsyncClient<_Client> {
+        /// Get the user
+        pub async fn get(&self) -> Result<User, _Client::Error> {
+            match self.0.send(Request::Get()).await? {
+                Response::Get(value) => Ok(value),
+                other => Err(WrongResponseType::new("get", other.fn_name()).into()),
+            }
+        }
+    }
+
+    /// A service for a single user
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct UserServiceBlockingClient<_Client>(_Client);
+    impl<_Client: BlockingClient<Request, Response>> UserServiceBlockingClient<_Client> {
+        /// Get the user
+        pub fn get(&self) -> Result<User, _Client::Error> {
+            match self.0.send(Request::Get())? {
+                Response::Get(value) => Ok(value),
+                other => Err(WrongResponseType::new("get", other.fn_name()).into()),
+            }
+        }
+    }
+}
@@ -0,0 +1,289 @@
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use todo_service::{TodoService, TodoServiceAsyncClient, TodoServiceBlockingClient, TodoServiceServer};
+
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod todo_service {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+
+    use std::marker::PhantomData;
+
+    /// A service for managing to-do items
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct TodoService;
+
+    impl Rpc for TodoService {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = TodoServiceAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> = TodoServiceBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(transport: _Client) -> TodoServiceAsyncClient<_Client> {
+            TodoServiceAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(transport: _Client) -> TodoServiceBlockingClient<_Client> {
+            TodoServiceBlockingClient(transport)
+        }
+    }
+
+    impl TodoService {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl TodoServiceServer) -> impl Handler<Rpc = Self> {
+            TodoServiceHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "get_todos")]
+        GetTodos(),
+        #[serde(rename = "get_todo")]
+        GetTodo(String),
+        #[serde(rename = "new_todo")]
+        NewTodo(Todo),
+        #[serde(rename = "list")]
+        List(String, <ListService as Rpc>::Request),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "get_todos")]
+        GetTodos(Vec<Todo>),
+        #[serde(rename = "get_todo")]
+        GetTodo(Option<Todo>),
+        #[serde(rename = "new_todo")]
+        NewTodo(()),
+        #[serde(rename = "list")]
+        List(<ListService as Rpc>::Response),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::GetTodos(..) => "get_todos",
+                Self::GetTodo(..) => "get_todo",
+                Self::NewTodo(..) => "new_todo",
+                Self::List(..) => "list",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::GetTodos(..) => "get_todos",
+                Self::GetTodo(..) => "get_todo",
+                Self::NewTodo(..) => "new_todo",
+                Self::List(..) => "list",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            match self {
+                Self::List(.., request) => request.is_stream(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            match self {
+                Self::List(.., request) => request.is_client_stream(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            match self {
+                Self::List(.., request) => request.is_stream_item(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            match self {
+                Self::List(.., request) => request.is_idempotent(),
+                other => matches!(other, Self::GetTodos(..) | Self::GetTodo(..)),
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            match self {
+                Self::List(.., request) => request.is_notification(),
+                _ => false,
+            }
+        }
+    }
+
+    /// A service for managing to-do items
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait TodoServiceServer: Send + Sync {
+        /// Get every to-do item
+        fn get_todos(&self) -> impl Future<Output = Vec<Todo>> + Send;
+        /// Get a to-do item by name
+        fn get_todo(&self, name: String) -> impl Future<Output = Option<Todo>> + Send;
+        /// Add a new to-do item
+        fn new_todo(&self, todo: Todo) -> impl Future<Output = ()> + Send;
+        /// Get the service for a single list of to-do items
+        fn list(&self, name: String) -> impl Future<Output = impl Handler<Rpc = ListService>> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct TodoServiceHandler<_Server>(_Server);
+
+    impl<_Server: TodoServiceServer> Handler for TodoServiceHandler<_Server> {
+        type Rpc = TodoService;
+        async fn handle(&self, ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::GetTodos() => Ok(Response::GetTodos(self.0.get_todos().await)),
+                Request::GetTodo(name) => Ok(Response::GetTodo(self.0.get_todo(name).await)),
+                Request::NewTodo(todo) => Ok(Response::NewTodo(self.0.new_todo(todo).await)),
+                Request::List(name, request) => self.0.list(name).await.handle(ctx, request).await.map(Response::List),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                Request::List(name, request) => {
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::List(.., request) => Some(request),
+                        _ => None,
+                    });
+                    let sink = MappedSink::new(&mut sink, Response::List);
+                    self.0.list(name).await.handle_stream(ctx, request, incoming, sink).await?;
+                }
+                request @ (Request::GetTodos(..) | Request::GetTodo(..) | Request::NewTodo(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service for managing to-do items
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct TodoServiceAsyncClient<_Client>(_Client);
+
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> TodoServiceAsyncClient<_Client> {
+        /// Get every to-do item
+        pub async fn get_todos(&self) -> Result<Vec<Todo>, _Client::Error> {
+            match self.0.send(Request::GetTodos()).await? {
+                Response::GetTodos(value) => Ok(value),
+                other => Err(WrongResponseType::new("get_todos", other.fn_name()).into()),
+            }
+        }
+        /// Get a to-do item by name
+        pub async fn get_todo(&self, name: String) -> Result<Option<Todo>, _Client::Error> {
+            match self.0.send(Request::GetTodo(name)).await? {
+                Response::GetTodo(value) => Ok(value),
+                other => Err(WrongResponseType::new("get_todo", other.fn_name()).into()),
+            }
+        }
+        /// Add a new to-do item
+        pub async fn new_todo(&self, todo: Todo) -> Result<(), _Client::Error> {
+            match self.0.send(Request::NewTodo(todo)).await? {
+                Response::NewTodo(value) => Ok(value),
+                other => Err(WrongResponseType::new("new_todo", other.fn_name()).into()),
+            }
+        }
+        /// Get the service for a single list of to-do items
+        pub fn list(
+            &self,
+            name: String,
+        ) -> <ListService as Rpc>::AsyncClient<
+            MappedClient<_Client, <ListService as Rpc>::Request, Request, <ListService as Rpc>::Response, Response, (String,)>,
+        > {
+            ListService::async_client(MappedClient::new(self.0.clone(), (name,), Self::list_to_inner, Self::list_to_outer))
+        }
+        fn list_to_inner(outer: Result<Response, ::trait_rpc::client::NestedError>) -> Result<<ListService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::List(inner)) => Ok(inner),
+                Ok(other) => Err(WrongResponseType::new("list", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("list")),
+            }
+        }
+        fn list_to_outer((name,): (String,), inner: <ListService as Rpc>::Request) -> Request {
+            Request::List(name, inner)
+        }
+    }
+
+    /// A service for managing to-do items
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct TodoServiceBlockingClient<_Client>(_Client);
+
+    impl<_Client: BlockingClient<Request, Response>> TodoServiceBlockingClient<_Client> {
+        /// Get every to-do item
+        pub fn get_todos(&self) -> Result<Vec<Todo>, _Client::Error> {
+            match self.0.send(Request::GetTodos())? {
+                Response::GetTodos(value) => Ok(value),
+                other => Err(WrongResponseType::new("get_todos", other.fn_name()).into()),
+            }
+        }
+        /// Get a to-do item by name
+        pub fn get_todo(&self, name: String) -> Result<Option<Todo>, _Client::Error> {
+            match self.0.send(Request::GetTodo(name))? {
+                Response::GetTodo(value) => Ok(value),
+                other => Err(WrongResponseType::new("get_todo", other.fn_name()).into()),
+            }
+        }
+        /// Add a new to-do item
+        pub fn new_todo(&self, todo: Todo) -> Result<(), _Client::Error> {
+            match self.0.send(Request::NewTodo(todo))? {
+                Response::NewTodo(value) => Ok(value),
+                other => Err(WrongResponseType::new("new_todo", other.fn_name()).into()),
+            }
+        }
+        /// Get the service for a single list of to-do items
+        pub fn list(
+            &self,
+            name: String,
+        ) -> <ListService as Rpc>::BlockingClient<
+            MappedClient<_Client, <ListService as Rpc>::Request, Request, <ListService as Rpc>::Response, Response, (String,)>,
+        > {
+            ListService::blocking_client(MappedClient::new(self.0.clone(), (name,), Self::list_to_inner, Self::list_to_outer))
+        }
+        fn list_to_inner(outer: Result<Response, ::trait_rpc::client::NestedError>) -> Result<<ListService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::List(inner)) => Ok(inner),
+                Ok(other) => Err(WrongResponseType::new("list", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("list")),
+            }
+        }
+        fn list_to_outer((name,): (String,), inner: <ListService as Rpc>::Request) -> Request {
+            Request::List(name, inner)
+        }
+    }
+}
@@ -7,10 +7,11 @@ pub use api_service::{
 mod api_service {
     use super::*;
     use ::trait_rpc::{
-        Rpc,
-        client::{AsyncClient, BlockingClient, MappedClient, WrongResponseType},
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
         serde::{Deserialize, Serialize},
-        server::Handler,
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
     };
     use std::marker::PhantomData;
     /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
@@ -39,7 +40,7 @@ mod api_service {
             ApiServiceHandler(server)
         }
     }
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     #[serde(crate = "::trait_rpc::serde")]
     #[serde(tag = "method", content = "args")]
     pub enum Request {
@@ -66,6 +67,50 @@ mod api_service {
         }
     }
 
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Users(..) => "users",
+                Self::Login(..) => "login",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            match self {
+                Self::Users(.., request) => request.is_stream(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            match self {
+                Self::Users(.., request) => request.is_client_stream(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            match self {
+                Self::Users(.., request) => request.is_stream_item(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            match self {
+                Self::Users(.., request) => request.is_idempotent(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            match self {
+                Self::Users(.., request) => request.is_notification(),
+                _ => false,
+            }
+        }
+    }
+
     /// This is the trait which is used by the server side in order to serve the client
     pub trait ApiServiceServer: Send + Sync {
         fn users(&self) -> impl Future<Output = impl Handler<Rpc = UsersService>> + Send;
@@ -80,16 +125,37 @@ mod api_service {
     pub struct ApiServiceHandler<_Server>(_Server);
     impl<_Server: ApiServiceServer> Handler for ApiServiceHandler<_Server> {
         type Rpc = ApiService;
-        async fn handle(&self, request: Request) -> Response {
+        async fn handle(&self, ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::Users(request) => self.0.users().await.handle(ctx, request).await.map(Response::Users),
+                Request::Login(username, password) => Ok(Response::Login(self.0.login(username, password).await)),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
             match request {
                 Request::Users(request) => {
-                    let response = self.0.users().await.handle(request).await;
-                    Response::Users(response)
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::Users(.., request) => Some(request),
+                        _ => None,
+                    });
+                    let sink = MappedSink::new(&mut sink, Response::Users);
+                    self.0.users().await.handle_stream(ctx, request, incoming, sink).await?;
                 }
-                Request::Login(username, password) => {
-                    Response::Login(self.0.login(username, password).await)
+                request @ Request::Login(..) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
                 }
             }
+            Ok(())
         }
     }
 
@@ -124,8 +190,8 @@ mod api_service {
             ))
         }
         fn users_to_inner(
-            outer: Result<Response, WrongResponseType>,
-        ) -> Result<<UsersService as Rpc>::Response, WrongResponseType> {
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UsersService as Rpc>::Response, ::trait_rpc::client::NestedError> {
             match outer {
                 Ok(Response::Users(inner)) => Ok(inner),
                 Ok(other) => Err(WrongResponseType::new("users", other.fn_name()).into()),
@@ -174,8 +240,8 @@ mod api_service {
             ))
         }
         fn users_to_inner(
-            outer: Result<Response, WrongResponseType>,
-        ) -> Result<<UsersService as Rpc>::Response, WrongResponseType> {
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UsersService as Rpc>::Response, ::trait_rpc::client::NestedError> {
             match outer {
                 Ok(Response::Users(inner)) => Ok(inner),
                 Ok(other) => Err(WrongResponseType::new("users", other.fn_name()).into()),
@@ -205,10 +271,11 @@ pub use users_service::{
 mod users_service {
     use super::*;
     use ::trait_rpc::{
-        Rpc,
-        client::{AsyncClient, BlockingClient, MappedClient, WrongResponseType},
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
         serde::{Deserialize, Serialize},
-        server::Handler,
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
     };
     use std::marker::PhantomData;
     /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
@@ -237,7 +304,7 @@ mod users_service {
             UsersServiceHandler(server)
         }
     }
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     #[serde(crate = "::trait_rpc::serde")]
     #[serde(tag = "method", content = "args")]
     pub enum Request {
@@ -273,6 +340,58 @@ mod users_service {
             }
         }
     }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::New(..) => "new",
+                Self::List(..) => "list",
+                Self::ById(..) => "by_id",
+                Self::Current(..) => "current",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_stream(),
+                Self::Current(.., request) => request.is_stream(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_client_stream(),
+                Self::Current(.., request) => request.is_client_stream(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_stream_item(),
+                Self::Current(.., request) => request.is_stream_item(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_idempotent(),
+                Self::Current(.., request) => request.is_idempotent(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            match self {
+                Self::ById(.., request) => request.is_notification(),
+                Self::Current(.., request) => request.is_notification(),
+                _ => false,
+            }
+        }
+    }
+
     /// This is the trait which is used by the server side in order to serve the client
     pub trait UsersServiceServer: Send + Sync {
         fn new(&self, user: NewUser) -> impl Future<Output = User> + Send;
@@ -289,19 +408,47 @@ mod users_service {
     pub struct UsersServiceHandler<_Server>(_Server);
     impl<_Server: UsersServiceServer> Handler for UsersServiceHandler<_Server> {
         type Rpc = UsersService;
-        async fn handle(&self, request: Request) -> Response {
+        async fn handle(&self, ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::New(user) => Ok(Response::New(self.0.new(user).await)),
+                Request::List() => Ok(Response::List(self.0.list().await)),
+                Request::ById(id, request) => self.0.by_id(id).await.handle(ctx, request).await.map(Response::ById),
+                Request::Current(token, request) => self.0.current(token).await.handle(ctx, request).await.map(Response::Current),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
             match request {
-                Request::New(user) => Response::New(self.0.new(user).await),
-                Request::List() => Response::List(self.0.list().await),
                 Request::ById(id, request) => {
-                    let response = self.0.by_id(id).await.handle(request).await;
-                    Response::ById(response)
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::ById(.., request) => Some(request),
+                        _ => None,
+                    });
+                    let sink = MappedSink::new(&mut sink, Response::ById);
+                    self.0.by_id(id).await.handle_stream(ctx, request, incoming, sink).await?;
                 }
                 Request::Current(token, request) => {
-                    let response = self.0.current(token).await.handle(request).await;
-                    Response::Current(response)
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::Current(.., request) => Some(request),
+                        _ => None,
+                    });
+                    let sink = MappedSink::new(&mut sink, Response::Current);
+                    self.0.current(token).await.handle_stream(ctx, request, incoming, sink).await?;
+                }
+                request @ (Request::New(..) | Request::List(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
                 }
             }
+            Ok(())
         }
     }
 
@@ -349,8 +496,8 @@ mod users_service {
             ))
         }
         fn by_id_to_inner(
-            outer: Result<Response, WrongResponseType>,
-        ) -> Result<<UserService as Rpc>::Response, WrongResponseType> {
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
             match outer {
                 Ok(Response::ById(inner)) => Ok(inner),
                 Ok(other) => Err(WrongResponseType::new("by_id", other.fn_name()).into()),
@@ -381,8 +528,8 @@ mod users_service {
             ))
         }
         fn current_to_inner(
-            outer: Result<Response, WrongResponseType>,
-        ) -> Result<<UserService as Rpc>::Response, WrongResponseType> {
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
             match outer {
                 Ok(Response::Current(inner)) => Ok(inner),
                 Ok(other) => Err(WrongResponseType::new("current", other.fn_name()).into()),
@@ -437,8 +584,8 @@ mod users_service {
             ))
         }
         fn by_id_to_inner(
-            outer: Result<Response, WrongResponseType>,
-        ) -> Result<<UserService as Rpc>::Response, WrongResponseType> {
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
             match outer {
                 Ok(Response::ById(inner)) => Ok(inner),
                 Ok(other) => Err(WrongResponseType::new("by_id", other.fn_name()).into()),
@@ -469,8 +616,8 @@ mod users_service {
             ))
         }
         fn current_to_inner(
-            outer: Result<Response, WrongResponseType>,
-        ) -> Result<<UserService as Rpc>::Response, WrongResponseType> {
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<UserService as Rpc>::Response, ::trait_rpc::client::NestedError> {
             match outer {
                 Ok(Response::Current(inner)) => Ok(inner),
                 Ok(other) => Err(WrongResponseType::new("current", other.fn_name()).into()),
@@ -493,10 +640,11 @@ pub use user_service::{
 mod user_service {
     use super::*;
     use ::trait_rpc::{
-        Rpc,
-        client::{AsyncClient, BlockingClient, MappedClient, WrongResponseType},
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
         serde::{Deserialize, Serialize},
-        server::Handler,
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
     };
     use std::marker::PhantomData;
     /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
@@ -525,7 +673,7 @@ mod user_service {
             UserServiceHandler(server)
         }
     }
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     #[serde(crate = "::trait_rpc::serde")]
     #[serde(tag = "method", content = "args")]
     pub enum Request {
@@ -556,6 +704,37 @@ mod user_service {
             }
         }
     }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Get(..) => "get",
+                Self::Update(..) => "update",
+                Self::Delete(..) => "delete",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            false
+        }
+    }
+
     /// This is the trait which is used by the server side in order to serve the client
     pub trait UserServiceServer: Send + Sync {
         fn get(&self) -> impl Future<Output = Result<User, UserNotFound>> + Send;
@@ -570,12 +749,30 @@ mod user_service {
     pub struct UserServiceHandler<_Server>(_Server);
     impl<_Server: UserServiceServer> Handler for UserServiceHandler<_Server> {
         type Rpc = UserService;
-        async fn handle(&self, request: Request) -> Response {
+        async fn handle(&self, _ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
             match request {
-                Request::Get() => Response::Get(self.0.get().await),
-                Request::Update(user) => Response::Update(self.0.update(user).await),
-                Request::Delete() => Response::Delete(self.0.delete().await),
+                Request::Get() => Ok(Response::Get(self.0.get().await)),
+                Request::Update(user) => Ok(Response::Update(self.0.update(user).await)),
+                Request::Delete() => Ok(Response::Delete(self.0.delete().await)),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            _incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                request @ (Request::Get(..) | Request::Update(..) | Request::Delete(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
             }
+            Ok(())
         }
     }
 
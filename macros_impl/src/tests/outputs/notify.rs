@@ -0,0 +1,189 @@
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use telemetry::{Telemetry, TelemetryAsyncClient, TelemetryBlockingClient, TelemetryServer};
+
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod telemetry {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+    use std::marker::PhantomData;
+
+    /// A service for collecting telemetry
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct Telemetry;
+
+    impl Rpc for Telemetry {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = TelemetryAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> = TelemetryBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(transport: _Client) -> TelemetryAsyncClient<_Client> {
+            TelemetryAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(transport: _Client) -> TelemetryBlockingClient<_Client> {
+            TelemetryBlockingClient(transport)
+        }
+    }
+
+    impl Telemetry {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl TelemetryServer) -> impl Handler<Rpc = Self> {
+            TelemetryHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "log_event")]
+        LogEvent(Event),
+        #[serde(rename = "event_count")]
+        EventCount(),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "log_event")]
+        LogEvent(()),
+        #[serde(rename = "event_count")]
+        EventCount(u64),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::LogEvent(..) => "log_event",
+                Self::EventCount(..) => "event_count",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::LogEvent(..) => "log_event",
+                Self::EventCount(..) => "event_count",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            matches!(self, Self::LogEvent(..))
+        }
+    }
+
+    /// A service for collecting telemetry
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait TelemetryServer: Send + Sync {
+        /// Record an event, the client does not wait for the server to handle it
+        fn log_event(&self, event: Event) -> impl Future<Output = ()> + Send;
+        /// Get the number of events recorded so far
+        fn event_count(&self) -> impl Future<Output = u64> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct TelemetryHandler<_Server>(_Server);
+
+    impl<_Server: TelemetryServer> Handler for TelemetryHandler<_Server> {
+        type Rpc = Telemetry;
+        async fn handle(&self, _ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::LogEvent(event) => Ok(Response::LogEvent(self.0.log_event(event).await)),
+                Request::EventCount() => Ok(Response::EventCount(self.0.event_count().await)),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            _incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                request @ (Request::LogEvent(..) | Request::EventCount(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service for collecting telemetry
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct TelemetryAsyncClient<_Client>(_Client);
+
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> TelemetryAsyncClient<_Client> {
+        /// Record an event, the client does not wait for the server to handle it
+        pub async fn log_event(&self, event: Event) -> Result<(), _Client::Error> {
+            self.0.notify(Request::LogEvent(event)).await
+        }
+        /// Get the number of events recorded so far
+        pub async fn event_count(&self) -> Result<u64, _Client::Error> {
+            match self.0.send(Request::EventCount()).await? {
+                Response::EventCount(value) => Ok(value),
+                other => Err(WrongResponseType::new("event_count", other.fn_name()).into()),
+            }
+        }
+    }
+
+    /// A service for collecting telemetry
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct TelemetryBlockingClient<_Client>(_Client);
+
+    impl<_Client: BlockingClient<Request, Response>> TelemetryBlockingClient<_Client> {
+        /// Record an event, the client does not wait for the server to handle it
+        pub fn log_event(&self, event: Event) -> Result<(), _Client::Error> {
+            self.0.notify(Request::LogEvent(event))
+        }
+        /// Get the number of events recorded so far
+        pub fn event_count(&self) -> Result<u64, _Client::Error> {
+            match self.0.send(Request::EventCount())? {
+                Response::EventCount(value) => Ok(value),
+                other => Err(WrongResponseType::new("event_count", other.fn_name()).into()),
+            }
+        }
+    }
+}
@@ -0,0 +1,695 @@
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use auth_service::{AuthService, AuthServiceAsyncClient, AuthServiceBlockingClient, AuthServiceServer, AuthServicePipeline};
+
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod auth_service {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+
+    use std::marker::PhantomData;
+
+    /// A service for signing in
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct AuthService;
+
+    impl Rpc for AuthService {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = AuthServiceAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> = AuthServiceBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(transport: _Client) -> AuthServiceAsyncClient<_Client> {
+            AuthServiceAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(transport: _Client) -> AuthServiceBlockingClient<_Client> {
+            AuthServiceBlockingClient(transport)
+        }
+
+        fn handle_pipeline<'a, _Handler: Handler<Rpc = Self> + Sync>(
+            handler: &'a _Handler,
+            ctx: &'a ::trait_rpc::server::Context,
+            request: Self::Request,
+        ) -> Result<::trait_rpc::futures::future::BoxFuture<'a, Result<Self::Response, HandlerError>>, Self::Request> {
+            match request {
+                Request::Pipeline(calls) => Ok(Box::pin(async move {
+                    Ok(Response::Pipeline(::trait_rpc::pipeline::run(handler, ctx, calls).await?))
+                })),
+                request => Err(request),
+            }
+        }
+    }
+
+    impl AuthService {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl AuthServiceServer) -> impl Handler<Rpc = Self> {
+            AuthServiceHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "login")]
+        Login(String, String),
+        #[serde(rename = "revoked")]
+        Revoked(),
+        #[serde(rename = "session")]
+        Session(Token, <SessionService as Rpc>::Request),
+        #[serde(rename = "pipeline")]
+        Pipeline(Vec<Call>),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "login")]
+        Login(Option<Token>),
+        #[serde(rename = "revoked")]
+        Revoked(Token),
+        #[serde(rename = "session")]
+        Session(Result<<SessionService as Rpc>::Response, Expired>),
+        #[serde(rename = "pipeline")]
+        Pipeline(Vec<Option<Response>>),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Login(..) => "login",
+                Self::Revoked(..) => "revoked",
+                Self::Session(..) => "session",
+                Self::Pipeline(..) => "pipeline",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::Login(..) => "login",
+                Self::Revoked(..) => "revoked",
+                Self::Session(..) => "session",
+                Self::Pipeline(..) => "pipeline",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            match self {
+                Self::Session(.., request) => request.is_stream(),
+                other => matches!(other, Self::Revoked(..)),
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            match self {
+                Self::Session(.., request) => request.is_client_stream(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            match self {
+                Self::Session(.., request) => request.is_stream_item(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            match self {
+                Self::Session(.., request) => request.is_idempotent(),
+                _ => false,
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            match self {
+                Self::Session(.., request) => request.is_notification(),
+                _ => false,
+            }
+        }
+    }
+
+    /// A service for signing in
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait AuthServiceServer: Send + Sync {
+        /// Sign in, returning a token if the credentials are correct
+        fn login(&self, username: String, password: String) -> impl Future<Output = Option<Token>> + Send;
+        /// Receive every token which is revoked
+        fn revoked(&self) -> impl Future<Output = impl ::trait_rpc::futures::Stream<Item = Token> + Send> + Send;
+        /// Get the session of a token
+        fn session(&self, token: Token) -> impl Future<Output = Result<impl Handler<Rpc = SessionService>, Expired>> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct AuthServiceHandler<_Server>(_Server);
+
+    impl<_Server: AuthServiceServer> Handler for AuthServiceHandler<_Server> {
+        type Rpc = AuthService;
+        async fn handle(&self, ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::Login(username, password) => Ok(Response::Login(self.0.login(username, password).await)),
+                Request::Revoked(..) => {
+                    Err(HandlerError::Unsupported("`revoked` is a streaming method, requests for it must be passed to `Handler::handle_stream`".to_string()))
+                }
+                Request::Session(token, request) => match self.0.session(token).await {
+                    Ok(handler) => handler.handle(ctx, request).await.map(|response| Response::Session(Ok(response))),
+                    Err(error) => Ok(Response::Session(Err(error))),
+                },
+                Request::Pipeline(calls) => Ok(Response::Pipeline(::trait_rpc::pipeline::run(self, ctx, calls).await?)),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                Request::Revoked() => {
+                    let responses = self.0.revoked().await.map(|value| Ok(Response::Revoked(value)));
+                    let _ = sink.send_all(&mut ::std::pin::pin!(responses)).await;
+                }
+                Request::Session(token, request) => {
+                    let incoming = filter_incoming(incoming, |request| match request {
+                        Request::Session(.., request) => Some(request),
+                        _ => None,
+                    });
+                    match self.0.session(token).await {
+                        Ok(handler) => {
+                            let sink = MappedSink::new(&mut sink, |response| Response::Session(Ok(response)));
+                            handler.handle_stream(ctx, request, incoming, sink).await?;
+                        }
+                        Err(error) => {
+                            let _ = sink.send(Response::Session(Err(error))).await;
+                        }
+                    }
+                }
+                request @ (Request::Login(..) | Request::Pipeline(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service for signing in
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct AuthServiceAsyncClient<_Client>(_Client);
+
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> AuthServiceAsyncClient<_Client> {
+        /// Sign in, returning a token if the credentials are correct
+        pub async fn login(&self, username: String, password: String) -> Result<Option<Token>, _Client::Error> {
+            match self.0.send(Request::Login(username, password)).await? {
+                Response::Login(value) => Ok(value),
+                other => Err(WrongResponseType::new("login", other.fn_name()).into()),
+            }
+        }
+        /// Receive every token which is revoked
+        pub async fn revoked(&self) -> Result<impl ::trait_rpc::futures::Stream<Item = Result<Token, _Client::Error>>, _Client::Error> {
+            let responses = self.0.send_stream(Request::Revoked()).await?;
+            Ok(responses.map(|response| match response? {
+                Response::Revoked(value) => Ok(value),
+                other => Err(WrongResponseType::new("revoked", other.fn_name()).into()),
+            }))
+        }
+        /// Get the session of a token
+        pub fn session(
+            &self,
+            token: Token,
+        ) -> <SessionService as Rpc>::AsyncClient<
+            MappedClient<_Client, <SessionService as Rpc>::Request, Request, <SessionService as Rpc>::Response, Response, (Token,)>,
+        > {
+            SessionService::async_client(MappedClient::new(self.0.clone(), (token,), Self::session_to_inner, Self::session_to_outer))
+        }
+        fn session_to_inner(
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<SessionService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::Session(Ok(inner))) => Ok(inner),
+                Ok(Response::Session(Err(error))) => Err(::trait_rpc::client::ApplicationError::new("session", error).into()),
+                Ok(other) => Err(WrongResponseType::new("session", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("session")),
+            }
+        }
+        fn session_to_outer((token,): (Token,), inner: <SessionService as Rpc>::Request) -> Request {
+            Request::Session(token, inner)
+        }
+        /// Send several calls to this service as a single request, `calls` makes each call with
+        /// the given pipeline and returns the result to wait for. The result of a call can be
+        /// passed as an argument to a later call, see [pipeline](::trait_rpc::pipeline)
+        pub async fn pipeline<_Output: Clone + 'static>(
+            &self,
+            calls: impl FnOnce(AuthServicePipeline<&::trait_rpc::pipeline::Calls<Call>>) -> ::trait_rpc::pipeline::Pending<_Output>,
+        ) -> Result<_Output, ::trait_rpc::pipeline::PipelineError<_Client::Error>> {
+            let pipeline = ::trait_rpc::pipeline::Calls::default();
+            let result = calls(AuthServicePipeline(&pipeline));
+            match self.0.send(Request::Pipeline(pipeline.into_inner())).await.map_err(::trait_rpc::pipeline::PipelineError::Rpc)? {
+                Response::Pipeline(responses) => ::trait_rpc::pipeline::result::<AuthService, _, _>(&responses, result),
+                other => Err(::trait_rpc::pipeline::PipelineError::Rpc(WrongResponseType::new("pipeline", other.fn_name()).into())),
+            }
+        }
+    }
+
+    /// A service for signing in
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct AuthServiceBlockingClient<_Client>(_Client);
+
+    impl<_Client: BlockingClient<Request, Response>> AuthServiceBlockingClient<_Client> {
+        /// Sign in, returning a token if the credentials are correct
+        pub fn login(&self, username: String, password: String) -> Result<Option<Token>, _Client::Error> {
+            match self.0.send(Request::Login(username, password))? {
+                Response::Login(value) => Ok(value),
+                other => Err(WrongResponseType::new("login", other.fn_name()).into()),
+            }
+        }
+        /// Receive every token which is revoked
+        pub fn revoked(&self) -> Result<impl Iterator<Item = Result<Token, _Client::Error>>, _Client::Error> {
+            let responses = self.0.send_stream(Request::Revoked())?;
+            Ok(responses.map(|response| match response? {
+                Response::Revoked(value) => Ok(value),
+                other => Err(WrongResponseType::new("revoked", other.fn_name()).into()),
+            }))
+        }
+        /// Get the session of a token
+        pub fn session(
+            &self,
+            token: Token,
+        ) -> <SessionService as Rpc>::BlockingClient<
+            MappedClient<_Client, <SessionService as Rpc>::Request, Request, <SessionService as Rpc>::Response, Response, (Token,)>,
+        > {
+            SessionService::blocking_client(MappedClient::new(self.0.clone(), (token,), Self::session_to_inner, Self::session_to_outer))
+        }
+        fn session_to_inner(
+            outer: Result<Response, ::trait_rpc::client::NestedError>,
+        ) -> Result<<SessionService as Rpc>::Response, ::trait_rpc::client::NestedError> {
+            match outer {
+                Ok(Response::Session(Ok(inner))) => Ok(inner),
+                Ok(Response::Session(Err(error))) => Err(::trait_rpc::client::ApplicationError::new("session", error).into()),
+                Ok(other) => Err(WrongResponseType::new("session", other.fn_name()).into()),
+                Err(err) => Err(err.in_subservice("session")),
+            }
+        }
+        fn session_to_outer((token,): (Token,), inner: <SessionService as Rpc>::Request) -> Request {
+            Request::Session(token, inner)
+        }
+        /// Send several calls to this service as a single request, `calls` makes each call with
+        /// the given pipeline and returns the result to wait for. The result of a call can be
+        /// passed as an argument to a later call, see [pipeline](::trait_rpc::pipeline)
+        pub fn pipeline<_Output: Clone + 'static>(
+            &self,
+            calls: impl FnOnce(AuthServicePipeline<&::trait_rpc::pipeline::Calls<Call>>) -> ::trait_rpc::pipeline::Pending<_Output>,
+        ) -> Result<_Output, ::trait_rpc::pipeline::PipelineError<_Client::Error>> {
+            let pipeline = ::trait_rpc::pipeline::Calls::default();
+            let result = calls(AuthServicePipeline(&pipeline));
+            match self.0.send(Request::Pipeline(pipeline.into_inner())).map_err(::trait_rpc::pipeline::PipelineError::Rpc)? {
+                Response::Pipeline(responses) => ::trait_rpc::pipeline::result::<AuthService, _, _>(&responses, result),
+                other => Err(::trait_rpc::pipeline::PipelineError::Rpc(WrongResponseType::new("pipeline", other.fn_name()).into())),
+            }
+        }
+    }
+
+    /// A call to the service in a pipeline, this is a request whose arguments may be the
+    /// results of earlier calls, see [pipeline](::trait_rpc::pipeline)
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Call {
+        #[serde(rename = "login")]
+        Login(::trait_rpc::pipeline::Arg<String>, ::trait_rpc::pipeline::Arg<String>),
+        #[serde(rename = "session")]
+        Session(::trait_rpc::pipeline::Arg<Token>, <SessionService as ::trait_rpc::pipeline::Pipelined>::Call),
+    }
+
+    impl ::trait_rpc::pipeline::Pipelined for AuthService {
+        type Call = Call;
+        type Pipeline<_Calls: ::trait_rpc::pipeline::PipelineCalls<Call>> = AuthServicePipeline<_Calls>;
+        fn pipeline<_Calls: ::trait_rpc::pipeline::PipelineCalls<Call>>(calls: _Calls) -> AuthServicePipeline<_Calls> {
+            AuthServicePipeline(calls)
+        }
+        fn resolve<_Root: ::trait_rpc::pipeline::Pipelined>(call: Call, results: &::trait_rpc::pipeline::Results<'_, _Root>) -> Option<Request> {
+            Some(match call {
+                Call::Login(username, password) => Request::Login(username.resolve(results)?, password.resolve(results)?),
+                Call::Session(token, call) => {
+                    Request::Session(token.resolve(results)?, <SessionService as ::trait_rpc::pipeline::Pipelined>::resolve(call, results)?)
+                }
+            })
+        }
+        fn output(response: &Response, projection: ::trait_rpc::pipeline::Projection) -> Option<&dyn ::std::any::Any> {
+            use ::trait_rpc::pipeline::{ProjectOption as _, ProjectResult as _, ProjectValue as _};
+            match response {
+                Response::Login(value) => (&&::trait_rpc::pipeline::Output(value)).project(projection),
+                Response::Session(Ok(response)) => <SessionService as ::trait_rpc::pipeline::Pipelined>::output(response, projection),
+                Response::Revoked(..) | Response::Session(..) | Response::Pipeline(..) => None,
+            }
+        }
+    }
+
+    /// A service for signing in
+    ///
+    /// This is the pipeline of calls to the service, each call returns its pending result
+    /// straight away, see [pipeline](::trait_rpc::pipeline)
+    #[derive(Debug, Copy, Clone)]
+    pub struct AuthServicePipeline<_Calls>(_Calls);
+
+    impl<_Calls: ::trait_rpc::pipeline::PipelineCalls<Call>> AuthServicePipeline<_Calls> {
+        /// Sign in, returning a token if the credentials are correct
+        pub fn login(
+            &self,
+            username: impl Into<::trait_rpc::pipeline::Arg<String>>,
+            password: impl Into<::trait_rpc::pipeline::Arg<String>>,
+        ) -> ::trait_rpc::pipeline::Pending<Option<Token>> {
+            ::trait_rpc::pipeline::Pending::new(::trait_rpc::pipeline::PipelineCalls::push(&self.0, Call::Login(username.into(), password.into())))
+        }
+        /// Get the session of a token
+        pub fn session(
+            &self,
+            token: impl Into<::trait_rpc::pipeline::Arg<Token>>,
+        ) -> <SessionService as ::trait_rpc::pipeline::Pipelined>::Pipeline<
+            ::trait_rpc::pipeline::MappedCalls<
+                _Calls,
+                <SessionService as ::trait_rpc::pipeline::Pipelined>::Call,
+                Call,
+                (::trait_rpc::pipeline::Arg<Token>,),
+            >,
+        > {
+            <SessionService as ::trait_rpc::pipeline::Pipelined>::pipeline(::trait_rpc::pipeline::MappedCalls::new(
+                self.0.clone(),
+                (token.into(),),
+                Self::session_to_outer,
+            ))
+        }
+        fn session_to_outer(
+            (token,): (::trait_rpc::pipeline::Arg<Token>,),
+            inner: <SessionService as ::trait_rpc::pipeline::Pipelined>::Call,
+        ) -> Call {
+            Call::Session(token, inner)
+        }
+    }
+}
+
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use session_service::{SessionService, SessionServiceAsyncClient, SessionServiceBlockingClient, SessionServiceServer, SessionServicePipeline};
+
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod session_service {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+
+    use std::marker::PhantomData;
+
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct SessionService;
+
+    impl Rpc for SessionService {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = SessionServiceAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> = SessionServiceBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(transport: _Client) -> SessionServiceAsyncClient<_Client> {
+            SessionServiceAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(transport: _Client) -> SessionServiceBlockingClient<_Client> {
+            SessionServiceBlockingClient(transport)
+        }
+
+        fn handle_pipeline<'a, _Handler: Handler<Rpc = Self> + Sync>(
+            handler: &'a _Handler,
+            ctx: &'a ::trait_rpc::server::Context,
+            request: Self::Request,
+        ) -> Result<::trait_rpc::futures::future::BoxFuture<'a, Result<Self::Response, HandlerError>>, Self::Request> {
+            match request {
+                Request::Pipeline(calls) => Ok(Box::pin(async move {
+                    Ok(Response::Pipeline(::trait_rpc::pipeline::run(handler, ctx, calls).await?))
+                })),
+                request => Err(request),
+            }
+        }
+    }
+
+    impl SessionService {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl SessionServiceServer) -> impl Handler<Rpc = Self> {
+            SessionServiceHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "user")]
+        User(),
+        #[serde(rename = "logout")]
+        Logout(),
+        #[serde(rename = "pipeline")]
+        Pipeline(Vec<Call>),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "user")]
+        User(Result<User, SessionError>),
+        #[serde(rename = "logout")]
+        Logout(Result<(), SessionError>),
+        #[serde(rename = "pipeline")]
+        Pipeline(Vec<Option<Response>>),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::User(..) => "user",
+                Self::Logout(..) => "logout",
+                Self::Pipeline(..) => "pipeline",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::User(..) => "user",
+                Self::Logout(..) => "logout",
+                Self::Pipeline(..) => "pipeline",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            false
+        }
+    }
+
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait SessionServiceServer: Send + Sync {
+        fn user(&self) -> impl Future<Output = Result<User, SessionError>> + Send;
+        fn logout(&self) -> impl Future<Output = Result<(), SessionError>> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct SessionServiceHandler<_Server>(_Server);
+
+    impl<_Server: SessionServiceServer> Handler for SessionServiceHandler<_Server> {
+        type Rpc = SessionService;
+        async fn handle(&self, ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::User() => Ok(Response::User(self.0.user().await)),
+                Request::Logout() => Ok(Response::Logout(self.0.logout().await)),
+                Request::Pipeline(calls) => Ok(Response::Pipeline(::trait_rpc::pipeline::run(self, ctx, calls).await?)),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            _incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                request @ (Request::User(..) | Request::Logout(..) | Request::Pipeline(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct SessionServiceAsyncClient<_Client>(_Client);
+
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> SessionServiceAsyncClient<_Client> {
+        pub async fn user(&self) -> Result<User, CallError<SessionError, _Client::Error>> {
+            match self.0.send(Request::User()).await.map_err(CallError::Rpc)? {
+                Response::User(value) => value.map_err(CallError::Application),
+                other => Err(CallError::Rpc(WrongResponseType::new("user", other.fn_name()).into())),
+            }
+        }
+        pub async fn logout(&self) -> Result<(), CallError<SessionError, _Client::Error>> {
+            match self.0.send(Request::Logout()).await.map_err(CallError::Rpc)? {
+                Response::Logout(value) => value.map_err(CallError::Application),
+                other => Err(CallError::Rpc(WrongResponseType::new("logout", other.fn_name()).into())),
+            }
+        }
+        /// Send several calls to this service as a single request, `calls` makes each call with
+        /// the given pipeline and returns the result to wait for. The result of a call can be
+        /// passed as an argument to a later call, see [pipeline](::trait_rpc::pipeline)
+        pub async fn pipeline<_Output: Clone + 'static>(
+            &self,
+            calls: impl FnOnce(SessionServicePipeline<&::trait_rpc::pipeline::Calls<Call>>) -> ::trait_rpc::pipeline::Pending<_Output>,
+        ) -> Result<_Output, ::trait_rpc::pipeline::PipelineError<_Client::Error>> {
+            let pipeline = ::trait_rpc::pipeline::Calls::default();
+            let result = calls(SessionServicePipeline(&pipeline));
+            match self.0.send(Request::Pipeline(pipeline.into_inner())).await.map_err(::trait_rpc::pipeline::PipelineError::Rpc)? {
+                Response::Pipeline(responses) => ::trait_rpc::pipeline::result::<SessionService, _, _>(&responses, result),
+                other => Err(::trait_rpc::pipeline::PipelineError::Rpc(WrongResponseType::new("pipeline", other.fn_name()).into())),
+            }
+        }
+    }
+
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct SessionServiceBlockingClient<_Client>(_Client);
+
+    impl<_Client: BlockingClient<Request, Response>> SessionServiceBlockingClient<_Client> {
+        pub fn user(&self) -> Result<User, CallError<SessionError, _Client::Error>> {
+            match self.0.send(Request::User()).map_err(CallError::Rpc)? {
+                Response::User(value) => value.map_err(CallError::Application),
+                other => Err(CallError::Rpc(WrongResponseType::new("user", other.fn_name()).into())),
+            }
+        }
+        pub fn logout(&self) -> Result<(), CallError<SessionError, _Client::Error>> {
+            match self.0.send(Request::Logout()).map_err(CallError::Rpc)? {
+                Response::Logout(value) => value.map_err(CallError::Application),
+                other => Err(CallError::Rpc(WrongResponseType::new("logout", other.fn_name()).into())),
+            }
+        }
+        /// Send several calls to this service as a single request, `calls` makes each call with
+        /// the given pipeline and returns the result to wait for. The result of a call can be
+        /// passed as an argument to a later call, see [pipeline](::trait_rpc::pipeline)
+        pub fn pipeline<_Output: Clone + 'static>(
+            &self,
+            calls: impl FnOnce(SessionServicePipeline<&::trait_rpc::pipeline::Calls<Call>>) -> ::trait_rpc::pipeline::Pending<_Output>,
+        ) -> Result<_Output, ::trait_rpc::pipeline::PipelineError<_Client::Error>> {
+            let pipeline = ::trait_rpc::pipeline::Calls::default();
+            let result = calls(SessionServicePipeline(&pipeline));
+            match self.0.send(Request::Pipeline(pipeline.into_inner())).map_err(::trait_rpc::pipeline::PipelineError::Rpc)? {
+                Response::Pipeline(responses) => ::trait_rpc::pipeline::result::<SessionService, _, _>(&responses, result),
+                other => Err(::trait_rpc::pipeline::PipelineError::Rpc(WrongResponseType::new("pipeline", other.fn_name()).into())),
+            }
+        }
+    }
+
+    /// A call to the service in a pipeline, this is a request whose arguments may be the
+    /// results of earlier calls, see [pipeline](::trait_rpc::pipeline)
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Call {
+        #[serde(rename = "user")]
+        User(),
+        #[serde(rename = "logout")]
+        Logout(),
+    }
+
+    impl ::trait_rpc::pipeline::Pipelined for SessionService {
+        type Call = Call;
+        type Pipeline<_Calls: ::trait_rpc::pipeline::PipelineCalls<Call>> = SessionServicePipeline<_Calls>;
+        fn pipeline<_Calls: ::trait_rpc::pipeline::PipelineCalls<Call>>(calls: _Calls) -> SessionServicePipeline<_Calls> {
+            SessionServicePipeline(calls)
+        }
+        fn resolve<_Root: ::trait_rpc::pipeline::Pipelined>(call: Call, _results: &::trait_rpc::pipeline::Results<'_, _Root>) -> Option<Request> {
+            Some(match call {
+                Call::User() => Request::User(),
+                Call::Logout() => Request::Logout(),
+            })
+        }
+        fn output(response: &Response, projection: ::trait_rpc::pipeline::Projection) -> Option<&dyn ::std::any::Any> {
+            use ::trait_rpc::pipeline::{ProjectOption as _, ProjectResult as _, ProjectValue as _};
+            match response {
+                Response::User(value) => (&&::trait_rpc::pipeline::Output(value)).project(projection),
+                Response::Logout(value) => (&&::trait_rpc::pipeline::Output(value)).project(projection),
+                Response::Pipeline(..) => None,
+            }
+        }
+    }
+
+    /// This is the pipeline of calls to the service, each call returns its pending result
+    /// straight away, see [pipeline](::trait_rpc::pipeline)
+    #[derive(Debug, Copy, Clone)]
+    pub struct SessionServicePipeline<_Calls>(_Calls);
+
+    impl<_Calls: ::trait_rpc::pipeline::PipelineCalls<Call>> SessionServicePipeline<_Calls> {
+        pub fn user(&self) -> ::trait_rpc::pipeline::Pending<Result<User, SessionError>> {
+            ::trait_rpc::pipeline::Pending::new(::trait_rpc::pipeline::PipelineCalls::push(&self.0, Call::User()))
+        }
+        pub fn logout(&self) -> ::trait_rpc::pipeline::Pending<Result<(), SessionError>> {
+            ::trait_rpc::pipeline::Pending::new(::trait_rpc::pipeline::PipelineCalls::push(&self.0, Call::Logout()))
+        }
+    }
+}
@@ -4,10 +4,11 @@ pub use resources::{Resources, ResourcesAsyncClient, ResourcesBlockingClient, Re
 mod resources {
     use super::*;
     use ::trait_rpc::{
-        Rpc,
-        client::{AsyncClient, BlockingClient, MappedClient, WrongResponseType},
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
         serde::{Deserialize, Serialize},
-        server::Handler,
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
     };
     use std::marker::PhantomData;
     /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
@@ -39,7 +40,7 @@ mod resources {
             ResourcesHandler(server, PhantomData::<fn() -> (T,)>)
         }
     }
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     #[serde(crate = "::trait_rpc::serde")]
     #[serde(tag = "method", content = "args")]
     pub enum Request<T> {
@@ -70,6 +71,37 @@ mod resources {
             }
         }
     }
+
+    impl<T> RequestInfo for Request<T> {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::List(..) => "list",
+                Self::Get(..) => "get",
+                Self::New(..) => "new",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            false
+        }
+    }
+
     /// This is the trait which is used by the server side in order to serve the client
     pub trait ResourcesServer<T>: Send + Sync {
         fn list(&self) -> impl Future<Output = Vec<T>> + Send;
@@ -81,12 +113,30 @@ mod resources {
     pub struct ResourcesHandler<_Server, T>(_Server, (PhantomData<fn() -> (T,)>));
     impl<_Server: ResourcesServer<T>, T: Send + 'static> Handler for ResourcesHandler<_Server, T> {
         type Rpc = Resources<T>;
-        async fn handle(&self, request: Request<T>) -> Response<T> {
+        async fn handle(&self, _ctx: &::trait_rpc::server::Context, request: Request<T>) -> Result<Response<T>, HandlerError> {
+            match request {
+                Request::List() => Ok(Response::List(self.0.list().await)),
+                Request::Get(id) => Ok(Response::Get(self.0.get(id).await)),
+                Request::New(value) => Ok(Response::New(self.0.new(value).await)),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request<T>,
+            _incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request<T>> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response<T>> + Send + Unpin,
+        {
             match request {
-                Request::List() => Response::List(self.0.list().await),
-                Request::Get(id) => Response::Get(self.0.get(id).await),
-                Request::New(value) => Response::New(self.0.new(value).await),
+                request @ (Request::List(..) | Request::Get(..) | Request::New(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
             }
+            Ok(())
         }
     }
 
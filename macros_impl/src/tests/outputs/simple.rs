@@ -7,10 +7,11 @@ pub use todo_service::{
 mod todo_service {
     use super::*;
     use ::trait_rpc::{
-        Rpc,
-        client::{AsyncClient, BlockingClient, MappedClient, WrongResponseType},
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
         serde::{Deserialize, Serialize},
-        server::Handler,
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
     };
     use std::marker::PhantomData;
 
@@ -43,7 +44,7 @@ mod todo_service {
         }
     }
 
-    #[derive(Debug, Serialize, Deserialize)]
+    #[derive(Debug, Clone, Serialize, Deserialize)]
     #[serde(crate = "::trait_rpc::serde")]
     #[serde(tag = "method", content = "args")]
     pub enum Request {
@@ -76,6 +77,36 @@ mod todo_service {
         }
     }
 
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::GetTodos(..) => "get_todos",
+                Self::GetTodo(..) => "get_todo",
+                Self::NewTodo(..) => "new_todo",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            false
+        }
+    }
+
     /// A service for managing to-do items
     ///
     /// This is the trait which is used by the server side in order to serve the client
@@ -94,12 +125,30 @@ mod todo_service {
 
     impl<_Server: TodoServiceServer> Handler for TodoServiceHandler<_Server> {
         type Rpc = TodoService;
-        async fn handle(&self, request: Request) -> Response {
+        async fn handle(&self, _ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::GetTodos() => Ok(Response::GetTodos(self.0.get_todos().await)),
+                Request::GetTodo(name) => Ok(Response::GetTodo(self.0.get_todo(name).await)),
+                Request::NewTodo(todo) => Ok(Response::NewTodo(self.0.new_todo(todo).await)),
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            _incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
             match request {
-                Request::GetTodos() => Response::GetTodos(self.0.get_todos().await),
-                Request::GetTodo(name) => Response::GetTodo(self.0.get_todo(name).await),
-                Request::NewTodo(todo) => Response::NewTodo(self.0.new_todo(todo).await),
+                request @ (Request::GetTodos(..) | Request::GetTodo(..) | Request::NewTodo(..)) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
             }
+            Ok(())
         }
     }
 
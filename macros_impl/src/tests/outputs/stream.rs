@@ -0,0 +1,238 @@
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use todo_watcher::{TodoWatcher, TodoWatcherAsyncClient, TodoWatcherBlockingClient, TodoWatcherServer};
+
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod todo_watcher {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+
+    use std::marker::PhantomData;
+
+    /// A service for watching to-do items
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct TodoWatcher;
+
+    impl Rpc for TodoWatcher {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = TodoWatcherAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> = TodoWatcherBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(transport: _Client) -> TodoWatcherAsyncClient<_Client> {
+            TodoWatcherAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(transport: _Client) -> TodoWatcherBlockingClient<_Client> {
+            TodoWatcherBlockingClient(transport)
+        }
+    }
+
+    impl TodoWatcher {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl TodoWatcherServer) -> impl Handler<Rpc = Self> {
+            TodoWatcherHandler(server)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "get_todos")]
+        GetTodos(),
+        #[serde(rename = "watch_todos")]
+        WatchTodos(),
+        #[serde(rename = "watch_todo")]
+        WatchTodo(String),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "get_todos")]
+        GetTodos(Vec<Todo>),
+        #[serde(rename = "watch_todos")]
+        WatchTodos(Todo),
+        #[serde(rename = "watch_todo")]
+        WatchTodo(Todo),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::GetTodos(..) => "get_todos",
+                Self::WatchTodos(..) => "watch_todos",
+                Self::WatchTodo(..) => "watch_todo",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::GetTodos(..) => "get_todos",
+                Self::WatchTodos(..) => "watch_todos",
+                Self::WatchTodo(..) => "watch_todo",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            matches!(self, Self::WatchTodos(..) | Self::WatchTodo(..))
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            false
+        }
+    }
+
+    /// A service for watching to-do items
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait TodoWatcherServer: Send + Sync {
+        /// Get a list of to-do items
+        fn get_todos(&self) -> impl Future<Output = Vec<Todo>> + Send;
+        /// Receive each new to-do item as it is created
+        fn watch_todos(&self) -> impl Future<Output = impl ::trait_rpc::futures::Stream<Item = Todo> + Send> + Send;
+        /// Receive every update to the to-do item with the given name
+        fn watch_todo(&self, name: String) -> impl Future<Output = impl ::trait_rpc::futures::Stream<Item = Todo> + Send> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct TodoWatcherHandler<_Server>(_Server);
+
+    impl<_Server: TodoWatcherServer> Handler for TodoWatcherHandler<_Server> {
+        type Rpc = TodoWatcher;
+        async fn handle(&self, _ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::GetTodos() => Ok(Response::GetTodos(self.0.get_todos().await)),
+                Request::WatchTodos(..) => {
+                    Err(HandlerError::Unsupported("`watch_todos` is a streaming method, requests for it must be passed to `Handler::handle_stream`".to_string()))
+                }
+                Request::WatchTodo(..) => {
+                    Err(HandlerError::Unsupported("`watch_todo` is a streaming method, requests for it must be passed to `Handler::handle_stream`".to_string()))
+                }
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            _incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                Request::WatchTodos() => {
+                    let responses = self.0.watch_todos().await.map(|value| Ok(Response::WatchTodos(value)));
+                    let _ = sink.send_all(&mut ::std::pin::pin!(responses)).await;
+                }
+                Request::WatchTodo(name) => {
+                    let responses = self.0.watch_todo(name).await.map(|value| Ok(Response::WatchTodo(value)));
+                    let _ = sink.send_all(&mut ::std::pin::pin!(responses)).await;
+                }
+                request @ Request::GetTodos(..) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service for watching to-do items
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct TodoWatcherAsyncClient<_Client>(_Client);
+
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> TodoWatcherAsyncClient<_Client> {
+        /// Get a list of to-do items
+        pub async fn get_todos(&self) -> Result<Vec<Todo>, _Client::Error> {
+            match self.0.send(Request::GetTodos()).await? {
+                Response::GetTodos(value) => Ok(value),
+                other => Err(WrongResponseType::new("get_todos", other.fn_name()).into()),
+            }
+        }
+        /// Receive each new to-do item as it is created
+        pub async fn watch_todos(&self) -> Result<impl ::trait_rpc::futures::Stream<Item = Result<Todo, _Client::Error>>, _Client::Error> {
+            let responses = self.0.send_stream(Request::WatchTodos()).await?;
+            Ok(responses.map(|response| match response? {
+                Response::WatchTodos(value) => Ok(value),
+                other => Err(WrongResponseType::new("watch_todos", other.fn_name()).into()),
+            }))
+        }
+        /// Receive every update to the to-do item with the given name
+        pub async fn watch_todo(
+            &self,
+            name: String,
+        ) -> Result<impl ::trait_rpc::futures::Stream<Item = Result<Todo, _Client::Error>>, _Client::Error> {
+            let responses = self.0.send_stream(Request::WatchTodo(name)).await?;
+            Ok(responses.map(|response| match response? {
+                Response::WatchTodo(value) => Ok(value),
+                other => Err(WrongResponseType::new("watch_todo", other.fn_name()).into()),
+            }))
+        }
+    }
+
+    /// A service for watching to-do items
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct TodoWatcherBlockingClient<_Client>(_Client);
+
+    impl<_Client: BlockingClient<Request, Response>> TodoWatcherBlockingClient<_Client> {
+        /// Get a list of to-do items
+        pub fn get_todos(&self) -> Result<Vec<Todo>, _Client::Error> {
+            match self.0.send(Request::GetTodos())? {
+                Response::GetTodos(value) => Ok(value),
+                other => Err(WrongResponseType::new("get_todos", other.fn_name()).into()),
+            }
+        }
+        /// Receive each new to-do item as it is created
+        pub fn watch_todos(&self) -> Result<impl Iterator<Item = Result<Todo, _Client::Error>>, _Client::Error> {
+            let responses = self.0.send_stream(Request::WatchTodos())?;
+            Ok(responses.map(|response| match response? {
+                Response::WatchTodos(value) => Ok(value),
+                other => Err(WrongResponseType::new("watch_todos", other.fn_name()).into()),
+            }))
+        }
+        /// Receive every update to the to-do item with the given name
+        pub fn watch_todo(&self, name: String) -> Result<impl Iterator<Item = Result<Todo, _Client::Error>>, _Client::Error> {
+            let responses = self.0.send_stream(Request::WatchTodo(name))?;
+            Ok(responses.map(|response| match response? {
+                Response::WatchTodo(value) => Ok(value),
+                other => Err(WrongResponseType::new("watch_todo", other.fn_name()).into()),
+            }))
+        }
+    }
+}
@@ -0,0 +1,215 @@
+#[allow(unused_imports, reason = "These might not always be used, but they should be available in this module anyway")]
+pub use todo_feed::{TodoFeed, TodoFeedAsyncClient, TodoFeedBlockingClient, TodoFeedServer};
+
+#[allow(unused_imports, reason = "These might not always be used, but it's easier to include always")]
+mod todo_feed {
+    use super::*;
+    use ::trait_rpc::{
+        Rpc, RequestInfo,
+        client::{AsyncClient, BlockingClient, CallError, MappedClient, WrongResponseType},
+        futures::{SinkExt as _, StreamExt as _},
+        serde::{Deserialize, Serialize},
+        server::{Handler, HandlerError, MappedSink, filter_incoming},
+    };
+
+    use std::marker::PhantomData;
+
+    /// A service for to-do items which publishes each change
+    ///
+    /// This is the [Rpc](::trait_rpc::Rpc) definition for this service
+    pub struct TodoFeed;
+
+    impl Rpc for TodoFeed {
+        type AsyncClient<_Client: AsyncClient<Self::Request, Self::Response>> = TodoFeedAsyncClient<_Client>;
+        type BlockingClient<_Client: BlockingClient<Self::Request, Self::Response>> = TodoFeedBlockingClient<_Client>;
+        type Request = Request;
+        type Response = Response;
+        fn async_client<_Client: AsyncClient<Request, Response>>(transport: _Client) -> TodoFeedAsyncClient<_Client> {
+            TodoFeedAsyncClient(transport)
+        }
+        fn blocking_client<_Client: BlockingClient<Request, Response>>(transport: _Client) -> TodoFeedBlockingClient<_Client> {
+            TodoFeedBlockingClient(transport)
+        }
+    }
+
+    impl TodoFeed {
+        /// Create a new [Handler](trait_rpc::Handler) for the service
+        pub fn server(server: impl TodoFeedServer) -> impl Handler<Rpc = Self> {
+            TodoFeedHandler(server)
+        }
+
+        /// Each new to-do item as it is created
+        ///
+        /// Publish an event to every client subscribed to this topic, returns the number of
+        /// subscribers it was sent to
+        pub fn publish_todo_created(topics: &::trait_rpc::server::topics::Topics<Self>, event: Todo) -> usize {
+            topics.publish("todo_created", || Response::TodoCreated(event.clone()))
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "args")]
+    pub enum Request {
+        #[serde(rename = "get_todos")]
+        GetTodos(),
+        #[serde(rename = "todo_created")]
+        TodoCreated(),
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(crate = "::trait_rpc::serde")]
+    #[serde(tag = "method", content = "result")]
+    pub enum Response {
+        #[serde(rename = "get_todos")]
+        GetTodos(Vec<Todo>),
+        #[serde(rename = "todo_created")]
+        TodoCreated(Todo),
+    }
+
+    impl Response {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::GetTodos(..) => "get_todos",
+                Self::TodoCreated(..) => "todo_created",
+            }
+        }
+    }
+
+    impl RequestInfo for Request {
+        fn fn_name(&self) -> &'static str {
+            match self {
+                Self::GetTodos(..) => "get_todos",
+                Self::TodoCreated(..) => "todo_created",
+            }
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream(&self) -> bool {
+            matches!(self, Self::TodoCreated(..))
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_client_stream(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_stream_item(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_idempotent(&self) -> bool {
+            false
+        }
+        #[allow(clippy::match_same_arms, reason = "nested services of the same type have identical arms")]
+        fn is_notification(&self) -> bool {
+            false
+        }
+    }
+
+    /// A service for to-do items which publishes each change
+    ///
+    /// This is the trait which is used by the server side in order to serve the client
+    pub trait TodoFeedServer: Send + Sync {
+        /// Get a list of to-do items
+        fn get_todos(&self) -> impl Future<Output = Vec<Todo>> + Send;
+    }
+
+    /// A [Handler](Handler) which handles requests/responses for a given service
+    #[derive(Debug, Clone)]
+    pub struct TodoFeedHandler<_Server>(_Server);
+
+    impl<_Server: TodoFeedServer> Handler for TodoFeedHandler<_Server> {
+        type Rpc = TodoFeed;
+        async fn handle(&self, _ctx: &::trait_rpc::server::Context, request: Request) -> Result<Response, HandlerError> {
+            match request {
+                Request::GetTodos() => Ok(Response::GetTodos(self.0.get_todos().await)),
+                Request::TodoCreated(..) => {
+                    Err(HandlerError::Unsupported("`todo_created` is a streaming method, requests for it must be passed to `Handler::handle_stream`".to_string()))
+                }
+            }
+        }
+        async fn handle_stream<_Incoming, _Sink>(
+            &self,
+            ctx: &::trait_rpc::server::Context,
+            request: Request,
+            _incoming: _Incoming,
+            mut sink: _Sink,
+        ) -> Result<(), HandlerError>
+        where
+            _Incoming: ::trait_rpc::futures::Stream<Item = Request> + Send + Unpin,
+            _Sink: ::trait_rpc::futures::Sink<Response> + Send + Unpin,
+        {
+            match request {
+                Request::TodoCreated() => {
+                    let Some(topics) = ctx.get::<::trait_rpc::server::topics::Topics<<Self as Handler>::Rpc>>() else {
+                        return Err(HandlerError::Other(
+                            "`todo_created` is a topic, but there is no `Topics` for the service in the context".to_string(),
+                        ));
+                    };
+                    let mut events = topics.subscribe("todo_created").map(Ok);
+                    let _ = sink.send_all(&mut events).await;
+                }
+                request @ Request::GetTodos(..) => {
+                    let _ = sink.send(self.handle(ctx, request).await?).await;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A service for to-do items which publishes each change
+    ///
+    /// This is the async client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct TodoFeedAsyncClient<_Client>(_Client);
+
+    #[allow(clippy::future_not_send)]
+    impl<_Client: AsyncClient<Request, Response>> TodoFeedAsyncClient<_Client> {
+        /// Get a list of to-do items
+        pub async fn get_todos(&self) -> Result<Vec<Todo>, _Client::Error> {
+            match self.0.send(Request::GetTodos()).await? {
+                Response::GetTodos(value) => Ok(value),
+                other => Err(WrongResponseType::new("get_todos", other.fn_name()).into()),
+            }
+        }
+        /// Each new to-do item as it is created
+        pub async fn todo_created(&self) -> Result<impl ::trait_rpc::futures::Stream<Item = Result<Todo, _Client::Error>>, _Client::Error> {
+            let responses = self.0.send_stream(Request::TodoCreated()).await?;
+            Ok(responses.map(|response| match response? {
+                Response::TodoCreated(value) => Ok(value),
+                other => Err(WrongResponseType::new("todo_created", other.fn_name()).into()),
+            }))
+        }
+    }
+
+    /// A service for to-do items which publishes each change
+    ///
+    /// This is the blocking client for the service, it produces requests from method calls
+    /// (including chained method calls) and sends the requests with the given
+    /// [transport](::trait_rpc::AsyncClient) before returning the response
+    ///
+    /// The return value is always wrapped in a result: `Result<T, _Client::Error>` where `T` is the service return value
+    #[derive(Debug, Copy, Clone)]
+    pub struct TodoFeedBlockingClient<_Client>(_Client);
+
+    impl<_Client: BlockingClient<Request, Response>> TodoFeedBlockingClient<_Client> {
+        /// Get a list of to-do items
+        pub fn get_todos(&self) -> Result<Vec<Todo>, _Client::Error> {
+            match self.0.send(Request::GetTodos())? {
+                Response::GetTodos(value) => Ok(value),
+                other => Err(WrongResponseType::new("get_todos", other.fn_name()).into()),
+            }
+        }
+        /// Each new to-do item as it is created
+        pub fn todo_created(&self) -> Result<impl Iterator<Item = Result<Todo, _Client::Error>>, _Client::Error> {
+            let responses = self.0.send_stream(Request::TodoCreated())?;
+            Ok(responses.map(|response| match response? {
+                Response::TodoCreated(value) => Ok(value),
+                other => Err(WrongResponseType::new("todo_created", other.fn_name()).into()),
+            }))
+        }
+    }
+}
@@ -0,0 +1,390 @@
+//! Sending many independent calls in a single round trip
+//!
+//! A [`Batch`] wraps a client which supports batches (see [`BatchClient`]), and is itself a client
+//! which is given to a service, eg: `ApiService::async_client(batch.clone())`. Calls made with it
+//! are only sent while the batch is [run](Batch::run), every call made while the future given to
+//! `run` is waiting is sent together, and each call then receives its own typed result. A call made
+//! while the batch is not running fails straight away. Calls to nested services are batched in the
+//! same way. Streaming methods and methods with a stream argument cannot be batched
+//!
+//! A batch is encoded as a sequence of frames (see [`frame`](crate::frame)), each prefixed by its
+//! length as a little endian `u32`. Each request is a request frame whose id is its position in the
+//! batch, the server replies with a response frame or an error frame with the same id for each of
+//! them. Over HTTP the batch is the body of the request and the response, the request also has the
+//! [`BATCH_HEADER`](crate::client::BATCH_HEADER). Over a connection shared by many requests (eg: a
+//! websocket) the batch is the payload of a [`FrameKind::Batch`] frame in each direction
+//!
+//! The server handles the calls of a batch one after the other by default, or concurrently (see
+//! [`Axum`](crate::server::axum::Axum)), a [`HandlerLayer`](crate::server::HandlerLayer) sees each
+//! call as a separate request
+#![allow(clippy::future_not_send, reason = "Cannot explicitly make futures `Send` while supporting WASM")]
+
+use crate::client::{AsyncClient, BatchClient, MaybeWrongResponse, NestedError, ResponseError, WrongResponseType};
+use crate::format::Format;
+use crate::frame::{ErrorFrame, ErrorKind, Frame, FrameError, FrameKind};
+use crate::server::multiplex::{handler_error, parse_error};
+use crate::server::{Context, Handler};
+use crate::{RequestInfo, Rpc};
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, Either};
+use futures::lock::{Mutex, MutexGuard};
+use futures::{Stream, StreamExt, stream};
+use std::fmt::{Debug, Formatter};
+use std::pin::pin;
+use std::sync::{Arc, PoisonError};
+use thiserror::Error;
+
+/// The length of the prefix of each frame in a batch in bytes
+const LENGTH_LEN: usize = 4;
+
+/// Encode the given frames as a batch
+#[must_use]
+pub fn encode(frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(frames.iter().map(|frame| LENGTH_LEN + frame.len()).sum());
+    for frame in frames {
+        let length = u32::try_from(frame.len()).unwrap_or(u32::MAX);
+        bytes.extend(length.to_le_bytes());
+        bytes.extend(frame);
+    }
+    bytes
+}
+
+/// Decode each frame of a batch
+///
+/// # Errors
+/// Returns an error if the batch is truncated, or any of its frames could not be decoded
+pub fn decode(mut bytes: &[u8]) -> Result<Vec<Frame<'_>>, FrameError> {
+    let mut frames = Vec::new();
+    while !bytes.is_empty() {
+        let (length, rest) = bytes
+            .split_first_chunk::<LENGTH_LEN>()
+            .ok_or(FrameError::TruncatedBatch)?;
+        let length = usize::try_from(u32::from_le_bytes(*length)).map_err(|_| FrameError::TruncatedBatch)?;
+        if rest.len() < length {
+            return Err(FrameError::TruncatedBatch);
+        }
+        let (frame, rest) = rest.split_at(length);
+        frames.push(Frame::decode(frame)?);
+        bytes = rest;
+    }
+    Ok(frames)
+}
+
+/// Encode the given encoded requests as a batch
+pub(crate) fn requests(requests: &[Vec<u8>]) -> Vec<u8> {
+    let frames: Vec<_> = (0..)
+        .zip(requests)
+        .map(|(id, request)| Frame::new(FrameKind::Request, id, request).encode())
+        .collect();
+    encode(&frames)
+}
+
+/// Split the responses to a batch of `count` requests, returning the encoded response or the error
+/// for each request in order. A request without a response fails with
+/// [`ResponseError::Unexpected`]
+pub(crate) fn responses(bytes: &[u8], count: usize) -> Result<Vec<Result<&[u8], ResponseError>>, FrameError> {
+    let mut responses = vec![Err(ResponseError::Unexpected); count];
+    for frame in decode(bytes)? {
+        let Some(response) = usize::try_from(frame.id).ok().and_then(|id| responses.get_mut(id)) else {
+            continue;
+        };
+        *response = match frame.kind {
+            FrameKind::Response => Ok(frame.payload),
+            FrameKind::Error => Err(ErrorFrame::decode(frame.id, frame.payload)?.into()),
+            _ => Err(ResponseError::Unexpected),
+        };
+    }
+    Ok(responses)
+}
+
+/// Handle each request of an encoded batch with `handler`, returning the encoded responses
+///
+/// At most `concurrency` calls are handled at once, with 1 they are handled one after the other in
+/// order. This is called by the servers, it does not usually need to be called directly
+///
+/// # Errors
+/// Returns an error if the batch could not be decoded
+pub async fn handle<H: Handler + Sync>(
+    format: &dyn Format<<H::Rpc as Rpc>::Request, <H::Rpc as Rpc>::Response>,
+    bytes: &[u8],
+    handler: &H,
+    ctx: &Context,
+    concurrency: usize,
+) -> Result<Vec<u8>, FrameError> {
+    let calls: Vec<_> = decode(bytes)?
+        .into_iter()
+        .map(|frame| handle_call(format, frame, handler, ctx))
+        .collect();
+    let responses: Vec<_> = stream::iter(calls).buffered(concurrency.max(1)).collect().await;
+    Ok(encode(&responses))
+}
+
+/// Handle a single frame of a batch, returning the encoded response or error frame
+async fn handle_call<H: Handler + Sync>(
+    format: &dyn Format<<H::Rpc as Rpc>::Request, <H::Rpc as Rpc>::Response>,
+    frame: Frame<'_>,
+    handler: &H,
+    ctx: &Context,
+) -> Vec<u8> {
+    let request_id = frame.id;
+    if frame.kind != FrameKind::Request {
+        return ErrorFrame::new(
+            request_id,
+            ErrorKind::BadRequest,
+            format!("Unexpected {:?} frame in a batch", frame.kind),
+        )
+        .encode();
+    }
+    let request = match format.read(frame.payload) {
+        Ok(request) if request.is_stream() || request.is_client_stream() || request.is_stream_item() => {
+            return ErrorFrame::new(request_id, ErrorKind::BadRequest, "Streaming methods cannot be batched")
+                .encode();
+        }
+        Ok(request) => request,
+        Err(error) => return parse_error(request_id, format, frame.payload, &*error).encode(),
+    };
+    match handler.handle(ctx, request).await {
+        Ok(response) => match format.write(response) {
+            Ok(response) => Frame::new(FrameKind::Response, request_id, &response).encode(),
+            Err(error) => ErrorFrame::new(
+                request_id,
+                ErrorKind::Internal,
+                format!("Failed to write response: {error}"),
+            )
+            .encode(),
+        },
+        Err(error) => handler_error(request_id, &error).encode(),
+    }
+}
+
+/// A call waiting to be sent in a batch
+type Queued<Req, Resp, E> = (Req, oneshot::Sender<Result<Resp, BatchError<E>>>);
+
+/// The calls waiting to be sent, shared by every clone of a batch
+type Queue<Req, Resp, E> = Arc<Mutex<mpsc::UnboundedReceiver<Queued<Req, Resp, E>>>>;
+
+/// Whether a batch is running, calls are only queued while this is set
+type Running = Arc<std::sync::Mutex<bool>>;
+
+/// Marks a batch as running until it is dropped, the calls still queued are then cancelled
+struct RunGuard<'a, Req, Resp, E> {
+    queue: MutexGuard<'a, mpsc::UnboundedReceiver<Queued<Req, Resp, E>>>,
+    running: &'a std::sync::Mutex<bool>,
+}
+
+impl<'a, Req, Resp, E> RunGuard<'a, Req, Resp, E> {
+    fn new(queue: MutexGuard<'a, mpsc::UnboundedReceiver<Queued<Req, Resp, E>>>, running: &'a std::sync::Mutex<bool>) -> Self {
+        *lock(running) = true;
+        Self { queue, running }
+    }
+}
+
+impl<Req, Resp, E> Drop for RunGuard<'_, Req, Resp, E> {
+    fn drop(&mut self) {
+        // calls are queued while holding the lock, so none are queued once this is unset and every
+        // call left in the queue can be cancelled
+        *lock(self.running) = false;
+        while let Ok(Some(_call)) = self.queue.try_next() {}
+    }
+}
+
+fn lock(running: &std::sync::Mutex<bool>) -> std::sync::MutexGuard<'_, bool> {
+    // the flag is never left inconsistent, so it can still be used after a panic
+    running.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A client which collects calls into batches, see the [module documentation](self)
+///
+/// Clones share the same calls, so a clone can be given to each service
+pub struct Batch<C, Req, Resp>
+where
+    C: BatchClient<Req, Resp>,
+{
+    client: C,
+    calls: mpsc::UnboundedSender<Queued<Req, Resp, C::Error>>,
+    queue: Queue<Req, Resp, C::Error>,
+    running: Running,
+}
+
+impl<C, Req, Resp> Clone for Batch<C, Req, Resp>
+where
+    C: BatchClient<Req, Resp>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            calls: self.calls.clone(),
+            queue: self.queue.clone(),
+            running: self.running.clone(),
+        }
+    }
+}
+
+impl<C, Req, Resp> Debug for Batch<C, Req, Resp>
+where
+    C: BatchClient<Req, Resp>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Batch").finish_non_exhaustive()
+    }
+}
+
+impl<C, Req, Resp> Batch<C, Req, Resp>
+where
+    C: BatchClient<Req, Resp>,
+{
+    /// Collect calls to be sent with the given client
+    pub fn new(client: C) -> Self {
+        let (calls, queue) = mpsc::unbounded();
+        Self {
+            client,
+            calls,
+            queue: Arc::new(Mutex::new(queue)),
+            running: Arc::default(),
+        }
+    }
+
+    /// Drive `future` to completion, each time it is waiting the calls it has made with this batch
+    /// are sent together. Calls which are made after an earlier batch has been sent (eg: using its
+    /// results) are sent in a later batch
+    ///
+    /// Calls are only sent while this is running, a call which has not been sent once `future`
+    /// has finished fails with [`BatchError::Cancelled`]. If a clone of the batch is already
+    /// running, this waits for it to finish first
+    pub async fn run<F: Future>(&self, future: F) -> F::Output {
+        let mut guard = RunGuard::new(self.queue.lock().await, &self.running);
+        let queue = &mut guard.queue;
+        let send = async {
+            while let Some(call) = queue.next().await {
+                let mut calls = vec![call];
+                // every other call made while the future was last polled
+                while let Ok(Some(call)) = queue.try_next() {
+                    calls.push(call);
+                }
+                self.send(calls).await;
+            }
+        };
+        let mut future = pin!(future);
+        // sending only stops once every clone of the batch has been dropped
+        if let Either::Left((output, _)) = future::select(future.as_mut(), pin!(send)).await {
+            return output;
+        }
+        future.await
+    }
+
+    /// Queue a call to be sent, if the batch is running
+    fn queue(&self, call: Queued<Req, Resp, C::Error>) -> Result<(), BatchError<C::Error>> {
+        // the call is queued while holding the lock, so it cannot be queued after the batch stops
+        let running = lock(&self.running);
+        let queued = (*running).then(|| self.calls.unbounded_send(call));
+        drop(running);
+        queued
+            .ok_or(BatchError::NotRunning)?
+            .map_err(|_| BatchError::Cancelled)
+    }
+
+    /// Send the given calls as a single batch, passing each response to its call
+    async fn send(&self, calls: Vec<Queued<Req, Resp, C::Error>>) {
+        let (requests, senders): (Vec<_>, Vec<_>) = calls.into_iter().unzip();
+        match self.client.send_batch(requests).await {
+            Ok(responses) => {
+                for (sender, response) in senders.into_iter().zip(responses) {
+                    // the call is no longer waiting for its response, so it is dropped
+                    let _ = sender.send(response.map_err(BatchError::Call));
+                }
+            }
+            Err(error) => {
+                let error = Arc::new(error);
+                for sender in senders {
+                    let _ = sender.send(Err(BatchError::Batch(error.clone())));
+                }
+            }
+        }
+    }
+}
+
+impl<C, Req, Resp> AsyncClient<Req, Resp> for Batch<C, Req, Resp>
+where
+    C: BatchClient<Req, Resp>,
+{
+    type Error = BatchError<C::Error>;
+
+    /// The call waits to be sent with the other calls of the batch
+    ///
+    /// # Errors
+    /// Returns [`BatchError::NotRunning`] if the batch is not being [run](Batch::run)
+    async fn send(&self, request: Req) -> Result<Resp, Self::Error> {
+        let (sender, receiver) = oneshot::channel();
+        self.queue((request, sender))?;
+        receiver.await.map_err(|_| BatchError::Cancelled)?
+    }
+
+    /// Streaming methods cannot be batched
+    ///
+    /// # Errors
+    /// Always returns [`BatchError::StreamingNotSupported`]
+    async fn send_stream(&self, _request: Req) -> Result<impl Stream<Item = Result<Resp, Self::Error>>, Self::Error> {
+        Err::<stream::Empty<_>, _>(BatchError::StreamingNotSupported)
+    }
+
+    /// Streaming methods cannot be batched
+    ///
+    /// # Errors
+    /// Always returns [`BatchError::StreamingNotSupported`]
+    async fn send_with_stream(&self, _request: Req, _items: impl Stream<Item = Req>) -> Result<Resp, Self::Error> {
+        Err(BatchError::StreamingNotSupported)
+    }
+    /// Notifications have no response to wait for, so they are sent straight away by the client
+    /// rather than being added to the batch
+    async fn notify(&self, request: Req) -> Result<(), Self::Error> {
+        self.client.notify(request).await.map_err(BatchError::Call)
+    }
+}
+
+/// The error returned by a call made with a [`Batch`]
+#[derive(Debug, Error)]
+pub enum BatchError<T> {
+    /// The call failed, see the client error type (eg: [`RpcError`](crate::client::RpcError))
+    #[error(transparent)]
+    Call(T),
+    /// The whole batch failed, so every call in it receives the same error
+    #[error("The batch failed: {0}")]
+    Batch(Arc<T>),
+    /// The batch stopped running before the call received its response
+    #[error("The batch was cancelled before the call received its response")]
+    Cancelled,
+    /// The call was made while the batch was not being run, so it would never have been sent
+    #[error("The call was made outside of `Batch::run`")]
+    NotRunning,
+    /// A streaming method (or a method with a stream argument) was called
+    #[error("Streaming methods cannot be batched")]
+    StreamingNotSupported,
+}
+
+impl<T: From<WrongResponseType>> From<WrongResponseType> for BatchError<T> {
+    fn from(err: WrongResponseType) -> Self {
+        Self::Call(err.into())
+    }
+}
+
+impl<T: From<NestedError>> From<NestedError> for BatchError<T> {
+    fn from(err: NestedError) -> Self {
+        Self::Call(err.into())
+    }
+}
+
+impl<T: MaybeWrongResponse> MaybeWrongResponse for BatchError<T> {
+    fn into_wrong_response(self) -> Result<WrongResponseType, Self> {
+        match self {
+            Self::Call(err) => err.into_wrong_response().map_err(Self::Call),
+            other => Err(other),
+        }
+    }
+
+    fn into_nested_error(self) -> Result<NestedError, Self> {
+        match self {
+            Self::Call(err) => err.into_nested_error().map_err(Self::Call),
+            other => Err(other),
+        }
+    }
+}
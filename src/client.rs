@@ -1,10 +1,19 @@
 //! Contains modules for individual client implementations
 #![allow(clippy::future_not_send, reason = "Cannot explicitly make futures `Send` while supporting WASM")]
 
+use crate::batch;
 use crate::format::Format;
+use crate::RequestInfo;
 use bon::bon;
+use futures::future::{self, Either};
+use futures::{Stream, StreamExt, TryFutureExt};
+use futures_timer::Delay;
 use std::error::Error;
-use std::fmt::Debug;
+use std::any::Any;
+use std::fmt::{Debug, Display, Formatter};
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 /// Implementation for making requests from browser wasm using the Fetch API
@@ -18,6 +27,14 @@ pub mod reqwest;
 /// Implementation for making requests using the reqwest crate
 #[cfg(all(feature = "reqwest-blocking", not(target_arch = "wasm32")))]
 pub mod reqwest_blocking;
+pub mod layer;
+pub mod loopback;
+pub mod multiplex;
+pub mod retry;
+#[cfg(any(feature = "tcp", feature = "unix", feature = "stdio"))]
+pub mod stream;
+#[cfg(all(feature = "unix", unix))]
+pub mod unix_blocking;
 #[cfg(feature = "websocket-client")]
 pub mod websocket;
 #[cfg(all(feature = "wasm-websocket", not(target_arch = "wasm32")))]
@@ -53,11 +70,15 @@ impl Builder {
         /// Available options are:
         ///  * [request](reqwest::Reqwest)
         ///  * [browser](browser::Browser) (WASM-only)
-        transport: T
+        transport: T,
+        /// The timeout of each call, a call fails with [`RpcError::Timeout`] if no response is
+        /// received in time. The timeout is sent to the server, so that it can stop handling the
+        /// request. Default: no timeout
+        timeout: Option<Duration>,
     ) -> SimpleClient<F, T>
     where T: AsyncTransport
     {
-        SimpleClient { format, transport }
+        SimpleClient { format, transport, timeout }
     }
 
     /// Build a blocking client
@@ -73,26 +94,95 @@ impl Builder {
         /// Available options are:
         ///  * [request](reqwest::Reqwest)
         ///  * [browser](browser::Browser) (WASM-only)
-        transport: T
+        transport: T,
+        /// The timeout of each call, a call fails with [`RpcError::Timeout`] if no response is
+        /// received in time. The timeout is sent to the server, so that it can stop handling the
+        /// request. Default: no timeout
+        timeout: Option<Duration>,
     ) -> SimpleClient<F, T>
     where T: BlockingTransport
     {
-        SimpleClient { format, transport }
+        SimpleClient { format, transport, timeout }
     }
 }
 
 /// A client implementation for sending requests asynchronously
 pub trait AsyncClient<Req, Resp>: Clone {
     /// The error that can happen during send
-    type Error: Error + MaybeWrongResponse + From<WrongResponseType> + 'static;
+    type Error: Error + MaybeWrongResponse + From<WrongResponseType> + From<NestedError> + 'static;
     /// Send a request and receive a response
     fn send(&self, request: Req) -> impl Future<Output = Result<Resp, Self::Error>>;
+    /// Send a request for a streaming method and receive a stream of responses
+    fn send_stream(
+        &self,
+        request: Req,
+    ) -> impl Future<Output = Result<impl Stream<Item = Result<Resp, Self::Error>>, Self::Error>>;
+    /// Send a request for a method which takes a stream argument, followed by each of the given
+    /// items, and receive a response
+    fn send_with_stream(
+        &self,
+        request: Req,
+        items: impl Stream<Item = Req>,
+    ) -> impl Future<Output = Result<Resp, Self::Error>>;
+    /// Send a request for a method marked with `#[rpc(notify)]`, this returns once the request
+    /// has been handed to the transport without waiting for a response
+    fn notify(&self, request: Req) -> impl Future<Output = Result<(), Self::Error>>;
+}
+
+/// A client which can send several requests in a single round trip, this is used by a
+/// [`Batch`](crate::batch::Batch)
+pub trait BatchClient<Req, Resp>: AsyncClient<Req, Resp> {
+    /// Send the requests together and receive the response to each of them, in the same order.
+    /// Fails as a whole if the batch could not be sent, or its responses could not be received
+    fn send_batch(
+        &self,
+        requests: Vec<Req>,
+    ) -> impl Future<Output = Result<Vec<Result<Resp, Self::Error>>, Self::Error>>;
+}
+
+/// A client which can keep a request open, this is used to open a handle to a nested service
+/// marked with `#[rpc(handle)]`, see [`server::objects`](crate::server::objects)
+pub trait HandleClient<Req, Resp>: AsyncClient<Req, Resp> {
+    /// Send a request for a streaming method and receive its first response, the request is kept
+    /// open until the returned value is dropped, at which point it is cancelled
+    fn open(&self, request: Req) -> impl Future<Output = Result<(Resp, impl Send + Sync + 'static), Self::Error>>;
+}
+
+/// A handle to an object kept by the server, clones share the same object, which is released once
+/// every clone has been dropped or the connection has closed
+#[derive(Clone)]
+pub struct ObjectHandle {
+    id: u64,
+    _open: Arc<dyn Any + Send + Sync>,
+}
+
+impl ObjectHandle {
+    #[doc(hidden)]
+    #[must_use]
+    pub fn new(id: u64, open: impl Send + Sync + 'static) -> Self {
+        Self {
+            id,
+            _open: Arc::new(open),
+        }
+    }
+
+    /// The id of the object on the server, this is only unique within a single connection
+    #[must_use]
+    pub const fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl Debug for ObjectHandle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObjectHandle").field("id", &self.id).finish_non_exhaustive()
+    }
 }
 
 /// A client implementation for sending requests in a blocking manner
 pub trait BlockingClient<Req, Resp>: Clone {
     /// The error that can happen during send
-    type Error: Error + MaybeWrongResponse + From<WrongResponseType> + 'static;
+    type Error: Error + MaybeWrongResponse + From<WrongResponseType> + From<NestedError> + 'static;
     /// Send a request and receive a response
     ///
     /// # Errors
@@ -101,6 +191,32 @@ pub trait BlockingClient<Req, Resp>: Clone {
     /// * Failed to serialise/deserialise
     /// * Received the wrong type of response
     fn send(&self, request: Req) -> Result<Resp, Self::Error>;
+    /// Send a request for a streaming method and receive an iterator over the responses
+    ///
+    /// # Errors
+    /// Returns an error for any of the following cases:
+    /// * The transport does not support streaming
+    /// * Failed at the transport layer
+    /// * Failed to serialise the request
+    fn send_stream(&self, request: Req) -> Result<impl Iterator<Item = Result<Resp, Self::Error>>, Self::Error>;
+    /// Send a request for a method which takes a stream argument, followed by each of the given
+    /// items, and receive a response
+    ///
+    /// # Errors
+    /// Returns an error for any of the following cases:
+    /// * The transport does not support streaming
+    /// * Failed at the transport layer
+    /// * Failed to serialise/deserialise
+    /// * Received the wrong type of response
+    fn send_with_stream(&self, request: Req, items: impl Iterator<Item = Req>) -> Result<Resp, Self::Error>;
+    /// Send a request for a method marked with `#[rpc(notify)]`, this returns once the request
+    /// has been handed to the transport without waiting for a response
+    ///
+    /// # Errors
+    /// Returns an error for any of the following cases:
+    /// * Failed at the transport layer
+    /// * Failed to serialise the request
+    fn notify(&self, request: Req) -> Result<(), Self::Error>;
 }
 
 /// A simple client which has a transport and format specified
@@ -108,12 +224,76 @@ pub trait BlockingClient<Req, Resp>: Clone {
 pub struct SimpleClient<F, T> {
     format: F,
     transport: T,
+    timeout: Option<Duration>,
+}
+
+impl<F, T> SimpleClient<F, T> {
+    /// Set the timeout of each call made with this client, replacing the default given to the
+    /// [builder](Builder). Clients are cheap to clone, so this can be used to give a single call a
+    /// different timeout
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The information about a call, given to the transport
+    fn call_info<Req: RequestInfo>(&self, request: &Req, content_type: &'static str) -> CallInfo<'static> {
+        CallInfo {
+            content_type,
+            timeout: self.timeout,
+            idempotent: request.is_idempotent(),
+            batch: false,
+            notification: request.is_notification(),
+            headers: Vec::new(),
+        }
+    }
+}
+
+/// The HTTP header used to send the timeout of a call to the server, in milliseconds
+pub const TIMEOUT_HEADER: &str = "rpc-timeout";
+/// The HTTP header set on a request whose body is a batch of requests, see
+/// [`batch`]
+pub const BATCH_HEADER: &str = "rpc-batch";
+
+/// Information about a call, this is given to the transport along with the encoded request
+#[derive(Debug, Clone)]
+pub struct CallInfo<'a> {
+    /// The content type of the encoded request
+    pub content_type: &'a str,
+    /// The timeout of the call, this should be sent to the server (eg: in the [`TIMEOUT_HEADER`])
+    pub timeout: Option<Duration>,
+    /// Whether the request can safely be sent more than once, see
+    /// [`RequestInfo::is_idempotent`]
+    pub idempotent: bool,
+    /// Whether the request is a batch of requests, this should be sent to the server (eg: in the
+    /// [`BATCH_HEADER`]), see [`batch`]
+    pub batch: bool,
+    /// Whether the request is a notification, see [`RequestInfo::is_notification`]. The server
+    /// does not send a response to it, so the transport need not wait for one
+    pub notification: bool,
+    /// Extra headers to send with the request, these are usually added by an
+    /// [`Interceptor`](layer::Interceptor). HTTP transports send them as headers of the request,
+    /// other transports ignore them
+    pub headers: Vec<(String, String)>,
+}
+
+/// Wait for `future` to complete, failing with the timeout if it elapses first
+pub(crate) async fn timeout<F: Future>(timeout: Option<Duration>, future: F) -> Result<F::Output, Duration> {
+    let Some(timeout) = timeout else {
+        return Ok(future.await);
+    };
+    match future::select(pin!(future), Delay::new(timeout)).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(((), _)) => Err(timeout),
+    }
 }
 
 impl<F, T, Req, Resp> AsyncClient<Req, Resp> for SimpleClient<F, T>
 where
     F: Format<Resp, Req>,
     T: AsyncTransport,
+    Req: RequestInfo,
     Self: Clone
 {
     type Error = RpcError<T::Error>;
@@ -124,27 +304,132 @@ where
     /// * Failed at the transport layer
     /// * Failed to serialise/deserialise
     /// * Received the wrong type of response
+    /// * The timeout elapsed before the response was received
     async fn send(&self, request: Req) -> Result<Resp, Self::Error> {
+        let call = self.call_info(&request, self.format.content_type());
         let request = self.format.write(request).map_err(RpcError::Serialize)?;
-        let response = self.transport.send(request, self.format.content_type()).await.map_err(RpcError::Transport)??;
+        let response = self.transport.send(request, call);
+        let response = timeout(self.timeout, response)
+            .await
+            .map_err(RpcError::Timeout)?
+            .map_err(RpcError::Transport)??;
         let response = self.format.read(response.as_slice()).map_err(RpcError::Deserialize)?;
         Ok(response)
     }
+
+    /// Streaming is not supported by request/response transports
+    ///
+    /// # Errors
+    /// Always returns [`RpcError::StreamingNotSupported`]
+    async fn send_stream(&self, _request: Req) -> Result<impl Stream<Item = Result<Resp, Self::Error>>, Self::Error> {
+        Err::<futures::stream::Empty<_>, _>(RpcError::StreamingNotSupported)
+    }
+
+    /// Streaming is not supported by request/response transports
+    ///
+    /// # Errors
+    /// Always returns [`RpcError::StreamingNotSupported`]
+    async fn send_with_stream(&self, _request: Req, _items: impl Stream<Item = Req>) -> Result<Resp, Self::Error> {
+        Err(RpcError::StreamingNotSupported)
+    }
+
+    /// Send a notification, this returns once the transport has taken it and does not wait for
+    /// the server (see [`AsyncTransport::notify`])
+    ///
+    /// # Errors
+    /// Returns an error if the notification failed at the transport layer, could not be
+    /// serialised, or the timeout elapsed before it was sent
+    async fn notify(&self, request: Req) -> Result<(), Self::Error> {
+        let call = self.call_info(&request, self.format.content_type());
+        let request = self.format.write(request).map_err(RpcError::Serialize)?;
+        let response = self.transport.notify(request, call);
+        timeout(self.timeout, response)
+            .await
+            .map_err(RpcError::Timeout)?
+            .map_err(RpcError::Transport)??;
+        Ok(())
+    }
+}
+
+impl<F, T, Req, Resp> BatchClient<Req, Resp> for SimpleClient<F, T>
+where
+    F: Format<Resp, Req>,
+    T: AsyncTransport,
+    Req: RequestInfo,
+    Self: Clone
+{
+    /// Send the requests as the body of a single request, the timeout applies to the whole batch
+    ///
+    /// # Errors
+    /// Returns an error if the batch failed at the transport layer, could not be
+    /// serialised/deserialised, or the timeout elapsed before the response was received
+    async fn send_batch(&self, requests: Vec<Req>) -> Result<Vec<Result<Resp, Self::Error>>, Self::Error> {
+        let call = CallInfo {
+            content_type: self.format.content_type(),
+            timeout: self.timeout,
+            idempotent: requests.iter().all(RequestInfo::is_idempotent),
+            batch: true,
+            notification: false,
+            headers: Vec::new(),
+        };
+        let count = requests.len();
+        let requests = requests
+            .into_iter()
+            .map(|request| self.format.write(request))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(RpcError::Serialize)?;
+        let response = self.transport.send(batch::requests(&requests), call);
+        let response = timeout(self.timeout, response)
+            .await
+            .map_err(RpcError::Timeout)?
+            .map_err(RpcError::Transport)??;
+        let responses = batch::responses(&response, count).map_err(|error| RpcError::Deserialize(Box::new(error)))?;
+        Ok(responses
+            .into_iter()
+            .map(|response| self.format.read(response?).map_err(RpcError::Deserialize))
+            .collect())
+    }
 }
 
 impl<F, T, Req, Resp> BlockingClient<Req, Resp> for SimpleClient<F, T>
 where
     F: Format<Resp, Req>,
     T: BlockingTransport,
+    Req: RequestInfo,
     Self: Clone
 {
     type Error = RpcError<T::Error>;
     fn send(&self, request: Req) -> Result<Resp, Self::Error> {
+        let call = self.call_info(&request, self.format.content_type());
         let request = self.format.write(request).map_err(RpcError::Serialize)?;
-        let response = self.transport.send(request, self.format.content_type()).map_err(RpcError::Transport)??;
+        let start = Instant::now();
+        let response = self
+            .transport
+            .send(request, call)
+            .map_err(|error| match self.timeout {
+                // the transport gives up once the timeout has elapsed
+                Some(timeout) if start.elapsed() >= timeout => RpcError::Timeout(timeout),
+                _ => RpcError::Transport(error),
+            })??;
         let response = self.format.read(response.as_slice()).map_err(RpcError::Deserialize)?;
         Ok(response)
     }
+    fn send_stream(&self, _request: Req) -> Result<impl Iterator<Item = Result<Resp, Self::Error>>, Self::Error> {
+        Err::<std::iter::Empty<_>, _>(RpcError::StreamingNotSupported)
+    }
+    fn send_with_stream(&self, _request: Req, _items: impl Iterator<Item = Req>) -> Result<Resp, Self::Error> {
+        Err(RpcError::StreamingNotSupported)
+    }
+    fn notify(&self, request: Req) -> Result<(), Self::Error> {
+        let call = self.call_info(&request, self.format.content_type());
+        let request = self.format.write(request).map_err(RpcError::Serialize)?;
+        let start = Instant::now();
+        self.transport.send(request, call).map_err(|error| match self.timeout {
+            Some(timeout) if start.elapsed() >= timeout => RpcError::Timeout(timeout),
+            _ => RpcError::Transport(error),
+        })??;
+        Ok(())
+    }
 }
 
 /// This trait describes the transport layer of a client,
@@ -160,7 +445,17 @@ pub trait AsyncTransport: Clone {
     /// This is the error type which is returned in the case that some part of the transport failed
     type Error: Error + 'static;
     /// Sends the request and returns the response
-    fn send(&self, request: Vec<u8>, content_type: &str) -> impl Future<Output=Result<Result<Vec<u8>, ResponseError>, Self::Error>>;
+    ///
+    /// The timeout of the call should be sent to the server (eg: in the [`TIMEOUT_HEADER`]), the
+    /// client stops waiting for the response once it has elapsed
+    fn send(&self, request: Vec<u8>, call: CallInfo<'_>) -> impl Future<Output=Result<Result<Vec<u8>, ResponseError>, Self::Error>>;
+    /// Sends a notification, returning once it has been handed to the transport
+    ///
+    /// By default this is sent like any other request and waits for the response, a transport
+    /// which can send it in the background should do so
+    fn notify(&self, request: Vec<u8>, call: CallInfo<'_>) -> impl Future<Output=Result<Result<(), ResponseError>, Self::Error>> {
+        self.send(request, call).map_ok(|response| response.map(drop))
+    }
 }
 
 /// This trait describes the transport layer of a client,
@@ -181,9 +476,12 @@ pub trait BlockingTransport: Clone {
     type Error: Error + 'static;
     /// Sends the request and returns the response
     ///
+    /// The timeout of the call should be sent to the server (eg: in the [`TIMEOUT_HEADER`]), and
+    /// the transport should return an error once it has elapsed
+    ///
     /// # Errors
     /// Returns an error in the case that the communication failed for any reason
-    fn send(&self, request: Vec<u8>, content_type: &str) -> Result<Result<Vec<u8>, ResponseError>, Self::Error>;
+    fn send(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error>;
 }
 
 /// This is a transport layer used for nesting services
@@ -191,7 +489,7 @@ pub trait BlockingTransport: Clone {
 pub struct MappedClient<T, InnerReq, OuterReq, InnerResp, OuterResp, Args> {
     outer: T,
     args: Args,
-    to_inner: fn(Result<OuterResp, WrongResponseType>) -> Result<InnerResp, WrongResponseType>,
+    to_inner: fn(Result<OuterResp, NestedError>) -> Result<InnerResp, NestedError>,
     to_outer: fn(Args, InnerReq) -> OuterReq,
 }
 
@@ -220,7 +518,7 @@ MappedClient<T, InnerReq, OuterReq, InnerResp, OuterResp, Args>
     pub fn new(
         inner: T,
         args: Args,
-        to_inner: fn(Result<OuterResp, WrongResponseType>) -> Result<InnerResp, WrongResponseType>,
+        to_inner: fn(Result<OuterResp, NestedError>) -> Result<InnerResp, NestedError>,
         to_outer: fn(Args, InnerReq) -> OuterReq,
     ) -> Self {
         Self {
@@ -242,11 +540,40 @@ where
         let request = (self.to_outer)(self.args.clone(), request);
         let response = match self.outer.send(request).await {
             Ok(response) => Ok(response),
-            Err(err) => Err(err.into_wrong_response()?),
+            Err(err) => Err(err.into_nested_error()?),
         };
         let response = (self.to_inner)(response)?;
         Ok(response)
     }
+    async fn send_stream(&self, request: InnerReq) -> Result<impl Stream<Item = Result<InnerResp, Self::Error>>, Self::Error> {
+        let request = (self.to_outer)(self.args.clone(), request);
+        let to_inner = self.to_inner;
+        let responses = self.outer.send_stream(request).await?;
+        Ok(responses.map(move |response| map_response(to_inner, response)))
+    }
+    async fn send_with_stream(&self, request: InnerReq, items: impl Stream<Item = InnerReq>) -> Result<InnerResp, Self::Error> {
+        let request = (self.to_outer)(self.args.clone(), request);
+        let (args, to_outer) = (self.args.clone(), self.to_outer);
+        let items = items.map(move |item| to_outer(args.clone(), item));
+        map_response(self.to_inner, self.outer.send_with_stream(request, items).await)
+    }
+    async fn notify(&self, request: InnerReq) -> Result<(), Self::Error> {
+        let request = (self.to_outer)(self.args.clone(), request);
+        self.outer.notify(request).await
+    }
+}
+
+impl<T, InnerReq, OuterReq, InnerResp, OuterResp, Args> HandleClient<InnerReq, InnerResp>
+for MappedClient<T, InnerReq, OuterReq, InnerResp, OuterResp, Args>
+where
+    Args: Clone,
+    T: HandleClient<OuterReq, OuterResp>,
+{
+    async fn open(&self, request: InnerReq) -> Result<(InnerResp, impl Send + Sync + 'static), Self::Error> {
+        let request = (self.to_outer)(self.args.clone(), request);
+        let (response, open) = self.outer.open(request).await?;
+        Ok((map_response(self.to_inner, Ok::<_, Self::Error>(response))?, open))
+    }
 }
 
 impl<T, InnerReq, OuterReq, InnerResp, OuterResp, Args> BlockingClient<InnerReq, InnerResp>
@@ -260,11 +587,41 @@ where
         let request = (self.to_outer)(self.args.clone(), request);
         let response = match self.outer.send(request) {
             Ok(response) => Ok(response),
-            Err(err) => Err(err.into_wrong_response()?),
+            Err(err) => Err(err.into_nested_error()?),
         };
         let response = (self.to_inner)(response)?;
         Ok(response)
     }
+    fn send_stream(&self, request: InnerReq) -> Result<impl Iterator<Item = Result<InnerResp, Self::Error>>, Self::Error> {
+        let request = (self.to_outer)(self.args.clone(), request);
+        let to_inner = self.to_inner;
+        let responses = self.outer.send_stream(request)?;
+        Ok(responses.map(move |response| map_response(to_inner, response)))
+    }
+    fn send_with_stream(&self, request: InnerReq, items: impl Iterator<Item = InnerReq>) -> Result<InnerResp, Self::Error> {
+        let request = (self.to_outer)(self.args.clone(), request);
+        let items = items.map(|item| (self.to_outer)(self.args.clone(), item));
+        map_response(self.to_inner, self.outer.send_with_stream(request, items))
+    }
+    fn notify(&self, request: InnerReq) -> Result<(), Self::Error> {
+        let request = (self.to_outer)(self.args.clone(), request);
+        self.outer.notify(request)
+    }
+}
+
+/// Map a single response from the outer service to the inner response
+fn map_response<OuterResp, InnerResp, E>(
+    to_inner: fn(Result<OuterResp, NestedError>) -> Result<InnerResp, NestedError>,
+    response: Result<OuterResp, E>,
+) -> Result<InnerResp, E>
+where
+    E: MaybeWrongResponse + From<NestedError>,
+{
+    let response = match response {
+        Ok(response) => Ok(response),
+        Err(err) => Err(err.into_nested_error()?),
+    };
+    Ok(to_inner(response)?)
 }
 
 /// This is a error that the client may return after a request
@@ -288,6 +645,32 @@ pub enum RpcError<T> {
     /// This error either means the server side is misbehaving quite badly, or the transport is not configured to the correct endpoint
     #[error(transparent)]
     WrongResponseType(#[from] WrongResponseType),
+    /// A nested service was not found by the server
+    #[error(transparent)]
+    NotFound(#[from] NotFound),
+    /// The server could not look up a nested service, as the method which looks it up returned an
+    /// error
+    #[error(transparent)]
+    Application(#[from] ApplicationError),
+    /// A streaming method (or a method with a stream argument) was called, but the transport only
+    /// supports a single request and response per call
+    #[error("Streaming methods are not supported by this transport")]
+    StreamingNotSupported,
+    /// No response was received before the timeout of the call elapsed
+    #[error("Timed out after {0:?} waiting for a response")]
+    Timeout(Duration),
+}
+
+/// The error returned by the client for a method with an application error type, declared with
+/// `#[rpc(error = E)]`, this flattens the application error and the client error into one type
+#[derive(Debug, Error)]
+pub enum CallError<E, T> {
+    /// The server implementation returned an application error
+    #[error(transparent)]
+    Application(E),
+    /// The call failed, see the client error type (eg: [`RpcError`])
+    #[error(transparent)]
+    Rpc(T),
 }
 
 /// Indicates that the transport was successful, but the response indicated some problem
@@ -299,6 +682,15 @@ pub enum ResponseError {
     /// Internal Server Error
     #[error("Internal Server Error: {0}")]
     InternalServerError(String),
+    /// The request was for a method which the server does not have
+    #[error("Unknown method: {0}")]
+    UnknownMethod(String),
+    /// The caller has not provided valid credentials
+    #[error("Request was not authenticated: {0}")]
+    Unauthenticated(String),
+    /// The caller is not allowed to make the request
+    #[error("Request was not permitted: {0}")]
+    PermissionDenied(String),
     /// Unexpected response
     #[error("Unexpected response")]
     Unexpected,
@@ -336,6 +728,109 @@ impl WrongResponseType {
     }
 }
 
+/// A nested service was not found: the method of the outer service which looks it up returned
+/// `None` (eg: `-> Option<impl UserService>`)
+#[derive(Debug, Error, Clone)]
+#[error("Sub-service {service} was not found")]
+pub struct NotFound {
+    /// The method which looked up the nested service
+    pub service: String,
+}
+
+impl NotFound {
+    #[doc(hidden)]
+    #[must_use]
+    pub fn new(service: &str) -> Self {
+        Self {
+            service: format!("{service}()"),
+        }
+    }
+    #[doc(hidden)]
+    #[must_use]
+    pub fn in_subservice(self, name: &str) -> Self {
+        Self {
+            service: format!("{name}().{}", self.service),
+        }
+    }
+}
+
+/// The method of the outer service which looks up a nested service returned an error
+///
+/// The error of the method (eg: `-> Result<impl UserService, LoginExpired>`) is sent to the
+/// client, and can be recovered with [`downcast_ref`](Self::downcast_ref)
+#[derive(Debug, Error, Clone)]
+#[error("Sub-service {service} returned an error: {message}")]
+pub struct ApplicationError {
+    /// The method which looked up the nested service
+    pub service: String,
+    message: String,
+    error: Arc<dyn Any + Send + Sync>,
+}
+
+impl ApplicationError {
+    #[doc(hidden)]
+    #[must_use]
+    pub fn new<E: Display + Send + Sync + 'static>(service: &str, error: E) -> Self {
+        Self {
+            service: format!("{service}()"),
+            message: error.to_string(),
+            error: Arc::new(error),
+        }
+    }
+    #[doc(hidden)]
+    #[must_use]
+    pub fn in_subservice(self, name: &str) -> Self {
+        Self {
+            service: format!("{name}().{}", self.service),
+            ..self
+        }
+    }
+
+    /// The error returned by the method, if it is an `E`, this is the error type of the `Result`
+    /// returned by the method
+    #[must_use]
+    pub fn downcast_ref<E: 'static>(&self) -> Option<&E> {
+        self.error.downcast_ref()
+    }
+}
+
+/// An error from the response of a nested service, this is passed back through each service it is
+/// nested in so that it names the full chain of methods
+#[derive(Debug, Error, Clone)]
+pub enum NestedError {
+    /// See [`WrongResponseType`]
+    #[error(transparent)]
+    WrongResponseType(#[from] WrongResponseType),
+    /// See [`NotFound`]
+    #[error(transparent)]
+    NotFound(#[from] NotFound),
+    /// See [`ApplicationError`]
+    #[error(transparent)]
+    Application(#[from] ApplicationError),
+}
+
+impl NestedError {
+    #[doc(hidden)]
+    #[must_use]
+    pub fn in_subservice(self, name: &str) -> Self {
+        match self {
+            Self::WrongResponseType(err) => Self::WrongResponseType(err.in_subservice(name)),
+            Self::NotFound(err) => Self::NotFound(err.in_subservice(name)),
+            Self::Application(err) => Self::Application(err.in_subservice(name)),
+        }
+    }
+}
+
+impl<T> From<NestedError> for RpcError<T> {
+    fn from(err: NestedError) -> Self {
+        match err {
+            NestedError::WrongResponseType(err) => Self::WrongResponseType(err),
+            NestedError::NotFound(err) => Self::NotFound(err),
+            NestedError::Application(err) => Self::Application(err),
+        }
+    }
+}
+
 /// An error which might be a [`WrongResponseType`] error
 pub trait MaybeWrongResponse: Sized {
     /// Try to cast to a [`WrongResponseType`]
@@ -344,6 +839,15 @@ pub trait MaybeWrongResponse: Sized {
     ///
     /// Returns an Err variant with self if this value is not a [`WrongResponseType`]
     fn into_wrong_response(self) -> Result<WrongResponseType, Self>;
+
+    /// Try to cast to a [`NestedError`], which is passed back through each nested service
+    ///
+    /// # Errors
+    ///
+    /// Returns an Err variant with self if this value is not a [`NestedError`]
+    fn into_nested_error(self) -> Result<NestedError, Self> {
+        self.into_wrong_response().map(NestedError::WrongResponseType)
+    }
 }
 
 impl<T: Error> MaybeWrongResponse for RpcError<T> {
@@ -354,4 +858,13 @@ impl<T: Error> MaybeWrongResponse for RpcError<T> {
             Err(self)
         }
     }
+
+    fn into_nested_error(self) -> Result<NestedError, Self> {
+        match self {
+            Self::WrongResponseType(err) => Ok(err.into()),
+            Self::NotFound(err) => Ok(err.into()),
+            Self::Application(err) => Ok(err.into()),
+            other => Err(other),
+        }
+    }
 }
@@ -11,7 +11,7 @@ use wasm_bindgen_futures::wasm_bindgen::JsCast;
 use web_sys::wasm_bindgen::JsValue;
 use web_sys::{Request, RequestInit, RequestMode, Response, Window};
 use web_sys::js_sys::{Uint8Array};
-use crate::client::ResponseError;
+use crate::client::{BATCH_HEADER, CallInfo, ResponseError, TIMEOUT_HEADER};
 
 /// A client which uses the browsers Fetch API along with JSON format (via serde),
 /// only supported on wasm32 architecture
@@ -50,21 +50,42 @@ impl Browser {
     }
 }
 
-impl AsyncTransport for Browser {
-    type Error = Error;
-
-    async fn send(&self, request: Vec<u8>, content_type: &str) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+impl Browser {
+    fn request(&self, request: &[u8], call: &CallInfo<'_>) -> Result<Request, Error> {
         let opts = self.request_options.clone();
-        let body = Uint8Array::from(request.as_slice());
+        let body = Uint8Array::from(request);
         opts.set_body(&body);
 
         let request =
             Request::new_with_str_and_init(&self.url, &opts).map_err(Error::NewRequest)?;
         request
             .headers()
-            .set("Content-Type", content_type)
+            .set("Content-Type", call.content_type)
             .map_err(Error::SetHeader)?;
+        if let Some(timeout) = call.timeout {
+            request
+                .headers()
+                .set(TIMEOUT_HEADER, &timeout.as_millis().to_string())
+                .map_err(Error::SetHeader)?;
+        }
+        if call.batch {
+            request
+                .headers()
+                .set(BATCH_HEADER, "true")
+                .map_err(Error::SetHeader)?;
+        }
+        for (name, value) in &call.headers {
+            request.headers().set(name, value).map_err(Error::SetHeader)?;
+        }
+        Ok(request)
+    }
+}
 
+impl AsyncTransport for Browser {
+    type Error = Error;
+
+    async fn send(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+        let request = self.request(&request, &call)?;
         let promise = self.window.fetch_with_request(&request);
         let future = JsFuture::from(promise);
         let response = future.await.map_err(Error::Fetch)?;
@@ -74,11 +95,20 @@ impl AsyncTransport for Browser {
         let body = Uint8Array::new(&body).to_vec();
         match response.status() {
             200..=299 => Ok(Ok(body)),
+            401 => Ok(Err(ResponseError::Unauthenticated(String::from_utf8(body).unwrap()))),
+            403 => Ok(Err(ResponseError::PermissionDenied(String::from_utf8(body).unwrap()))),
             400..=499 => Ok(Err(ResponseError::BadRequest(String::from_utf8(body).unwrap()))),
             500..=599 => Ok(Err(ResponseError::InternalServerError(String::from_utf8(body).unwrap()))),
             _ => Ok(Err(ResponseError::Unexpected))
         }
     }
+
+    /// The fetch is started and not waited for, the browser sends it in the background
+    async fn notify(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<(), ResponseError>, Self::Error> {
+        let request = self.request(&request, &call)?;
+        let _ = self.window.fetch_with_request(&request);
+        Ok(Ok(()))
+    }
 }
 
 /// This represents the various errors which can occur when using the Fetch API
@@ -0,0 +1,266 @@
+//! Client middleware, this allows behaviour to be added between a generated client and the
+//! transport without implementing a whole new transport
+//!
+//! Layers implement [`Layer`] from the `tower` crate, so they can be composed with other tower
+//! layers (eg: using `tower::ServiceBuilder`). Any type implementing [`AsyncClient`] or
+//! [`BlockingClient`] can be wrapped in a layer to implement a new client, this includes
+//! [`SimpleClient`](super::SimpleClient), [`WebsocketClient`](super::websocket::WebsocketClient)
+//! and [`MappedClient`](super::MappedClient)
+
+use crate::client::{AsyncClient, AsyncTransport, BatchClient, BlockingClient, BlockingTransport, CallInfo, HandleClient, ResponseError};
+use futures::{Stream, TryFutureExt, stream};
+use std::cell::RefCell;
+use std::error::Error;
+use std::sync::{Mutex, PoisonError};
+pub use tower_layer::Layer;
+
+/// Middleware which is called around each call made through an [`Intercepted`] client
+///
+/// Each hook is given the request and `next`, which sends a request on to the wrapped client or
+/// transport. A hook can change the request, inspect or replace the response, time the call, or
+/// call `next` more than once (eg: to retry the call). By default each hook simply calls `next`
+///
+/// At the client level (wrapping an [`AsyncClient`] or [`BlockingClient`]) the hooks are called
+/// with the typed requests and responses of the service. At the transport level (wrapping an
+/// [`AsyncTransport`] or a [`BlockingTransport`]) the request is the encoded request along with
+/// its [`CallInfo`], whose headers can be changed, and the response is the encoded response or
+/// the error returned by the server
+pub trait Interceptor<Req, Resp>: Clone {
+    /// Called around a call which returns a single response, this includes methods which take a
+    /// stream argument. The items of a stream argument are only sent once, if `next` is called
+    /// again the request is sent with the items which have not been sent yet
+    fn call<N, F, E>(&self, request: Req, next: N) -> impl Future<Output = Result<Resp, E>>
+    where
+        N: Fn(Req) -> F,
+        F: Future<Output = Result<Resp, E>>,
+        E: Error + 'static,
+    {
+        next(request)
+    }
+
+    /// Called around opening a streaming method, or a handle to a nested service, the
+    /// responses which follow are not passed through the interceptor
+    fn open<T, N, F, E>(&self, request: Req, next: N) -> impl Future<Output = Result<T, E>>
+    where
+        N: Fn(Req) -> F,
+        F: Future<Output = Result<T, E>>,
+        E: Error + 'static,
+    {
+        next(request)
+    }
+
+    /// Called around sending a notification
+    fn notify<N, F, E>(&self, request: Req, next: N) -> impl Future<Output = Result<(), E>>
+    where
+        N: Fn(Req) -> F,
+        F: Future<Output = Result<(), E>>,
+        E: Error + 'static,
+    {
+        next(request)
+    }
+
+    /// The blocking equivalent of [`call`](Self::call)
+    ///
+    /// # Errors
+    /// Returns the error of the wrapped client or transport
+    fn call_blocking<N, E>(&self, request: Req, next: N) -> Result<Resp, E>
+    where
+        N: Fn(Req) -> Result<Resp, E>,
+        E: Error + 'static,
+    {
+        next(request)
+    }
+
+    /// The blocking equivalent of [`open`](Self::open)
+    ///
+    /// # Errors
+    /// Returns the error of the wrapped client
+    fn open_blocking<T, N, E>(&self, request: Req, next: N) -> Result<T, E>
+    where
+        N: Fn(Req) -> Result<T, E>,
+        E: Error + 'static,
+    {
+        next(request)
+    }
+
+    /// The blocking equivalent of [`notify`](Self::notify)
+    ///
+    /// # Errors
+    /// Returns the error of the wrapped client
+    fn notify_blocking<N, E>(&self, request: Req, next: N) -> Result<(), E>
+    where
+        N: Fn(Req) -> Result<(), E>,
+        E: Error + 'static,
+    {
+        next(request)
+    }
+}
+
+/// A [`Layer`] which wraps a client or transport with the given [`Interceptor`]
+#[derive(Debug, Copy, Clone)]
+pub struct InterceptLayer<I> {
+    interceptor: I,
+}
+
+impl<I> InterceptLayer<I> {
+    /// Create a new layer with the given interceptor
+    pub const fn new(interceptor: I) -> Self {
+        Self { interceptor }
+    }
+}
+
+impl<I: Clone, C> Layer<C> for InterceptLayer<I> {
+    type Service = Intercepted<C, I>;
+
+    fn layer(&self, inner: C) -> Self::Service {
+        Intercepted::new(inner, self.interceptor.clone())
+    }
+}
+
+/// A client or transport wrapped with an [`Interceptor`], see [`InterceptLayer`]
+#[derive(Debug, Copy, Clone)]
+pub struct Intercepted<C, I> {
+    inner: C,
+    interceptor: I,
+}
+
+impl<C, I> Intercepted<C, I> {
+    /// Wrap `inner` with the given interceptor
+    pub const fn new(inner: C, interceptor: I) -> Self {
+        Self { inner, interceptor }
+    }
+
+    /// The wrapped client or transport
+    pub const fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C, I, Req, Resp> AsyncClient<Req, Resp> for Intercepted<C, I>
+where
+    C: AsyncClient<Req, Resp>,
+    I: Interceptor<Req, Resp>,
+{
+    type Error = C::Error;
+
+    async fn send(&self, request: Req) -> Result<Resp, Self::Error> {
+        self.interceptor.call(request, |request| self.inner.send(request)).await
+    }
+
+    async fn send_stream(&self, request: Req) -> Result<impl Stream<Item = Result<Resp, Self::Error>>, Self::Error> {
+        self.interceptor
+            .open(request, |request| self.inner.send_stream(request))
+            .await
+    }
+
+    async fn send_with_stream(&self, request: Req, items: impl Stream<Item = Req>) -> Result<Resp, Self::Error> {
+        // the items are shared by each call to `next`, so each item is only sent once
+        let items = Mutex::new(Box::pin(items));
+        self.interceptor
+            .call(request, |request| {
+                let items = stream::poll_fn(|cx| {
+                    items.lock().unwrap_or_else(PoisonError::into_inner).as_mut().poll_next(cx)
+                });
+                self.inner.send_with_stream(request, items)
+            })
+            .await
+    }
+
+    async fn notify(&self, request: Req) -> Result<(), Self::Error> {
+        self.interceptor.notify(request, |request| self.inner.notify(request)).await
+    }
+}
+
+/// The requests of a batch are passed straight to the wrapped client, as they are sent together,
+/// wrap the transport of the client to intercept the batch as a whole
+impl<C, I, Req, Resp> BatchClient<Req, Resp> for Intercepted<C, I>
+where
+    C: BatchClient<Req, Resp>,
+    I: Interceptor<Req, Resp>,
+{
+    async fn send_batch(&self, requests: Vec<Req>) -> Result<Vec<Result<Resp, Self::Error>>, Self::Error> {
+        self.inner.send_batch(requests).await
+    }
+}
+
+impl<C, I, Req, Resp> HandleClient<Req, Resp> for Intercepted<C, I>
+where
+    C: HandleClient<Req, Resp>,
+    I: Interceptor<Req, Resp>,
+{
+    async fn open(&self, request: Req) -> Result<(Resp, impl Send + Sync + 'static), Self::Error> {
+        self.interceptor.open(request, |request| self.inner.open(request)).await
+    }
+}
+
+impl<C, I, Req, Resp> BlockingClient<Req, Resp> for Intercepted<C, I>
+where
+    C: BlockingClient<Req, Resp>,
+    I: Interceptor<Req, Resp>,
+{
+    type Error = C::Error;
+
+    fn send(&self, request: Req) -> Result<Resp, Self::Error> {
+        self.interceptor.call_blocking(request, |request| self.inner.send(request))
+    }
+
+    fn send_stream(&self, request: Req) -> Result<impl Iterator<Item = Result<Resp, Self::Error>>, Self::Error> {
+        self.interceptor
+            .open_blocking(request, |request| self.inner.send_stream(request))
+    }
+
+    fn send_with_stream(&self, request: Req, items: impl Iterator<Item = Req>) -> Result<Resp, Self::Error> {
+        // the items are shared by each call to `next`, so each item is only sent once
+        let items = RefCell::new(items);
+        self.interceptor.call_blocking(request, |request| {
+            self.inner
+                .send_with_stream(request, std::iter::from_fn(|| items.borrow_mut().next()))
+        })
+    }
+
+    fn notify(&self, request: Req) -> Result<(), Self::Error> {
+        self.interceptor
+            .notify_blocking(request, |request| self.inner.notify(request))
+    }
+}
+
+impl<T, I> AsyncTransport for Intercepted<T, I>
+where
+    T: AsyncTransport,
+    I: for<'a> Interceptor<(Vec<u8>, CallInfo<'a>), Result<Vec<u8>, ResponseError>>,
+{
+    type Error = T::Error;
+
+    async fn send(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+        self.interceptor
+            .call((request, call), |(request, call)| self.inner.send(request, call))
+            .await
+    }
+
+    /// Notifications are passed to [`Interceptor::call`] with an empty response, given once the
+    /// wrapped transport has taken the notification
+    async fn notify(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<(), ResponseError>, Self::Error> {
+        let response = self
+            .interceptor
+            .call((request, call), |(request, call)| {
+                self.inner
+                    .notify(request, call)
+                    .map_ok(|response| response.map(|()| Vec::new()))
+            })
+            .await?;
+        Ok(response.map(drop))
+    }
+}
+
+impl<T, I> BlockingTransport for Intercepted<T, I>
+where
+    T: BlockingTransport,
+    I: for<'a> Interceptor<(Vec<u8>, CallInfo<'a>), Result<Vec<u8>, ResponseError>>,
+{
+    type Error = T::Error;
+
+    fn send(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+        self.interceptor
+            .call_blocking((request, call), |(request, call)| self.inner.send(request, call))
+    }
+}
@@ -0,0 +1,268 @@
+//! Defines a client which calls a [`Handler`] in the same process, without any network
+//!
+//! This is useful for testing a server implementation through its generated client, or for
+//! splitting a single program into services which may later be moved to separate processes
+
+use crate::client::{AsyncClient, BatchClient, ResponseError};
+use crate::frame::ErrorKind;
+use crate::format::Format;
+use crate::server::{Context, HandlerError};
+use crate::{Handler, RequestInfo, RpcError, Rpc};
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, ready};
+use futures::{FutureExt, SinkExt, Stream, StreamExt, stream};
+use std::convert::Infallible;
+use std::pin::{Pin, pin};
+use std::sync::Arc;
+use tracing::debug;
+
+/// A client which passes each request straight to a [`Handler`]
+///
+/// By default requests and responses are passed to and from the handler as they are (see
+/// [`Direct`]), use [`with_format`](Self::with_format) to serialise and deserialise each of them
+/// with a [`Format`] instead, this catches any values which cannot be sent over a real transport
+pub struct Loopback<H, C = Direct> {
+    handler: Arc<H>,
+    ctx: Arc<Context>,
+    codec: C,
+    spawner: Option<Spawner>,
+}
+
+/// Runs a task in the background, eg: `|task| { tokio::spawn(task); }`, see
+/// [`Loopback::with_spawner`]
+pub type Spawner = Arc<dyn Fn(Pin<Box<dyn Future<Output = ()> + Send>>) + Send + Sync>;
+
+impl<H, C: Clone> Clone for Loopback<H, C> {
+    fn clone(&self) -> Self {
+        Self {
+            handler: self.handler.clone(),
+            ctx: self.ctx.clone(),
+            codec: self.codec.clone(),
+            spawner: self.spawner.clone(),
+        }
+    }
+}
+
+impl<H> Loopback<H> {
+    /// Create a client which calls the given handler, eg: `Service::server(implementation)`
+    pub fn new(handler: H) -> Self {
+        Self {
+            handler: Arc::new(handler),
+            ctx: Arc::default(),
+            codec: Direct,
+            spawner: None,
+        }
+    }
+}
+
+impl<H, C> Loopback<H, C> {
+    /// Serialise each request and response with the given format, then deserialise it again before
+    /// passing it on
+    #[must_use]
+    pub fn with_format<F>(self, format: F) -> Loopback<H, Formatted<F>> {
+        Loopback {
+            handler: self.handler,
+            ctx: self.ctx,
+            codec: Formatted(format),
+            spawner: self.spawner,
+        }
+    }
+
+    /// Set the context passed to the handler with every request, by default this is empty
+    #[must_use]
+    pub fn with_context(mut self, ctx: Context) -> Self {
+        self.ctx = Arc::new(ctx);
+        self
+    }
+
+    /// Handle notifications in the background with the given spawner, so that
+    /// [`notify`](AsyncClient::notify) returns without waiting for the handler
+    #[must_use]
+    pub fn with_spawner(mut self, spawner: impl Fn(Pin<Box<dyn Future<Output = ()> + Send>>) + Send + Sync + 'static) -> Self {
+        self.spawner = Some(Arc::new(spawner));
+        self
+    }
+}
+
+/// How a [`Loopback`] client passes requests to the handler and responses back to the client
+///
+/// This is implemented for [`Direct`], and for [`Formatted`] with any format which can both read and
+/// write the requests and responses
+pub trait Codec<Req, Resp>: Clone {
+    /// Pass a request from the client to the handler
+    ///
+    /// # Errors
+    /// Returns an error if the request could not be serialised or deserialised
+    fn request(&self, request: Req) -> Result<Req, RpcError<Infallible>>;
+
+    /// Pass a response from the handler to the client
+    ///
+    /// # Errors
+    /// Returns an error if the response could not be serialised or deserialised
+    fn response(&self, response: Resp) -> Result<Resp, RpcError<Infallible>>;
+}
+
+/// Passes requests and responses between a [`Loopback`] client and the handler as they are, without
+/// serialising them
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Direct;
+
+impl<Req, Resp> Codec<Req, Resp> for Direct {
+    fn request(&self, request: Req) -> Result<Req, RpcError<Infallible>> {
+        Ok(request)
+    }
+
+    fn response(&self, response: Resp) -> Result<Resp, RpcError<Infallible>> {
+        Ok(response)
+    }
+}
+
+/// Passes requests and responses between a [`Loopback`] client and the handler by serialising them
+/// with a [`Format`], then deserialising them again, see [`Loopback::with_format`]
+#[derive(Debug, Copy, Clone)]
+pub struct Formatted<F>(pub F);
+
+impl<Req, Resp, F> Codec<Req, Resp> for Formatted<F>
+where
+    F: Format<Req, Resp> + Format<Resp, Req> + Clone,
+{
+    fn request(&self, request: Req) -> Result<Req, RpcError<Infallible>> {
+        let request = <F as Format<Resp, Req>>::write(&self.0, request).map_err(RpcError::Serialize)?;
+        <F as Format<Req, Resp>>::read(&self.0, &request).map_err(|error| {
+            RpcError::Response(ResponseError::BadRequest(format!("Failed to parse request: {error}")))
+        })
+    }
+
+    fn response(&self, response: Resp) -> Result<Resp, RpcError<Infallible>> {
+        let response = <F as Format<Req, Resp>>::write(&self.0, response).map_err(|error| {
+            RpcError::Response(ResponseError::InternalServerError(format!("Failed to write response: {error}")))
+        })?;
+        <F as Format<Resp, Req>>::read(&self.0, &response).map_err(RpcError::Deserialize)
+    }
+}
+
+/// The error received by the client when the handler rejects a request, this matches the error
+/// sent by a real transport
+fn handler_error(error: &HandlerError) -> RpcError<Infallible> {
+    RpcError::Response(ErrorKind::from(error).response_error(error.to_string()))
+}
+
+type RpcRequest<H> = <<H as Handler>::Rpc as Rpc>::Request;
+type RpcResponse<H> = <<H as Handler>::Rpc as Rpc>::Response;
+
+impl<H, C> AsyncClient<RpcRequest<H>, RpcResponse<H>> for Loopback<H, C>
+where
+    H: Handler + Sync + 'static,
+    C: Codec<RpcRequest<H>, RpcResponse<H>>,
+{
+    type Error = RpcError<Infallible>;
+
+    async fn send(&self, request: RpcRequest<H>) -> Result<RpcResponse<H>, Self::Error> {
+        let request = self.codec.request(request)?;
+        let response = self
+            .handler
+            .handle(&self.ctx, request)
+            .await
+            .map_err(|error| handler_error(&error))?;
+        self.codec.response(response)
+    }
+
+    async fn send_stream(
+        &self,
+        request: RpcRequest<H>,
+    ) -> Result<impl Stream<Item = Result<RpcResponse<H>, Self::Error>>, Self::Error> {
+        let request = self.codec.request(request)?;
+        let (sink, responses) = mpsc::channel(16);
+        let (result, error) = oneshot::channel();
+        let handler = self.handler.clone();
+        let ctx = self.ctx.clone();
+        let codec = self.codec.clone();
+        // the handler is driven by the stream of responses, once it has finished any error is
+        // received after the responses it sent
+        let run = Box::pin(async move {
+            let _ = result.send(handler.handle_stream(&ctx, request, stream::empty(), sink).await);
+        });
+        let responses = responses
+            .map(move |response| codec.response(response))
+            .chain(error.into_stream().filter_map(|result| {
+                ready(match result {
+                    Ok(Err(error)) => Some(Err(handler_error(&error))),
+                    _ => None,
+                })
+            }));
+        Ok(stream::select(run.into_stream().filter_map(|()| ready(None)), responses))
+    }
+
+    async fn send_with_stream(
+        &self,
+        request: RpcRequest<H>,
+        items: impl Stream<Item = RpcRequest<H>>,
+    ) -> Result<RpcResponse<H>, Self::Error> {
+        let request = self.codec.request(request)?;
+        let (mut incoming, items_receiver) = mpsc::channel(16);
+        let (sink, mut responses) = mpsc::channel(1);
+        // the items end once this has finished and dropped the sender, including when an item could
+        // not be passed to the handler
+        let forward = async move {
+            let mut items = pin!(items);
+            while let Some(item) = items.next().await {
+                // the handler has stopped reading the items, so the rest are dropped
+                if incoming.send(self.codec.request(item)?).await.is_err() {
+                    break;
+                }
+            }
+            Ok::<(), RpcError<Infallible>>(())
+        };
+        let run = self.handler.handle_stream(&self.ctx, request, items_receiver, sink);
+        let (forwarded, result) = future::join(forward, run).await;
+        forwarded?;
+        result.map_err(|error| handler_error(&error))?;
+        let response = responses
+            .next()
+            .await
+            .ok_or(RpcError::Response(ResponseError::Unexpected))?;
+        self.codec.response(response)
+    }
+
+    /// The handler is spawned with the spawner (see [`Loopback::with_spawner`]) and its response
+    /// and any error are discarded. Without a spawner the handler is run before this returns, as
+    /// there is nothing else to drive it
+    async fn notify(&self, request: RpcRequest<H>) -> Result<(), Self::Error> {
+        let request = self.codec.request(request)?;
+        let handler = self.handler.clone();
+        let ctx = self.ctx.clone();
+        let handle = async move {
+            if let Err(error) = handler.handle(&ctx, request).await {
+                debug!("Failed to handle notification: {error}");
+            }
+        };
+        match &self.spawner {
+            Some(spawner) => spawner(Box::pin(handle)),
+            None => handle.await,
+        }
+        Ok(())
+    }
+}
+
+/// The calls of a batch are passed to the handler one after the other, in order. A call to a
+/// streaming method fails, as it does when batched over a real transport
+impl<H, C> BatchClient<RpcRequest<H>, RpcResponse<H>> for Loopback<H, C>
+where
+    H: Handler + Sync + 'static,
+    C: Codec<RpcRequest<H>, RpcResponse<H>>,
+{
+    async fn send_batch(
+        &self,
+        requests: Vec<RpcRequest<H>>,
+    ) -> Result<Vec<Result<RpcResponse<H>, Self::Error>>, Self::Error> {
+        let calls = stream::iter(requests).then(|request| async move {
+            if request.is_stream() || request.is_client_stream() || request.is_stream_item() {
+                return Err(RpcError::Response(ResponseError::BadRequest(
+                    "Streaming methods cannot be batched".to_string(),
+                )));
+            }
+            self.send(request).await
+        });
+        Ok(calls.collect().await)
+    }
+}
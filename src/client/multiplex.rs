@@ -0,0 +1,780 @@
+//! Defines a client which shares a single connection between many concurrent calls, over any
+//! [`Sink`] and [`Stream`] of byte messages
+//!
+//! Each message is a single [`Frame`], so the connection must keep messages separate, eg: a
+//! websocket, a channel, or a byte stream with length-delimited messages. This is the client used
+//! by the websocket and byte stream transports, it can be used directly to run a service over any
+//! other kind of connection, see [`server::multiplex`](crate::server::multiplex) for the server
+//!
+//! The client does not depend on an async runtime, it is created alongside the future which drives
+//! the connection, this must be spawned (or otherwise polled) for any call to make progress
+
+use crate::client::retry::backoff;
+use crate::batch;
+use crate::client::{AsyncClient, BatchClient, HandleClient, ResponseError, timeout};
+use crate::format::Format;
+use crate::frame::{ErrorFrame, Frame, FrameError, FrameKind};
+use crate::server::multiplex::{self as server, RpcRequest, RpcResponse};
+use crate::{Handler, RequestInfo, RpcError};
+use bon::Builder;
+use futures::channel::{mpsc, oneshot};
+use futures::future;
+use futures::lock::Mutex;
+use futures::stream::{Fuse, FusedStream};
+use futures::{Sink, SinkExt, Stream, StreamExt, select};
+use futures_timer::Delay;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt::Display;
+use std::mem;
+use std::pin::{Pin, pin};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{error, warn};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A client which sends requests over a single connection, many calls can be in progress at once
+///
+/// Dropping the future of a request (or the stream of responses of a streaming method) before it
+/// has finished cancels the request, the server is sent a cancel frame and stops handling it. A
+/// request can also be cancelled explicitly by wrapping it with [`futures::future::abortable`]
+pub struct MultiplexClient<Req, Resp> {
+    sender: RequestSender<Req>,
+    senders: SenderMap<Resp>,
+    cancels: mpsc::UnboundedSender<u64>,
+    events: EventSenders,
+    timeout: Option<Duration>,
+}
+
+/// How a [`MultiplexClient`] reconnects after the connection is lost, see
+/// [`MultiplexClient::reconnecting`]
+#[derive(Debug, Copy, Clone, Builder)]
+pub struct Reconnect {
+    /// The maximum number of attempts to reconnect before giving up, default: no limit
+    max_attempts: Option<u32>,
+    /// The delay before the first attempt, this doubles for each following attempt, default: 100ms
+    #[builder(default = Duration::from_millis(100))]
+    initial_backoff: Duration,
+    /// The maximum delay before an attempt, default: 30s
+    #[builder(default = Duration::from_secs(30))]
+    max_backoff: Duration,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// A handler which a [`MultiplexClient`] serves over its own connection, so that the server can
+/// call back into the client (see [`Peer`](crate::server::multiplex::Peer))
+#[derive(Debug, Builder)]
+pub struct Callbacks<H> {
+    /// The handler of the callbacks
+    handler: H,
+    /// The context each callback is handled with, default: an empty context
+    #[builder(default)]
+    ctx: crate::server::Context,
+    /// The maximum number of callbacks handled at once, see [`server::serve`]. Default: 64
+    #[builder(default = 64)]
+    max_concurrent_requests: usize,
+    /// The maximum number of calls of a batch of callbacks handled at once, with 1 the calls are
+    /// handled in order. Default: 1
+    #[builder(default = 1)]
+    batch_concurrency: usize,
+}
+
+impl<H> Callbacks<H> {
+    /// Serve `handler` with the default options
+    pub fn new(handler: H) -> Self {
+        Self::builder().handler(handler).build()
+    }
+}
+
+/// A change to the state of the connection of a [`MultiplexClient`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// The connection was lost
+    Disconnected,
+    /// Attempting to reconnect, this is the number of the attempt, starting from 1
+    Reconnecting(u32),
+    /// The connection was re-established
+    Reconnected,
+    /// The client has stopped, every request which has not finished fails, as does every later
+    /// request
+    Closed,
+}
+
+/// The receivers of connection events
+type EventSenders = Arc<Mutex<Vec<mpsc::UnboundedSender<ConnectionEvent>>>>;
+
+/// Sends each request frame to the worker
+type RequestSender<Req> = Arc<Mutex<mpsc::Sender<(u64, Outgoing<Req>)>>>;
+
+type SenderMap<Resp> = Arc<Mutex<HashMap<u64, Pending<Resp>>>>;
+
+/// Opens a new connection, used by a client which never reconnects
+type NoReconnect<Si, St> = fn() -> future::Pending<Result<(Si, St), Infallible>>;
+
+/// A frame to be sent to the server
+enum Outgoing<Req> {
+    /// A request for a method, with the timeout of the call
+    Request(Req, Option<Duration>),
+    /// An item of a stream argument
+    Item(Req),
+    /// The end of a request's stream items
+    End,
+    /// A batch of requests, with the timeout of the batch
+    Batch(Vec<Req>, Option<Duration>),
+    /// A request which does not receive a response
+    Notification(Req),
+}
+
+/// A request which is waiting for a response
+enum Pending<Resp> {
+    /// Waiting for a single response
+    Unary(oneshot::Sender<Result<Resp, RpcError<MultiplexError>>>),
+    /// Waiting for any number of responses, until the server ends the stream
+    Stream(mpsc::UnboundedSender<Result<Resp, RpcError<MultiplexError>>>),
+    /// Waiting for the responses to a batch of the given number of requests
+    Batch(usize, oneshot::Sender<BatchResponses<Resp>>),
+}
+
+/// The responses to a batch of requests, or the error of the whole batch
+type BatchResponses<Resp> = Result<Vec<Result<Resp, RpcError<MultiplexError>>>, RpcError<MultiplexError>>;
+
+/// Cancels a request when dropped, this is ignored by the worker if the request has already
+/// finished
+struct CancelOnDrop {
+    request_id: u64,
+    cancels: mpsc::UnboundedSender<u64>,
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        // will only fail if the worker has stopped, in which case there is nothing to cancel
+        let _ = self.cancels.unbounded_send(self.request_id);
+    }
+}
+
+/// The responses of a streaming method, the request is cancelled if this is dropped before the
+/// stream has ended
+struct Responses<Resp> {
+    receiver: mpsc::UnboundedReceiver<Result<Resp, RpcError<MultiplexError>>>,
+    _cancel: CancelOnDrop,
+}
+
+impl<Resp> Stream for Responses<Resp> {
+    type Item = Result<Resp, RpcError<MultiplexError>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+impl<Resp> Pending<Resp> {
+    /// Fail the request with the given error
+    fn fail(self, error: RpcError<MultiplexError>) {
+        // will only fail if the receiver is dropped, this case is not considered an error and can safely be ignored
+        match self {
+            Self::Unary(sender) => {
+                let _ = sender.send(Err(error));
+            }
+            Self::Stream(sender) => {
+                let _ = sender.unbounded_send(Err(error));
+            }
+            Self::Batch(_, sender) => {
+                let _ = sender.send(Err(error));
+            }
+        }
+    }
+}
+
+/// Deliver a frame from the server to the request waiting for it
+async fn receive<Resp>(
+    senders: &SenderMap<Resp>,
+    frame: Frame<'_>,
+    read: impl Fn(&[u8]) -> Result<Resp, Box<dyn Error + Send>>,
+) {
+    let mut senders = senders.lock().await;
+    let request_id = frame.id;
+    match frame.kind {
+        FrameKind::Response => {}
+        FrameKind::Batch => {
+            match senders.remove(&request_id) {
+                Some(Pending::Batch(count, sender)) => {
+                    let _ = sender.send(read_batch(frame.payload, count, read));
+                }
+                Some(pending) => pending.fail(RpcError::Response(ResponseError::Unexpected)),
+                None => warn!("Received batch for unknown request: {request_id}"),
+            }
+            return;
+        }
+        FrameKind::End => {
+            if senders.remove(&request_id).is_none() {
+                warn!("Received end of stream for unknown request: {request_id}");
+            }
+            return;
+        }
+        FrameKind::Error => {
+            // an error ends the request, even if it is a stream
+            let error = match ErrorFrame::decode(request_id, frame.payload) {
+                Ok(error) => RpcError::Response(error.into()),
+                Err(error) => RpcError::Transport(MultiplexError::InvalidFrame(error)),
+            };
+            if let Some(pending) = senders.remove(&request_id) {
+                pending.fail(error);
+            } else {
+                warn!("Received error for unknown request: {request_id}");
+            }
+            return;
+        }
+        kind => {
+            warn!("Received unexpected {kind:?} frame for request: {request_id}");
+            return;
+        }
+    }
+    let response = read(frame.payload).map_err(|error| RpcError::Transport(MultiplexError::DeserialiseResponse(error)));
+    match senders.remove(&request_id) {
+        Some(Pending::Unary(sender)) => {
+            let _: Result<(), _> = sender.send(response);
+        }
+        Some(Pending::Stream(sender)) => {
+            // keep waiting for more responses, unless the stream has been dropped
+            if sender.unbounded_send(response).is_ok() {
+                senders.insert(request_id, Pending::Stream(sender));
+            }
+        }
+        Some(pending @ Pending::Batch(..)) => pending.fail(RpcError::Response(ResponseError::Unexpected)),
+        None => warn!("Received response for unknown request: {request_id}"),
+    }
+}
+
+/// Read the responses to a batch of `count` requests from the payload of a batch frame
+fn read_batch<Resp>(
+    payload: &[u8],
+    count: usize,
+    read: impl Fn(&[u8]) -> Result<Resp, Box<dyn Error + Send>>,
+) -> BatchResponses<Resp> {
+    let responses = batch::responses(payload, count).map_err(|error| RpcError::Transport(MultiplexError::InvalidFrame(error)))?;
+    Ok(responses
+        .into_iter()
+        .map(|response| {
+            read(response?).map_err(|error| RpcError::Transport(MultiplexError::DeserialiseResponse(error)))
+        })
+        .collect())
+}
+
+impl<Req, Resp> Clone for MultiplexClient<Req, Resp> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            senders: self.senders.clone(),
+            cancels: self.cancels.clone(),
+            events: self.events.clone(),
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl<Req: RequestInfo, Resp> MultiplexClient<Req, Resp> {
+    /// Create a client which sends frames to `sink` and receives frames from `stream`, once the
+    /// connection is lost every later call fails, see [`reconnecting`](Self::reconnecting) for a
+    /// client which reconnects instead
+    ///
+    /// Returns the client and the future which drives the connection. This finishes once the
+    /// connection is lost, or once every clone of the client has been dropped and every request
+    /// has finished, the sink is closed before it finishes
+    pub fn new<Si, St>(sink: Si, stream: St, format: impl Format<Resp, Req>) -> (Self, impl Future<Output = ()>)
+    where
+        Si: Sink<Vec<u8>> + Unpin,
+        Si::Error: Display,
+        St: Stream<Item = Vec<u8>> + Unpin,
+    {
+        let connect: NoReconnect<Si, St> = future::pending;
+        Self::start(sink, stream, format, None, connect)
+    }
+
+    /// Create a client which sends frames to `sink` and receives frames from `stream`, while
+    /// serving `callbacks` over the same connection (see [`server::split_callbacks`]). Once the
+    /// connection is lost every later call fails
+    ///
+    /// Returns the client and the future which drives the connection and serves the callbacks,
+    /// this finishes once the connection is lost, or once every clone of the client has been
+    /// dropped and every call and callback has finished
+    pub fn with_callbacks<Si, St, F, H>(sink: Si, stream: St, format: F, callbacks: Callbacks<H>) -> (Self, impl Future<Output = ()>)
+    where
+        Si: Sink<Vec<u8>> + Unpin,
+        Si::Error: Display,
+        St: Stream<Item = Vec<u8>> + Unpin,
+        F: Format<Resp, Req> + Format<RpcRequest<H>, RpcResponse<H>>,
+        H: Handler + Sync,
+    {
+        let ((calls_sink, calls), (callbacks_sink, callbacks_stream), connection) = server::split_callbacks(sink, stream);
+        let format = Arc::new(format);
+        let (client, worker) = Self::new(calls_sink, calls, format.clone());
+        let serve = async move {
+            let Callbacks { handler, ctx, max_concurrent_requests, batch_concurrency } = callbacks;
+            let format: &dyn Format<RpcRequest<H>, RpcResponse<H>> = &*format;
+            let serve = server::serve(callbacks_sink, callbacks_stream, format, &handler, &ctx, max_concurrent_requests, batch_concurrency);
+            if let Err(error) = serve.await {
+                warn!("Stopped serving callbacks after receiving an invalid frame: {error}");
+            }
+        };
+        (client, async move {
+            future::join3(connection, worker, serve).await;
+        })
+    }
+
+    /// Create a client which calls `connect` to open a connection, and again whenever the
+    /// connection is lost
+    ///
+    /// While reconnecting any new requests are queued, they are sent once the connection has been
+    /// re-established. Requests which were waiting for a response when the connection was lost
+    /// fail with [`MultiplexError::ConnectionClosed`], unless they are for an idempotent method
+    /// (see [`RequestInfo::is_idempotent`]) in which case they are sent again. Changes to the
+    /// state of the connection can be observed with
+    /// [`connection_events`](Self::connection_events)
+    ///
+    /// Returns the client and the future which drives the connection, see [`new`](Self::new)
+    ///
+    /// # Errors
+    /// Returns an error if the first connection could not be opened
+    pub async fn reconnecting<Si, St, C, Fut, E>(
+        mut connect: C,
+        format: impl Format<Resp, Req>,
+        reconnect: Reconnect,
+    ) -> Result<(Self, impl Future<Output = ()>), E>
+    where
+        Si: Sink<Vec<u8>> + Unpin,
+        Si::Error: Display,
+        St: Stream<Item = Vec<u8>> + Unpin,
+        C: FnMut() -> Fut,
+        Fut: Future<Output = Result<(Si, St), E>>,
+        E: Display,
+    {
+        let (sink, stream) = connect().await?;
+        Ok(Self::start(sink, stream, format, Some(reconnect), connect))
+    }
+
+    fn start<Si, St, F, C, Fut, E>(
+        sink: Si,
+        stream: St,
+        format: F,
+        reconnect: Option<Reconnect>,
+        connect: C,
+    ) -> (Self, impl Future<Output = ()> + use<Req, Resp, Si, St, F, C, Fut, E>)
+    where
+        Si: Sink<Vec<u8>> + Unpin,
+        Si::Error: Display,
+        St: Stream<Item = Vec<u8>> + Unpin,
+        F: Format<Resp, Req>,
+        C: FnMut() -> Fut,
+        Fut: Future<Output = Result<(Si, St), E>>,
+        E: Display,
+    {
+        let (sender, requests) = mpsc::channel::<(u64, Outgoing<Req>)>(100);
+        let senders: SenderMap<Resp> = Arc::default();
+        let (cancels, cancel_receiver) = mpsc::unbounded::<u64>();
+        let events: EventSenders = Arc::default();
+        let worker = Worker {
+            format,
+            reconnect,
+            connect,
+            requests,
+            cancels: cancel_receiver,
+            senders: senders.clone(),
+            events: events.clone(),
+            in_flight: HashMap::new(),
+        };
+        let client = Self {
+            sender: Arc::new(Mutex::new(sender)),
+            senders,
+            cancels,
+            events,
+            timeout: None,
+        };
+        (client, worker.run(sink, stream))
+    }
+}
+
+/// Owns the connection, it sends each request to the server and delivers each response to the
+/// request waiting for it
+struct Worker<Req, Resp, F, C> {
+    format: F,
+    reconnect: Option<Reconnect>,
+    connect: C,
+    requests: mpsc::Receiver<(u64, Outgoing<Req>)>,
+    cancels: mpsc::UnboundedReceiver<u64>,
+    senders: SenderMap<Resp>,
+    events: EventSenders,
+    /// The requests which have been sent on the current connection and are waiting for a response,
+    /// with the encoded request frame if the request can be sent again after reconnecting
+    in_flight: HashMap<u64, Option<Vec<u8>>>,
+}
+
+impl<Req: RequestInfo, Resp, F: Format<Resp, Req>, C> Worker<Req, Resp, F, C> {
+    async fn run<Si, St, Fut, E>(mut self, mut sink: Si, stream: St)
+    where
+        Si: Sink<Vec<u8>> + Unpin,
+        Si::Error: Display,
+        St: Stream<Item = Vec<u8>> + Unpin,
+        C: FnMut() -> Fut,
+        Fut: Future<Output = Result<(Si, St), E>>,
+        E: Display,
+    {
+        let mut stream = stream.fuse();
+        loop {
+            self.serve(&mut sink, &mut stream).await;
+            // the connection may already be closed, in which case there is nothing to do
+            let _ = sink.close().await;
+            let Some((reconnected_sink, reconnected_stream)) = self.reconnect().await else {
+                break;
+            };
+            sink = reconnected_sink;
+            stream = reconnected_stream.fuse();
+        }
+        self.emit(ConnectionEvent::Closed).await;
+        let senders = mem::take(&mut *self.senders.lock().await);
+        for (_, sender) in senders {
+            sender.fail(RpcError::Transport(MultiplexError::ConnectionClosed));
+        }
+    }
+
+    /// Returns true once every client has been dropped and every request has finished, so no more
+    /// frames need to be sent or received
+    async fn finished(&self) -> bool {
+        self.requests.is_terminated() && self.senders.lock().await.is_empty()
+    }
+
+    /// Send requests and receive responses until the connection is lost, or the client has finished
+    async fn serve<Si, St>(&mut self, sink: &mut Si, stream: &mut Fuse<St>)
+    where
+        Si: Sink<Vec<u8>> + Unpin,
+        Si::Error: Display,
+        St: Stream<Item = Vec<u8>> + Unpin,
+    {
+        while !self.finished().await {
+            select! {
+                req = self.requests.next() => {
+                    let Some((request_id, outgoing)) = req else {
+                        // every client has been dropped, the requests which have already been sent
+                        // are still waiting for their responses
+                        continue;
+                    };
+                    // nothing waits for the response to a notification
+                    let notification = matches!(outgoing, Outgoing::Notification(_));
+                    if !notification && !self.senders.lock().await.contains_key(&request_id) {
+                        // the request has been cancelled or has already finished
+                        continue;
+                    }
+                    let (kind, requests, timeout) = match outgoing {
+                        Outgoing::Request(request, timeout) => (FrameKind::Request, vec![request], timeout),
+                        Outgoing::Item(item) => (FrameKind::StreamItem, vec![item], None),
+                        // the end frame has no payload
+                        Outgoing::End => (FrameKind::End, Vec::new(), None),
+                        Outgoing::Batch(requests, timeout) => (FrameKind::Batch, requests, timeout),
+                        Outgoing::Notification(request) => (FrameKind::Request, vec![request], None),
+                    };
+                    // only requests with a single response can be sent again after reconnecting
+                    let resend = !requests.is_empty() && requests
+                        .iter()
+                        .all(|request| request.is_idempotent() && !request.is_stream() && !request.is_client_stream());
+                    let requests: Result<Vec<_>, _> = requests.into_iter().map(|request| self.format.write(request)).collect();
+                    let request = match (kind, requests) {
+                        (FrameKind::Batch, Ok(requests)) => batch::requests(&requests),
+                        (_, Ok(requests)) => requests.into_iter().next().unwrap_or_default(),
+                        (_, Err(error)) => {
+                            if notification {
+                                error!("Failed to serialise notification: {}", error);
+                            } else if let Some(response) = self.senders.lock().await.remove(&request_id) {
+                                response.fail(RpcError::Transport(MultiplexError::SerialiseRequest(error)));
+                            }
+                            continue;
+                        }
+                    };
+                    let request = Frame::new(kind, request_id, &request).with_timeout(timeout).encode();
+                    if matches!(kind, FrameKind::Request | FrameKind::Batch) && !notification {
+                        self.in_flight.insert(request_id, resend.then(|| request.clone()));
+                    }
+                    if let Err(error) = sink.send(request).await {
+                        warn!("Error sending message: {}", error);
+                        return;
+                    }
+                },
+                request_id = self.cancels.select_next_some() => {
+                    // only requests which are still waiting for a response need to be cancelled
+                    self.in_flight.remove(&request_id);
+                    if self.senders.lock().await.remove(&request_id).is_none() {
+                        continue;
+                    }
+                    let cancel = Frame::new(FrameKind::Cancel, request_id, &[]).encode();
+                    if let Err(error) = sink.send(cancel).await {
+                        warn!("Error sending message: {}", error);
+                        return;
+                    }
+                },
+                response = stream.next() => {
+                    let Some(response) = response else {
+                        warn!("Connection closed");
+                        return;
+                    };
+                    match Frame::decode(&response) {
+                        Ok(frame) => {
+                            receive(&self.senders, frame, |response| self.format.read(response)).await;
+                            if !self.senders.lock().await.contains_key(&frame.id) {
+                                self.in_flight.remove(&frame.id);
+                            }
+                        }
+                        Err(error) => error!("Received an invalid frame: {}", error),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reconnect after the connection was lost, returns the new connection or `None` if the client
+    /// should stop
+    async fn reconnect<Si, St, Fut, E>(&mut self) -> Option<(Si, St)>
+    where
+        Si: Sink<Vec<u8>> + Unpin,
+        Si::Error: Display,
+        C: FnMut() -> Fut,
+        Fut: Future<Output = Result<(Si, St), E>>,
+        E: Display,
+    {
+        let reconnect = self.reconnect?;
+        if self.finished().await {
+            return None;
+        }
+        self.emit(ConnectionEvent::Disconnected).await;
+        {
+            let mut senders = self.senders.lock().await;
+            // requests which cannot be sent again fail, any other request is sent again after
+            // reconnecting
+            self.in_flight.retain(|request_id, request| {
+                if request.is_none()
+                    && let Some(pending) = senders.remove(request_id)
+                {
+                    pending.fail(RpcError::Transport(MultiplexError::ConnectionClosed));
+                }
+                request.is_some() && senders.contains_key(request_id)
+            });
+            // every client has been dropped, so no more requests can be made
+            if self.requests.is_terminated() && senders.is_empty() {
+                return None;
+            }
+        }
+        for attempt in 1.. {
+            if reconnect.max_attempts.is_some_and(|max_attempts| attempt > max_attempts) {
+                return None;
+            }
+            self.emit(ConnectionEvent::Reconnecting(attempt)).await;
+            Delay::new(backoff(reconnect.initial_backoff, reconnect.max_backoff, attempt - 1)).await;
+            match (self.connect)().await {
+                Ok((mut sink, stream)) => {
+                    self.emit(ConnectionEvent::Reconnected).await;
+                    for request in self.in_flight.values().flatten() {
+                        if let Err(error) = sink.send(request.clone()).await {
+                            warn!("Error sending message: {}", error);
+                        }
+                    }
+                    return Some((sink, stream));
+                }
+                Err(error) => warn!("Failed to reconnect: {}", error),
+            }
+        }
+        None
+    }
+
+    /// Send an event to every receiver of connection events
+    async fn emit(&self, event: ConnectionEvent) {
+        self.events
+            .lock()
+            .await
+            .retain(|events| events.unbounded_send(event).is_ok());
+    }
+}
+
+impl<Req, Resp> MultiplexClient<Req, Resp> {
+    /// Receive an event each time the state of the connection changes, see [`ConnectionEvent`]
+    pub async fn connection_events(&self) -> mpsc::UnboundedReceiver<ConnectionEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.events.lock().await.push(sender);
+        receiver
+    }
+
+    /// Set the timeout of each call made with this client, a call fails with
+    /// [`RpcError::Timeout`] if no response is received in time and the request is cancelled. The
+    /// timeout is also sent to the server. Clones of a client share the same connection, so this
+    /// can be used to give a single call a different timeout
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Allocate an id for a new request, the request is cancelled when the returned guard is
+    /// dropped unless it has already finished
+    fn start_request(&self) -> (u64, CancelOnDrop) {
+        let request_id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let cancel = CancelOnDrop {
+            request_id,
+            cancels: self.cancels.clone(),
+        };
+        (request_id, cancel)
+    }
+}
+
+impl<Req, Resp> AsyncClient<Req, Resp> for MultiplexClient<Req, Resp> {
+    type Error = RpcError<MultiplexError>;
+
+    async fn send(&self, request: Req) -> Result<Resp, Self::Error> {
+        let (sender, receiver) = oneshot::channel();
+        let (request_id, _cancel) = self.start_request();
+        self.senders
+            .lock()
+            .await
+            .insert(request_id, Pending::Unary(sender));
+        let call = async {
+            self.sender
+                .lock()
+                .await
+                .send((request_id, Outgoing::Request(request, self.timeout)))
+                .await
+                .map_err(|_| RpcError::Transport(MultiplexError::RequestChannelClosed))?;
+            receiver
+                .await
+                .map_err(|_| RpcError::Transport(MultiplexError::ResponseChannelClosed))?
+        };
+        timeout(self.timeout, call).await.map_err(RpcError::Timeout)?
+    }
+
+    /// The timeout of the client does not apply to streaming methods, the stream of responses can
+    /// be dropped to cancel the request instead
+    async fn send_stream(&self, request: Req) -> Result<impl Stream<Item = Result<Resp, Self::Error>>, Self::Error> {
+        let (sender, receiver) = mpsc::unbounded();
+        let (request_id, cancel) = self.start_request();
+        self.senders.lock().await.insert(request_id, Pending::Stream(sender));
+        self.sender
+            .lock()
+            .await
+            .send((request_id, Outgoing::Request(request, None)))
+            .await
+            .map_err(|_| RpcError::Transport(MultiplexError::RequestChannelClosed))?;
+        Ok(Responses {
+            receiver,
+            _cancel: cancel,
+        })
+    }
+
+    async fn send_with_stream(&self, request: Req, items: impl Stream<Item = Req>) -> Result<Resp, Self::Error> {
+        let (sender, receiver) = oneshot::channel();
+        let (request_id, _cancel) = self.start_request();
+        self.senders.lock().await.insert(request_id, Pending::Unary(sender));
+        // use a separate sender so that other requests are not blocked while the items are sent
+        let mut requests = self.sender.lock().await.clone();
+        let items = items.map(|item| Ok((request_id, Outgoing::Item(item))));
+        let call = async {
+            async {
+                requests.send((request_id, Outgoing::Request(request, self.timeout))).await?;
+                requests.send_all(&mut pin!(items)).await?;
+                requests.send((request_id, Outgoing::End)).await
+            }
+            .await
+            .map_err(|_| RpcError::Transport(MultiplexError::RequestChannelClosed))?;
+            receiver
+                .await
+                .map_err(|_| RpcError::Transport(MultiplexError::ResponseChannelClosed))?
+        };
+        timeout(self.timeout, call).await.map_err(RpcError::Timeout)?
+    }
+
+    /// This returns once the request has been queued to be sent on the connection, a notification
+    /// is not sent again after reconnecting
+    async fn notify(&self, request: Req) -> Result<(), Self::Error> {
+        let request_id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        self.sender
+            .lock()
+            .await
+            .send((request_id, Outgoing::Notification(request)))
+            .await
+            .map_err(|_| RpcError::Transport(MultiplexError::RequestChannelClosed))
+    }
+}
+
+/// The requests of a batch are sent in a single batch frame, the timeout applies to the whole batch
+impl<Req, Resp> BatchClient<Req, Resp> for MultiplexClient<Req, Resp> {
+    async fn send_batch(&self, requests: Vec<Req>) -> Result<Vec<Result<Resp, Self::Error>>, Self::Error> {
+        let (sender, receiver) = oneshot::channel();
+        let (request_id, _cancel) = self.start_request();
+        self.senders
+            .lock()
+            .await
+            .insert(request_id, Pending::Batch(requests.len(), sender));
+        let call = async {
+            self.sender
+                .lock()
+                .await
+                .send((request_id, Outgoing::Batch(requests, self.timeout)))
+                .await
+                .map_err(|_| RpcError::Transport(MultiplexError::RequestChannelClosed))?;
+            receiver
+                .await
+                .map_err(|_| RpcError::Transport(MultiplexError::ResponseChannelClosed))?
+        };
+        timeout(self.timeout, call).await.map_err(RpcError::Timeout)?
+    }
+}
+
+/// The request stays open until the handle is dropped, which sends a cancel frame to the server
+impl<Req, Resp> HandleClient<Req, Resp> for MultiplexClient<Req, Resp> {
+    async fn open(&self, request: Req) -> Result<(Resp, impl Send + Sync + 'static), Self::Error> {
+        let (sender, mut receiver) = mpsc::unbounded();
+        let (request_id, cancel) = self.start_request();
+        self.senders.lock().await.insert(request_id, Pending::Stream(sender));
+        let call = async {
+            self.sender
+                .lock()
+                .await
+                .send((request_id, Outgoing::Request(request, None)))
+                .await
+                .map_err(|_| RpcError::Transport(MultiplexError::RequestChannelClosed))?;
+            receiver
+                .next()
+                .await
+                .ok_or(RpcError::Transport(MultiplexError::ResponseChannelClosed))?
+        };
+        let response = timeout(self.timeout, call).await.map_err(RpcError::Timeout)??;
+        // later responses are dropped, the server does not send any until the request ends
+        Ok((response, cancel))
+    }
+}
+
+/// An error from a call made with a [`MultiplexClient`]
+#[derive(Debug, Error)]
+pub enum MultiplexError {
+    /// The worker has closed the request channel, this is not expected
+    #[error("Failed to send request to worker: channel closed")]
+    RequestChannelClosed,
+    /// The worker has closed the response channel, this is not expected
+    #[error("Failed to read response from worker: channel closed")]
+    ResponseChannelClosed,
+    /// The request could not be serialised
+    #[error("Failed to write request: {0}")]
+    SerialiseRequest(Box<dyn Error + Send>),
+    /// The response could not be deserialised
+    #[error("Failed to read response: {0}")]
+    DeserialiseResponse(Box<dyn Error + Send>),
+    /// The connection has closed
+    #[error("Connection closed")]
+    ConnectionClosed,
+    /// The server sent a frame which could not be decoded
+    #[error("Received an invalid frame: {0}")]
+    InvalidFrame(#[from] FrameError),
+}
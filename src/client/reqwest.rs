@@ -3,7 +3,10 @@
 use bon::bon;
 use crate::AsyncTransport;
 pub use reqwest::Error;
-use crate::client::ResponseError;
+use reqwest::StatusCode;
+#[cfg(not(target_arch = "wasm32"))]
+use tracing::debug;
+use crate::client::{BATCH_HEADER, CallInfo, ResponseError, TIMEOUT_HEADER};
 
 /// An [`AsyncTransport`] which uses the [reqwest] crate
 #[derive(Debug, Clone)]
@@ -34,19 +37,37 @@ impl Reqwest {
     }
 }
 
-impl AsyncTransport for Reqwest {
-    type Error = Error;
-
-    async fn send(&self, request: Vec<u8>, content_type: &str) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
-        let response = self
+impl Reqwest {
+    fn request(&self, request: Vec<u8>, call: &CallInfo<'_>) -> reqwest::RequestBuilder {
+        let mut request = self
             .client
             .request(self.method.clone(), &self.url)
             .body(request)
-            .header(reqwest::header::CONTENT_TYPE, content_type)
-            .send()
-            .await?;
+            .header(reqwest::header::CONTENT_TYPE, call.content_type);
+        if let Some(timeout) = call.timeout {
+            request = request.header(TIMEOUT_HEADER, timeout.as_millis().to_string());
+        }
+        if call.batch {
+            request = request.header(BATCH_HEADER, "true");
+        }
+        for (name, value) in &call.headers {
+            request = request.header(name, value);
+        }
+        request
+    }
+}
+
+impl AsyncTransport for Reqwest {
+    type Error = Error;
+
+    async fn send(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+        let response = self.request(request, &call).send().await?;
         if response.status().is_success() {
-            Ok(Ok(response.json().await?))
+            Ok(Ok(response.bytes().await?.to_vec()))
+        } else if response.status() == StatusCode::UNAUTHORIZED {
+            Ok(Err(ResponseError::Unauthenticated(response.text().await?)))
+        } else if response.status() == StatusCode::FORBIDDEN {
+            Ok(Err(ResponseError::PermissionDenied(response.text().await?)))
         } else if response.status().is_client_error() {
             Ok(Err(ResponseError::BadRequest(response.text().await?)))
         } else if response.status().is_server_error() {
@@ -55,4 +76,22 @@ impl AsyncTransport for Reqwest {
             Ok(Err(ResponseError::Unexpected))
         }
     }
-}
\ No newline at end of file
+
+    /// The notification is sent in the background on the current tokio runtime, a failure to send
+    /// it or an error status from the server is only logged
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn notify(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<(), ResponseError>, Self::Error> {
+        let request = self.request(request, &call).build()?;
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            match client.execute(request).await {
+                Ok(response) if !response.status().is_success() => {
+                    debug!("Notification was rejected: {}", response.status());
+                }
+                Ok(_) => {}
+                Err(error) => debug!("Failed to send notification: {error}"),
+            }
+        });
+        Ok(Ok(()))
+    }
+}
@@ -2,8 +2,8 @@ use bon::bon;
 use crate::BlockingTransport;
 pub use reqwest::Error;
 use reqwest::blocking::Client;
-use reqwest::{Method};
-use crate::client::ResponseError;
+use reqwest::{Method, StatusCode};
+use crate::client::{CallInfo, ResponseError, TIMEOUT_HEADER};
 
 /// A [`AsyncTransport`] which uses the [reqwest] crate
 #[derive(Debug, Clone)]
@@ -36,15 +36,27 @@ impl ReqwestBlocking {
 impl BlockingTransport for ReqwestBlocking {
     type Error = Error;
 
-    fn send(&self, request: Vec<u8>, content_type: &str) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
-        let response = self
+    fn send(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+        let mut request = self
             .client
             .request(self.method.clone(), &self.url)
             .body(request)
-            .header(reqwest::header::CONTENT_TYPE, content_type)
-            .send()?;
+            .header(reqwest::header::CONTENT_TYPE, call.content_type);
+        if let Some(timeout) = call.timeout {
+            request = request
+                .timeout(timeout)
+                .header(TIMEOUT_HEADER, timeout.as_millis().to_string());
+        }
+        for (name, value) in call.headers {
+            request = request.header(name, value);
+        }
+        let response = request.send()?;
         if response.status().is_success() {
-            Ok(Ok(response.json()?))
+            Ok(Ok(response.bytes()?.to_vec()))
+        } else if response.status() == StatusCode::UNAUTHORIZED {
+            Ok(Err(ResponseError::Unauthenticated(response.text()?)))
+        } else if response.status() == StatusCode::FORBIDDEN {
+            Ok(Err(ResponseError::PermissionDenied(response.text()?)))
         } else if response.status().is_client_error() {
             Ok(Err(ResponseError::BadRequest(response.text()?)))
         } else if response.status().is_server_error() {
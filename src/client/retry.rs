@@ -0,0 +1,288 @@
+//! Retrying failed calls to idempotent methods
+//!
+//! [`Retry`] wraps a client or a transport, a call is retried if it fails at the transport layer
+//! or the server responds with [`ResponseError::InternalServerError`], but only if the method is
+//! marked with `#[rpc(idempotent)]` (see [`RequestInfo::is_idempotent`]), any other call is only
+//! sent once. Wrapping a client (eg: a [`StreamClient`](super::stream::StreamClient)) retries the
+//! calls made with it, this is the only way to retry calls with a client which has no separate
+//! transport. Only calls with a single response are retried, streaming methods, methods with a
+//! stream argument and notifications are only sent once
+//!
+//! The delay before each retry grows exponentially, with a random jitter so that many clients
+//! which failed at the same time do not all retry at the same time. When wrapping a transport, the
+//! timeout of the call covers every attempt, each attempt is sent with the time which is left.
+//! When wrapping a client, the timeout of the wrapped client applies to each attempt
+
+use crate::client::{
+    AsyncClient, AsyncTransport, BatchClient, BlockingClient, BlockingTransport, CallInfo, HandleClient, ResponseError, RpcError,
+};
+use crate::RequestInfo;
+use bon::Builder;
+use futures::Stream;
+use futures_timer::Delay;
+use std::error::Error;
+use std::hash::{BuildHasher, RandomState};
+use std::thread;
+use std::time::Duration;
+use tower_layer::Layer;
+
+/// A [`Layer`] which retries failed calls to idempotent methods, see the
+/// [module documentation](self)
+#[derive(Debug, Copy, Clone, Builder)]
+pub struct RetryLayer {
+    /// The maximum number of times a single call is retried, default: 3
+    #[builder(default = 3)]
+    max_retries: u32,
+    /// The delay before the first retry, this doubles for each following retry, default: 100ms
+    #[builder(default = Duration::from_millis(100))]
+    initial_backoff: Duration,
+    /// The maximum delay before a retry, default: 10s
+    #[builder(default = Duration::from_secs(10))]
+    max_backoff: Duration,
+}
+
+impl Default for RetryLayer {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl RetryLayer {
+    /// Returns true if the call should be retried after the given response
+    const fn should_retry<E>(&self, retries: u32, response: &Result<Result<Vec<u8>, ResponseError>, E>) -> bool {
+        retries < self.max_retries && matches!(response, Err(_) | Ok(Err(ResponseError::InternalServerError(_))))
+    }
+
+    /// Returns true if the call made with a client should be retried after the given response
+    const fn should_retry_call<T, E>(&self, retries: u32, response: &Result<T, RpcError<E>>) -> bool {
+        retries < self.max_retries
+            && matches!(
+                response,
+                Err(RpcError::Transport(_) | RpcError::Response(ResponseError::InternalServerError(_)))
+            )
+    }
+
+    /// The delay before a retry, given the number of previous retries
+    fn backoff(&self, retries: u32) -> Duration {
+        backoff(self.initial_backoff, self.max_backoff, retries)
+    }
+}
+
+/// An exponential backoff with jitter, given the number of previous attempts
+pub(crate) fn backoff(initial: Duration, max: Duration, attempts: u32) -> Duration {
+    let backoff = initial.saturating_mul(2u32.saturating_pow(attempts)).min(max);
+    // wait for at least half of the backoff, the rest is random
+    let jitter = u32::try_from(RandomState::new().hash_one(attempts) % 1001).unwrap_or_default();
+    backoff / 2 + backoff / 2 * jitter / 1000
+}
+
+impl<T> Layer<T> for RetryLayer {
+    type Service = Retry<T>;
+
+    fn layer(&self, inner: T) -> Self::Service {
+        Retry::new(inner, *self)
+    }
+}
+
+/// A client or transport wrapped with a [`RetryLayer`]
+#[derive(Debug, Clone)]
+pub struct Retry<T> {
+    inner: T,
+    layer: RetryLayer,
+}
+
+impl<T> Retry<T> {
+    /// Wrap `inner` with the given retry layer
+    pub const fn new(inner: T, layer: RetryLayer) -> Self {
+        Self { inner, layer }
+    }
+
+    /// The wrapped client or transport
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: AsyncTransport> AsyncTransport for Retry<T> {
+    type Error = T::Error;
+
+    async fn send(&self, request: Vec<u8>, mut call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+        if !call.idempotent {
+            return self.inner.send(request, call).await;
+        }
+        let deadline = call.timeout.map(|timeout| clock::now() + timeout);
+        let mut retries = 0;
+        loop {
+            let response = self.inner.send(request.clone(), call.clone()).await;
+            if !self.layer.should_retry(retries, &response) {
+                return response;
+            }
+            // each attempt is only given the time left of the timeout of the call
+            let backoff = self.layer.backoff(retries);
+            call.timeout = match remaining(deadline, backoff) {
+                Ok(timeout) => timeout,
+                Err(()) => return response,
+            };
+            Delay::new(backoff).await;
+            retries += 1;
+        }
+    }
+
+    /// Notifications are not retried, as they are not waited for
+    async fn notify(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<(), ResponseError>, Self::Error> {
+        self.inner.notify(request, call).await
+    }
+}
+
+impl<T: BlockingTransport> BlockingTransport for Retry<T> {
+    type Error = T::Error;
+
+    fn send(&self, request: Vec<u8>, mut call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+        if !call.idempotent {
+            return self.inner.send(request, call);
+        }
+        let deadline = call.timeout.map(|timeout| clock::now() + timeout);
+        let mut retries = 0;
+        loop {
+            let response = self.inner.send(request.clone(), call.clone());
+            if !self.layer.should_retry(retries, &response) {
+                return response;
+            }
+            let backoff = self.layer.backoff(retries);
+            call.timeout = match remaining(deadline, backoff) {
+                Ok(timeout) => timeout,
+                Err(()) => return response,
+            };
+            thread::sleep(backoff);
+            retries += 1;
+        }
+    }
+}
+
+/// The time which will be left before the deadline once the backoff has elapsed, this fails if
+/// there would be no time left for another attempt
+fn remaining(deadline: Option<Duration>, backoff: Duration) -> Result<Option<Duration>, ()> {
+    let Some(deadline) = deadline else {
+        return Ok(None);
+    };
+    match deadline.checked_sub(clock::now() + backoff) {
+        Some(remaining) if !remaining.is_zero() => Ok(Some(remaining)),
+        _ => Err(()),
+    }
+}
+
+/// A monotonic clock, `Instant` is not available in the browser
+mod clock {
+    use std::time::Duration;
+
+    /// The time since an arbitrary point, which is the same for every call
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn now() -> Duration {
+        use std::sync::OnceLock;
+        use std::time::Instant;
+
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed()
+    }
+
+    /// The time since an arbitrary point, which is the same for every call
+    #[cfg(target_arch = "wasm32")]
+    pub fn now() -> Duration {
+        Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+    }
+}
+
+impl<C, Req, Resp, E> AsyncClient<Req, Resp> for Retry<C>
+where
+    C: AsyncClient<Req, Resp, Error = RpcError<E>>,
+    Req: RequestInfo + Clone,
+    E: Error + 'static,
+{
+    type Error = C::Error;
+
+    async fn send(&self, request: Req) -> Result<Resp, Self::Error> {
+        if !request.is_idempotent() {
+            return self.inner.send(request).await;
+        }
+        let mut retries = 0;
+        loop {
+            let response = self.inner.send(request.clone()).await;
+            if !self.layer.should_retry_call(retries, &response) {
+                return response;
+            }
+            Delay::new(self.layer.backoff(retries)).await;
+            retries += 1;
+        }
+    }
+
+    async fn send_stream(&self, request: Req) -> Result<impl Stream<Item = Result<Resp, Self::Error>>, Self::Error> {
+        self.inner.send_stream(request).await
+    }
+
+    async fn send_with_stream(&self, request: Req, items: impl Stream<Item = Req>) -> Result<Resp, Self::Error> {
+        self.inner.send_with_stream(request, items).await
+    }
+
+    async fn notify(&self, request: Req) -> Result<(), Self::Error> {
+        self.inner.notify(request).await
+    }
+}
+
+/// A batch is sent once, as it may contain calls which are not idempotent
+impl<C, Req, Resp, E> BatchClient<Req, Resp> for Retry<C>
+where
+    C: BatchClient<Req, Resp, Error = RpcError<E>>,
+    Req: RequestInfo + Clone,
+    E: Error + 'static,
+{
+    async fn send_batch(&self, requests: Vec<Req>) -> Result<Vec<Result<Resp, Self::Error>>, Self::Error> {
+        self.inner.send_batch(requests).await
+    }
+}
+
+impl<C, Req, Resp, E> HandleClient<Req, Resp> for Retry<C>
+where
+    C: HandleClient<Req, Resp, Error = RpcError<E>>,
+    Req: RequestInfo + Clone,
+    E: Error + 'static,
+{
+    async fn open(&self, request: Req) -> Result<(Resp, impl Send + Sync + 'static), Self::Error> {
+        self.inner.open(request).await
+    }
+}
+
+impl<C, Req, Resp, E> BlockingClient<Req, Resp> for Retry<C>
+where
+    C: BlockingClient<Req, Resp, Error = RpcError<E>>,
+    Req: RequestInfo + Clone,
+    E: Error + 'static,
+{
+    type Error = C::Error;
+
+    fn send(&self, request: Req) -> Result<Resp, Self::Error> {
+        if !request.is_idempotent() {
+            return self.inner.send(request);
+        }
+        let mut retries = 0;
+        loop {
+            let response = self.inner.send(request.clone());
+            if !self.layer.should_retry_call(retries, &response) {
+                return response;
+            }
+            thread::sleep(self.layer.backoff(retries));
+            retries += 1;
+        }
+    }
+
+    fn send_stream(&self, request: Req) -> Result<impl Iterator<Item = Result<Resp, Self::Error>>, Self::Error> {
+        self.inner.send_stream(request)
+    }
+
+    fn send_with_stream(&self, request: Req, items: impl Iterator<Item = Req>) -> Result<Resp, Self::Error> {
+        self.inner.send_with_stream(request, items)
+    }
+
+    fn notify(&self, request: Req) -> Result<(), Self::Error> {
+        self.inner.notify(request)
+    }
+}
@@ -0,0 +1,182 @@
+//! Defines a client which communicates over any byte stream, eg: a TCP connection, a Unix socket or
+//! the stdin and stdout of a child process, see [`server::stream`](crate::server::stream) for the
+//! protocol
+
+use crate::client::multiplex::{MultiplexClient, MultiplexError};
+use crate::client::{AsyncClient, BatchClient, HandleClient};
+use crate::format::Format;
+use crate::{RequestInfo, RpcError};
+use futures::future::ready;
+use futures::{SinkExt, Stream, StreamExt};
+use std::io;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(feature = "tcp")]
+use tokio::net::{TcpStream, ToSocketAddrs};
+#[cfg(all(feature = "unix", unix))]
+use {std::path::Path, tokio::net::UnixStream};
+#[cfg(feature = "stdio")]
+use {std::process::{Command, Stdio}, tracing::debug};
+use tokio_util::bytes::Bytes;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use tracing::warn;
+
+/// A client which communicates over a single byte stream, many calls can be in progress at once on
+/// the same stream, see [`MultiplexClient`]
+pub struct StreamClient<Req, Resp> {
+    inner: MultiplexClient<Req, Resp>,
+}
+
+impl<Req, Resp> Clone for StreamClient<Req, Resp> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<Req: RequestInfo + Send + 'static, Resp: Send + 'static> StreamClient<Req, Resp> {
+    /// Connect to a server over TCP at the given address
+    ///
+    /// # Errors
+    /// Returns an error if the connection could not be opened, or if the server does not support
+    /// the format
+    #[cfg(feature = "tcp")]
+    pub async fn connect_tcp(addr: impl ToSocketAddrs, format: impl Format<Resp, Req> + 'static) -> Result<Self, StreamError> {
+        let stream = TcpStream::connect(addr).await?;
+        stream.set_nodelay(true)?;
+        Self::new(stream, format).await
+    }
+
+    /// Connect to a server listening on the Unix socket at the given path
+    ///
+    /// # Errors
+    /// Returns an error if the connection could not be opened, or if the server does not support
+    /// the format
+    #[cfg(all(feature = "unix", unix))]
+    pub async fn connect_unix(path: impl AsRef<Path>, format: impl Format<Resp, Req> + 'static) -> Result<Self, StreamError> {
+        let stream = UnixStream::connect(path).await?;
+        Self::new(stream, format).await
+    }
+
+    /// Spawn the command as a child process and communicate with it over its stdin and stdout, the
+    /// child should serve the service with
+    /// [`StreamServer::serve_stdio`](crate::server::stream::StreamServer::serve_stdio)
+    ///
+    /// The stdout of the child is only used for frames, its stderr is passed through to the stderr
+    /// of this process so that the child can write its logs there. Once every clone of the client
+    /// has been dropped the stdin of the child is closed, which stops the server
+    ///
+    /// # Errors
+    /// Returns an error if the child process could not be spawned, or if it does not support the
+    /// format
+    #[cfg(feature = "stdio")]
+    pub async fn spawn(command: Command, format: impl Format<Resp, Req> + 'static) -> Result<Self, StreamError> {
+        let mut child = tokio::process::Command::from(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(io::Error::other("the stdin and stdout of the child process are not piped").into());
+        };
+        tokio::spawn(async move {
+            match child.wait().await {
+                Ok(status) => debug!("Child process exited: {status}"),
+                Err(error) => warn!("Failed to wait for child process: {error}"),
+            }
+        });
+        Self::new(tokio::io::join(stdout, stdin), format).await
+    }
+
+    /// Create a client which communicates over the given stream, the server must be serving the
+    /// other end of the stream
+    ///
+    /// # Errors
+    /// Returns an error if the stream fails or closes while choosing the format, or if the server
+    /// does not support the format
+    pub async fn new<S>(stream: S, format: impl Format<Resp, Req> + 'static) -> Result<Self, StreamError>
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let mut stream = Framed::new(stream, LengthDelimitedCodec::new());
+        stream.send(Bytes::from_static(format.content_type().as_bytes())).await?;
+        match stream.next().await.transpose()? {
+            Some(content_type) if content_type == format.content_type().as_bytes() => {}
+            Some(_) => return Err(StreamError::UnsupportedFormat(format.content_type())),
+            None => return Err(StreamError::ConnectionClosed),
+        }
+        let (sender, receiver) = stream.split();
+        let sender = sender.with(|frame: Vec<u8>| ready(Ok::<_, io::Error>(Bytes::from(frame))));
+        let receiver = receiver
+            .take_while(|message| {
+                ready(
+                    message
+                        .as_ref()
+                        .map_err(|error| warn!("Error from connection: {error}"))
+                        .is_ok(),
+                )
+            })
+            .filter_map(|message| ready(message.ok().map(Vec::from)));
+        let (inner, worker) = MultiplexClient::new(sender, receiver, format);
+        tokio::spawn(worker);
+        Ok(Self { inner })
+    }
+}
+
+impl<Req, Resp> StreamClient<Req, Resp> {
+    /// Set the timeout of each call made with this client, see [`MultiplexClient::with_timeout`]
+    #[must_use]
+    pub fn with_timeout(self, timeout: Option<Duration>) -> Self {
+        Self {
+            inner: self.inner.with_timeout(timeout),
+        }
+    }
+}
+
+impl<Req, Resp> AsyncClient<Req, Resp> for StreamClient<Req, Resp> {
+    type Error = RpcError<MultiplexError>;
+
+    async fn send(&self, request: Req) -> Result<Resp, Self::Error> {
+        self.inner.send(request).await
+    }
+
+    async fn send_stream(&self, request: Req) -> Result<impl Stream<Item = Result<Resp, Self::Error>>, Self::Error> {
+        self.inner.send_stream(request).await
+    }
+
+    async fn send_with_stream(&self, request: Req, items: impl Stream<Item = Req>) -> Result<Resp, Self::Error> {
+        self.inner.send_with_stream(request, items).await
+    }
+
+    async fn notify(&self, request: Req) -> Result<(), Self::Error> {
+        self.inner.notify(request).await
+    }
+}
+
+impl<Req, Resp> BatchClient<Req, Resp> for StreamClient<Req, Resp> {
+    async fn send_batch(&self, requests: Vec<Req>) -> Result<Vec<Result<Resp, Self::Error>>, Self::Error> {
+        self.inner.send_batch(requests).await
+    }
+}
+
+impl<Req, Resp> HandleClient<Req, Resp> for StreamClient<Req, Resp> {
+    async fn open(&self, request: Req) -> Result<(Resp, impl Send + Sync + 'static), Self::Error> {
+        self.inner.open(request).await
+    }
+}
+
+/// An error from connecting a stream client, errors from calls are [`MultiplexError`]s
+#[derive(Debug, Error)]
+pub enum StreamError {
+    /// The connection could not be opened, or failed while choosing the format
+    #[error("Failed to connect: {0}")]
+    Connect(#[from] io::Error),
+    /// The server does not support the format of the client
+    #[error("The server does not support the {0} format")]
+    UnsupportedFormat(&'static str),
+    /// The connection closed while choosing the format
+    #[error("Connection closed")]
+    ConnectionClosed,
+}
@@ -0,0 +1,128 @@
+//! Defines a blocking transport which sends requests over a Unix socket, using the same protocol as
+//! [`server::stream`](crate::server::stream)
+
+use crate::client::{BlockingTransport, CallInfo, ResponseError};
+use crate::frame::{ErrorFrame, Frame, FrameError, FrameKind};
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use thiserror::Error;
+
+/// A [`BlockingTransport`] which sends requests over a Unix socket, one request at a time
+///
+/// The socket is connected on the first call, if a call fails the connection is closed and a new
+/// one is opened by the next call. Clones of the transport share the same connection
+#[derive(Debug, Clone)]
+pub struct UnixBlocking {
+    path: PathBuf,
+    connection: Arc<Mutex<Option<Connection>>>,
+}
+
+/// An open connection, after the format has been chosen
+#[derive(Debug)]
+struct Connection {
+    stream: UnixStream,
+    next_id: u64,
+}
+
+impl UnixBlocking {
+    /// Create a transport which connects to the Unix socket at the given path
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            connection: Arc::default(),
+        }
+    }
+}
+
+impl BlockingTransport for UnixBlocking {
+    type Error = UnixBlockingError;
+
+    fn send(&self, request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+        let mut connection = self.connection.lock().unwrap_or_else(PoisonError::into_inner);
+        let open = match connection.take() {
+            Some(open) => open,
+            None => Connection::open(&self.path, call.content_type)?,
+        };
+        let result = connection.insert(open).call(&request, &call);
+        if result.is_err() {
+            // the response may still arrive after a timeout, so the connection cannot be reused
+            *connection = None;
+        }
+        result
+    }
+}
+
+impl Connection {
+    /// Connect to the socket and choose the format with the server
+    fn open(path: &Path, content_type: &str) -> Result<Self, UnixBlockingError> {
+        let mut stream = UnixStream::connect(path)?;
+        write_message(&mut stream, content_type.as_bytes())?;
+        if read_message(&mut stream)? != content_type.as_bytes() {
+            return Err(UnixBlockingError::UnsupportedFormat(content_type.to_string()));
+        }
+        Ok(Self { stream, next_id: 0 })
+    }
+
+    /// Send a request and wait for its response
+    fn call(&mut self, request: &[u8], call: &CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, UnixBlockingError> {
+        let request_id = self.next_id;
+        self.next_id += 1;
+        self.stream.set_read_timeout(call.timeout)?;
+        let frame = Frame::new(FrameKind::Request, request_id, request)
+            .with_timeout(call.timeout)
+            .encode();
+        write_message(&mut self.stream, &frame)?;
+        if call.notification {
+            // the server does not respond to a notification
+            return Ok(Ok(Vec::new()));
+        }
+        loop {
+            let message = read_message(&mut self.stream)?;
+            let frame = Frame::decode(&message)?;
+            if frame.id != request_id {
+                // a late response to an earlier request, which has already failed
+                continue;
+            }
+            return match frame.kind {
+                FrameKind::Response => Ok(Ok(frame.payload.to_vec())),
+                FrameKind::Error => Ok(Err(ErrorFrame::decode(request_id, frame.payload)?.into())),
+                kind => Err(UnixBlockingError::UnexpectedFrame(kind)),
+            };
+        }
+    }
+}
+
+/// Write a message, prefixed with its length as a big endian `u32`
+fn write_message(stream: &mut UnixStream, message: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(message.len()).map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    stream.write_all(&len.to_be_bytes())?;
+    stream.write_all(message)
+}
+
+/// Read a message, prefixed with its length as a big endian `u32`
+fn read_message(stream: &mut UnixStream) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let mut message = vec![0; u32::from_be_bytes(len) as usize];
+    stream.read_exact(&mut message)?;
+    Ok(message)
+}
+
+/// An error from the blocking Unix socket transport
+#[derive(Debug, Error)]
+pub enum UnixBlockingError {
+    /// Reading from or writing to the socket failed, this includes the timeout of the call elapsing
+    #[error("Unix socket error: {0}")]
+    Io(#[from] io::Error),
+    /// The server does not support the format of the client
+    #[error("The server does not support the {0} format")]
+    UnsupportedFormat(String),
+    /// The server sent a frame which could not be decoded
+    #[error("Received an invalid frame: {0}")]
+    InvalidFrame(#[from] FrameError),
+    /// The server sent a frame which is not a response
+    #[error("Received an unexpected {0:?} frame")]
+    UnexpectedFrame(FrameKind),
+}
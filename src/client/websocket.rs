@@ -1,185 +1,155 @@
 //! Defines a websocket client
 
-use crate::client::AsyncClient;
+use crate::client::multiplex::{MultiplexClient, MultiplexError};
+use crate::client::{AsyncClient, BatchClient, HandleClient};
 use crate::format::Format;
-use crate::{get_request_id, prepend_id, RpcError};
-use futures::channel::{mpsc, oneshot};
-use futures::lock::Mutex;
-use futures::{select, SinkExt, StreamExt};
-use std::collections::HashMap;
-use std::error::Error;
-use std::mem;
-use std::sync::atomic::{AtomicU32, Ordering};
-use std::sync::Arc;
-use thiserror::Error;
+use crate::server::multiplex::{RpcRequest, RpcResponse};
+use crate::{Handler, RequestInfo, RpcError};
+use futures::channel::mpsc;
+use futures::future::ready;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::time::Duration;
 use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::http::Uri;
 use tokio_tungstenite::tungstenite::{ClientRequestBuilder, Error as WsError, Message};
-use tracing::{error, warn};
+use tracing::warn;
 
-static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+pub use crate::client::multiplex::{Callbacks, ConnectionEvent, Reconnect};
 
-/// A client which communicates using a websocket connection
+/// A client which communicates using a websocket connection, the format is chosen with the
+/// websocket subprotocol and each binary message is a single frame, see [`MultiplexClient`]
 pub struct WebsocketClient<Req, Resp> {
-    sender: Arc<Mutex<mpsc::Sender<(u32, Req)>>>,
-    senders: SenderMap<Resp>,
+    inner: MultiplexClient<Req, Resp>,
 }
 
-type SenderMap<Resp> = Arc<Mutex<HashMap<u32, oneshot::Sender<Result<Resp, WebsocketError>>>>>;
-
 impl<Req, Resp> Clone for WebsocketClient<Req, Resp> {
     fn clone(&self) -> Self {
         Self {
-            sender: self.sender.clone(),
-            senders: self.senders.clone(),
+            inner: self.inner.clone(),
         }
     }
 }
 
-impl<Req: Send + 'static, Resp: Send + 'static> WebsocketClient<Req, Resp> {
-    /// Create a new websocket client
+impl<Req: RequestInfo + Send + 'static, Resp: Send + 'static> WebsocketClient<Req, Resp> {
+    /// Create a new websocket client, once the connection is lost every later call fails, see
+    /// [`reconnecting`](Self::reconnecting) for a client which reconnects instead
     ///
     /// # Errors
     /// Returns an error if the websocket connection could not be opened
-    ///
-    /// # Panics
-    /// Certain unexpected edge cases that cannot be proven safe with the type system may cause a panic
     pub async fn new(url: Uri, format: impl Format<Resp, Req> + 'static) -> Result<Self, WsError> {
-        let (mut stream, _) =
-            connect_async(ClientRequestBuilder::new(url).with_sub_protocol(format.content_type()))
-                .await?;
-        let (sender, mut request_receiver) = mpsc::channel(100);
-        let sender = Arc::new(Mutex::new(sender));
-        let senders: SenderMap<Resp> = Arc::default();
-        tokio::spawn({
-            let response_senders = senders.clone();
-            async move {
-                let closed: bool = 'worker: loop {
-                    select! {
-                    req = request_receiver.next() => {
-                        let Some((request_id, request)) = req else {
-                            continue 'worker;
-                        };
-                        let request = match format.write(request) {
-                            Ok(request) => request,
-                            Err(error) => {
-                                let Some(response) = response_senders.lock().await.remove(&request_id) else {
-                                    error!("Response sender was not loaded prior to request");
-                                    continue 'worker;
-                                };
-                                // will only fail if the future is dropped, this case is not considered an error and can safely be ignored
-                                let _ = response.send(Err(WebsocketError::SerialiseRequest(error)));
-                                continue 'worker;
-                            }
-                        };
-                        let request = prepend_id(request_id, request);
-                        if let Err(error) = stream.send(Message::Binary(request.into())).await {
-                            warn!("Error sending message: {}", error);
-                            break 'worker false;
-                        }
-                    },
-                    response = stream.next() => {
-                            let response = match response {
-                                Some(Ok(message)) => {
-                                    match message {
-                                        Message::Text(error) => {
-                                            warn!("Error from server: {}", error);
-                                            continue 'worker;
-                                        }
-                                        Message::Binary(response) => response,
-                                        Message::Ping(bytes) => {
-                                            if let Err(error) = stream.send(Message::Pong(bytes)).await {
-                                                warn!("Error sending pong message: {}", error);
-                                            }
-                                            continue 'worker;
-                                        }
-                                        Message::Pong(_) => {
-                                            continue 'worker;
-                                        }
-                                        Message::Close(_) => {
-                                            let _: Result<(), _> = stream.send(Message::Close(None)).await;
-                                            break 'worker true;
-                                        }
-                                        Message::Frame(_) => unreachable!("Cannot receive raw data frame"),
-                                    }
-                                }
-                                Some(Err(error)) => {
-                                    error!("Error from websocket connection: {}", error);
-                                    continue 'worker;
-                                }
-                                None => {
-                                    warn!("websocket closed");
-                                    break 'worker false;
-                                }
-                            };
-                            let (request_id, response) = get_request_id(&response);
-                            let sender = response_senders.lock().await.remove(&request_id).expect("sender not found");
-                            let response = match format.read(response) {
-                                Ok(response) => response,
-                                Err(error) => {
-                                    let _: Result<(), _> = sender.send(Err(WebsocketError::DeserialiseResponse(error)));
-                                    continue 'worker;
-                                }
-                            };
-                            let _: Result<(), _> = sender.send(Ok(response));
-                    }
-                    }
-                };
-                if !closed {
-                    let _: Result<(), _> = stream.send(Message::Close(None)).await;
-                }
-                let senders = mem::take(&mut *response_senders.lock().await);
-                for (_, sender) in senders {
-                    let _ = sender.send(Err(WebsocketError::ConnectionClosed));
+        let (sink, stream) = dial(url, format.content_type()).await?;
+        let (inner, worker) = MultiplexClient::new(sink, stream, format);
+        tokio::spawn(worker);
+        Ok(Self { inner })
+    }
+
+    /// Create a new websocket client which also serves `callbacks` over the same connection, so
+    /// that the server can call back into this client (see [`Peer`](crate::server::multiplex::Peer)).
+    /// The callbacks are served until the connection is closed, once every clone of the client has
+    /// been dropped and every call has finished. This client does not reconnect
+    ///
+    /// # Errors
+    /// Returns an error if the websocket connection could not be opened
+    pub async fn with_callbacks<H, F>(url: Uri, format: F, callbacks: Callbacks<H>) -> Result<Self, WsError>
+    where
+        H: Handler + Send + Sync + 'static,
+        F: Format<Resp, Req> + Format<RpcRequest<H>, RpcResponse<H>> + 'static,
+    {
+        let (sink, stream) = dial(url, <F as Format<Resp, Req>>::content_type(&format)).await?;
+        let (inner, worker) = MultiplexClient::with_callbacks(sink, stream, format, callbacks);
+        tokio::spawn(worker);
+        Ok(Self { inner })
+    }
+
+    /// Create a new websocket client which reconnects to the same URL whenever the connection is
+    /// lost, see [`MultiplexClient::reconnecting`]
+    ///
+    /// # Errors
+    /// Returns an error if the first websocket connection could not be opened
+    pub async fn reconnecting(url: Uri, format: impl Format<Resp, Req> + 'static, reconnect: Reconnect) -> Result<Self, WsError> {
+        let protocol = format.content_type();
+        let connect = move || dial(url.clone(), protocol);
+        let (inner, worker) = MultiplexClient::reconnecting(connect, format, reconnect).await?;
+        tokio::spawn(worker);
+        Ok(Self { inner })
+    }
+}
+
+/// Open a websocket connection using the given subprotocol, and split it into a sink and a stream
+/// of binary messages
+async fn dial(
+    url: Uri,
+    protocol: &str,
+) -> Result<(impl Sink<Vec<u8>, Error = WsError> + Unpin, impl Stream<Item = Vec<u8>> + Unpin), WsError> {
+    let (socket, _) = connect_async(ClientRequestBuilder::new(url).with_sub_protocol(protocol)).await?;
+    let (sink, stream) = socket.split();
+    let sink = sink.with(|frame: Vec<u8>| ready(Ok(Message::Binary(frame.into()))));
+    // pings and close frames are answered by the websocket itself
+    let stream = stream
+        .take_while(|message| {
+            ready(
+                message
+                    .as_ref()
+                    .map_err(|error| warn!("Error from websocket connection: {error}"))
+                    .is_ok(),
+            )
+        })
+        .filter_map(|message| {
+            ready(match message {
+                Ok(Message::Binary(frame)) => Some(frame.into()),
+                Ok(Message::Text(text)) => {
+                    warn!("Unexpected text frame from server: {text}");
+                    None
                 }
-            }
+                _ => None,
+            })
         });
-        Ok(Self {
-            sender,
-            senders,
-        })
+    Ok((sink, stream))
+}
+
+impl<Req, Resp> WebsocketClient<Req, Resp> {
+    /// Receive an event each time the state of the connection changes, see [`ConnectionEvent`]
+    pub async fn connection_events(&self) -> mpsc::UnboundedReceiver<ConnectionEvent> {
+        self.inner.connection_events().await
+    }
+
+    /// Set the timeout of each call made with this client, see [`MultiplexClient::with_timeout`]
+    #[must_use]
+    pub fn with_timeout(self, timeout: Option<Duration>) -> Self {
+        Self {
+            inner: self.inner.with_timeout(timeout),
+        }
     }
 }
 
 impl<Req, Resp> AsyncClient<Req, Resp> for WebsocketClient<Req, Resp> {
-    type Error = RpcError<WebsocketError>;
+    type Error = RpcError<MultiplexError>;
 
     async fn send(&self, request: Req) -> Result<Resp, Self::Error> {
-        let (sender, receiver) = oneshot::channel();
-        let request_id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
-        self.senders
-            .lock()
-            .await
-            .insert(request_id, sender);
-        self.sender
-            .lock()
-            .await
-            .send((request_id, request))
-            .await
-            .map_err(|_| RpcError::Transport(WebsocketError::RequestChannelClosed))?;
-        receiver
-            .await
-            .map_err(|_| RpcError::Transport(WebsocketError::ResponseChannelClosed))?
-            .map_err(RpcError::Transport)
+        self.inner.send(request).await
+    }
+
+    async fn send_stream(&self, request: Req) -> Result<impl Stream<Item = Result<Resp, Self::Error>>, Self::Error> {
+        self.inner.send_stream(request).await
+    }
+
+    async fn send_with_stream(&self, request: Req, items: impl Stream<Item = Req>) -> Result<Resp, Self::Error> {
+        self.inner.send_with_stream(request, items).await
+    }
+
+    async fn notify(&self, request: Req) -> Result<(), Self::Error> {
+        self.inner.notify(request).await
     }
 }
 
-/// An error from the websocket client
-#[derive(Debug, Error)]
-pub enum WebsocketError {
-    /// The websocket worker has closed the request channel, this is not expected
-    #[error("Failed to send request to worker: channel closed")]
-    RequestChannelClosed,
-    /// The websocket worker has closed the response channel, this is not expected
-    #[error("Failed to read response from worker: channel closed")]
-    ResponseChannelClosed,
-    /// The request could not be serialised
-    #[error("Failed to write request: {0}")]
-    SerialiseRequest(Box<dyn Error + Send>),
-    /// The response could not be deserialised
-    #[error("Failed to write request: {0}")]
-    DeserialiseResponse(Box<dyn Error + Send>),
-    /// The websocket connection has closed
-    #[error("Websocket connection closed")]
-    ConnectionClosed,
+impl<Req, Resp> BatchClient<Req, Resp> for WebsocketClient<Req, Resp> {
+    async fn send_batch(&self, requests: Vec<Req>) -> Result<Vec<Result<Resp, Self::Error>>, Self::Error> {
+        self.inner.send_batch(requests).await
+    }
+}
+
+impl<Req, Resp> HandleClient<Req, Resp> for WebsocketClient<Req, Resp> {
+    async fn open(&self, request: Req) -> Result<(Resp, impl Send + Sync + 'static), Self::Error> {
+        self.inner.open(request).await
+    }
 }
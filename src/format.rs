@@ -2,6 +2,7 @@
 #![allow(clippy::missing_errors_doc, reason = "Errors are obvious")]
 
 use std::error::Error;
+use std::sync::Arc;
 
 #[cfg(feature = "json")]
 pub mod json;
@@ -11,6 +12,10 @@ pub mod browser_json;
 compile_error!("browser-json is only available on wasm32 arch");
 #[cfg(feature = "cbor")]
 pub mod cbor;
+#[cfg(any(feature = "json", feature = "cbor", feature = "browser-json"))]
+mod method;
+#[cfg(any(feature = "json", feature = "cbor", feature = "browser-json"))]
+use method::{Method, is_unknown_method};
 
 /// A format which is able to deserialise `Read` and serialise `Write`
 pub trait Format<Read, Write>: Send + Sync {
@@ -21,6 +26,50 @@ pub trait Format<Read, Write>: Send + Sync {
     fn read(&self, reader: &[u8]) -> Result<Read, Box<dyn Error + Send>>;
     /// write the given value to the given [Write]
     fn write(&self, value: Write) -> Result<Vec<u8>, Box<dyn Error + Send>>;
+
+    /// Whether the given request names a method which `Read` does not have, only the name of the
+    /// method is read so this is true even if the arguments are invalid. This lets a server tell a
+    /// client that a method does not exist, rather than that the request is malformed
+    fn is_unknown_method(&self, request: &[u8]) -> bool {
+        let _ = request;
+        false
+    }
 }
 
 impl<Read, Write> dyn Format<Read, Write> {}
+
+impl<Read, Write, F: Format<Read, Write> + ?Sized> Format<Read, Write> for &F {
+    fn content_type(&self) -> &'static str {
+        (**self).content_type()
+    }
+
+    fn read(&self, reader: &[u8]) -> Result<Read, Box<dyn Error + Send>> {
+        (**self).read(reader)
+    }
+
+    fn write(&self, value: Write) -> Result<Vec<u8>, Box<dyn Error + Send>> {
+        (**self).write(value)
+    }
+
+    fn is_unknown_method(&self, request: &[u8]) -> bool {
+        (**self).is_unknown_method(request)
+    }
+}
+
+impl<Read, Write, F: Format<Read, Write> + ?Sized> Format<Read, Write> for Arc<F> {
+    fn content_type(&self) -> &'static str {
+        (**self).content_type()
+    }
+
+    fn read(&self, reader: &[u8]) -> Result<Read, Box<dyn Error + Send>> {
+        (**self).read(reader)
+    }
+
+    fn write(&self, value: Write) -> Result<Vec<u8>, Box<dyn Error + Send>> {
+        (**self).write(value)
+    }
+
+    fn is_unknown_method(&self, request: &[u8]) -> bool {
+        (**self).is_unknown_method(request)
+    }
+}
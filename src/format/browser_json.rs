@@ -7,7 +7,7 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 use thiserror::Error;
 use web_sys::js_sys;
-use crate::format::{Format};
+use crate::format::{Format, Method, is_unknown_method};
 
 const CONTENT_TYPE: & str = "application/json";
 
@@ -29,6 +29,10 @@ where Read: DeserializeOwned, Write: Serialize
     fn write(&self, value: Write) -> Result<Vec<u8>, Box<dyn StdError + Send>> {
         Self::write_impl(value).map_err(|e| Box::new(e) as Box<dyn StdError + Send>)
     }
+
+    fn is_unknown_method(&self, json: &[u8]) -> bool {
+        Self::read_impl::<Method>(json).is_ok_and(|request| is_unknown_method::<Read>(&request.method))
+    }
 }
 
 impl BrowserJson {
@@ -1,6 +1,6 @@
 //! Provides support for the CBOR ([Concise Binary Object Representation](https://cbor.io/)) format
 
-use crate::format::Format;
+use crate::format::{Format, Method, is_unknown_method};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::error::Error;
@@ -27,4 +27,8 @@ where Read: DeserializeOwned, Write: Serialize
         ciborium::into_writer(&value, &mut buffer).map_err(|error| Box::new(error) as Box<dyn Error + Send>)?;
         Ok(buffer)
     }
+
+    fn is_unknown_method(&self, request: &[u8]) -> bool {
+        ciborium::from_reader::<Method, _>(request).is_ok_and(|request| is_unknown_method::<Read>(&request.method))
+    }
 }
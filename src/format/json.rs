@@ -3,7 +3,7 @@
 use std::error::Error;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use crate::format::{Format};
+use crate::format::{Format, Method, is_unknown_method};
 
 const CONTENT_TYPE: & str = "application/json";
 
@@ -25,4 +25,8 @@ where Read: DeserializeOwned, Write: Serialize
     fn write(&self, value: Write) -> Result<Vec<u8>, Box<dyn Error + Send>> {
         serde_json::to_vec(&value).map_err(|error| Box::new(error) as Box<dyn Error + Send>)
     }
+
+    fn is_unknown_method(&self, request: &[u8]) -> bool {
+        serde_json::from_slice::<Method>(request).is_ok_and(|request| is_unknown_method::<Read>(&request.method))
+    }
 }
@@ -0,0 +1,42 @@
+//! Reads the method of a request without its arguments, so that a request for a method which does
+//! not exist can be told apart from a malformed request
+
+use serde::de::value::MapDeserializer;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::fmt::Display;
+use std::iter;
+use thiserror::Error;
+
+/// The method of an encoded request, read without its arguments
+#[derive(Deserialize)]
+pub struct Method {
+    pub method: String,
+}
+
+/// An error reading the method of a request, which tells an unknown method apart from any other
+/// error without relying on how the format words it
+#[derive(Debug, Error)]
+enum MethodError {
+    #[error("unknown method")]
+    Unknown,
+    #[error("{0}")]
+    Other(String),
+}
+
+impl serde::de::Error for MethodError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Other(msg.to_string())
+    }
+
+    fn unknown_variant(_variant: &str, _expected: &'static [&'static str]) -> Self {
+        Self::Unknown
+    }
+}
+
+/// Whether `Read` has no method with the given name, the name is read as the tag of a request with
+/// no arguments, so any error other than an unknown variant means that the method does exist
+pub fn is_unknown_method<Read: DeserializeOwned>(method: &str) -> bool {
+    let request = MapDeserializer::<_, MethodError>::new(iter::once(("method", method)));
+    matches!(Read::deserialize(request), Err(MethodError::Unknown))
+}
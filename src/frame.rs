@@ -0,0 +1,466 @@
+//! The framing protocol used by transports which share a single connection for many concurrent
+//! requests (eg: websockets, TCP connections and Unix sockets)
+//!
+//! Each message is a single frame, made up of a 12 byte header followed by the payload, all
+//! integers are little endian:
+//!
+//! | Bytes  | Field   | Description                                              |
+//! |--------|---------|----------------------------------------------------------|
+//! | 0      | version | The protocol version, currently [`VERSION`]              |
+//! | 1      | kind    | The [`FrameKind`]                                        |
+//! | 2..4   | flags   | See below                                                |
+//! | 4..12  | id      | The id of the request, chosen by the client              |
+//! | 12..   | payload | Depends on the kind of frame                             |
+//!
+//! When [`TIMEOUT_FLAG`] is set the payload starts with the timeout of the request in milliseconds
+//! as a `u64`, the server stops handling the request once this has elapsed. [`CALLBACK_FLAG`] is
+//! set on every frame of a call made by the server to a service provided by the client over the
+//! same connection, the server acts as the client of such a call and the client as its server (see
+//! [`split_callbacks`](crate::server::multiplex::split_callbacks)). All other flags are reserved
+//! for future use and must be zero
+//!
+//! A frame which does not follow this layout is rejected with a [`FrameError`], since the frame
+//! cannot be matched to a request this is a protocol error for the whole connection
+//!
+//! See [`client::multiplex`](crate::client::multiplex) and
+//! [`server::multiplex`](crate::server::multiplex) to use this protocol over any connection
+
+use crate::client::ResponseError;
+use crate::server::HandlerError;
+use std::time::Duration;
+use thiserror::Error;
+
+/// The current version of the framing protocol
+pub const VERSION: u8 = 1;
+/// The length of a frame header in bytes
+pub const HEADER_LEN: usize = 12;
+/// The flag set on a frame whose payload starts with a timeout
+pub const TIMEOUT_FLAG: u16 = 0x0001;
+/// The flag set on every frame of a call made by the server to a service provided by the client
+pub const CALLBACK_FLAG: u16 = 0x0002;
+/// Every flag which is currently defined
+const KNOWN_FLAGS: u16 = TIMEOUT_FLAG | CALLBACK_FLAG;
+/// The length of the timeout at the start of the payload in bytes
+const TIMEOUT_LEN: usize = 8;
+
+/// The kind of frame, this determines the meaning of the payload
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FrameKind {
+    /// A request from the client, the payload is the encoded request. The server sends no
+    /// response or error frame for a notification
+    Request = 0,
+    /// A response from the server, the payload is the encoded response. A streaming method may
+    /// send any number of responses with the same id
+    Response = 1,
+    /// The request has failed, the payload is an [`ErrorFrame`]. This ends the request, no more
+    /// frames are sent for it
+    Error = 2,
+    /// The client is no longer interested in the request, the payload is empty
+    Cancel = 3,
+    /// An item of a stream argument from the client, the payload is the encoded request
+    StreamItem = 4,
+    /// The end of a stream, sent by the server after the last response of a streaming method, or
+    /// by the client after the last item of a stream argument. The payload is empty
+    End = 5,
+    /// Several requests from the client, or their responses from the server, the payload is a
+    /// batch of frames, see [`batch`](crate::batch)
+    Batch = 6,
+}
+
+impl TryFrom<u8> for FrameKind {
+    type Error = FrameError;
+
+    fn try_from(kind: u8) -> Result<Self, FrameError> {
+        match kind {
+            0 => Ok(Self::Request),
+            1 => Ok(Self::Response),
+            2 => Ok(Self::Error),
+            3 => Ok(Self::Cancel),
+            4 => Ok(Self::StreamItem),
+            5 => Ok(Self::End),
+            6 => Ok(Self::Batch),
+            kind => Err(FrameError::UnknownKind(kind)),
+        }
+    }
+}
+
+/// A single frame, see the [module documentation](self) for the layout
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Frame<'a> {
+    /// The kind of frame
+    pub kind: FrameKind,
+    /// The id of the request this frame belongs to
+    pub id: u64,
+    /// The flags of the frame, see the [module documentation](self). [`TIMEOUT_FLAG`] is always
+    /// encoded from [`timeout`](Self::timeout), whatever its value here
+    pub flags: u16,
+    /// The time after which the client is no longer waiting for the request, this is sent as part
+    /// of the payload if set
+    pub timeout: Option<Duration>,
+    /// The payload of the frame, this does not include the timeout
+    pub payload: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    /// Create a new frame with no flags
+    #[must_use]
+    pub const fn new(kind: FrameKind, id: u64, payload: &'a [u8]) -> Self {
+        Self {
+            kind,
+            id,
+            flags: 0,
+            timeout: None,
+            payload,
+        }
+    }
+
+    /// Set the timeout of this frame, this also sets [`TIMEOUT_FLAG`] if there is a timeout
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        if timeout.is_some() {
+            self.flags |= TIMEOUT_FLAG;
+        } else {
+            self.flags &= !TIMEOUT_FLAG;
+        }
+        self
+    }
+
+    /// Encode this frame, including the header
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        // the flag must match whether the timeout is in the payload, or the peer misreads it
+        let flags = if self.timeout.is_some() {
+            self.flags | TIMEOUT_FLAG
+        } else {
+            self.flags & !TIMEOUT_FLAG
+        };
+        let mut bytes = Vec::with_capacity(HEADER_LEN + TIMEOUT_LEN + self.payload.len());
+        bytes.push(VERSION);
+        bytes.push(self.kind as u8);
+        bytes.extend(flags.to_le_bytes());
+        bytes.extend(self.id.to_le_bytes());
+        if let Some(timeout) = self.timeout {
+            let millis = u64::try_from(timeout.as_millis()).unwrap_or(u64::MAX);
+            bytes.extend(millis.to_le_bytes());
+        }
+        bytes.extend(self.payload);
+        bytes
+    }
+
+    /// Decode a frame
+    ///
+    /// # Errors
+    /// Returns an error if the frame is shorter than a header, or the header is not valid for the
+    /// current version
+    pub fn decode(bytes: &'a [u8]) -> Result<Self, FrameError> {
+        let Some((header, payload)) = bytes.split_first_chunk::<HEADER_LEN>() else {
+            return Err(FrameError::TooShort(bytes.len()));
+        };
+        let [version, kind, flags_low, flags_high, id @ ..] = *header;
+        if version != VERSION {
+            return Err(FrameError::UnsupportedVersion(version));
+        }
+        let kind = FrameKind::try_from(kind)?;
+        let flags = u16::from_le_bytes([flags_low, flags_high]);
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(FrameError::UnknownFlags(flags & !KNOWN_FLAGS));
+        }
+        let (timeout, payload) = if flags & TIMEOUT_FLAG == 0 {
+            (None, payload)
+        } else {
+            let (timeout, payload) = payload
+                .split_first_chunk::<TIMEOUT_LEN>()
+                .ok_or(FrameError::MissingTimeout)?;
+            (Some(Duration::from_millis(u64::from_le_bytes(*timeout))), payload)
+        };
+        Ok(Self {
+            kind,
+            id: u64::from_le_bytes(id),
+            flags,
+            timeout,
+            payload,
+        })
+    }
+}
+
+/// Returns true if the encoded frame has [`CALLBACK_FLAG`] set, without decoding the rest of it
+pub(crate) fn is_callback(bytes: &[u8]) -> bool {
+    matches!(bytes, [_, _, flags_low, flags_high, ..] if u16::from_le_bytes([*flags_low, *flags_high]) & CALLBACK_FLAG != 0)
+}
+
+/// Set or clear [`CALLBACK_FLAG`] on an encoded frame, a frame which is too short to have flags is
+/// left as it is
+pub(crate) fn set_callback(bytes: &mut [u8], callback: bool) {
+    if let [_, _, flags_low, flags_high, ..] = bytes {
+        let mut flags = u16::from_le_bytes([*flags_low, *flags_high]);
+        if callback {
+            flags |= CALLBACK_FLAG;
+        } else {
+            flags &= !CALLBACK_FLAG;
+        }
+        [*flags_low, *flags_high] = flags.to_le_bytes();
+    }
+}
+
+/// A frame could not be decoded
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// The frame is shorter than the header
+    #[error("Frame is too short: expected at least {HEADER_LEN} bytes, received {0}")]
+    TooShort(usize),
+    /// The frame uses a different version of the protocol
+    #[error("Unsupported protocol version: {0}, expected {VERSION}")]
+    UnsupportedVersion(u8),
+    /// The frame kind is not known
+    #[error("Unknown frame kind: {0}")]
+    UnknownKind(u8),
+    /// The frame has flags set which are not known
+    #[error("Unknown frame flags: {0:#06x}")]
+    UnknownFlags(u16),
+    /// The timeout flag is set, but the payload is too short to contain a timeout
+    #[error("Frame is missing its timeout")]
+    MissingTimeout,
+    /// The payload of an error frame is empty
+    #[error("Error frame is missing the error kind")]
+    MissingErrorKind,
+    /// The kind of an error frame is not known
+    #[error("Unknown error kind: {0}")]
+    UnknownErrorKind(u8),
+    /// The length of a frame in a batch is longer than the rest of the batch
+    #[error("Batch is truncated")]
+    TruncatedBatch,
+}
+
+/// The kind of error reported by an [`ErrorFrame`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ErrorKind {
+    /// The request could not be parsed, or was rejected
+    BadRequest = 0,
+    /// The request was for a method which the server does not have
+    UnknownMethod = 1,
+    /// The server failed to handle the request
+    Internal = 2,
+    /// The caller has not provided valid credentials
+    Unauthenticated = 3,
+    /// The caller is not allowed to make the request
+    PermissionDenied = 4,
+}
+
+impl TryFrom<u8> for ErrorKind {
+    type Error = FrameError;
+
+    fn try_from(kind: u8) -> Result<Self, FrameError> {
+        match kind {
+            0 => Ok(Self::BadRequest),
+            1 => Ok(Self::UnknownMethod),
+            2 => Ok(Self::Internal),
+            3 => Ok(Self::Unauthenticated),
+            4 => Ok(Self::PermissionDenied),
+            kind => Err(FrameError::UnknownErrorKind(kind)),
+        }
+    }
+}
+
+/// Reports that a request has failed, the payload of an error frame is the error kind as a single
+/// byte followed by a UTF-8 description of the error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorFrame {
+    /// The id of the failed request
+    pub request_id: u64,
+    /// The kind of error
+    pub kind: ErrorKind,
+    /// A description of the error
+    pub message: String,
+}
+
+impl ErrorFrame {
+    /// Create a new error frame
+    pub fn new(request_id: u64, kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            request_id,
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Encode this error as a frame, including the header
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(1 + self.message.len());
+        payload.push(self.kind as u8);
+        payload.extend(self.message.as_bytes());
+        Frame::new(FrameKind::Error, self.request_id, &payload).encode()
+    }
+
+    /// Read the error from the payload of an error frame with the given request id
+    ///
+    /// # Errors
+    /// Returns an error if the payload does not start with a valid error kind
+    pub fn decode(request_id: u64, payload: &[u8]) -> Result<Self, FrameError> {
+        let (&kind, message) = payload.split_first().ok_or(FrameError::MissingErrorKind)?;
+        Ok(Self::new(
+            request_id,
+            ErrorKind::try_from(kind)?,
+            String::from_utf8_lossy(message),
+        ))
+    }
+}
+
+impl ErrorKind {
+    /// The error received by the client for an error of this kind with the given description
+    pub(crate) const fn response_error(self, message: String) -> ResponseError {
+        match self {
+            Self::BadRequest => ResponseError::BadRequest(message),
+            Self::UnknownMethod => ResponseError::UnknownMethod(message),
+            Self::Internal => ResponseError::InternalServerError(message),
+            Self::Unauthenticated => ResponseError::Unauthenticated(message),
+            Self::PermissionDenied => ResponseError::PermissionDenied(message),
+        }
+    }
+}
+
+/// The kind of error reported when a handler rejects a request
+impl From<&HandlerError> for ErrorKind {
+    fn from(error: &HandlerError) -> Self {
+        match error {
+            HandlerError::Unauthenticated(_) => Self::Unauthenticated,
+            HandlerError::PermissionDenied(_) => Self::PermissionDenied,
+            HandlerError::Unsupported(_) => Self::BadRequest,
+            HandlerError::Other(_) => Self::Internal,
+        }
+    }
+}
+
+impl From<ErrorFrame> for ResponseError {
+    fn from(frame: ErrorFrame) -> Self {
+        frame.kind.response_error(frame.message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::frame::{
+        CALLBACK_FLAG, ErrorFrame, ErrorKind, Frame, FrameError, FrameKind, HEADER_LEN, TIMEOUT_FLAG, VERSION,
+    };
+    use std::time::Duration;
+
+    /// A header with the given version, kind and flags, for request id 7
+    fn header(version: u8, kind: u8, flags: u16) -> Vec<u8> {
+        let mut bytes = vec![version, kind];
+        bytes.extend(flags.to_le_bytes());
+        bytes.extend(7u64.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let frame = Frame::new(FrameKind::Response, 42, b"payload");
+        let bytes = frame.encode();
+        assert_eq!(bytes.len(), HEADER_LEN + 7);
+        assert_eq!(Frame::decode(&bytes), Ok(frame));
+    }
+
+    #[test]
+    fn round_trip_every_kind() {
+        for kind in [
+            FrameKind::Request,
+            FrameKind::Response,
+            FrameKind::Error,
+            FrameKind::Cancel,
+            FrameKind::StreamItem,
+            FrameKind::End,
+            FrameKind::Batch,
+        ] {
+            let frame = Frame::new(kind, u64::MAX, &[]);
+            assert_eq!(Frame::decode(&frame.encode()), Ok(frame));
+        }
+    }
+
+    #[test]
+    fn round_trip_timeout() {
+        let frame = Frame::new(FrameKind::Request, 1, b"request").with_timeout(Some(Duration::from_millis(1500)));
+        let bytes = frame.encode();
+        let decoded = Frame::decode(&bytes).unwrap();
+        assert_eq!(decoded.flags, TIMEOUT_FLAG);
+        assert_eq!(decoded.timeout, Some(Duration::from_millis(1500)));
+        assert_eq!(decoded.payload, b"request");
+    }
+
+    #[test]
+    fn timeout_flag_follows_timeout() {
+        // replacing the flags after setting the timeout must not drop the timeout flag
+        let mut frame = Frame::new(FrameKind::Request, 1, b"request").with_timeout(Some(Duration::from_secs(1)));
+        frame.flags = CALLBACK_FLAG;
+        let bytes = frame.encode();
+        let decoded = Frame::decode(&bytes).unwrap();
+        assert_eq!(decoded.flags, CALLBACK_FLAG | TIMEOUT_FLAG);
+        assert_eq!(decoded.timeout, Some(Duration::from_secs(1)));
+        assert_eq!(decoded.payload, b"request");
+
+        // the timeout flag without a timeout must not make the peer read the payload as a timeout
+        let frame = Frame {
+            flags: TIMEOUT_FLAG,
+            ..Frame::new(FrameKind::Request, 1, b"request")
+        };
+        let bytes = frame.encode();
+        let decoded = Frame::decode(&bytes).unwrap();
+        assert_eq!(decoded.flags, 0);
+        assert_eq!(decoded.timeout, None);
+        assert_eq!(decoded.payload, b"request");
+    }
+
+    #[test]
+    fn rejects_short_header() {
+        let bytes = header(VERSION, 0, 0);
+        assert_eq!(Frame::decode(&bytes[..HEADER_LEN - 1]), Err(FrameError::TooShort(HEADER_LEN - 1)));
+        assert_eq!(Frame::decode(&[]), Err(FrameError::TooShort(0)));
+    }
+
+    #[test]
+    fn rejects_bad_version() {
+        assert_eq!(Frame::decode(&header(VERSION + 1, 0, 0)), Err(FrameError::UnsupportedVersion(VERSION + 1)));
+    }
+
+    #[test]
+    fn rejects_unknown_kind() {
+        assert_eq!(Frame::decode(&header(VERSION, 200, 0)), Err(FrameError::UnknownKind(200)));
+    }
+
+    #[test]
+    fn rejects_unknown_flags() {
+        assert_eq!(Frame::decode(&header(VERSION, 0, 0x8000 | CALLBACK_FLAG)), Err(FrameError::UnknownFlags(0x8000)));
+    }
+
+    #[test]
+    fn rejects_missing_timeout() {
+        let mut bytes = header(VERSION, 0, TIMEOUT_FLAG);
+        bytes.extend([1, 2, 3]);
+        assert_eq!(Frame::decode(&bytes), Err(FrameError::MissingTimeout));
+    }
+
+    #[test]
+    fn error_frame_round_trip() {
+        for kind in [
+            ErrorKind::BadRequest,
+            ErrorKind::UnknownMethod,
+            ErrorKind::Internal,
+            ErrorKind::Unauthenticated,
+            ErrorKind::PermissionDenied,
+        ] {
+            let error = ErrorFrame::new(3, kind, "message");
+            let bytes = error.encode();
+            let frame = Frame::decode(&bytes).unwrap();
+            assert_eq!(frame.kind, FrameKind::Error);
+            assert_eq!(ErrorFrame::decode(frame.id, frame.payload), Ok(error));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_error_kind() {
+        assert_eq!(ErrorFrame::decode(3, &[200, b'a']), Err(FrameError::UnknownErrorKind(200)));
+        assert_eq!(ErrorFrame::decode(3, &[]), Err(FrameError::MissingErrorKind));
+    }
+}
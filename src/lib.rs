@@ -2,15 +2,21 @@
 #![warn(missing_docs)]
 
 pub use serde;
+pub use futures;
 
 pub mod server;
+pub mod batch;
 pub mod client;
 pub mod format;
+pub mod frame;
+pub mod pipeline;
 
 pub use macros::rpc;
 pub use crate::client::{AsyncTransport, BlockingTransport, MappedClient, RpcError};
 pub use server::Handler;
 use crate::client::{AsyncClient, BlockingClient};
+use crate::server::{Context, HandlerError};
+use futures::future::BoxFuture;
 
 /// This is a trait for the main entry point of the RPC, it describes the types for client,
 /// request and response
@@ -20,9 +26,9 @@ pub trait Rpc: Sized {
     /// This is the blocking client type used for accessing the RPC service
     type BlockingClient<T: BlockingClient<Self::Request, Self::Response>>;
     /// This is the request type accepted by the service
-    type Request: 'static;
+    type Request: RequestInfo + Send + 'static;
     /// This is the response type returned by the service
-    type Response: 'static;
+    type Response: Send + 'static;
 
     /// Create a new asynchronous client, using the given underlying transport, if you wish to re-use the
     /// client for multiple calls, ensure you pass a copyable transport (eg: a reference)
@@ -34,27 +40,48 @@ pub trait Rpc: Sized {
     fn blocking_client<C>(transport: C) -> Self::BlockingClient<C>
     where
         C: BlockingClient<Self::Request, Self::Response>;
-}
 
-#[allow(dead_code, reason = "only using in certain features, but better to leave it open")]
-/// Build a request/response from a request ID and a payload. Useful for implementing transport
-/// protocols that share a single connection for many concurrent requests
-fn prepend_id(request_id:u32, payload: Vec<u8>) -> Vec<u8> {
-    let mut bytes = Vec::with_capacity(payload.len() + 4);
-    bytes.extend(request_id.to_le_bytes());
-    bytes.extend(payload);
-    bytes
+    /// If the request is a [pipeline], make each of its calls with `handler`, this lets a
+    /// [`Layered`](server::Layered) handler pass each call through its layer. This is implemented by
+    /// the `#[rpc(pipeline)]` macro, by default no request is a pipeline
+    ///
+    /// # Errors
+    /// Returns the request back if it is not a pipeline
+    fn handle_pipeline<'a, H>(
+        _handler: &'a H,
+        _ctx: &'a Context,
+        request: Self::Request,
+    ) -> Result<BoxFuture<'a, Result<Self::Response, HandlerError>>, Self::Request>
+    where
+        H: Handler<Rpc = Self> + Sync,
+    {
+        Err(request)
+    }
 }
 
-#[allow(dead_code, reason = "only using in certain features, but better to leave it open")]
-/// Get request id and payload from the given request/response. Useful for implementing transport
-/// protocols that share a single connection for many concurrent requests
-fn get_request_id(request: &[u8]) -> (u32, &[u8]) {
-    let request_id = u32::from_le_bytes([
-        request[0],
-        request[1],
-        request[2],
-        request[3],
-    ]);
-    (request_id, &request[4..])
+/// Describes a request, this allows transports to decide how a request should be served without
+/// knowing which service it belongs to
+///
+/// Implementations are generated by the `#[rpc]` macro
+pub trait RequestInfo {
+    /// The name of the method this request is for, for a request to a nested service this is the
+    /// name of the method returning the nested service
+    fn fn_name(&self) -> &'static str;
+    /// Returns true if this request is for a streaming method, in which case the server will send
+    /// any number of responses for it, see [`Handler::handle_stream`]
+    fn is_stream(&self) -> bool;
+    /// Returns true if this request is for a method which takes a stream argument, in which case
+    /// the client will follow it with any number of stream items, see [`Handler::handle_stream`]
+    fn is_client_stream(&self) -> bool;
+    /// Returns true if this is an item of a stream argument rather than a request for a method,
+    /// such items must be passed to [`Handler::handle_stream`] as incoming items of the request
+    /// they follow
+    fn is_stream_item(&self) -> bool;
+    /// Returns true if this request is for a method marked with `#[rpc(idempotent)]`, such a
+    /// request can safely be sent more than once, eg: when retrying a failed call (see
+    /// [`Retry`](client::retry::Retry))
+    fn is_idempotent(&self) -> bool;
+    /// Returns true if this request is for a method marked with `#[rpc(notify)]`, the server
+    /// does not send a response to such a request
+    fn is_notification(&self) -> bool;
 }
@@ -0,0 +1,417 @@
+//! Pipelining several calls into a single request, where the result of an earlier call can be
+//! passed as an argument to a later call without waiting for it
+//!
+//! A service declared with `#[rpc(pipeline)]` has a `pipeline` method on its clients, which is
+//! given a closure making each call with the service's pipeline (eg: `ApiServicePipeline`). Each
+//! call returns a [`Pending`] result straight away, which can be passed as an argument of a later
+//! call, or with [`Pending::some`] and [`Pending::ok`] its value if it is an `Option` or a
+//! `Result`. The closure returns the result to wait for, once every call has been made they are
+//! sent as a single request
+//!
+//! The server makes each call in order, replacing each pending argument with the result of the
+//! earlier call. If that result is not available (eg: the earlier call returned `None` where its
+//! value was used) the call is skipped, and so is every call depending on it. The server then
+//! sends back the result of every call
+//!
+//! Nested services are pipelined in the same way, every nested service used in a pipeline must
+//! also be declared with `#[rpc(pipeline)]`. Streaming methods, methods with a stream argument and
+//! topics cannot be pipelined. The arguments and the results of calls in a pipeline are cloned,
+//! so they must implement [`Clone`]
+//!
+//! A [`HandlerLayer`](crate::server::HandlerLayer) sees each call of a pipeline as a separate
+//! request, so a layer which rejects a method also rejects it in a pipeline. The pipeline fails
+//! with the error of the first call which is rejected
+
+use crate::server::{Context, Handler, HandlerError};
+use crate::Rpc;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use thiserror::Error;
+
+/// A service whose calls can be pipelined, this is implemented by the `#[rpc(pipeline)]` macro
+pub trait Pipelined: Rpc {
+    /// A call made in a pipeline, this is a request whose arguments may be pending results
+    type Call: Send + 'static;
+    /// The pipeline of calls to this service, which pushes each call to the given calls
+    type Pipeline<_Calls: PipelineCalls<Self::Call>>;
+
+    /// Create the pipeline of calls to this service, pushing each call to `calls`
+    fn pipeline<C: PipelineCalls<Self::Call>>(calls: C) -> Self::Pipeline<C>;
+    /// Replace each pending argument of a call with the result of the earlier call, returns `None`
+    /// if any of these results is not available
+    fn resolve<R: Pipelined>(call: Self::Call, results: &Results<'_, R>) -> Option<Self::Request>;
+    /// The result of the method a response is for, with the given projection applied, returns
+    /// `None` if the response has no such value
+    fn output(response: &Self::Response, projection: Projection) -> Option<&dyn Any>;
+}
+
+/// The calls made in a pipeline, each call is pushed to this by the generated pipeline of a
+/// service
+pub trait PipelineCalls<Call>: Clone {
+    /// Push a call to the pipeline, returning its position
+    fn push(&self, call: Call) -> usize;
+}
+
+/// The calls of a pipeline to be sent to the server
+#[derive(Debug)]
+pub struct Calls<Call>(RefCell<Vec<Call>>);
+
+impl<Call> Default for Calls<Call> {
+    fn default() -> Self {
+        Self(RefCell::new(Vec::new()))
+    }
+}
+
+impl<Call> Calls<Call> {
+    /// The calls made so far, in order
+    #[must_use]
+    pub fn into_inner(self) -> Vec<Call> {
+        self.0.into_inner()
+    }
+}
+
+impl<Call> PipelineCalls<Call> for &Calls<Call> {
+    fn push(&self, call: Call) -> usize {
+        let mut calls = self.0.borrow_mut();
+        calls.push(call);
+        calls.len() - 1
+    }
+}
+
+/// This is used for nesting services in a pipeline, it maps each call to the inner service to a
+/// call to the outer service
+#[derive(Debug)]
+pub struct MappedCalls<T, InnerCall, OuterCall, Args> {
+    outer: T,
+    args: Args,
+    to_outer: fn(Args, InnerCall) -> OuterCall,
+}
+
+impl<T: Clone, InnerCall, OuterCall, Args: Clone> Clone for MappedCalls<T, InnerCall, OuterCall, Args> {
+    fn clone(&self) -> Self {
+        Self {
+            outer: self.outer.clone(),
+            args: self.args.clone(),
+            to_outer: self.to_outer,
+        }
+    }
+}
+
+impl<T, InnerCall, OuterCall, Args> MappedCalls<T, InnerCall, OuterCall, Args> {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn new(outer: T, args: Args, to_outer: fn(Args, InnerCall) -> OuterCall) -> Self {
+        Self { outer, args, to_outer }
+    }
+}
+
+impl<T, InnerCall, OuterCall, Args> PipelineCalls<InnerCall> for MappedCalls<T, InnerCall, OuterCall, Args>
+where
+    T: PipelineCalls<OuterCall>,
+    Args: Clone,
+{
+    fn push(&self, call: InnerCall) -> usize {
+        self.outer.push((self.to_outer)(self.args.clone(), call))
+    }
+}
+
+/// The result of a call in a pipeline, this can be passed as an argument of a later call in the
+/// same pipeline
+pub struct Pending<T> {
+    call: usize,
+    _result: PhantomData<fn() -> T>,
+}
+
+impl<T> Pending<T> {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn new(call: usize) -> Self {
+        Self {
+            call,
+            _result: PhantomData,
+        }
+    }
+}
+
+impl<T> Pending<Option<T>> {
+    /// The value of an optional result, a call with this as an argument is skipped if the result
+    /// is `None`
+    #[must_use]
+    pub const fn some(self) -> Projected<T> {
+        Projected::new(self.call, Projection::Some)
+    }
+}
+
+impl<T, E> Pending<Result<T, E>> {
+    /// The value of a successful result, a call with this as an argument is skipped if the result
+    /// is an error
+    #[must_use]
+    pub const fn ok(self) -> Projected<T> {
+        Projected::new(self.call, Projection::Ok)
+    }
+}
+
+impl<T> Clone for Pending<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Pending<T> {}
+
+impl<T> Debug for Pending<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pending").field("call", &self.call).finish()
+    }
+}
+
+/// The value within the result of a call in a pipeline, see [`Pending::some`] and [`Pending::ok`]
+pub struct Projected<T> {
+    reference: Ref,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> Projected<T> {
+    const fn new(call: usize, projection: Projection) -> Self {
+        Self {
+            reference: Ref { call, projection },
+            _value: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for Projected<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Projected<T> {}
+
+impl<T> Debug for Projected<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Projected").field("reference", &self.reference).finish()
+    }
+}
+
+/// Which part of the result of an earlier call is used
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Projection {
+    /// The whole result
+    Value,
+    /// The value of an `Option`
+    Some,
+    /// The value of a `Result`
+    Ok,
+}
+
+impl Projection {
+    #[doc(hidden)]
+    pub fn value<T: Any>(self, value: &T) -> Option<&dyn Any> {
+        match self {
+            Self::Value => Some(value),
+            Self::Some | Self::Ok => None,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn option<T: Any>(self, value: &Option<T>) -> Option<&dyn Any> {
+        match self {
+            Self::Value => Some(value),
+            Self::Some => value.as_ref().map(|value| value as &dyn Any),
+            Self::Ok => None,
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn result<T: Any, E: Any>(self, value: &Result<T, E>) -> Option<&dyn Any> {
+        match self {
+            Self::Value => Some(value),
+            Self::Ok => value.as_ref().ok().map(|value| value as &dyn Any),
+            Self::Some => None,
+        }
+    }
+}
+
+/// The output of a method, to be projected by its type rather than by the name of its type, so
+/// that an alias of `Option` or `Result` is projected as one
+///
+/// The generated `Pipelined::output` calls `(&&Output(value)).project(projection)` with each of
+/// the `Project` traits in scope, method resolution picks [`ProjectOption`] or [`ProjectResult`]
+/// for an `Option` or a `Result`, and [`ProjectValue`] for any other output
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct Output<'a, T>(pub &'a T);
+
+#[doc(hidden)]
+pub trait ProjectValue<'a> {
+    fn project(self, projection: Projection) -> Option<&'a dyn Any>;
+}
+
+impl<'a, T: Any> ProjectValue<'a> for &Output<'a, T> {
+    fn project(self, projection: Projection) -> Option<&'a dyn Any> {
+        projection.value(self.0)
+    }
+}
+
+#[doc(hidden)]
+pub trait ProjectOption<'a> {
+    fn project(self, projection: Projection) -> Option<&'a dyn Any>;
+}
+
+impl<'a, T: Any> ProjectOption<'a> for &&Output<'a, Option<T>> {
+    fn project(self, projection: Projection) -> Option<&'a dyn Any> {
+        projection.option(self.0)
+    }
+}
+
+#[doc(hidden)]
+pub trait ProjectResult<'a> {
+    fn project(self, projection: Projection) -> Option<&'a dyn Any>;
+}
+
+impl<'a, T: Any, E: Any> ProjectResult<'a> for &&Output<'a, Result<T, E>> {
+    fn project(self, projection: Projection) -> Option<&'a dyn Any> {
+        projection.result(self.0)
+    }
+}
+
+/// A reference to the result of an earlier call in a pipeline
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ref {
+    /// The position of the call in the pipeline
+    pub call: usize,
+    /// The part of the result which is used
+    pub projection: Projection,
+}
+
+/// An argument of a call in a pipeline, either a value or the result of an earlier call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arg<T> {
+    /// The value of the argument
+    Value(T),
+    /// The result of an earlier call
+    Result(Ref),
+}
+
+impl<T> From<T> for Arg<T> {
+    fn from(value: T) -> Self {
+        Self::Value(value)
+    }
+}
+
+impl<T> From<Pending<T>> for Arg<T> {
+    fn from(pending: Pending<T>) -> Self {
+        Self::Result(Ref {
+            call: pending.call,
+            projection: Projection::Value,
+        })
+    }
+}
+
+impl<T> From<Projected<T>> for Arg<T> {
+    fn from(projected: Projected<T>) -> Self {
+        Self::Result(projected.reference)
+    }
+}
+
+impl<T: Clone + 'static> Arg<T> {
+    /// The value of this argument, returns `None` if it is the result of an earlier call which is
+    /// not available
+    pub fn resolve<R: Pipelined>(self, results: &Results<'_, R>) -> Option<T> {
+        match self {
+            Self::Value(value) => Some(value),
+            Self::Result(reference) => results.get(reference)?.downcast_ref().cloned(),
+        }
+    }
+}
+
+/// The results of the calls of a pipeline to the service `R` made so far, each call is `None` if
+/// it was skipped
+pub struct Results<'a, R: Rpc> {
+    responses: &'a [Option<R::Response>],
+}
+
+impl<'a, R: Pipelined> Results<'a, R> {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn new(responses: &'a [Option<R::Response>]) -> Self {
+        Self { responses }
+    }
+
+    /// The result of an earlier call, if it is available
+    #[must_use]
+    pub fn get(&self, reference: Ref) -> Option<&'a dyn Any> {
+        let response = self.responses.get(reference.call)?.as_ref()?;
+        R::output(response, reference.projection)
+    }
+}
+
+impl<R: Rpc> Debug for Results<'_, R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Results").field("calls", &self.responses.len()).finish()
+    }
+}
+
+/// The error returned by the client for a pipeline
+#[derive(Debug, Error)]
+pub enum PipelineError<T> {
+    /// The result of the given call is not available, either it was skipped as one of its
+    /// arguments was not available, or its nested service was not found
+    #[error("The result of call {0} of the pipeline is not available")]
+    Unavailable(usize),
+    /// The request failed, see the client error type (eg: [`RpcError`](crate::client::RpcError))
+    #[error(transparent)]
+    Rpc(T),
+}
+
+/// Make each call of a pipeline in order with `handler`, returning the response to each call, or
+/// `None` for a call which was skipped
+///
+/// This is called by the generated handler, a server does not usually need to call it
+///
+/// # Errors
+/// Returns an error if the handler returns an error for any of the calls
+pub async fn run<H>(
+    handler: &H,
+    ctx: &Context,
+    calls: Vec<<H::Rpc as Pipelined>::Call>,
+) -> Result<Vec<Option<<H::Rpc as Rpc>::Response>>, HandlerError>
+where
+    H: Handler + Sync,
+    H::Rpc: Pipelined,
+{
+    let mut responses = Vec::with_capacity(calls.len());
+    for call in calls {
+        let Some(request) = H::Rpc::resolve(call, &Results::<H::Rpc>::new(&responses)) else {
+            responses.push(None);
+            continue;
+        };
+        // boxed, as the handler calls this function to handle a pipeline
+        let response: BoxFuture<'_, _> = Box::pin(handler.handle(ctx, request));
+        responses.push(Some(response.await?));
+    }
+    Ok(responses)
+}
+
+/// The value of the result the client waits for, from the response to each call of a pipeline
+///
+/// This is called by the generated client, it does not usually need to be called directly
+///
+/// # Errors
+/// Returns an error if the result of the call is not available
+pub fn result<R, T, E>(responses: &[Option<R::Response>], pending: Pending<T>) -> Result<T, PipelineError<E>>
+where
+    R: Pipelined,
+    T: Clone + 'static,
+{
+    Arg::from(pending)
+        .resolve(&Results::<R>::new(responses))
+        .ok_or(PipelineError::Unavailable(pending.call))
+}
@@ -1,21 +1,161 @@
 //! Contains modules for individual server implementations
 
 use crate::Rpc;
+use futures::future::ready;
+use futures::{Sink, Stream, StreamExt};
+use std::pin::Pin;
+use std::task::{self, Poll};
+use thiserror::Error;
 
 /// Helpers for serving a service from an axum server
 #[cfg(feature = "axum")]
 pub mod axum;
+mod context;
+mod layer;
+pub mod multiplex;
+pub mod objects;
+#[cfg(any(feature = "tcp", feature = "unix", feature = "stdio"))]
+pub mod stream;
+pub mod topics;
+
+pub use context::Context;
+pub use layer::{HandlerLayer, Layered};
 
 /// This trait describes a handler which takes a request and calls the appropriate method of
 /// an underlying server implementation, then builds and returns the response
 ///
+/// Each request is handled along with a [`Context`] from the transport, which is passed to any
+/// server method taking a `ctx: &Context` parameter
+///
+/// A handler may also return a [`HandlerError`] instead of a response, this is how a
+/// [`HandlerLayer`] rejects a request
+///
 /// Implementations are generated by the `#[rpc]` macro
 pub trait Handler: Send {
     /// The Rpc service served by this handler
     type Rpc: Rpc;
     /// takes the request and returns a response, see [trait documentation](Self) for details
+    ///
+    /// Streaming requests (see [`RequestInfo::is_stream`](crate::RequestInfo::is_stream)) do not
+    /// have a single response, they must be passed to [`handle_stream`](Self::handle_stream)
+    /// instead. The same applies to requests for methods with a stream argument (see
+    /// [`RequestInfo::is_client_stream`](crate::RequestInfo::is_client_stream)) and to stream items
+    ///
+    /// # Errors
+    /// Returns an error if the request was rejected, or [`HandlerError::Unsupported`] if it must be
+    /// passed to [`handle_stream`](Self::handle_stream) instead
     fn handle(
         &self,
+        ctx: &Context,
+        request: <Self::Rpc as Rpc>::Request,
+    ) -> impl Future<Output = Result<<Self::Rpc as Rpc>::Response, HandlerError>> + Send;
+
+    /// takes the request and sends each response to the given sink, returning once the method has
+    /// finished sending responses
+    ///
+    /// Any request can be passed to this method, a request for a non-streaming method will send
+    /// exactly one response. The sink is never closed by the handler, so a transport may signal
+    /// the end of the stream itself once the returned future completes
+    ///
+    /// If the method takes a stream argument, it is made up of the stream items from `incoming`
+    /// (see [`RequestInfo::is_stream_item`](crate::RequestInfo::is_stream_item)), any other requests
+    /// in `incoming` are ignored. The transport should end `incoming` once the client has sent the
+    /// last item, for any other request it is not used
+    ///
+    /// # Errors
+    /// Returns an error if the request was rejected, any responses already sent to the sink are
+    /// still valid
+    fn handle_stream<I, S>(
+        &self,
+        ctx: &Context,
         request: <Self::Rpc as Rpc>::Request,
-    ) -> impl Future<Output = <Self::Rpc as Rpc>::Response> + Send;
+        incoming: I,
+        sink: S,
+    ) -> impl Future<Output = Result<(), HandlerError>> + Send
+    where
+        I: Stream<Item = <Self::Rpc as Rpc>::Request> + Send + Unpin,
+        S: Sink<<Self::Rpc as Rpc>::Response> + Send + Unpin;
+
+    /// Wrap this handler in the given layer, layers added later are called first
+    fn layer<L>(self, layer: L) -> Layered<Self, L>
+    where
+        Self: Sized,
+        L: HandlerLayer<Self::Rpc>,
+    {
+        Layered::new(self, layer)
+    }
+}
+
+/// An error returned by a [`Handler`] in place of a response, the transport reports this to the
+/// client instead
+#[derive(Debug, Error)]
+pub enum HandlerError {
+    /// The caller has not provided valid credentials
+    #[error("Unauthenticated: {0}")]
+    Unauthenticated(String),
+    /// The caller is not allowed to make this request
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    /// The request cannot be handled this way, eg: a streaming request passed to
+    /// [`Handler::handle`]
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+    /// The request was rejected for any other reason
+    #[error("{0}")]
+    Other(String),
+}
+
+/// This is a sink used for nesting services, it maps each response of the inner service to a
+/// response of the outer service
+#[derive(Debug)]
+pub struct MappedSink<S, F> {
+    sink: S,
+    map: F,
+}
+
+impl<S, F> MappedSink<S, F> {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn new(sink: S, map: F) -> Self {
+        Self { sink, map }
+    }
+}
+
+impl<S, F, Inner, Outer> Sink<Inner> for MappedSink<S, F>
+where
+    S: Sink<Outer> + Unpin,
+    F: FnMut(Inner) -> Outer + Unpin,
+{
+    type Error = S::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().sink).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Inner) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        Pin::new(&mut this.sink).start_send((this.map)(item))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().sink).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().sink).poll_close(cx)
+    }
+}
+
+/// Keeps only the incoming requests which `filter` maps to `Some`, this is used to pass the
+/// incoming requests on to a nested service or a stream argument
+#[doc(hidden)]
+pub fn filter_incoming<I, T>(
+    incoming: I,
+    filter: fn(I::Item) -> Option<T>,
+) -> impl Stream<Item = T> + Send + Unpin
+where
+    I: Stream + Send + Unpin,
+    T: Send,
+{
+    incoming.filter_map(move |request| ready(filter(request)))
 }
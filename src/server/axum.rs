@@ -1,9 +1,16 @@
 #[allow(unused_imports, reason = "only used if certain features are enabled")]
 use crate::format;
+use crate::batch;
+use crate::client::{BATCH_HEADER, TIMEOUT_HEADER};
 use crate::format::Format;
-use crate::{get_request_id, prepend_id, Handler, Rpc};
+use crate::client::multiplex::MultiplexClient;
+use crate::server::multiplex::{self, Formats, Peer, RpcFormat, RpcRequest, RpcResponse};
+use crate::server::objects::Objects;
+use crate::server::topics::Topics;
+use crate::server::{Context, HandlerError};
+use crate::{Handler, RequestInfo, Rpc};
 use axum::body::Bytes;
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
 use axum::extract::{ConnectInfo, FromRequest, Request, WebSocketUpgrade};
 use axum::http::header::CONTENT_TYPE;
 use axum::http::{Method, StatusCode};
@@ -11,17 +18,36 @@ use axum::response::{IntoResponse, Response};
 use axum::RequestExt;
 use bon::Builder;
 use bon::__::IsUnset;
-use futures::future::BoxFuture;
-use futures::FutureExt;
+use futures::channel::mpsc;
+use futures::future::{self, ready, BoxFuture};
+use futures::{FutureExt, SinkExt, StreamExt};
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::ops::Deref;
 use std::sync::Arc;
-use std::task::{Context, Poll};
+use std::task::{self, Poll};
+use std::time::Duration;
 use tower::Service;
 use tracing::{debug, info, info_span, Instrument};
 
 /// A service which serves an RPC service in multiple formats as part of an axum server
+///
+/// The [`Context`] of each request contains the peer address (if the server was served with
+/// [`ConnectInfo`]), the request's [`HeaderMap`](axum::http::HeaderMap) and its
+/// [`Extensions`](axum::http::Extensions). For websockets these are taken from the upgrade request
+/// and shared by every request on the connection
+///
+/// A handler stops as soon as its request is cancelled: when an HTTP client disconnects before
+/// the response is sent, when a websocket client sends a cancel frame or disconnects, or when the
+/// timeout sent by the client (see [`TIMEOUT_HEADER`] and
+/// [`Frame::timeout`](crate::frame::Frame::timeout)) has elapsed
+///
+/// An HTTP request with the [`BATCH_HEADER`], or a batch frame on a websocket, carries a
+/// [batch] of requests, which are handled one after the other unless
+/// `batch_concurrency` is set
+///
+/// If [`Topics`] were given they are also added to the context, so websocket clients can subscribe
+/// to the topics of the service, each subscription is removed once its client disconnects
 #[derive(Builder)]
 pub struct Axum<H>
 where
@@ -31,10 +57,24 @@ where
     methods: Vec<Method>,
     #[builder(field)]
     formats: Formats<H::Rpc>,
+    #[builder(field)]
+    peers: Vec<(&'static str, AttachPeer)>,
     #[builder(setters(name = arc_service, vis = "pub(crate)"))]
     handler: Arc<H>,
     #[builder(default)]
     enable_websockets: bool,
+    /// The registry of subscribers to the topics of the service, see [`topics`](crate::server::topics)
+    topics: Option<Topics<H::Rpc>>,
+    /// The maximum number of requests handled at once on a single websocket connection, further
+    /// requests wait until an earlier request has finished. Once as many requests are waiting, the
+    /// server stops reading from the connection until one of them can start, see
+    /// [`multiplex::serve`]. Defaults to 64
+    #[builder(default = 64)]
+    max_concurrent_requests: usize,
+    /// The maximum number of calls of a [batch] handled at once, with 1 the calls
+    /// are handled one after the other in order. Defaults to 1
+    #[builder(default = 1)]
+    batch_concurrency: usize,
 }
 
 impl<H> Clone for Axum<H>
@@ -45,8 +85,12 @@ where
         Self {
             methods: self.methods.clone(),
             formats: self.formats.clone(),
+            peers: self.peers.clone(),
             handler: self.handler.clone(),
             enable_websockets: self.enable_websockets,
+            topics: self.topics.clone(),
+            max_concurrent_requests: self.max_concurrent_requests,
+            batch_concurrency: self.batch_concurrency,
         }
     }
 }
@@ -73,6 +117,24 @@ where
         self
     }
 
+    /// Call a service provided by each websocket client, in the given format. The client of the
+    /// service is inserted into the context of every request on the connection as a [`Peer`], a
+    /// handler can take a `ctx: &Context` parameter to call back into the client which made the
+    /// request. Call this once for each format, there is no peer if the client chose a format
+    /// which was not given
+    pub fn peer<R>(mut self, format: &'static impl Format<R::Response, R::Request>) -> Self
+    where
+        R: Rpc + 'static,
+    {
+        let attach: AttachPeer = Arc::new(move |sink, stream, ctx| {
+            let (client, worker) = MultiplexClient::new(sink, stream, format);
+            ctx.insert(Peer::<R>::new(client));
+            Box::pin(worker)
+        });
+        self.peers.push((format.content_type(), attach));
+        self
+    }
+
     /// Add a method to allow, NOTE: method must allow a body in both request and response
     pub fn method(mut self, method: Method) -> Self {
         self.methods.push(method);
@@ -113,12 +175,6 @@ where
     }
 }
 
-type Formats<R> = Vec<&'static dyn Format<<R as Rpc>::Request, <R as Rpc>::Response>>;
-type RpcFormat<H> = &'static dyn Format<RpcRequest<H>, RpcResponse<H>>;
-type RpcRequest<H> = <HandlerRpc<H> as Rpc>::Request;
-type RpcResponse<H> = <HandlerRpc<H> as Rpc>::Response;
-type HandlerRpc<H> = <H as Handler>::Rpc;
-
 impl<H> Service<Request> for Axum<H>
 where
     H: Handler + Send + Sync + 'static,
@@ -127,7 +183,7 @@ where
     type Error = Infallible;
     type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
-    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+    fn poll_ready(&mut self, _cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
         Poll::Ready(Ok(()))
     }
 
@@ -146,8 +202,16 @@ where
     ) -> impl Future<Output = Result<Response, Error>> + Send + 'static {
         let methods = self.methods.clone();
         let formats = self.formats.clone();
+        let peers = self.peers.clone();
         let handler = self.handler.clone();
+        let topics = self.topics.clone();
+        let limit = self.max_concurrent_requests.max(1);
+        let batch_concurrency = self.batch_concurrency;
         async move {
+            let mut ctx = Self::context(&req);
+            if let Some(topics) = topics {
+                ctx.insert(topics);
+            }
             if let Ok(mut ws) = req.extract_parts::<WebSocketUpgrade>().await
                 && let Ok(ConnectInfo(addr)) = req.extract_parts::<ConnectInfo<SocketAddr>>().await
             {
@@ -162,8 +226,12 @@ where
                     .find(|format| format.content_type() == protocol)
                     .ok_or(Error::UnsupportedSubprotocol(protocols))?;
                 let format: RpcFormat<H> = *format;
+                let peer = peers
+                    .into_iter()
+                    .find(|(content_type, _)| *content_type == protocol)
+                    .map(|(_, attach)| attach);
                 return Ok(ws.on_upgrade(move |socket|
-                    Self::handle_websocket(socket, format, handler).instrument(
+                    Self::handle_websocket(socket, format, peer, handler, ctx, limit, batch_concurrency).instrument(
                         info_span!(target: "websocket", "Websocket connection", address = addr.to_string())
                     )
                 ));
@@ -179,17 +247,49 @@ where
                 .to_str()
                 .map_err(|error| Error::Internal(error.to_string()))?;
             let content_type = content_type.split(';').next().unwrap_or(content_type);
+            let timeout = req
+                .headers()
+                .get(TIMEOUT_HEADER)
+                .map(|timeout| {
+                    timeout
+                        .to_str()
+                        .ok()
+                        .and_then(|timeout| timeout.parse().ok())
+                        .map(Duration::from_millis)
+                        .ok_or(Error::InvalidTimeout)
+                })
+                .transpose()?;
             let format = formats
                 .iter()
                 .find(|format| format.content_type() == content_type)
                 .ok_or(Error::UnsupportedContentType)?;
+            let batch = req.headers().contains_key(BATCH_HEADER);
             let bytes = Bytes::from_request(req, &())
                 .await
                 .map_err(|error| Error::Internal(error.to_string()))?;
+            if batch {
+                let response = batch::handle(*format, &bytes, &*handler, &ctx, batch_concurrency);
+                let response = with_timeout(timeout, response)
+                    .await?
+                    .map_err(|error| Error::Deserialise(error.to_string()))?;
+                return Ok((
+                    StatusCode::OK,
+                    [(CONTENT_TYPE, format.content_type())],
+                    response,
+                )
+                    .into_response());
+            }
             let request = format
                 .read(&bytes)
                 .map_err(|error| Error::Deserialise(error.to_string()))?;
-            let response = handler.deref().handle(request).await;
+            if request.is_stream() || request.is_client_stream() || request.is_stream_item() {
+                return Err(Error::StreamingNotSupported);
+            }
+            if request.is_notification() {
+                return Ok(Self::notify(handler, ctx, request));
+            }
+            let response = handler.deref().handle(&ctx, request);
+            let response = with_timeout(timeout, response).await?.map_err(Error::Handler)?;
             let response = format
                 .write(response)
                 .map_err(|error| Error::Serialise(error.to_string()))?;
@@ -202,13 +302,46 @@ where
         }
     }
 
+    /// Handle a notification in the background, the client is only told that the request was
+    /// accepted and does not receive a response
+    fn notify(handler: Arc<H>, ctx: Context, request: RpcRequest<H>) -> Response {
+        tokio::spawn(async move {
+            if let Err(error) = handler.handle(&ctx, request).await {
+                debug!("Failed to handle notification: {error}");
+            }
+        });
+        StatusCode::ACCEPTED.into_response()
+    }
+
+    /// Build the context of a request, this contains the peer address (if the server was served
+    /// with [`ConnectInfo`]), the request's headers and its extensions
+    fn context(req: &Request) -> Context {
+        let mut ctx = Context::new();
+        ctx.set_peer_addr(
+            req.extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| *addr),
+        );
+        ctx.insert(req.headers().clone());
+        ctx.insert(req.extensions().clone());
+        ctx
+    }
+
+    /// Serve a websocket connection, `ctx` is the context of the upgrade request, this is shared
+    /// by every request on the connection, see [`multiplex::serve`]. If the server calls a service
+    /// provided by the client, the connection also carries these callbacks
     async fn handle_websocket(
-        mut socket: WebSocket,
-        format: &'static dyn Format<RpcRequest<H>, RpcResponse<H>>,
+        socket: WebSocket,
+        format: RpcFormat<H>,
+        peer: Option<AttachPeer>,
         handler: Arc<H>,
+        mut ctx: Context,
+        limit: usize,
+        batch_concurrency: usize,
     ) {
         info!("Started websocket connection");
-        if socket
+        let (mut sender, receiver) = socket.split();
+        if sender
             .send(Message::Ping(Bytes::from_static(&[1, 2, 3])))
             .await
             .is_err()
@@ -218,67 +351,71 @@ where
         }
         debug!("Sent ping message");
 
-        loop {
-            let Some(msg) = socket.recv().await else {
-                info!("Websocket disconnected abruptly");
-                return;
-            };
-            let msg = match msg {
-                Ok(msg) => msg,
-                Err(error) => {
-                    info!("Websocket disconnected with error: {error}");
-                    return;
-                }
-            };
-            let response = match msg {
-                Message::Text(_) => Some(Message::Text("text frames not supported".into())),
-                Message::Binary(bytes) => {
-                    Some(match Self::handle_request(format, &bytes, &handler).await {
-                        Ok(msg) => Message::Binary(msg.into()),
-                        Err(error) => Message::Text(error.into()),
-                    })
-                }
-                Message::Ping(bytes) => Some(Message::Pong(bytes)),
-                Message::Pong(_) => None,
-                Message::Close(frame) => {
-                    if let Some(frame) = frame {
+        let frames = (&mut sender).with(|frame: Vec<u8>| ready(Ok::<_, axum::Error>(Message::Binary(frame.into()))));
+        // pings and close frames are answered by the websocket itself
+        let receiver = receiver
+            .take_while(|msg| {
+                ready(
+                    msg.as_ref()
+                        .map_err(|error| info!("Websocket disconnected with error: {error}"))
+                        .is_ok(),
+                )
+            })
+            .filter_map(|msg| {
+                ready(match msg {
+                    Ok(Message::Binary(bytes)) => Some(bytes.into()),
+                    Ok(Message::Text(_)) => {
+                        info!("Ignoring text frame, only binary frames are supported");
+                        None
+                    }
+                    Ok(Message::Close(Some(frame))) => {
                         info!(
                             "Websocket connection closed, code: {}, reason: {}",
                             frame.code, frame.reason
                         );
-                    } else {
-                        info!("Websocket connection closed without frame");
+                        None
                     }
-                    Some(Message::Close(None))
-                }
+                    _ => None,
+                })
+            });
+        ctx.insert(Objects::new());
+        let result = if let Some(attach) = peer {
+            let ((calls_sink, calls), (callbacks_sink, callbacks), connection) =
+                multiplex::split_callbacks(frames, receiver);
+            let worker = attach(callbacks_sink, callbacks, &mut ctx);
+            let serve = multiplex::serve(calls_sink, calls, format, &*handler, &ctx, limit, batch_concurrency);
+            let (result, (), ()) = future::join3(serve, worker, connection).await;
+            result
+        } else {
+            multiplex::serve(frames, receiver, format, &*handler, &ctx, limit, batch_concurrency).await
+        };
+        if let Err(error) = result {
+            info!("Closing websocket after receiving an invalid frame: {error}");
+            let close = CloseFrame {
+                code: close_code::PROTOCOL,
+                reason: error.to_string().into(),
             };
-            if let Some(response) = response
-                && socket.send(response).await.is_err()
-            {
-                debug!("Failed to send response message");
-                return;
-            }
+            let _ = sender.send(Message::Close(Some(close))).await;
         }
     }
+}
 
-    async fn handle_request(
-        format: RpcFormat<H>,
-        request: &[u8],
-        handler: &H,
-    ) -> Result<Vec<u8>, String> {
-        let (request_id, request) = get_request_id(request);
-        let request: RpcRequest<H> = format
-            .read(request)
-            .map_err(|error| format!("Failed to parse request: {error}"))?;
-        let response = handler.handle(request).await;
-        let response = format
-            .write(response)
-            .map_err(|error| format!("Failed to write response: {error}"))?;
-        let response = prepend_id(request_id, response);
-        Ok(response)
+/// Wait for a response, failing with [`Error::Timeout`] if the timeout sent by the client elapses
+/// first
+async fn with_timeout<T>(timeout: Option<Duration>, response: impl Future<Output = T>) -> Result<T, Error> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, response)
+            .await
+            .map_err(|_| Error::Timeout),
+        None => Ok(response.await),
     }
 }
 
+/// Creates the client for a service provided by a websocket client over the given channel of
+/// callbacks, and inserts it into the context of the connection, the returned future drives the
+/// client
+type AttachPeer = Arc<dyn Fn(mpsc::Sender<Vec<u8>>, mpsc::Receiver<Vec<u8>>, &mut Context) -> BoxFuture<'static, ()> + Send + Sync>;
+
 /// An Error which may occur when handling RPC requests
 pub enum Error {
     /// The wrong HTTP method was used
@@ -295,6 +432,15 @@ pub enum Error {
     Serialise(String),
     /// An internal error occurred while processing the request
     Internal(String),
+    /// A streaming method (or a method with a stream argument) was requested over a transport which
+    /// only supports a single request and response
+    StreamingNotSupported,
+    /// The handler rejected the request
+    Handler(HandlerError),
+    /// The timeout header is not a number of milliseconds
+    InvalidTimeout,
+    /// The timeout sent by the client elapsed before the request was handled
+    Timeout,
 }
 
 impl IntoResponse for Error {
@@ -334,6 +480,30 @@ impl IntoResponse for Error {
             )
                 .into_response(),
             Self::Internal(error) => (StatusCode::INTERNAL_SERVER_ERROR, error).into_response(),
+            Self::StreamingNotSupported => (
+                StatusCode::BAD_REQUEST,
+                "streaming methods are only supported over websockets".to_string(),
+            )
+                .into_response(),
+            Self::Handler(error) => {
+                let status = match error {
+                    HandlerError::Unauthenticated(_) => StatusCode::UNAUTHORIZED,
+                    HandlerError::PermissionDenied(_) => StatusCode::FORBIDDEN,
+                    HandlerError::Unsupported(_) => StatusCode::BAD_REQUEST,
+                    HandlerError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                };
+                (status, error.to_string()).into_response()
+            }
+            Self::InvalidTimeout => (
+                StatusCode::BAD_REQUEST,
+                format!("{TIMEOUT_HEADER} header must be a number of milliseconds"),
+            )
+                .into_response(),
+            Self::Timeout => (
+                StatusCode::GATEWAY_TIMEOUT,
+                "timed out before the request was handled".to_string(),
+            )
+                .into_response(),
         }
     }
 }
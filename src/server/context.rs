@@ -0,0 +1,86 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Information about the caller of a request, provided by the transport
+///
+/// A server method can access the context by taking a `#[context] ctx: &Context` parameter (or one
+/// of type `&trait_rpc::server::Context`), this parameter is not part of the request sent by the
+/// client. Without the marker any other type named `Context` is an ordinary argument. Besides the
+/// peer address, any values can be stored as typed extensions, eg: the headers of an HTTP request
+/// or the identity of an authenticated user
+///
+/// A connection which carries many requests (eg: a websocket) shares a single context between
+/// them. Cloning a context is cheap, the clone shares the existing extensions, so a
+/// [`HandlerLayer`](super::HandlerLayer) can attach a value to a single request by inserting it
+/// into a clone and passing that to the inner handler
+#[derive(Default, Clone)]
+pub struct Context {
+    peer_addr: Option<SocketAddr>,
+    extensions: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Context {
+    /// Create an empty context
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The address of the remote peer, if known by the transport
+    #[must_use]
+    pub const fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Set the address of the remote peer
+    pub const fn set_peer_addr(&mut self, peer_addr: Option<SocketAddr>) {
+        self.peer_addr = peer_addr;
+    }
+
+    /// Get a reference to the extension of type `T`, if one has been inserted
+    #[must_use]
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.extensions
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Get a mutable reference to the extension of type `T`, if one has been inserted and it is
+    /// not shared with a clone of this context
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.extensions
+            .get_mut(&TypeId::of::<T>())
+            .and_then(Arc::get_mut)
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Insert an extension, returning the previous extension of the same type if there was one and
+    /// it was not shared with a clone of this context
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.extensions
+            .insert(TypeId::of::<T>(), Arc::new(value))
+            .and_then(unwrap)
+    }
+
+    /// Remove the extension of type `T`, returning it if there was one and it was not shared with a
+    /// clone of this context
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.extensions.remove(&TypeId::of::<T>()).and_then(unwrap)
+    }
+}
+
+/// Take the value of an extension, if it has the given type and is not shared
+fn unwrap<T: Send + Sync + 'static>(value: Arc<dyn Any + Send + Sync>) -> Option<T> {
+    value.downcast().ok().and_then(|value| Arc::try_unwrap(value).ok())
+}
+
+impl Debug for Context {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Context")
+            .field("peer_addr", &self.peer_addr)
+            .finish_non_exhaustive()
+    }
+}
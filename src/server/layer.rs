@@ -0,0 +1,120 @@
+use super::{Context, Handler, HandlerError};
+use crate::Rpc;
+use futures::{Sink, SinkExt, Stream};
+
+/// A middleware which wraps every call to a [`Handler`]
+///
+/// A layer can inspect or modify the request before passing it on to the inner handler, inspect or
+/// modify the response, or reject the request with a [`HandlerError`] without calling the inner
+/// handler at all
+///
+/// Layers are added with [`Handler::layer`], the resulting [`Layered`] handler can be served by
+/// any transport. A layer sees every request for the service it wraps, including requests for any
+/// nested services, the name of the method being called is available from
+/// [`RequestInfo::fn_name`](crate::RequestInfo::fn_name). Each call of a
+/// [pipeline](crate::pipeline) is passed to the layer as a separate request
+///
+/// The context may be shared by every request on the same connection, to pass a value (eg: the
+/// identity of the caller) to the inner handler for this request only, insert it into a clone of
+/// the context, see [`Context`]
+///
+/// See the `todo_server` example for a layer which logs each call
+pub trait HandlerLayer<R: Rpc>: Send + Sync {
+    /// Handle a request, this should usually call [`Handler::handle`] of `inner`
+    ///
+    /// # Errors
+    /// Returns an error if the request was rejected by this layer or by the inner handler
+    fn handle<H>(
+        &self,
+        ctx: &Context,
+        request: R::Request,
+        inner: &H,
+    ) -> impl Future<Output = Result<R::Response, HandlerError>> + Send
+    where
+        H: Handler<Rpc = R> + Sync;
+
+    /// Handle a request with a stream of responses, this should usually call
+    /// [`Handler::handle_stream`] of `inner`
+    ///
+    /// The multiplexed transports pass every streaming request, every request with a stream
+    /// argument and every request opening a handle to this rather than to [`handle`](Self::handle),
+    /// so a layer which rejects requests must check them here as well
+    ///
+    /// # Errors
+    /// Returns an error if the request was rejected by this layer or by the inner handler
+    fn handle_stream<H, I, S>(
+        &self,
+        ctx: &Context,
+        request: R::Request,
+        incoming: I,
+        sink: S,
+        inner: &H,
+    ) -> impl Future<Output = Result<(), HandlerError>> + Send
+    where
+        H: Handler<Rpc = R> + Sync,
+        I: Stream<Item = R::Request> + Send + Unpin,
+        S: Sink<R::Response> + Send + Unpin;
+}
+
+/// A [`Handler`] wrapped in a [`HandlerLayer`], see [`Handler::layer`]
+#[derive(Debug, Clone)]
+pub struct Layered<H, L> {
+    inner: H,
+    layer: L,
+}
+
+impl<H, L> Layered<H, L> {
+    /// Wrap `inner` in the given layer
+    pub const fn new(inner: H, layer: L) -> Self {
+        Self { inner, layer }
+    }
+
+    /// The handler wrapped by this layer
+    pub const fn inner(&self) -> &H {
+        &self.inner
+    }
+}
+
+impl<H, L> Handler for Layered<H, L>
+where
+    H: Handler + Sync,
+    L: HandlerLayer<H::Rpc>,
+{
+    type Rpc = H::Rpc;
+
+    async fn handle(
+        &self,
+        ctx: &Context,
+        request: <Self::Rpc as Rpc>::Request,
+    ) -> Result<<Self::Rpc as Rpc>::Response, HandlerError> {
+        // each call of a pipeline is passed to the layer, rather than the pipeline as a whole
+        match H::Rpc::handle_pipeline(self, ctx, request) {
+            Ok(pipeline) => pipeline.await,
+            Err(request) => self.layer.handle(ctx, request, &self.inner).await,
+        }
+    }
+
+    async fn handle_stream<I, S>(
+        &self,
+        ctx: &Context,
+        request: <Self::Rpc as Rpc>::Request,
+        incoming: I,
+        mut sink: S,
+    ) -> Result<(), HandlerError>
+    where
+        I: Stream<Item = <Self::Rpc as Rpc>::Request> + Send + Unpin,
+        S: Sink<<Self::Rpc as Rpc>::Response> + Send + Unpin,
+    {
+        match H::Rpc::handle_pipeline(self, ctx, request) {
+            Ok(pipeline) => {
+                let _ = sink.send(pipeline.await?).await;
+                Ok(())
+            }
+            Err(request) => {
+                self.layer
+                    .handle_stream(ctx, request, incoming, sink, &self.inner)
+                    .await
+            }
+        }
+    }
+}
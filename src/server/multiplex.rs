@@ -0,0 +1,499 @@
+//! Serving a service over a single connection shared by many concurrent requests, over any
+//! [`Sink`] and [`Stream`] of byte messages
+//!
+//! Each message is a single [`Frame`], so the connection must keep messages separate, eg: a
+//! websocket, a channel, or a byte stream with length-delimited messages. This is used by the
+//! websocket and byte stream servers, it can be used directly to serve a service over any other
+//! kind of connection, see [`client::multiplex`](crate::client::multiplex) for the client
+//!
+//! Serving does not depend on an async runtime, every request on a connection is handled by the
+//! future returned from [`serve`]
+//!
+//! A connection can also carry calls in the other direction, from the server to a service provided
+//! by the client, see [`split_callbacks`] and [`Peer`]
+
+use crate::batch;
+use crate::client::multiplex::MultiplexClient;
+use crate::format::Format;
+use crate::frame::{self, ErrorFrame, ErrorKind, Frame, FrameError, FrameKind};
+use crate::server::{Context, HandlerError};
+use crate::{Handler, RequestInfo, Rpc};
+use futures::channel::mpsc;
+use futures::future::{self, AbortHandle, Either, ready};
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, Sink, SinkExt, Stream, StreamExt, select};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::marker::PhantomData;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+#[cfg(any(feature = "axum", feature = "tcp", feature = "unix", feature = "stdio"))]
+pub(crate) type Formats<R> = Vec<&'static dyn Format<<R as Rpc>::Request, <R as Rpc>::Response>>;
+#[cfg(any(feature = "axum", feature = "tcp", feature = "unix", feature = "stdio"))]
+pub(crate) type RpcFormat<H> = &'static dyn Format<RpcRequest<H>, RpcResponse<H>>;
+pub(crate) type RpcRequest<H> = <HandlerRpc<H> as Rpc>::Request;
+pub(crate) type RpcResponse<H> = <HandlerRpc<H> as Rpc>::Response;
+pub(crate) type HandlerRpc<H> = <H as Handler>::Rpc;
+
+/// One direction of calls over a connection split by [`split_callbacks`], the sink and stream of
+/// frames for either a client or a server
+pub type Channel = (mpsc::Sender<Vec<u8>>, mpsc::Receiver<Vec<u8>>);
+
+/// Split a connection into the frames of calls made by the client which opened it, and the frames
+/// of callbacks, calls made by the server to a service provided by the client
+///
+/// Both sides of the connection split it the same way, the frames of callbacks are marked with
+/// [`CALLBACK_FLAG`](frame::CALLBACK_FLAG), this is added and removed by the split so neither
+/// channel sees it. Returns the channel of calls, the channel of callbacks, and the future which
+/// drives the connection. This finishes once the stream has ended or the sink of calls has been
+/// closed or dropped, the sink is closed before it finishes and both streams end
+pub fn split_callbacks<Si, St>(mut sink: Si, stream: St) -> (Channel, Channel, impl Future<Output = ()>)
+where
+    Si: Sink<Vec<u8>> + Unpin,
+    Si::Error: Display,
+    St: Stream<Item = Vec<u8>> + Unpin,
+{
+    let (calls_sink, mut calls_out) = mpsc::channel(16);
+    let (mut calls_in, calls_stream) = mpsc::channel(16);
+    let (callbacks_sink, mut callbacks_out) = mpsc::channel::<Vec<u8>>(16);
+    let (mut callbacks_in, callbacks_stream) = mpsc::channel(16);
+    let connection = async move {
+        let mut stream = stream.fuse();
+        // a received frame waiting for room in its channel, and whether it is a callback, no more
+        // frames are read until it has been delivered
+        let mut incoming: Option<(bool, Vec<u8>)> = None;
+        loop {
+            let mut next = if incoming.is_none() {
+                stream.next().left_future()
+            } else {
+                future::pending().right_future()
+            };
+            let frame = select! {
+                frame = next => {
+                    let Some(mut frame) = frame else {
+                        break;
+                    };
+                    let callback = frame::is_callback(&frame);
+                    frame::set_callback(&mut frame, false);
+                    incoming = Some((callback, frame));
+                    continue;
+                }
+                ready = future::poll_fn(|cx| match &incoming {
+                    Some((true, _)) => callbacks_in.poll_ready(cx),
+                    Some((false, _)) => calls_in.poll_ready(cx),
+                    None => Poll::Pending,
+                }).fuse() => {
+                    if let Some((callback, frame)) = incoming.take()
+                        && ready.is_ok()
+                    {
+                        let channel = if callback { &mut callbacks_in } else { &mut calls_in };
+                        // only fails if the receiving side has stopped, in which case its frames
+                        // are dropped
+                        let _ = channel.start_send(frame);
+                    }
+                    continue;
+                }
+                frame = calls_out.next() => {
+                    let Some(frame) = frame else {
+                        break;
+                    };
+                    frame
+                }
+                mut frame = callbacks_out.select_next_some() => {
+                    frame::set_callback(&mut frame, true);
+                    frame
+                }
+            };
+            if let Err(error) = sink.send(frame).await {
+                debug!("Failed to send message: {error}");
+                break;
+            }
+        }
+        // the connection may already be closed, in which case there is nothing to do
+        let _ = sink.close().await;
+    };
+    ((calls_sink, calls_stream), (callbacks_sink, callbacks_stream), connection)
+}
+
+/// The client for a service provided by the peer of a connection, ie: the client of a websocket
+///
+/// A transport which supports callbacks inserts this into the [`Context`] of every request on the
+/// connection, a handler can then call back into the client which made the request by taking a
+/// `ctx: &Context` parameter, see [`split_callbacks`] to support callbacks on any connection
+pub struct Peer<R: Rpc> {
+    client: MultiplexClient<R::Request, R::Response>,
+    _rpc: PhantomData<fn() -> R>,
+}
+
+impl<R: Rpc> Peer<R> {
+    /// Wrap the client for the service provided by the peer
+    #[must_use]
+    pub const fn new(client: MultiplexClient<R::Request, R::Response>) -> Self {
+        Self {
+            client,
+            _rpc: PhantomData,
+        }
+    }
+
+    /// Get a client for the service, this shares the connection with every other client of it
+    #[must_use]
+    pub fn client(&self) -> R::AsyncClient<MultiplexClient<R::Request, R::Response>> {
+        R::async_client(self.client.clone())
+    }
+}
+
+/// Serve a single connection, receiving frames from `stream` and sending frames to `sink`, `ctx` is
+/// shared by every request on the connection
+///
+/// Requests are handled concurrently, at most `max_concurrent_requests` at a time, further requests
+/// wait until an earlier request has finished. Once as many requests are waiting, no more frames
+/// are read until one of them can start, unless a request being handled is still receiving the
+/// items of its stream argument, in which case any new request fails instead. A [batch] counts as a single request, at
+/// most `batch_concurrency` of its calls are handled at once, with 1 they are handled in order.
+/// A request with a stream argument which is still waiting to start can receive as many items as
+/// its channel holds, if the client sends more it fails instead of stopping the requests ahead of
+/// it from receiving their items. A handler stops as soon as its request is cancelled: when
+/// the client sends a cancel frame or disconnects, or when the timeout sent by the client (see
+/// [`Frame::timeout`]) has elapsed
+///
+/// Insert [`Objects`](crate::server::objects::Objects) into `ctx` to support handles to nested
+/// services on the connection
+///
+/// The returned future finishes once the stream has ended or the sink has failed, the sink is not
+/// closed
+///
+/// # Errors
+/// Returns an error if the client sent a frame which could not be decoded, the frame cannot be
+/// matched to a request so the connection should be closed
+#[allow(clippy::too_many_lines, reason = "the receive loop is easier to follow as a single function")]
+pub async fn serve<H, Si, St>(
+    mut sink: Si,
+    stream: St,
+    format: &dyn Format<RpcRequest<H>, RpcResponse<H>>,
+    handler: &H,
+    ctx: &Context,
+    max_concurrent_requests: usize,
+    batch_concurrency: usize,
+) -> Result<(), FrameError>
+where
+    H: Handler + Sync,
+    Si: Sink<Vec<u8>> + Unpin,
+    Si::Error: Display,
+    St: Stream<Item = Vec<u8>> + Unpin,
+{
+    let limit = max_concurrent_requests.max(1);
+    let mut stream = stream.fuse();
+    // responses to requests, which are handled alongside the receive loop
+    let (response_sender, mut responses) = mpsc::channel::<Vec<u8>>(100);
+    // requests which are being handled, and requests waiting for one of them to finish along with
+    // their ids
+    let mut requests = FuturesUnordered::new();
+    let mut waiting = VecDeque::new();
+    // abort handles for every request which has not finished, so that it can be cancelled
+    let mut cancels: HashMap<u64, AbortHandle> = HashMap::new();
+    // senders for the items of any stream arguments which are still being received
+    let mut client_streams: HashMap<u64, mpsc::Sender<RpcRequest<H>>> = HashMap::new();
+    loop {
+        while requests.len() < limit
+            && let Some((_, request)) = waiting.pop_front()
+        {
+            requests.push(request);
+        }
+        // once too many requests are waiting, stop reading until one of them has started, unless a
+        // running request is still receiving items, as it could never finish to let one start
+        let receiving = client_streams
+            .keys()
+            .any(|request_id| !waiting.iter().any(|(id, _)| id == request_id));
+        let mut next = if waiting.len() < limit || receiving {
+            stream.next().left_future()
+        } else {
+            future::pending().right_future()
+        };
+        let msg = select! {
+            msg = next => msg,
+            response = responses.select_next_some() => {
+                if let Err(error) = sink.send(response).await {
+                    debug!("Failed to send response message: {error}");
+                    return Ok(());
+                }
+                continue;
+            }
+            request_id = requests.select_next_some() => {
+                cancels.remove(&request_id);
+                continue;
+            }
+        };
+        let Some(msg) = msg else {
+            info!("Connection closed");
+            return Ok(());
+        };
+        let frame = Frame::decode(&msg)?;
+        let request_id = frame.id;
+        let timeout = frame.timeout;
+        let response = match frame.kind {
+            // the client does not wait for a notification, so one which is rejected is dropped
+            FrameKind::Request if waiting.len() >= limit && format.read(frame.payload).is_ok_and(|request| request.is_notification()) => {
+                debug!("Dropped notification {request_id}, too many requests are waiting to be handled");
+                None
+            }
+            FrameKind::Request | FrameKind::Batch if waiting.len() >= limit => Some(ErrorFrame::new(
+                request_id,
+                ErrorKind::Internal,
+                "Too many requests are waiting to be handled",
+            )),
+            FrameKind::Request => match format.read(frame.payload) {
+                Ok(request) if request.is_stream_item() => Some(ErrorFrame::new(
+                    request_id,
+                    ErrorKind::BadRequest,
+                    "Stream items must be sent in stream item frames",
+                )),
+                Ok(request) if request.is_stream() || request.is_client_stream() => {
+                    let (items, incoming) = mpsc::channel(16);
+                    if request.is_client_stream() {
+                        client_streams.insert(request_id, items);
+                    }
+                    let request = handle_stream(format, request_id, ctx, request, incoming, handler, response_sender.clone());
+                    waiting.push_back((request_id, cancellable(request_id, timeout, Either::Left(request), &mut cancels)));
+                    None
+                }
+                Ok(request) => {
+                    let request = handle_request(format, request_id, ctx, request, handler, response_sender.clone());
+                    let request = Either::Right(Either::Left(request));
+                    waiting.push_back((request_id, cancellable(request_id, timeout, request, &mut cancels)));
+                    None
+                }
+                Err(error) => Some(parse_error(request_id, format, frame.payload, &*error)),
+            },
+            FrameKind::StreamItem => match (client_streams.get_mut(&request_id), format.read(frame.payload)) {
+                (Some(items), Ok(item)) if item.is_stream_item() && waiting.iter().any(|(id, _)| *id == request_id) => {
+                    // the request has not started, waiting for room could stop the requests ahead
+                    // of it from receiving their items, so neither of them would ever finish
+                    match items.try_send(item) {
+                        Ok(()) => None,
+                        Err(error) if error.is_full() => {
+                            if let Some(cancel) = cancels.remove(&request_id) {
+                                cancel.abort();
+                            }
+                            client_streams.remove(&request_id);
+                            Some(ErrorFrame::new(
+                                request_id,
+                                ErrorKind::BadRequest,
+                                "Too many stream items were sent before the request started",
+                            ))
+                        }
+                        Err(_) => {
+                            client_streams.remove(&request_id);
+                            None
+                        }
+                    }
+                }
+                (Some(items), Ok(item)) if item.is_stream_item() => {
+                    // wait for the handler to make room for the item, while still driving the
+                    // other requests, this applies backpressure to the client
+                    let mut forward = items.send(item).fuse();
+                    let sent = loop {
+                        select! {
+                            result = forward => break result.is_ok(),
+                            response = responses.select_next_some() => {
+                                if let Err(error) = sink.send(response).await {
+                                    debug!("Failed to send response message: {error}");
+                                    return Ok(());
+                                }
+                            }
+                            request_id = requests.select_next_some() => {
+                                cancels.remove(&request_id);
+                                if let Some((_, request)) = waiting.pop_front() {
+                                    requests.push(request);
+                                }
+                            }
+                        }
+                    };
+                    if !sent {
+                        // the handler has stopped reading the items
+                        client_streams.remove(&request_id);
+                    }
+                    None
+                }
+                (None, _) => Some(ErrorFrame::new(
+                    request_id,
+                    ErrorKind::BadRequest,
+                    "Received a stream item for an unknown request",
+                )),
+                (Some(_), Ok(_)) => Some(ErrorFrame::new(
+                    request_id,
+                    ErrorKind::BadRequest,
+                    "Stream item frame does not contain a stream item",
+                )),
+                (Some(_), Err(error)) => Some(parse_error(request_id, format, frame.payload, &*error)),
+            },
+            FrameKind::Batch => {
+                let batch = frame.payload.to_vec();
+                let request = handle_batch(format, request_id, batch, ctx, handler, batch_concurrency, response_sender.clone());
+                let request = Either::Right(Either::Right(request));
+                waiting.push_back((request_id, cancellable(request_id, timeout, request, &mut cancels)));
+                None
+            }
+            FrameKind::End => {
+                // the client has sent the last item of a stream argument
+                client_streams.remove(&request_id);
+                None
+            }
+            FrameKind::Cancel => {
+                // the client is no longer waiting for the request, so nothing is sent
+                if let Some(cancel) = cancels.remove(&request_id) {
+                    cancel.abort();
+                }
+                client_streams.remove(&request_id);
+                None
+            }
+            FrameKind::Response | FrameKind::Error => Some(ErrorFrame::new(
+                request_id,
+                ErrorKind::BadRequest,
+                format!("Unexpected {:?} frame from the client", frame.kind),
+            )),
+        };
+        if let Some(error) = response
+            && let Err(error) = sink.send(error.encode()).await
+        {
+            debug!("Failed to send response message: {error}");
+            return Ok(());
+        }
+    }
+}
+
+/// Handle a request with a single response, the response (or an error frame if the request fails)
+/// is sent to `responses`
+async fn handle_request<H: Handler + Sync>(
+    format: &dyn Format<RpcRequest<H>, RpcResponse<H>>,
+    request_id: u64,
+    ctx: &Context,
+    request: RpcRequest<H>,
+    handler: &H,
+    mut responses: mpsc::Sender<Vec<u8>>,
+) {
+    // the client does not wait for a response to a notification, so none is sent
+    if request.is_notification() {
+        if let Err(error) = handler.handle(ctx, request).await {
+            debug!("Failed to handle notification: {error}");
+        }
+        return;
+    }
+    let response = match handler.handle(ctx, request).await {
+        Ok(response) => match format.write(response) {
+            Ok(response) => Frame::new(FrameKind::Response, request_id, &response).encode(),
+            Err(error) => ErrorFrame::new(
+                request_id,
+                ErrorKind::Internal,
+                format!("Failed to write response: {error}"),
+            )
+            .encode(),
+        },
+        Err(error) => handler_error(request_id, &error).encode(),
+    };
+    let _ = responses.send(response).await;
+}
+
+/// Handle a batch of requests, the responses are sent to `responses` as a single batch frame, or an
+/// error frame if the batch could not be decoded
+async fn handle_batch<H: Handler + Sync>(
+    format: &dyn Format<RpcRequest<H>, RpcResponse<H>>,
+    request_id: u64,
+    batch: Vec<u8>,
+    ctx: &Context,
+    handler: &H,
+    concurrency: usize,
+    mut responses: mpsc::Sender<Vec<u8>>,
+) {
+    let response = match batch::handle(format, &batch, handler, ctx, concurrency).await {
+        Ok(batch) => Frame::new(FrameKind::Batch, request_id, &batch).encode(),
+        Err(error) => ErrorFrame::new(request_id, ErrorKind::BadRequest, format!("Invalid batch: {error}")).encode(),
+    };
+    let _ = responses.send(response).await;
+}
+
+/// Handle a request for a streaming method or a method with a stream argument, each response is
+/// sent to `responses`, for a streaming method this is followed by an end frame. If the request
+/// fails an error frame is sent instead, this also ends the stream
+async fn handle_stream<H: Handler + Sync>(
+    format: &dyn Format<RpcRequest<H>, RpcResponse<H>>,
+    request_id: u64,
+    ctx: &Context,
+    request: RpcRequest<H>,
+    incoming: mpsc::Receiver<RpcRequest<H>>,
+    handler: &H,
+    mut responses: mpsc::Sender<Vec<u8>>,
+) {
+    let is_stream = request.is_stream();
+    // a response which cannot be written stops the handler, the error is reported afterwards
+    let mut write_error = None;
+    let sink = (&mut responses).sink_map_err(|_| ()).with(|response| {
+        ready(
+            format
+                .write(response)
+                .map(|response| Frame::new(FrameKind::Response, request_id, &response).encode())
+                .map_err(|error| write_error = Some(error.to_string())),
+        )
+    });
+    let result = handler.handle_stream(ctx, request, incoming, sink).await;
+    let end = match (result, write_error) {
+        (Err(error), _) => handler_error(request_id, &error).encode(),
+        (Ok(()), Some(error)) => ErrorFrame::new(
+            request_id,
+            ErrorKind::Internal,
+            format!("Failed to write response: {error}"),
+        )
+        .encode(),
+        (Ok(()), None) if is_stream => Frame::new(FrameKind::End, request_id, &[]).encode(),
+        (Ok(()), None) => return,
+    };
+    let _ = responses.send(end).await;
+}
+
+/// Allow a request to be cancelled with the abort handle stored in `cancels`, or by its timeout
+/// elapsing, the returned future resolves to the id of the request once it has finished or been
+/// cancelled. This is called once the request's frame has been received, the timeout runs from
+/// then, including any time spent waiting to start
+fn cancellable<F>(
+    request_id: u64,
+    timeout: Option<Duration>,
+    request: F,
+    cancels: &mut HashMap<u64, AbortHandle>,
+) -> impl Future<Output = u64> + use<F>
+where
+    F: Future<Output = ()>,
+{
+    // the client has already given up on the request once the timeout has elapsed, so nothing is
+    // sent
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    let request = async move {
+        let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        if remaining != Some(Duration::ZERO) {
+            let _ = crate::client::timeout(remaining, request).await;
+        }
+    };
+    let (request, cancel) = future::abortable(request);
+    cancels.insert(request_id, cancel);
+    request.map(move |_| request_id)
+}
+
+/// The error frame for a request which could not be parsed
+pub(crate) fn parse_error<R, W>(
+    request_id: u64,
+    format: &dyn Format<R, W>,
+    request: &[u8],
+    error: &dyn std::error::Error,
+) -> ErrorFrame {
+    let message = format!("Failed to parse request: {error}");
+    if format.is_unknown_method(request) {
+        ErrorFrame::new(request_id, ErrorKind::UnknownMethod, message)
+    } else {
+        ErrorFrame::new(request_id, ErrorKind::BadRequest, message)
+    }
+}
+
+/// The error frame for a request which was rejected by the handler
+pub(crate) fn handler_error(request_id: u64, error: &HandlerError) -> ErrorFrame {
+    ErrorFrame::new(request_id, error.into(), error.to_string())
+}
@@ -0,0 +1,183 @@
+//! Keeping the handler of a nested service on the server, so that calls to it do not repeat the
+//! method which returned it
+//!
+//! A nested service marked with `#[rpc(handle)]` can be opened as a handle, with the generated
+//! `open_{method}` function of the async client. The server calls the method once, and keeps the
+//! returned handler for as long as the handle is open, each call made through the handle is then
+//! passed directly to this handler. Otherwise every call to a nested service calls the method
+//! again, which may be expensive, eg: a database lookup
+//!
+//! A handle is a streaming request which stays open, the first response is the id of the object
+//! within the connection. The handle is released once the request is cancelled, which happens once
+//! every clone of the client has been dropped or the connection has closed. Handles can only be
+//! used with a transport which keeps a connection open (see
+//! [`HandleClient`](crate::client::HandleClient)), the server's transport provides the [`Objects`]
+//! of the connection in the [`Context`] of each request. An open handle counts towards the number
+//! of requests handled at once on its connection
+
+use crate::server::{Context, HandlerError};
+use crate::{Handler, Rpc};
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, Either};
+use futures::{Sink, SinkExt, Stream, StreamExt, stream};
+use std::any::Any;
+use std::collections::HashMap;
+use std::pin::pin;
+use std::sync::{Mutex, PoisonError};
+
+/// The objects kept open on a single connection, a transport inserts this into the [`Context`]
+/// shared by every request on the connection to support handles
+#[derive(Default)]
+pub struct Objects {
+    registry: Mutex<Registry>,
+}
+
+/// The calls to each object by id, the sender of each object is an
+/// `mpsc::UnboundedSender<Call<R>>` for the service `R` of the object
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    objects: HashMap<u64, Box<dyn Any + Send + Sync>>,
+}
+
+/// A call made to an object, the responses are sent to `responses` followed by the result
+struct Call<R: Rpc> {
+    request: R::Request,
+    incoming: mpsc::Receiver<R::Request>,
+    responses: mpsc::Sender<R::Response>,
+    result: oneshot::Sender<Result<(), HandlerError>>,
+}
+
+/// Removes an object from the registry once its handler has stopped
+struct Registration<'a> {
+    objects: &'a Objects,
+    id: u64,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        self.objects.lock().objects.remove(&self.id);
+    }
+}
+
+impl Objects {
+    /// Create an empty set of objects for a new connection
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn register<R: Rpc + 'static>(&self, calls: mpsc::UnboundedSender<Call<R>>) -> Registration<'_> {
+        let mut registry = self.lock();
+        let id = registry.next_id;
+        registry.next_id += 1;
+        registry.objects.insert(id, Box::new(calls));
+        drop(registry);
+        Registration { objects: self, id }
+    }
+
+    fn calls<R: Rpc + 'static>(&self, id: u64) -> Option<mpsc::UnboundedSender<Call<R>>> {
+        self.lock().objects.get(&id)?.downcast_ref().cloned()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Registry> {
+        // the registry is never left inconsistent, so it can still be used after a panic
+        self.registry.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Keep `handler` as a new object, sending its id to `sink` and then handling every call made to it
+/// until the returned future is dropped, which releases the object
+///
+/// This is called by the generated handler, a server does not usually need to call it
+///
+/// # Errors
+/// Returns an error if the transport does not support handles
+pub async fn serve<H, S>(ctx: &Context, handler: H, mut sink: S) -> Result<(), HandlerError>
+where
+    H: Handler + Sync,
+    H::Rpc: 'static,
+    S: Sink<u64> + Unpin,
+{
+    let objects = ctx
+        .get::<Objects>()
+        .ok_or_else(|| HandlerError::Other("Handles are not supported by this transport".to_string()))?;
+    let (calls, receiver) = mpsc::unbounded::<Call<H::Rpc>>();
+    let registration = objects.register(calls);
+    if sink.send(registration.id).await.is_err() {
+        return Ok(());
+    }
+    let handler = &handler;
+    receiver
+        .for_each_concurrent(None, |call| async move {
+            let result = handler.handle_stream(ctx, call.request, call.incoming, call.responses).await;
+            // the caller may have been cancelled
+            let _ = call.result.send(result);
+        })
+        .await;
+    Ok(())
+}
+
+/// Pass a request for a streaming method to the object with the given id, sending each response
+/// to `sink`, see [`Handler::handle_stream`]
+///
+/// This is called by the generated handler, a server does not usually need to call it
+///
+/// # Errors
+/// Returns an error if there is no object of the service `R` with the given id, or the handler of
+/// the object returned an error
+pub async fn call_stream<R, I, S>(ctx: &Context, id: u64, request: R::Request, incoming: I, sink: S) -> Result<(), HandlerError>
+where
+    R: Rpc + 'static,
+    I: Stream<Item = R::Request> + Unpin,
+    S: Sink<R::Response> + Unpin,
+{
+    let calls = ctx
+        .get::<Objects>()
+        .and_then(|objects| objects.calls::<R>(id))
+        .ok_or_else(|| HandlerError::Other(format!("No open handle with id {id}")))?;
+    let (items, items_receiver) = mpsc::channel(16);
+    let (responses, responses_receiver) = mpsc::channel(16);
+    let (result, result_receiver) = oneshot::channel();
+    calls
+        .unbounded_send(Call {
+            request,
+            incoming: items_receiver,
+            responses,
+            result,
+        })
+        .map_err(|_| HandlerError::Other(format!("The handle with id {id} has been released")))?;
+    let items = incoming.map(Ok).forward(items.sink_map_err(drop));
+    // the sink is not closed once the responses end, as the transport still sends the end frame
+    let mut sink = sink.sink_map_err(drop);
+    let mut responses_receiver = responses_receiver.map(Ok);
+    let responses = sink.send_all(&mut responses_receiver);
+    // the call may finish before every item has been sent
+    let call = future::join(responses, result_receiver);
+    let (_, result) = match future::select(pin!(items), pin!(call)).await {
+        Either::Left((_, call)) => call.await,
+        Either::Right((call, _)) => call,
+    };
+    result.map_err(|_| HandlerError::Other(format!("The handle with id {id} was released during the call")))?
+}
+
+/// Pass a request to the object with the given id and return its response, see [`Handler::handle`].
+/// Only the first response is returned, any later responses are dropped
+///
+/// This is called by the generated handler, a server does not usually need to call it
+///
+/// # Errors
+/// Returns an error if there is no object of the service `R` with the given id, or the handler of
+/// the object returned an error
+pub async fn call<R: Rpc + 'static>(ctx: &Context, id: u64, request: R::Request) -> Result<R::Response, HandlerError> {
+    let (sender, receiver) = mpsc::channel(1);
+    // the responses are read while the call is made, so the handler is never left waiting to send
+    let mut response = None;
+    let responses = receiver.for_each(|item| {
+        response.get_or_insert(item);
+        future::ready(())
+    });
+    let (result, ()) = future::join(call_stream::<R, _, _>(ctx, id, request, stream::empty(), sender), responses).await;
+    result?;
+    response.ok_or_else(|| HandlerError::Other(format!("The handle with id {id} did not respond")))
+}
@@ -0,0 +1,224 @@
+//! Serving a service over any byte stream, eg: TCP connections, Unix sockets or the stdin and
+//! stdout of a process
+//!
+//! Every message on a connection is prefixed with its length as a big endian `u32`. The first
+//! message sent by the client is the content type of the [`Format`] it uses, the server answers
+//! with the same content type if it supports the format, or with an empty message before closing
+//! the connection if it does not. After this every message is a single
+//! [`Frame`](crate::frame::Frame), so many requests can be in progress at once on the same
+//! connection, see [`multiplex`]
+
+#[allow(unused_imports, reason = "only used if certain features are enabled")]
+use crate::format;
+use crate::format::Format;
+use crate::server::multiplex::{self, Formats, RpcFormat, RpcRequest, RpcResponse};
+use crate::server::objects::Objects;
+use crate::server::Context;
+use crate::Handler;
+use bon::Builder;
+use bon::__::IsUnset;
+use futures::future::ready;
+use futures::{SinkExt, StreamExt};
+use std::io;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+#[cfg(feature = "tcp")]
+use tokio::net::TcpListener;
+#[cfg(all(feature = "unix", unix))]
+use tokio::net::UnixListener;
+use tokio_util::bytes::Bytes;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use tracing::{debug, info, info_span, Instrument};
+
+/// A server which serves an RPC service over byte streams, see the [module documentation](self)
+/// for the protocol
+///
+/// The [`Context`] of each request is shared by every request on the same connection, for a TCP
+/// connection it contains the peer address, for a Unix socket it contains the
+/// [`UCred`](tokio::net::unix::UCred) of the peer process if it is available
+///
+/// A handler stops as soon as its request is cancelled, see [`multiplex::serve`]
+#[derive(Builder)]
+pub struct StreamServer<H>
+where
+    H: Handler + Send + Sync + 'static,
+{
+    #[builder(field)]
+    formats: Formats<H::Rpc>,
+    #[builder(setters(name = arc_handler, vis = "pub(crate)"))]
+    handler: Arc<H>,
+    /// The maximum number of requests handled at once on a single connection, further requests
+    /// wait until an earlier request has finished. Once as many requests are waiting, the server
+    /// stops reading from the connection until one of them can start, see [`multiplex::serve`].
+    /// Defaults to 64
+    #[builder(default = 64)]
+    max_concurrent_requests: usize,
+}
+
+impl<H, State> StreamServerBuilder<H, State>
+where
+    H: Handler + Send + Sync + 'static,
+    State: stream_server_builder::State,
+{
+    /// the server handler to serve requests with
+    pub fn handler(self, handler: H) -> StreamServerBuilder<H, stream_server_builder::SetHandler<State>>
+    where
+        State::Handler: IsUnset,
+    {
+        self.arc_handler(Arc::new(handler))
+    }
+
+    /// Add a format to support
+    pub fn format(
+        mut self,
+        format: &'static impl for<'a> Format<RpcRequest<H>, RpcResponse<H>>,
+    ) -> Self {
+        self.formats.push(format);
+        self
+    }
+
+    /// Add JSON support to this server
+    #[cfg(feature = "json")]
+    pub fn allow_json(self) -> Self
+    where
+        format::json::Json: for<'a> Format<RpcRequest<H>, RpcResponse<H>>,
+    {
+        self.format(&format::json::Json)
+    }
+
+    /// Add CBOR support to this server
+    #[cfg(feature = "cbor")]
+    pub fn allow_cbor(self) -> Self
+    where
+        format::cbor::Cbor: for<'a> Format<RpcRequest<H>, RpcResponse<H>>,
+    {
+        self.format(&format::cbor::Cbor)
+    }
+}
+
+impl<H> StreamServer<H>
+where
+    H: Handler + Send + Sync + 'static,
+{
+    /// Accept TCP connections from the listener, serving each one on a separate task
+    ///
+    /// # Errors
+    /// Returns an error if the listener fails to accept a connection
+    #[cfg(feature = "tcp")]
+    pub async fn serve_tcp(self, listener: TcpListener) -> io::Result<()> {
+        loop {
+            let (stream, addr) = listener.accept().await?;
+            stream.set_nodelay(true)?;
+            let mut ctx = Context::new();
+            ctx.set_peer_addr(Some(addr));
+            tokio::spawn(
+                self.serve_connection(stream, ctx)
+                    .instrument(info_span!(target: "tcp", "TCP connection", address = addr.to_string())),
+            );
+        }
+    }
+
+    /// Accept connections from the Unix socket listener, serving each one on a separate task
+    ///
+    /// # Errors
+    /// Returns an error if the listener fails to accept a connection
+    #[cfg(all(feature = "unix", unix))]
+    pub async fn serve_unix(self, listener: UnixListener) -> io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let cred = stream.peer_cred().ok();
+            let mut ctx = Context::new();
+            if let Some(cred) = cred {
+                ctx.insert(cred);
+            }
+            tokio::spawn(
+                self.serve_connection(stream, ctx)
+                    .instrument(info_span!(target: "unix", "Unix socket connection", pid = cred.and_then(|cred| cred.pid()))),
+            );
+        }
+    }
+
+    /// Serve a single connection over the stdin and stdout of this process, eg: as a plugin spawned
+    /// with [`StreamClient::spawn`](crate::client::stream::StreamClient::spawn). The returned future
+    /// finishes once stdin has closed
+    ///
+    /// Nothing else may be written to stdout while serving, logs should be written to stderr
+    /// instead
+    #[cfg(feature = "stdio")]
+    pub fn serve_stdio(&self) -> impl Future<Output = ()> + Send + use<H> {
+        let stdio = tokio::io::join(tokio::io::stdin(), tokio::io::stdout());
+        self.serve_connection(stdio, Context::new())
+            .instrument(info_span!(target: "stdio", "stdio connection"))
+    }
+
+    /// Serve a single connection over the given stream, `ctx` is shared by every request on the
+    /// connection. The returned future finishes once the connection has closed
+    pub fn serve_connection<S>(&self, stream: S, ctx: Context) -> impl Future<Output = ()> + Send + use<H, S>
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin,
+    {
+        serve_connection(stream, self.formats.clone(), self.handler.clone(), ctx, self.max_concurrent_requests)
+    }
+}
+
+/// Serve a single connection, `ctx` is shared by every request on the connection, see
+/// [`multiplex::serve`]
+async fn serve_connection<H, S>(
+    stream: S,
+    formats: Formats<H::Rpc>,
+    handler: Arc<H>,
+    mut ctx: Context,
+    limit: usize,
+) where
+    H: Handler + Send + Sync + 'static,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    info!("Started connection");
+    let (mut sender, receiver) = Framed::new(stream, LengthDelimitedCodec::new()).split();
+    let mut receiver = receiver.fuse();
+
+    let content_type = match receiver.next().await {
+        Some(Ok(content_type)) => content_type,
+        Some(Err(error)) => {
+            info!("Connection closed with error: {error}");
+            return;
+        }
+        None => {
+            info!("Connection closed before choosing a format");
+            return;
+        }
+    };
+    let Some(format) = formats
+        .iter()
+        .find(|format| format.content_type().as_bytes() == content_type)
+    else {
+        info!("Closing connection which chose an unsupported format");
+        let _ = sender.send(Bytes::new()).await;
+        return;
+    };
+    let format: RpcFormat<H> = *format;
+    if sender
+        .send(Bytes::from_static(format.content_type().as_bytes()))
+        .await
+        .is_err()
+    {
+        debug!("Failed to send chosen format");
+        return;
+    }
+
+    let sender = sender.with(|frame: Vec<u8>| ready(Ok::<_, io::Error>(Bytes::from(frame))));
+    let receiver = receiver
+        .take_while(|message| {
+            ready(
+                message
+                    .as_ref()
+                    .map_err(|error| info!("Connection closed with error: {error}"))
+                    .is_ok(),
+            )
+        })
+        .filter_map(|message| ready(message.ok().map(Vec::from)));
+    ctx.insert(Objects::new());
+    if let Err(error) = multiplex::serve(sender, receiver, format, &*handler, &ctx, limit, 1).await {
+        info!("Closing connection after receiving an invalid frame: {error}");
+    }
+}
@@ -0,0 +1,161 @@
+//! Publishing events to every client subscribed to a topic
+//!
+//! A topic is declared by a method of the service marked with `#[rpc(topic)]`, its return type is
+//! the type of each event, which must implement [`Clone`] as every subscriber receives its own copy.
+//! Calling the method subscribes the client to the topic, and returns a stream of every event
+//! published after the subscription was made. A topic is not part of the server trait
+//!
+//! Events are published with the generated `publish_{method}` function of the service, to every
+//! subscriber registered with a [`Topics`]. A handler finds the [`Topics`] for its service in the
+//! [`Context`](crate::server::Context) of the request, a subscription fails if there is none. The
+//! subscription ends when the request is cancelled or the client disconnects, which removes the
+//! subscriber from the registry
+
+use crate::Rpc;
+use futures::channel::mpsc;
+use futures::Stream;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{self, Poll};
+use tracing::debug;
+
+/// A registry of the subscribers to each topic of the service `R`, clones share the same
+/// subscribers
+///
+/// Each subscriber has a buffer of events which have not been sent to the client yet, once this is
+/// full later events are dropped for that subscriber until it has caught up, so a slow client
+/// cannot hold up the others
+pub struct Topics<R: Rpc> {
+    registry: Arc<Mutex<Registry<R::Response>>>,
+    capacity: usize,
+}
+
+/// The subscribers to each topic, by topic name and then by the id of the subscription
+struct Registry<T> {
+    next_id: u64,
+    topics: HashMap<&'static str, HashMap<u64, mpsc::Sender<T>>>,
+}
+
+impl<R: Rpc> Clone for Topics<R> {
+    fn clone(&self) -> Self {
+        Self {
+            registry: self.registry.clone(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+impl<R: Rpc> Default for Topics<R> {
+    fn default() -> Self {
+        Self::with_capacity(16)
+    }
+}
+
+impl<R: Rpc> Topics<R> {
+    /// Create an empty registry, each subscriber buffers up to 16 events
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty registry, each subscriber buffers up to `capacity` events
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            registry: Arc::new(Mutex::new(Registry {
+                next_id: 0,
+                topics: HashMap::new(),
+            })),
+            capacity,
+        }
+    }
+
+    /// Subscribe to the given topic, the subscription is removed once it is dropped
+    ///
+    /// This is called by the generated handler, a server does not usually need to call it
+    #[must_use]
+    pub fn subscribe(&self, topic: &'static str) -> Subscription<R> {
+        // the channel always has room for one event per sender on top of its buffer
+        let (sender, events) = mpsc::channel(self.capacity.saturating_sub(1));
+        let id = {
+            let mut registry = self.lock();
+            let id = registry.next_id;
+            registry.next_id += 1;
+            registry.topics.entry(topic).or_default().insert(id, sender);
+            id
+        };
+        Subscription {
+            topic,
+            id,
+            events,
+            registry: self.registry.clone(),
+        }
+    }
+
+    /// Send an event to every subscriber of the given topic, `event` is called once for each of
+    /// them. Returns the number of subscribers the event was sent to
+    ///
+    /// This is called by the generated `publish_{method}` function of the service, which should be
+    /// used instead
+    pub fn publish(&self, topic: &'static str, event: impl Fn() -> R::Response) -> usize {
+        let mut registry = self.lock();
+        let Some(subscribers) = registry.topics.get_mut(topic) else {
+            return 0;
+        };
+        let mut sent = 0;
+        subscribers.retain(|id, sender| match sender.try_send(event()) {
+            Ok(()) => {
+                sent += 1;
+                true
+            }
+            Err(error) if error.is_full() => {
+                debug!("Dropped an event on topic {topic} for subscriber {id} which has not caught up");
+                true
+            }
+            Err(_) => false,
+        });
+        drop(registry);
+        sent
+    }
+
+    /// The number of subscribers to the given topic
+    #[must_use]
+    pub fn subscribers(&self, topic: &'static str) -> usize {
+        self.lock().topics.get(topic).map_or(0, HashMap::len)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Registry<R::Response>> {
+        // the registry is never left inconsistent, so it can still be used after a panic
+        self.registry.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A subscription to a topic, this is a stream of each event published to the topic after it was
+/// made. The subscriber is removed from the [`Topics`] once this is dropped
+pub struct Subscription<R: Rpc> {
+    topic: &'static str,
+    id: u64,
+    events: mpsc::Receiver<R::Response>,
+    registry: Arc<Mutex<Registry<R::Response>>>,
+}
+
+impl<R: Rpc> Stream for Subscription<R> {
+    type Item = R::Response;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().events).poll_next(cx)
+    }
+}
+
+impl<R: Rpc> Drop for Subscription<R> {
+    fn drop(&mut self) {
+        let mut registry = self.registry.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(subscribers) = registry.topics.get_mut(self.topic) {
+            subscribers.remove(&self.id);
+            if subscribers.is_empty() {
+                registry.topics.remove(self.topic);
+            }
+        }
+    }
+}
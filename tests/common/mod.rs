@@ -0,0 +1,86 @@
+//! A service shared by the integration tests
+
+use futures::{Stream, StreamExt, future, stream};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use trait_rpc::rpc;
+use trait_rpc::server::Context;
+
+#[rpc]
+/// A service used to test each transport
+pub trait TestService {
+    /// Add two numbers
+    #[rpc(idempotent)]
+    fn add(&self, a: u32, b: u32) -> u32;
+    /// Sum each of the given numbers
+    fn sum(&self, numbers: impl Stream<Item = u32>) -> u32;
+    /// Count from zero up to, but not including, the given number
+    fn count(&self, to: u32) -> impl Stream<Item = u32>;
+    /// Never return, until the call is cancelled
+    fn wait(&self);
+    /// The number of calls to `wait` which are still being handled
+    fn waiting(&self) -> u32;
+    /// Record the given value, without waiting for a reply
+    #[rpc(notify)]
+    fn record(&self, value: u32);
+    /// Each value recorded so far, in the order they were received
+    fn recorded(&self) -> Vec<u32>;
+    /// The name of the caller, from the context of the request
+    fn caller(&self, #[context] ctx: &Context) -> Option<String>;
+}
+
+/// The name of the caller, inserted into the context by the test
+#[derive(Debug, Clone)]
+pub struct Caller(pub String);
+
+/// The implementation of the service
+#[derive(Debug, Default)]
+pub struct Server {
+    waiting: AtomicU32,
+    recorded: Mutex<Vec<u32>>,
+}
+
+/// Counts a call to `wait` until it is dropped
+struct Waiting<'a>(&'a AtomicU32);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl TestServiceServer for Server {
+    async fn add(&self, a: u32, b: u32) -> u32 {
+        a + b
+    }
+
+    async fn sum(&self, numbers: impl Stream<Item = u32> + Send + Unpin) -> u32 {
+        numbers.fold(0, |sum, number| async move { sum + number }).await
+    }
+
+    async fn count(&self, to: u32) -> impl Stream<Item = u32> + Send {
+        stream::iter(0..to)
+    }
+
+    async fn wait(&self) -> () {
+        self.waiting.fetch_add(1, Ordering::SeqCst);
+        let _waiting = Waiting(&self.waiting);
+        future::pending::<()>().await;
+    }
+
+    async fn waiting(&self) -> u32 {
+        self.waiting.load(Ordering::SeqCst)
+    }
+
+    async fn record(&self, value: u32) -> () {
+        self.recorded.lock().unwrap().push(value);
+    }
+
+    async fn recorded(&self) -> Vec<u32> {
+        self.recorded.lock().unwrap().clone()
+    }
+
+    async fn caller(&self, ctx: &Context) -> Option<String> {
+        ctx.get::<Caller>().map(|caller| caller.0.clone())
+    }
+}
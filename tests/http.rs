@@ -0,0 +1,87 @@
+//! Tests of the reqwest client against the axum server, each request is sent with JSON over HTTP
+
+mod common;
+
+use common::{Server, TestService};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+use trait_rpc::client::reqwest::Reqwest;
+use trait_rpc::format::json::Json;
+use trait_rpc::server::axum::Axum;
+use trait_rpc::{Rpc, client};
+
+type Client = <TestService as Rpc>::AsyncClient<client::SimpleClient<Json, Reqwest>>;
+
+/// A client of the service at the given address, each call times out after a second
+fn connect(address: SocketAddr) -> Client {
+    TestService::async_client(
+        client::builder()
+            .non_blocking()
+            .format(Json)
+            .transport(Reqwest::builder().url(format!("http://{address}/api/test")).build())
+            .timeout(Duration::from_secs(1))
+            .build(),
+    )
+}
+
+/// Serve the test service on a local port, returning its address
+async fn serve() -> SocketAddr {
+    let app = axum::Router::new().route_service(
+        "/api/test",
+        Axum::builder()
+            .handler(TestService::server(Server::default()))
+            .allow_json()
+            .allow_post()
+            .build(),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("failed to bind");
+    let address = listener.local_addr().expect("no local address");
+    tokio::spawn(async move {
+        axum::serve::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .expect("server failed");
+    });
+    address
+}
+
+#[tokio::test]
+async fn unary() {
+    let client = connect(serve().await);
+    assert_eq!(client.add(1, 2).await.expect("add failed"), 3);
+}
+
+#[tokio::test]
+async fn notification() {
+    let client = connect(serve().await);
+    client.record(1).await.expect("record failed");
+    // the notification is sent in the background, so it may arrive after the request which follows it
+    let recorded = async {
+        loop {
+            let recorded = client.recorded().await.expect("recorded failed");
+            if !recorded.is_empty() {
+                return recorded;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+    };
+    let recorded = timeout(Duration::from_secs(5), recorded).await.expect("the notification was not handled");
+    assert_eq!(recorded, vec![1]);
+}
+
+#[tokio::test]
+async fn notification_does_not_wait_for_the_server() {
+    // connections are accepted but never answered
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("failed to bind");
+    let address = listener.local_addr().expect("no local address");
+    tokio::spawn(async move {
+        while let Ok((connection, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let _connection = connection;
+                std::future::pending::<()>().await;
+            });
+        }
+    });
+    let client = connect(address);
+    client.record(1).await.expect("record waited for the server");
+}
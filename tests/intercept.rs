@@ -0,0 +1,147 @@
+//! Tests of client interceptors, at both the client and the transport level
+
+mod common;
+
+use common::{Server, TestService};
+use futures::{StreamExt, stream};
+use std::convert::Infallible;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use trait_rpc::{RequestInfo, Rpc};
+use trait_rpc::client::layer::{InterceptLayer, Interceptor, Layer};
+use trait_rpc::client::loopback::Loopback;
+use trait_rpc::client::{self, AsyncTransport, BatchClient, CallInfo, ResponseError};
+use trait_rpc::format::Format;
+use trait_rpc::format::json::Json;
+
+type Response = <TestService as Rpc>::Response;
+
+/// Records the name of each method called, along with whether it succeeded
+#[derive(Clone, Default)]
+struct Calls(Arc<Mutex<Vec<(&'static str, bool)>>>);
+
+impl<Req: RequestInfo, Resp> Interceptor<Req, Resp> for Calls {
+    async fn call<N, F, E>(&self, request: Req, next: N) -> Result<Resp, E>
+    where
+        N: Fn(Req) -> F,
+        F: Future<Output = Result<Resp, E>>,
+        E: Error + 'static,
+    {
+        let name = request.fn_name();
+        let response = next(request).await;
+        self.0.lock().unwrap().push((name, response.is_ok()));
+        response
+    }
+
+    async fn open<T, N, F, E>(&self, request: Req, next: N) -> Result<T, E>
+    where
+        N: Fn(Req) -> F,
+        F: Future<Output = Result<T, E>>,
+        E: Error + 'static,
+    {
+        let name = request.fn_name();
+        let response = next(request).await;
+        self.0.lock().unwrap().push((name, response.is_ok()));
+        response
+    }
+
+    async fn notify<N, F, E>(&self, request: Req, next: N) -> Result<(), E>
+    where
+        N: Fn(Req) -> F,
+        F: Future<Output = Result<(), E>>,
+        E: Error + 'static,
+    {
+        let name = request.fn_name();
+        let response = next(request).await;
+        self.0.lock().unwrap().push((name, response.is_ok()));
+        response
+    }
+}
+
+/// Adds a header to each request sent by the transport, then sends it twice
+#[derive(Clone)]
+struct Authorization;
+
+impl<'a> Interceptor<(Vec<u8>, CallInfo<'a>), Result<Vec<u8>, ResponseError>> for Authorization {
+    async fn call<N, F, E>(&self, (request, mut call): (Vec<u8>, CallInfo<'a>), next: N) -> Result<Result<Vec<u8>, ResponseError>, E>
+    where
+        N: Fn((Vec<u8>, CallInfo<'a>)) -> F,
+        F: Future<Output = Result<Result<Vec<u8>, ResponseError>, E>>,
+        E: Error + 'static,
+    {
+        call.headers.push(("authorization".to_string(), "secret".to_string()));
+        let _ = next((request.clone(), call.clone())).await?;
+        next((request, call)).await
+    }
+}
+
+/// A transport which records the headers of each request, and responds to each with `3`
+#[derive(Clone, Default)]
+struct Headers(Arc<Mutex<Vec<(String, String)>>>);
+
+impl AsyncTransport for Headers {
+    type Error = Infallible;
+
+    async fn send(&self, _request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+        self.0.lock().unwrap().extend(call.headers);
+        let response = Format::<(), Response>::write(&Json, Response::Add(3)).expect("failed to write the response");
+        Ok(Ok(response))
+    }
+}
+
+#[tokio::test]
+async fn calls_are_intercepted() {
+    let calls = Calls::default();
+    let loopback = Loopback::new(TestService::server(Server::default())).with_format(Json);
+    let client = TestService::async_client(InterceptLayer::new(calls.clone()).layer(loopback));
+    assert_eq!(client.add(1, 2).await.expect("add failed"), 3);
+    assert_eq!(client.sum(stream::iter(1..=3)).await.expect("sum failed"), 6);
+    let numbers = client.count(3).await.expect("count failed");
+    assert_eq!(numbers.map(Result::unwrap).collect::<Vec<_>>().await, vec![0, 1, 2]);
+    client.record(1).await.expect("record failed");
+    assert_eq!(*calls.0.lock().unwrap(), vec![("add", true), ("sum", true), ("count", true), ("record", true)]);
+}
+
+#[tokio::test]
+async fn headers_are_sent_by_the_transport() {
+    let headers = Headers::default();
+    let client = TestService::async_client(
+        client::builder()
+            .non_blocking()
+            .format(Json)
+            .transport(InterceptLayer::new(Authorization).layer(headers.clone()))
+            .build(),
+    );
+    assert_eq!(client.add(1, 2).await.expect("add failed"), 3);
+    // the interceptor sends the request twice
+    let header = ("authorization".to_string(), "secret".to_string());
+    assert_eq!(*headers.0.lock().unwrap(), vec![header.clone(), header]);
+}
+
+#[tokio::test]
+async fn notifications_are_intercepted_by_the_transport() {
+    let headers = Headers::default();
+    let client = TestService::async_client(
+        client::builder()
+            .non_blocking()
+            .format(Json)
+            .transport(InterceptLayer::new(Authorization).layer(headers.clone()))
+            .build(),
+    );
+    client.record(1).await.expect("record failed");
+    let header = ("authorization".to_string(), "secret".to_string());
+    assert_eq!(*headers.0.lock().unwrap(), vec![header.clone(), header]);
+}
+
+#[tokio::test]
+async fn batch_is_passed_through() {
+    type Request = <TestService as Rpc>::Request;
+    let loopback = Loopback::new(TestService::server(Server::default())).with_format(Json);
+    let client = InterceptLayer::new(Calls::default()).layer(loopback);
+    let responses = client
+        .send_batch(vec![Request::Add(1, 2), Request::Add(3, 4)])
+        .await
+        .expect("batch failed");
+    assert_eq!(responses.len(), 2);
+    assert!(responses.iter().all(Result::is_ok));
+}
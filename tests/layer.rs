@@ -0,0 +1,135 @@
+//! Tests of handler layers, served over the loopback client and an in-memory byte stream
+
+mod common;
+
+use common::{Caller, Server, TestService};
+use futures::{Sink, Stream, StreamExt};
+use std::sync::{Arc, Mutex};
+use trait_rpc::client::ResponseError;
+use trait_rpc::client::loopback::Loopback;
+use trait_rpc::client::stream::StreamClient;
+use trait_rpc::format::json::Json;
+use trait_rpc::server::stream::StreamServer;
+use trait_rpc::server::{Context, Handler, HandlerError, HandlerLayer};
+use trait_rpc::{RequestInfo, Rpc, RpcError};
+
+/// A layer which rejects every call to the given method
+struct Deny(&'static str);
+
+impl Deny {
+    fn check(&self, request: &impl RequestInfo) -> Result<(), HandlerError> {
+        if request.fn_name() == self.0 {
+            return Err(HandlerError::PermissionDenied(format!("{} is not allowed", self.0)));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Rpc> HandlerLayer<R> for Deny {
+    async fn handle<H>(&self, ctx: &Context, request: R::Request, inner: &H) -> Result<R::Response, HandlerError>
+    where
+        H: Handler<Rpc = R> + Sync,
+    {
+        self.check(&request)?;
+        inner.handle(ctx, request).await
+    }
+
+    async fn handle_stream<H, I, S>(&self, ctx: &Context, request: R::Request, incoming: I, sink: S, inner: &H) -> Result<(), HandlerError>
+    where
+        H: Handler<Rpc = R> + Sync,
+        I: Stream<Item = R::Request> + Send + Unpin,
+        S: Sink<R::Response> + Send + Unpin,
+    {
+        self.check(&request)?;
+        inner.handle_stream(ctx, request, incoming, sink).await
+    }
+}
+
+/// A layer which records its name each time it is called, then names the caller in a clone of the
+/// context
+struct Record {
+    name: &'static str,
+    calls: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl<R: Rpc> HandlerLayer<R> for Record {
+    async fn handle<H>(&self, ctx: &Context, request: R::Request, inner: &H) -> Result<R::Response, HandlerError>
+    where
+        H: Handler<Rpc = R> + Sync,
+    {
+        self.calls.lock().unwrap().push(self.name);
+        let mut ctx = ctx.clone();
+        ctx.insert(Caller(self.name.to_string()));
+        inner.handle(&ctx, request).await
+    }
+
+    async fn handle_stream<H, I, S>(&self, ctx: &Context, request: R::Request, incoming: I, sink: S, inner: &H) -> Result<(), HandlerError>
+    where
+        H: Handler<Rpc = R> + Sync,
+        I: Stream<Item = R::Request> + Send + Unpin,
+        S: Sink<R::Response> + Send + Unpin,
+    {
+        self.calls.lock().unwrap().push(self.name);
+        inner.handle_stream(ctx, request, incoming, sink).await
+    }
+}
+
+/// Serve the handler over one end of an in-memory stream, returning a client for the other end
+async fn connect<H: Handler<Rpc = TestService> + Sync + 'static>(
+    handler: H,
+) -> <TestService as Rpc>::AsyncClient<StreamClient<<TestService as Rpc>::Request, <TestService as Rpc>::Response>> {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let serve = StreamServer::builder()
+        .handler(handler)
+        .allow_json()
+        .build()
+        .serve_connection(server, Context::new());
+    tokio::spawn(serve);
+    TestService::async_client(StreamClient::new(client, Json).await.expect("failed to connect"))
+}
+
+#[tokio::test]
+async fn layers_are_called_in_order() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let handler = TestService::server(Server::default())
+        .layer(Record { name: "inner", calls: calls.clone() })
+        .layer(Record { name: "outer", calls: calls.clone() });
+    let client = TestService::async_client(Loopback::new(handler).with_format(Json));
+    // the inner layer inserts the caller last, so the handler sees its value
+    assert_eq!(client.caller().await.expect("caller failed"), Some("inner".to_string()));
+    assert_eq!(*calls.lock().unwrap(), vec!["outer", "inner"]);
+}
+
+#[tokio::test]
+async fn layer_rejects_call() {
+    let client = TestService::async_client(Loopback::new(TestService::server(Server::default()).layer(Deny("add"))));
+    let result = client.add(1, 2).await;
+    assert!(
+        matches!(result, Err(RpcError::Response(ResponseError::PermissionDenied(_)))),
+        "expected the call to be rejected, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn layer_rejects_streaming_call_over_stream() {
+    let client = connect(TestService::server(Server::default()).layer(Deny("count"))).await;
+    let result = match client.count(3).await {
+        Ok(numbers) => numbers.collect::<Vec<_>>().await.into_iter().collect::<Result<Vec<_>, _>>(),
+        Err(error) => Err(error),
+    };
+    assert!(
+        matches!(result, Err(RpcError::Response(ResponseError::PermissionDenied(_)))),
+        "expected the call to be rejected, got {result:?}"
+    );
+    assert_eq!(client.add(1, 2).await.expect("add failed"), 3);
+}
+
+#[tokio::test]
+async fn layer_rejects_client_stream_over_stream() {
+    let client = connect(TestService::server(Server::default()).layer(Deny("sum"))).await;
+    let result = client.sum(futures::stream::iter(1..=3)).await;
+    assert!(
+        matches!(result, Err(RpcError::Response(ResponseError::PermissionDenied(_)))),
+        "expected the call to be rejected, got {result:?}"
+    );
+}
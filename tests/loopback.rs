@@ -0,0 +1,231 @@
+//! Tests of the loopback client, each request is serialised with JSON and passed straight to the
+//! handler
+
+mod common;
+
+use common::{Caller, Server, TestService};
+use futures::{Sink, Stream, StreamExt, join, poll, stream};
+use std::fmt::{self, Display, Formatter};
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::time::{sleep, timeout};
+use trait_rpc::batch::{Batch, BatchError};
+use trait_rpc::client::loopback::{Formatted, Loopback};
+use trait_rpc::format::json::Json;
+use trait_rpc::client::{BatchClient, ResponseError};
+use trait_rpc::serde::{Deserialize, Serialize};
+use trait_rpc::server::{Context, HandlerError, HandlerLayer};
+use trait_rpc::{Handler, RequestInfo, Rpc, RpcError, rpc};
+
+#[rpc]
+/// A service which only gives access to the test service with the right password
+pub trait Locked {
+    /// The test service, if the password is right
+    fn unlock(&self, password: String) -> Result<impl TestService, WrongPassword>;
+}
+
+/// The password given to [`Locked::unlock`] was wrong
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "trait_rpc::serde")]
+pub struct WrongPassword {
+    attempts_left: u32,
+}
+
+impl Display for WrongPassword {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "wrong password, {} attempts left", self.attempts_left)
+    }
+}
+
+/// The implementation of the locked service
+struct Lock;
+
+impl LockedServer for Lock {
+    async fn unlock(&self, password: String) -> Result<impl Handler<Rpc = TestService>, WrongPassword> {
+        if password == "open sesame" {
+            Ok(TestService::server(Server::default()))
+        } else {
+            Err(WrongPassword { attempts_left: 2 })
+        }
+    }
+}
+
+/// Holds back each notification until the gate is given a permit for it
+struct Gate(Arc<Semaphore>);
+
+impl HandlerLayer<TestService> for Gate {
+    async fn handle<H>(&self, ctx: &Context, request: Request, inner: &H) -> Result<Response, HandlerError>
+    where
+        H: Handler<Rpc = TestService> + Sync,
+    {
+        if request.is_notification() {
+            self.0.acquire().await.expect("the gate was closed").forget();
+        }
+        inner.handle(ctx, request).await
+    }
+
+    async fn handle_stream<H, I, S>(&self, ctx: &Context, request: Request, incoming: I, sink: S, inner: &H) -> Result<(), HandlerError>
+    where
+        H: Handler<Rpc = TestService> + Sync,
+        I: Stream<Item = Request> + Send + Unpin,
+        S: Sink<Response> + Send + Unpin,
+    {
+        inner.handle_stream(ctx, request, incoming, sink).await
+    }
+}
+
+type Request = <TestService as Rpc>::Request;
+type Response = <TestService as Rpc>::Response;
+
+/// A loopback client for a new server
+fn transport() -> Loopback<impl Handler<Rpc = TestService>, Formatted<Json>> {
+    Loopback::new(TestService::server(Server::default())).with_format(Json)
+}
+
+#[tokio::test]
+async fn unary() {
+    let client = TestService::async_client(transport());
+    assert_eq!(client.add(1, 2).await.expect("add failed"), 3);
+}
+
+#[tokio::test]
+async fn server_stream() {
+    let client = TestService::async_client(transport());
+    let numbers = client.count(4).await.expect("count failed");
+    let numbers: Vec<_> = numbers.map(Result::unwrap).collect().await;
+    assert_eq!(numbers, vec![0, 1, 2, 3]);
+}
+
+#[tokio::test]
+async fn client_stream() {
+    let client = TestService::async_client(transport());
+    let sum = client.sum(stream::iter(1..=10)).await.expect("sum failed");
+    assert_eq!(sum, 55);
+}
+
+#[tokio::test]
+async fn cancellation() {
+    let client = TestService::async_client(transport());
+    let mut call = Box::pin(client.wait());
+    timeout(Duration::from_millis(50), &mut call)
+        .await
+        .expect_err("wait returned");
+    assert_eq!(client.waiting().await.expect("waiting failed"), 1);
+    // the handler is stopped as soon as the call is dropped
+    drop(call);
+    assert_eq!(client.waiting().await.expect("waiting failed"), 0);
+}
+
+#[tokio::test]
+async fn batch() {
+    let batch = Batch::new(transport());
+    let client = TestService::async_client(batch.clone());
+    let (first, second, recorded) = batch
+        .run(async { join!(client.add(1, 2), client.add(3, 4), client.recorded()) })
+        .await;
+    assert_eq!(first.expect("add failed"), 3);
+    assert_eq!(second.expect("add failed"), 7);
+    assert_eq!(recorded.expect("recorded failed"), Vec::<u32>::new());
+}
+
+#[tokio::test]
+async fn batch_only_sends_calls_while_running() {
+    let batch = Batch::new(transport());
+    let client = TestService::async_client(batch.clone());
+    let result = timeout(Duration::from_secs(5), client.add(1, 2)).await.expect("the call was not failed");
+    assert!(matches!(result, Err(BatchError::NotRunning)), "expected the call to fail, got {result:?}");
+    assert_eq!(batch.run(client.add(1, 2)).await.expect("add failed"), 3);
+    let result = timeout(Duration::from_secs(5), client.add(1, 2)).await.expect("the call was not failed");
+    assert!(matches!(result, Err(BatchError::NotRunning)), "expected the call to fail, got {result:?}");
+}
+
+#[tokio::test]
+async fn batch_cancels_calls_left_once_run_finishes() {
+    let batch = Batch::new(transport());
+    let client = TestService::async_client(batch.clone());
+    let mut call = pin!(client.add(1, 2));
+    // the call is queued, but the future given to run finishes before it is sent
+    batch.run(async { assert!(poll!(call.as_mut()).is_pending()) }).await;
+    let result = timeout(Duration::from_secs(5), call).await.expect("the call was not failed");
+    assert!(matches!(result, Err(BatchError::Cancelled)), "expected the call to fail, got {result:?}");
+}
+
+#[tokio::test]
+async fn batch_rejects_streaming_calls() {
+    type Request = <TestService as Rpc>::Request;
+    let responses = transport()
+        .send_batch(vec![Request::Count(4), Request::Add(1, 2)])
+        .await
+        .expect("batch failed");
+    match &responses[0] {
+        Err(RpcError::Response(ResponseError::BadRequest(message))) => {
+            assert_eq!(message, "Streaming methods cannot be batched");
+        }
+        response => panic!("expected the streaming call to be rejected, got {response:?}"),
+    }
+    assert!(responses[1].is_ok());
+}
+
+#[tokio::test]
+async fn context() {
+    let client = TestService::async_client(transport());
+    assert_eq!(client.caller().await.expect("caller failed"), None);
+    let mut ctx = Context::new();
+    ctx.insert(Caller("dylan".to_string()));
+    let client = TestService::async_client(transport().with_context(ctx));
+    assert_eq!(client.caller().await.expect("caller failed"), Some("dylan".to_string()));
+}
+
+#[tokio::test]
+async fn notification() {
+    let client = TestService::async_client(transport());
+    client.record(1).await.expect("record failed");
+    client.record(2).await.expect("record failed");
+    assert_eq!(client.recorded().await.expect("recorded failed"), vec![1, 2]);
+}
+
+#[tokio::test]
+async fn notification_is_spawned() {
+    let gate = Arc::new(Semaphore::new(0));
+    let handler = TestService::server(Server::default()).layer(Gate(gate.clone()));
+    let transport = Loopback::new(handler).with_format(Json).with_spawner(|task| {
+        tokio::spawn(task);
+    });
+    let client = TestService::async_client(transport);
+    // the handler is held back, so this only returns because it does not wait for it
+    timeout(Duration::from_secs(5), client.record(1))
+        .await
+        .expect("the notification waited for the handler")
+        .expect("record failed");
+    assert_eq!(client.recorded().await.expect("recorded failed"), Vec::<u32>::new());
+    gate.add_permits(1);
+    let recorded = async {
+        loop {
+            let recorded = client.recorded().await.expect("recorded failed");
+            if !recorded.is_empty() {
+                return recorded;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+    };
+    let recorded = timeout(Duration::from_secs(5), recorded).await.expect("the notification was not handled");
+    assert_eq!(recorded, vec![1]);
+}
+
+#[tokio::test]
+async fn nested_lookup_error() {
+    let client = Locked::async_client(Loopback::new(Locked::server(Lock)).with_format(Json));
+    let unlocked = client.unlock("open sesame".to_string());
+    assert_eq!(unlocked.add(1, 2).await.expect("add failed"), 3);
+    let locked = client.unlock("password".to_string());
+    match locked.add(1, 2).await {
+        Err(RpcError::Application(error)) => {
+            assert_eq!(error.service, "unlock()");
+            assert_eq!(error.downcast_ref::<WrongPassword>(), Some(&WrongPassword { attempts_left: 2 }));
+            assert_eq!(error.to_string(), "Sub-service unlock() returned an error: wrong password, 2 attempts left");
+        }
+        result => panic!("expected the lookup error, got {result:?}"),
+    }
+}
@@ -0,0 +1,123 @@
+//! Tests of pipelines served by a layered handler
+
+use futures::{Sink, Stream};
+use trait_rpc::client::ResponseError;
+use trait_rpc::client::loopback::Loopback;
+use trait_rpc::format::json::Json;
+use trait_rpc::pipeline::PipelineError;
+use trait_rpc::server::{Context, Handler, HandlerError, HandlerLayer};
+use trait_rpc::{RequestInfo, Rpc, RpcError, rpc};
+
+#[rpc(pipeline)]
+/// A service whose calls can be pipelined
+pub trait Accounts {
+    /// The id of the account with the given name, if it exists
+    fn find(&self, name: String) -> Option<u32>;
+    /// The balance of the account with the given id
+    fn balance(&self, id: u32) -> u32;
+    /// The id of the account with the given name, if it exists
+    fn find_alias(&self, name: String) -> MaybeId;
+    /// The id of the account with the given name, in a type which is named `Option`
+    fn find_named(&self, name: String) -> named::Option;
+}
+
+/// An alias of `Option`, which is projected as one
+type MaybeId = Option<u32>;
+
+mod named {
+    use serde::{Deserialize, Serialize};
+
+    /// A type which has the name of `Option`, but is not one
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Option(pub u32);
+}
+
+/// The implementation of the service, with a single account
+struct Server;
+
+impl AccountsServer for Server {
+    async fn find(&self, name: String) -> Option<u32> {
+        (name == "dylan").then_some(1)
+    }
+
+    async fn balance(&self, id: u32) -> u32 {
+        id * 100
+    }
+
+    async fn find_alias(&self, name: String) -> MaybeId {
+        self.find(name).await
+    }
+
+    async fn find_named(&self, _name: String) -> named::Option {
+        named::Option(1)
+    }
+}
+
+/// A layer which rejects every call to the given method
+struct Deny(&'static str);
+
+impl Deny {
+    fn check(&self, request: &impl RequestInfo) -> Result<(), HandlerError> {
+        if request.fn_name() == self.0 {
+            return Err(HandlerError::PermissionDenied(format!("{} is not allowed", self.0)));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Rpc> HandlerLayer<R> for Deny {
+    async fn handle<H>(&self, ctx: &Context, request: R::Request, inner: &H) -> Result<R::Response, HandlerError>
+    where
+        H: Handler<Rpc = R> + Sync,
+    {
+        self.check(&request)?;
+        inner.handle(ctx, request).await
+    }
+
+    async fn handle_stream<H, I, S>(&self, ctx: &Context, request: R::Request, incoming: I, sink: S, inner: &H) -> Result<(), HandlerError>
+    where
+        H: Handler<Rpc = R> + Sync,
+        I: Stream<Item = R::Request> + Send + Unpin,
+        S: Sink<R::Response> + Send + Unpin,
+    {
+        self.check(&request)?;
+        inner.handle_stream(ctx, request, incoming, sink).await
+    }
+}
+
+#[tokio::test]
+async fn allowed_calls_are_pipelined() {
+    let client = Accounts::async_client(Loopback::new(Accounts::server(Server).layer(Deny("find"))).with_format(Json));
+    let balance = client.pipeline(|accounts| accounts.balance(2)).await;
+    assert_eq!(balance.expect("pipeline failed"), 200);
+}
+
+#[tokio::test]
+async fn layer_rejects_pipelined_call() {
+    let client = Accounts::async_client(Loopback::new(Accounts::server(Server).layer(Deny("balance"))).with_format(Json));
+    assert!(client.find("dylan".to_string()).await.is_ok());
+    let balance = client
+        .pipeline(|accounts| {
+            let id = accounts.find("dylan".to_string()).some();
+            accounts.balance(id)
+        })
+        .await;
+    assert!(
+        matches!(balance, Err(PipelineError::Rpc(RpcError::Response(ResponseError::PermissionDenied(_))))),
+        "expected the call to be rejected, got {balance:?}"
+    );
+}
+
+#[tokio::test]
+async fn outputs_are_projected_by_type() {
+    let client = Accounts::async_client(Loopback::new(Accounts::server(Server)).with_format(Json));
+    let balance = client
+        .pipeline(|accounts| {
+            let id = accounts.find_alias("dylan".to_string()).some();
+            accounts.balance(id)
+        })
+        .await;
+    assert_eq!(balance.expect("pipeline failed"), 100);
+    let named = client.pipeline(|accounts| accounts.find_named("dylan".to_string())).await;
+    assert_eq!(named.expect("pipeline failed"), named::Option(1));
+}
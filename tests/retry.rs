@@ -0,0 +1,124 @@
+//! Tests of retrying failed calls, both by wrapping a client and by wrapping a transport
+
+mod common;
+
+use common::{Server, TestService};
+use futures::{Sink, Stream};
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use trait_rpc::client::layer::Layer;
+use trait_rpc::client::loopback::Loopback;
+use trait_rpc::client::retry::RetryLayer;
+use trait_rpc::client::{self, AsyncTransport, CallInfo, ResponseError};
+use trait_rpc::format::json::Json;
+use trait_rpc::server::{Context, Handler, HandlerError, HandlerLayer};
+use trait_rpc::{Rpc, RpcError};
+
+/// A layer which fails the given number of calls before letting the rest through
+struct Flaky(AtomicU32);
+
+impl Flaky {
+    fn check(&self) -> Result<(), HandlerError> {
+        let failures = self.0.load(Ordering::SeqCst);
+        if failures > 0 {
+            self.0.store(failures - 1, Ordering::SeqCst);
+            return Err(HandlerError::Other("the server is unavailable".to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl<R: Rpc> HandlerLayer<R> for Flaky {
+    async fn handle<H>(&self, ctx: &Context, request: R::Request, inner: &H) -> Result<R::Response, HandlerError>
+    where
+        H: Handler<Rpc = R> + Sync,
+    {
+        self.check()?;
+        inner.handle(ctx, request).await
+    }
+
+    async fn handle_stream<H, I, S>(&self, ctx: &Context, request: R::Request, incoming: I, sink: S, inner: &H) -> Result<(), HandlerError>
+    where
+        H: Handler<Rpc = R> + Sync,
+        I: Stream<Item = R::Request> + Send + Unpin,
+        S: Sink<R::Response> + Send + Unpin,
+    {
+        self.check()?;
+        inner.handle_stream(ctx, request, incoming, sink).await
+    }
+}
+
+/// A retry layer which does not wait long between attempts
+fn retry_layer() -> RetryLayer {
+    RetryLayer::builder()
+        .max_retries(3)
+        .initial_backoff(Duration::from_millis(10))
+        .build()
+}
+
+#[tokio::test]
+async fn idempotent_call_is_retried() {
+    let handler = TestService::server(Server::default()).layer(Flaky(AtomicU32::new(2)));
+    let client = TestService::async_client(retry_layer().layer(Loopback::new(handler).with_format(Json)));
+    assert_eq!(client.add(1, 2).await.expect("add failed"), 3);
+}
+
+#[tokio::test]
+async fn retries_are_limited() {
+    let handler = TestService::server(Server::default()).layer(Flaky(AtomicU32::new(5)));
+    let client = TestService::async_client(retry_layer().layer(Loopback::new(handler).with_format(Json)));
+    let result = client.add(1, 2).await;
+    assert!(
+        matches!(result, Err(RpcError::Response(ResponseError::InternalServerError(_)))),
+        "expected the call to fail, got {result:?}"
+    );
+    // the call was sent four times, so the next call only fails once before it is retried
+    assert_eq!(client.add(1, 2).await.expect("add failed"), 3);
+}
+
+#[tokio::test]
+async fn other_calls_are_sent_once() {
+    let handler = TestService::server(Server::default()).layer(Flaky(AtomicU32::new(1)));
+    let client = TestService::async_client(retry_layer().layer(Loopback::new(handler).with_format(Json)));
+    let result = client.waiting().await;
+    assert!(
+        matches!(result, Err(RpcError::Response(ResponseError::InternalServerError(_)))),
+        "expected the call to fail, got {result:?}"
+    );
+    assert_eq!(client.waiting().await.expect("waiting failed"), 0);
+}
+
+/// A transport which records the timeout of each attempt, then fails it
+#[derive(Clone, Default)]
+struct Unavailable(Arc<Mutex<Vec<Option<Duration>>>>);
+
+impl AsyncTransport for Unavailable {
+    type Error = Infallible;
+
+    async fn send(&self, _request: Vec<u8>, call: CallInfo<'_>) -> Result<Result<Vec<u8>, ResponseError>, Self::Error> {
+        self.0.lock().unwrap().push(call.timeout);
+        Ok(Err(ResponseError::InternalServerError("the server is unavailable".to_string())))
+    }
+}
+
+#[tokio::test]
+async fn each_attempt_is_sent_with_the_time_left() {
+    let transport = Unavailable::default();
+    let timeout = Duration::from_secs(5);
+    let client = TestService::async_client(
+        client::builder()
+            .non_blocking()
+            .format(Json)
+            .transport(retry_layer().layer(transport.clone()))
+            .timeout(timeout)
+            .build(),
+    );
+    assert!(client.add(1, 2).await.is_err());
+    let timeouts: Vec<_> = transport.0.lock().unwrap().iter().map(|timeout| timeout.expect("no timeout was sent")).collect();
+    assert_eq!(timeouts.len(), 4);
+    assert_eq!(timeouts[0], timeout);
+    // the backoff before each retry is taken from the time left
+    assert!(timeouts.windows(2).all(|pair| pair[1] < pair[0]), "the timeouts did not decrease: {timeouts:?}");
+}
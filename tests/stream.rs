@@ -0,0 +1,414 @@
+//! Tests of the byte stream transport, the client and server are connected by an in-memory stream
+
+mod common;
+
+use common::{Caller, Server, TestService};
+use futures::channel::mpsc;
+use futures::{Sink, SinkExt, Stream, StreamExt, join, stream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+use trait_rpc::batch::Batch;
+use trait_rpc::client::ResponseError;
+use trait_rpc::{Rpc, RpcError, rpc};
+use trait_rpc::client::stream::StreamClient;
+use trait_rpc::format::Format;
+use trait_rpc::format::json::Json;
+use trait_rpc::frame::{Frame, FrameKind};
+use trait_rpc::server::{Context, Handler, HandlerError, HandlerLayer};
+use trait_rpc::server::stream::StreamServer;
+use tokio_util::bytes::Bytes;
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+#[rpc]
+/// A service which does not match the one being served
+pub trait Mismatched {
+    /// A method which the server does not have
+    fn missing(&self) -> u32;
+    /// A method which the server has, with the wrong arguments
+    fn add(&self, a: String) -> u32;
+}
+
+#[rpc]
+/// A service which opens the test service as a handle
+pub trait Services {
+    /// A new test service, this can be opened as a handle so that every call made through the
+    /// handle goes to the same service
+    #[rpc(handle)]
+    fn service(&self) -> impl TestService;
+    /// The number of services which have not been released
+    fn opened(&self) -> u32;
+}
+
+/// The implementation of the services, counting each service until it is released
+#[derive(Default)]
+struct Registry {
+    opened: Arc<AtomicU32>,
+}
+
+impl ServicesServer for Registry {
+    async fn service(&self) -> impl Handler<Rpc = TestService> {
+        self.opened.fetch_add(1, Ordering::SeqCst);
+        TestService::server(Server::default()).layer(Opened(self.opened.clone()))
+    }
+
+    async fn opened(&self) -> u32 {
+        self.opened.load(Ordering::SeqCst)
+    }
+}
+
+/// A layer over an opened service which counts it until it is dropped. Each call to `add` is sent
+/// two extra responses, which should be dropped by the server
+struct Opened(Arc<AtomicU32>);
+
+impl Drop for Opened {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl HandlerLayer<TestService> for Opened {
+    async fn handle<H>(&self, ctx: &Context, request: Request, inner: &H) -> Result<Response, HandlerError>
+    where
+        H: Handler<Rpc = TestService> + Sync,
+    {
+        inner.handle(ctx, request).await
+    }
+
+    async fn handle_stream<H, I, S>(&self, ctx: &Context, request: Request, incoming: I, mut sink: S, inner: &H) -> Result<(), HandlerError>
+    where
+        H: Handler<Rpc = TestService> + Sync,
+        I: Stream<Item = Request> + Send + Unpin,
+        S: Sink<Response> + Send + Unpin,
+    {
+        let extra = matches!(request, Request::Add(..));
+        inner.handle_stream(ctx, request, incoming, &mut sink).await?;
+        if extra {
+            let _ = sink.send(Response::Waiting(0)).await;
+            let _ = sink.send(Response::Waiting(0)).await;
+        }
+        Ok(())
+    }
+}
+
+type Request = <TestService as Rpc>::Request;
+type Response = <TestService as Rpc>::Response;
+type Transport = StreamClient<<TestService as Rpc>::Request, <TestService as Rpc>::Response>;
+type Client = <TestService as Rpc>::AsyncClient<Transport>;
+
+/// Serve the service over one end of an in-memory stream, returning a transport for the other end.
+/// The context of the connection names the caller as "dylan"
+async fn transport(max_concurrent_requests: usize, call_timeout: Option<Duration>) -> Transport {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let mut ctx = Context::new();
+    ctx.insert(Caller("dylan".to_string()));
+    let serve = StreamServer::builder()
+        .handler(TestService::server(Server::default()))
+        .allow_json()
+        .max_concurrent_requests(max_concurrent_requests)
+        .build()
+        .serve_connection(server, ctx);
+    tokio::spawn(serve);
+    let client = StreamClient::new(client, Json).await.expect("failed to connect");
+    client.with_timeout(call_timeout)
+}
+
+/// A client for the service, served over an in-memory stream
+async fn connect(max_concurrent_requests: usize, call_timeout: Option<Duration>) -> Client {
+    TestService::async_client(transport(max_concurrent_requests, call_timeout).await)
+}
+
+/// A client for the services, served over an in-memory stream. The context of the connection names
+/// the caller as "dylan"
+async fn connect_services() -> <Services as Rpc>::AsyncClient<StreamClient<<Services as Rpc>::Request, <Services as Rpc>::Response>> {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let mut ctx = Context::new();
+    ctx.insert(Caller("dylan".to_string()));
+    let serve = StreamServer::builder()
+        .handler(Services::server(Registry::default()))
+        .allow_json()
+        .build()
+        .serve_connection(server, ctx);
+    tokio::spawn(serve);
+    Services::async_client(StreamClient::new(client, Json).await.expect("failed to connect"))
+}
+
+/// Encode a request frame, as a client would send it
+fn request_frame(id: u64, request: Request, timeout: Option<Duration>) -> Bytes {
+    let request = Format::<Response, Request>::write(&Json, request).expect("failed to write the request");
+    Bytes::from(Frame::new(FrameKind::Request, id, &request).with_timeout(timeout).encode())
+}
+
+/// Wait for the server to stop handling every call to `wait`
+async fn stopped(client: &Client) {
+    let stopped = async {
+        while client.waiting().await.expect("waiting failed") > 0 {
+            sleep(Duration::from_millis(10)).await;
+        }
+    };
+    timeout(Duration::from_secs(5), stopped).await.expect("the call was not stopped");
+}
+
+#[tokio::test]
+async fn unary() {
+    let client = connect(64, None).await;
+    assert_eq!(client.add(1, 2).await.expect("add failed"), 3);
+}
+
+#[tokio::test]
+async fn server_stream() {
+    let client = connect(64, None).await;
+    let numbers = client.count(4).await.expect("count failed");
+    let numbers: Vec<_> = numbers.map(Result::unwrap).collect().await;
+    assert_eq!(numbers, vec![0, 1, 2, 3]);
+}
+
+#[tokio::test]
+async fn client_stream() {
+    let client = connect(64, None).await;
+    let sum = client.sum(stream::iter(1..=10)).await.expect("sum failed");
+    assert_eq!(sum, 55);
+}
+
+#[tokio::test]
+async fn cancellation() {
+    let client = connect(64, None).await;
+    let mut call = Box::pin(client.wait());
+    timeout(Duration::from_millis(100), &mut call)
+        .await
+        .expect_err("wait returned");
+    assert_eq!(client.waiting().await.expect("waiting failed"), 1);
+    // dropping the call sends a cancel frame to the server
+    drop(call);
+    stopped(&client).await;
+}
+
+#[tokio::test]
+async fn timeout_elapses() {
+    let client = connect(64, Some(Duration::from_millis(100))).await;
+    let result = client.wait().await;
+    assert!(matches!(result, Err(RpcError::Timeout(_))), "expected a timeout, got {result:?}");
+    stopped(&client).await;
+}
+
+#[tokio::test]
+async fn batch() {
+    let batch = Batch::new(transport(64, None).await);
+    let client = TestService::async_client(batch.clone());
+    let (first, second, recorded) = batch
+        .run(async { join!(client.add(1, 2), client.add(3, 4), client.recorded()) })
+        .await;
+    assert_eq!(first.expect("add failed"), 3);
+    assert_eq!(second.expect("add failed"), 7);
+    assert_eq!(recorded.expect("recorded failed"), Vec::<u32>::new());
+}
+
+#[tokio::test]
+async fn context() {
+    let client = connect(64, None).await;
+    assert_eq!(client.caller().await.expect("caller failed"), Some("dylan".to_string()));
+}
+
+#[tokio::test]
+async fn notification() {
+    let client = connect(64, None).await;
+    client.record(1).await.expect("record failed");
+    client.record(2).await.expect("record failed");
+    // nothing is received when a notification has been handled, and it may be handled after the
+    // requests which follow it
+    let recorded = async {
+        loop {
+            let mut recorded = client.recorded().await.expect("recorded failed");
+            if recorded.len() == 2 {
+                recorded.sort_unstable();
+                return recorded;
+            }
+            sleep(Duration::from_millis(10)).await;
+        }
+    };
+    let recorded = timeout(Duration::from_secs(5), recorded).await.expect("the notifications were not handled");
+    assert_eq!(recorded, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn queued_client_stream_does_not_block_the_connection() {
+    let client = connect(1, None).await;
+    let (mut numbers, receiver) = mpsc::channel(1);
+    // the first call is handled while the second waits behind it, the second sends more items than
+    // fit in its channel before the first has received all of its own
+    let first = client.sum(receiver);
+    let second = async {
+        sleep(Duration::from_millis(50)).await;
+        client.sum(stream::iter(0..100)).await
+    };
+    let send = async {
+        sleep(Duration::from_millis(100)).await;
+        numbers.send(1).await.unwrap();
+        numbers.send(2).await.unwrap();
+        numbers.close_channel();
+    };
+    let (first, second, ()) = timeout(Duration::from_secs(5), async { join!(first, second, send) })
+        .await
+        .expect("the connection is deadlocked");
+    assert_eq!(first.expect("sum failed"), 3);
+    assert!(second.is_err(), "the queued call should fail after too many items");
+}
+
+#[tokio::test]
+async fn unknown_method() {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let serve = StreamServer::builder()
+        .handler(TestService::server(Server::default()))
+        .allow_json()
+        .build()
+        .serve_connection(server, Context::new());
+    tokio::spawn(serve);
+    let client = Mismatched::async_client(StreamClient::new(client, Json).await.expect("failed to connect"));
+    let missing = client.missing().await;
+    assert!(
+        matches!(missing, Err(RpcError::Response(ResponseError::UnknownMethod(_)))),
+        "expected the method to be unknown, got {missing:?}"
+    );
+    // the method exists, so the request is malformed rather than for an unknown method
+    let add = client.add("one".to_string()).await;
+    assert!(
+        matches!(add, Err(RpcError::Response(ResponseError::BadRequest(_)))),
+        "expected the request to be rejected, got {add:?}"
+    );
+}
+
+#[tokio::test]
+async fn queued_client_stream_receives_its_items() {
+    let client = connect(1, None).await;
+    let (mut numbers, receiver) = mpsc::channel(1);
+    // the second call waits behind the first, its items are kept until it starts
+    let first = client.sum(receiver);
+    let second = async {
+        sleep(Duration::from_millis(50)).await;
+        client.sum(stream::iter(1..=4)).await
+    };
+    let send = async {
+        sleep(Duration::from_millis(100)).await;
+        numbers.send(1).await.unwrap();
+        numbers.close_channel();
+    };
+    let (first, second, ()) = timeout(Duration::from_secs(5), async { join!(first, second, send) })
+        .await
+        .expect("the connection is deadlocked");
+    assert_eq!(first.expect("sum failed"), 1);
+    assert_eq!(second.expect("sum failed"), 10);
+}
+
+#[tokio::test]
+async fn timeout_runs_from_when_the_request_is_received() {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let serve = StreamServer::builder()
+        .handler(TestService::server(Server::default()))
+        .allow_json()
+        .max_concurrent_requests(1)
+        .build()
+        .serve_connection(server, Context::new());
+    tokio::spawn(serve);
+    let mut connection = Framed::new(client, LengthDelimitedCodec::new());
+    connection.send(Bytes::from_static(b"application/json")).await.expect("failed to send");
+    let chosen = connection.next().await.expect("connection closed").expect("failed to receive");
+    assert_eq!(&chosen[..], b"application/json");
+    // the second request waits behind the first for longer than its own timeout
+    connection
+        .send(request_frame(1, Request::Wait(), Some(Duration::from_millis(300))))
+        .await
+        .expect("failed to send");
+    connection
+        .send(request_frame(2, Request::Add(1, 2), Some(Duration::from_millis(100))))
+        .await
+        .expect("failed to send");
+    connection.send(request_frame(3, Request::Add(3, 4), None)).await.expect("failed to send");
+    // the requests are handled in order, so the second would be answered first if it had run
+    let response = timeout(Duration::from_secs(5), connection.next())
+        .await
+        .expect("no response was received")
+        .expect("connection closed")
+        .expect("failed to receive");
+    let frame = Frame::decode(&response).expect("failed to decode the response");
+    assert_eq!((frame.kind, frame.id), (FrameKind::Response, 3));
+}
+
+#[tokio::test]
+async fn notification_is_dropped_once_too_many_requests_are_waiting() {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let serve = StreamServer::builder()
+        .handler(TestService::server(Server::default()))
+        .allow_json()
+        .max_concurrent_requests(1)
+        .build()
+        .serve_connection(server, Context::new());
+    tokio::spawn(serve);
+    let mut connection = Framed::new(client, LengthDelimitedCodec::new());
+    connection.send(Bytes::from_static(b"application/json")).await.expect("failed to send");
+    let chosen = connection.next().await.expect("connection closed").expect("failed to receive");
+    assert_eq!(&chosen[..], b"application/json");
+    // the running request is still receiving items, so the server keeps reading with a full queue
+    connection.send(request_frame(1, Request::Sum(), None)).await.expect("failed to send");
+    connection.send(request_frame(2, Request::Wait(), None)).await.expect("failed to send");
+    connection.send(request_frame(3, Request::Record(5), None)).await.expect("failed to send");
+    connection.send(request_frame(4, Request::Add(1, 2), None)).await.expect("failed to send");
+    // the rejected notification is answered before the request which follows it, if at all
+    let response = timeout(Duration::from_secs(5), connection.next())
+        .await
+        .expect("no response was received")
+        .expect("connection closed")
+        .expect("failed to receive");
+    let frame = Frame::decode(&response).expect("failed to decode the response");
+    assert_eq!((frame.kind, frame.id), (FrameKind::Error, 4));
+}
+
+/// Wait for the server to release every opened service
+async fn released(services: &<Services as Rpc>::AsyncClient<StreamClient<<Services as Rpc>::Request, <Services as Rpc>::Response>>) {
+    let released = async {
+        while services.opened().await.expect("opened failed") > 0 {
+            sleep(Duration::from_millis(10)).await;
+        }
+    };
+    timeout(Duration::from_secs(5), released).await.expect("the service was not released");
+}
+
+#[tokio::test]
+async fn handle_calls_go_to_the_opened_service() {
+    let services = connect_services().await;
+    let service = services.open_service().await.expect("open failed");
+    assert_eq!(services.opened().await.expect("opened failed"), 1);
+    let numbers: Vec<_> = service.count(3).await.expect("count failed").map(Result::unwrap).collect().await;
+    assert_eq!(numbers, vec![0, 1, 2]);
+    assert_eq!(service.sum(stream::iter(1..=4)).await.expect("sum failed"), 10);
+    assert_eq!(service.caller().await.expect("caller failed"), Some("dylan".to_string()));
+    // the service was only opened once for every call
+    assert_eq!(services.opened().await.expect("opened failed"), 1);
+}
+
+#[tokio::test]
+async fn handle_call_drops_extra_responses() {
+    let services = connect_services().await;
+    let service = services.open_service().await.expect("open failed");
+    let sum = timeout(Duration::from_secs(5), service.add(1, 2)).await.expect("add did not return");
+    assert_eq!(sum.expect("add failed"), 3);
+}
+
+#[tokio::test]
+async fn handle_is_released_once_dropped() {
+    let services = connect_services().await;
+    let first = services.open_service().await.expect("open failed");
+    let second = services.open_service().await.expect("open failed");
+    assert_eq!(services.opened().await.expect("opened failed"), 2);
+    drop(first);
+    assert_eq!(second.add(1, 2).await.expect("add failed"), 3);
+    drop(second);
+    released(&services).await;
+}
+
+#[tokio::test]
+async fn services_are_not_kept_without_a_handle() {
+    let services = connect_services().await;
+    assert_eq!(services.service().add(1, 2).await.expect("add failed"), 3);
+    released(&services).await;
+}
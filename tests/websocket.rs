@@ -0,0 +1,217 @@
+//! Tests of the websocket client and server, including calls made by the server back into the
+//! client and topics published to the clients
+
+mod common;
+
+use common::{Caller, Server, TestService};
+use futures::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::http::Uri;
+use tokio_tungstenite::tungstenite::{ClientRequestBuilder, Message};
+use trait_rpc::client::websocket::{Callbacks, WebsocketClient};
+use trait_rpc::format::Format;
+use trait_rpc::format::json::Json;
+use trait_rpc::frame::{Frame, FrameKind};
+use trait_rpc::server::Context;
+use trait_rpc::server::axum::Axum;
+use trait_rpc::server::multiplex::Peer;
+use trait_rpc::server::topics::Topics;
+use trait_rpc::{Rpc, rpc};
+
+#[rpc]
+/// A service which makes each call on the test service provided by its caller
+pub trait Relay {
+    /// Add two numbers using the service of the caller
+    fn add(&self, #[context] ctx: &Context, a: u32, b: u32) -> Option<u32>;
+    /// The name of the caller, as seen by the service of the caller
+    fn caller(&self, #[context] ctx: &Context) -> Option<String>;
+}
+
+/// The implementation of the relay service
+struct Relayer;
+
+impl Relayer {
+    /// A client for the test service of the caller
+    fn peer(ctx: &Context) -> Option<&Peer<TestService>> {
+        ctx.get::<Peer<TestService>>()
+    }
+}
+
+impl RelayServer for Relayer {
+    async fn add(&self, ctx: &Context, a: u32, b: u32) -> Option<u32> {
+        Self::peer(ctx)?.client().add(a, b).await.ok()
+    }
+
+    async fn caller(&self, ctx: &Context) -> Option<String> {
+        Self::peer(ctx)?.client().caller().await.ok()?
+    }
+}
+
+/// Serve `app` on a new port, returning its address
+async fn serve(app: axum::Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("failed to bind");
+    let address = listener.local_addr().expect("no local address");
+    tokio::spawn(async move {
+        axum::serve::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .expect("server failed");
+    });
+    address
+}
+
+/// Serve the relay service, returning its url
+async fn serve_relay() -> Uri {
+    let app = axum::Router::new().route_service(
+        "/relay",
+        Axum::builder()
+            .handler(Relay::server(Relayer))
+            .allow_json()
+            .peer::<TestService>(&Json)
+            .build(),
+    );
+    format!("ws://{}/relay", serve(app).await).parse().unwrap()
+}
+
+#[tokio::test]
+async fn callbacks_are_served() {
+    let url = serve_relay().await;
+    let callbacks = Callbacks::new(TestService::server(Server::default()));
+    let client = WebsocketClient::with_callbacks(url, Json, callbacks)
+        .await
+        .expect("failed to connect");
+    let client = Relay::async_client(client);
+    assert_eq!(client.add(1, 2).await.expect("add failed"), Some(3));
+}
+
+#[tokio::test]
+async fn callbacks_are_given_the_context() {
+    let url = serve_relay().await;
+    let mut ctx = Context::new();
+    ctx.insert(Caller("dylan".to_string()));
+    let callbacks = Callbacks::builder()
+        .handler(TestService::server(Server::default()))
+        .ctx(ctx)
+        .build();
+    let client = WebsocketClient::with_callbacks(url, Json, callbacks)
+        .await
+        .expect("failed to connect");
+    let client = Relay::async_client(client);
+    assert_eq!(client.caller().await.expect("caller failed"), Some("dylan".to_string()));
+}
+
+#[rpc]
+/// A feed of posts which clients can subscribe to
+pub trait Feed {
+    /// Publish a post, returns the number of subscribers it was sent to
+    fn post(&self, text: String) -> usize;
+    /// Each post published after subscribing
+    #[rpc(topic)]
+    fn posts(&self) -> String;
+}
+
+/// The implementation of the feed service
+struct Poster {
+    topics: Topics<Feed>,
+}
+
+impl FeedServer for Poster {
+    async fn post(&self, text: String) -> usize {
+        Feed::publish_posts(&self.topics, text)
+    }
+}
+
+type FeedClient = <Feed as Rpc>::AsyncClient<WebsocketClient<<Feed as Rpc>::Request, <Feed as Rpc>::Response>>;
+
+/// Serve the feed service, returning its topics and url
+async fn serve_feed() -> (Topics<Feed>, Uri) {
+    let topics = Topics::new();
+    let app = axum::Router::new().route_service(
+        "/feed",
+        Axum::builder()
+            .handler(Feed::server(Poster { topics: topics.clone() }))
+            .allow_json()
+            .topics(topics.clone())
+            .build(),
+    );
+    let url = format!("ws://{}/feed", serve(app).await).parse().unwrap();
+    (topics, url)
+}
+
+/// Connect a new client to the feed
+async fn connect(url: &Uri) -> FeedClient {
+    Feed::async_client(WebsocketClient::new(url.clone(), Json).await.expect("failed to connect"))
+}
+
+/// Wait until the feed has the given number of subscribers to its posts
+async fn wait_for_subscribers(topics: &Topics<Feed>, count: usize) {
+    let wait = async {
+        while topics.subscribers("posts") != count {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    timeout(Duration::from_secs(5), wait)
+        .await
+        .unwrap_or_else(|_| panic!("expected {count} subscribers, found {}", topics.subscribers("posts")));
+}
+
+#[tokio::test]
+async fn subscribers_receive_each_event() {
+    let (topics, url) = serve_feed().await;
+    let alice = connect(&url).await;
+    let bob = connect(&url).await;
+    let mut alice_posts = alice.posts().await.expect("posts failed");
+    let mut bob_posts = bob.posts().await.expect("posts failed");
+    wait_for_subscribers(&topics, 2).await;
+
+    assert_eq!(alice.post("first".to_string()).await.expect("post failed"), 2);
+    assert_eq!(bob.post("second".to_string()).await.expect("post failed"), 2);
+    for posts in [&mut alice_posts, &mut bob_posts] {
+        for expected in ["first", "second"] {
+            let post = posts.next().await.expect("subscription ended").expect("posts failed");
+            assert_eq!(post, expected);
+        }
+    }
+}
+
+#[tokio::test]
+async fn events_before_subscribing_are_not_received() {
+    let (topics, url) = serve_feed().await;
+    let client = connect(&url).await;
+    assert_eq!(client.post("unseen".to_string()).await.expect("post failed"), 0);
+    let mut posts = client.posts().await.expect("posts failed");
+    wait_for_subscribers(&topics, 1).await;
+    client.post("seen".to_string()).await.expect("post failed");
+    assert_eq!(posts.next().await.expect("subscription ended").expect("posts failed"), "seen");
+}
+
+#[tokio::test]
+async fn dropping_the_subscription_unsubscribes() {
+    let (topics, url) = serve_feed().await;
+    let client = connect(&url).await;
+    let posts = client.posts().await.expect("posts failed");
+    let other = client.posts().await.expect("posts failed");
+    wait_for_subscribers(&topics, 2).await;
+    drop(posts);
+    wait_for_subscribers(&topics, 1).await;
+    drop(other);
+    wait_for_subscribers(&topics, 0).await;
+}
+
+#[tokio::test]
+async fn disconnecting_unsubscribes() {
+    let (topics, url) = serve_feed().await;
+    // the client cancels each request before it closes the connection, so subscribe on a raw
+    // connection which is closed without cancelling the subscription
+    let request = ClientRequestBuilder::new(url).with_sub_protocol(Format::<(), ()>::content_type(&Json));
+    let (mut socket, _) = connect_async(request).await.expect("failed to connect");
+    let subscribe = Format::<<Feed as Rpc>::Response, _>::write(&Json, <Feed as Rpc>::Request::Posts())
+        .expect("failed to write the request");
+    let frame = Frame::new(FrameKind::Request, 0, &subscribe).encode();
+    socket.send(Message::Binary(frame.into())).await.expect("failed to send the request");
+    wait_for_subscribers(&topics, 1).await;
+    drop(socket);
+    wait_for_subscribers(&topics, 0).await;
+}